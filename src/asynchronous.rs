@@ -0,0 +1,118 @@
+use std::thread;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::task::spawn_blocking;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::event::SyncEvent;
+use crate::status::Status;
+use crate::{Trsync, TrsyncHandle};
+
+// Async facade of `Trsync`, to embed trsync in a tokio application :
+//
+//     let handle = AsyncTrsync::start(context).await?;
+//     let mut events = handle.subscribe();
+//     while let Some(event) = events.recv().await { ... }
+//     handle.stop().await?;
+//
+// Only this facade is asynchronous : synchronization still run in trsync threads with
+// the blocking client. Blocking calls are made on tokio blocking pool so that they
+// never block the async runtime.
+pub struct AsyncTrsync;
+
+impl AsyncTrsync {
+    pub async fn start(context: Context) -> Result<AsyncTrsyncHandle, Error> {
+        let handle = spawn_blocking(move || Trsync::start(context))
+            .await
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))??;
+        Ok(AsyncTrsyncHandle { handle })
+    }
+}
+
+pub struct AsyncTrsyncHandle {
+    handle: TrsyncHandle,
+}
+
+impl AsyncTrsyncHandle {
+    pub fn pause(&self) {
+        self.handle.pause()
+    }
+
+    pub fn resume(&self) {
+        self.handle.resume()
+    }
+
+    pub fn status(&self) -> Result<Status, Error> {
+        self.handle.status()
+    }
+
+    // Receive synchronization events from now. Events are forwarded by a dedicated
+    // thread, which stop once the receiver is dropped and a new event is published.
+    pub fn subscribe(&self) -> UnboundedReceiver<SyncEvent> {
+        let events = self.handle.subscribe();
+        let (sender, receiver) = unbounded_channel();
+        thread::spawn(move || {
+            for event in events {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    pub async fn stop(self) -> Result<(), Error> {
+        let handle = self.handle;
+        spawn_blocking(move || handle.stop())
+            .await
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+    }
+
+    pub async fn wait(self) -> Result<(), Error> {
+        let handle = self.handle;
+        spawn_blocking(move || handle.wait())
+            .await
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        testing::{self, StubResponse, TracimStub},
+        util,
+    };
+
+    #[tokio::test]
+    async fn async_trsync_synchronizes_until_end() {
+        let stub = TracimStub::start();
+        stub.on(
+            "GET",
+            "/api/auth/whoami",
+            StubResponse::json(200, r#"{"user_id": 1}"#),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents"),
+            StubResponse::json(
+                200,
+                r#"{"has_next": false, "has_previous": false, "items": [],
+                    "next_page_token": "", "per_page": 0, "previous_page_token": ""}"#,
+            ),
+        );
+        let folder = testing::temp_folder("async");
+        let mut context = testing::context(&stub, &folder);
+        context.database_path = util::path_to_string(&folder.join(".trsync.db")).unwrap();
+
+        let handle = AsyncTrsync::start(context).await.unwrap();
+        let status = handle.status().unwrap();
+        handle.wait().await.unwrap();
+
+        assert_eq!(status.errors, 0);
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -1,23 +1,45 @@
+use std::fs;
 use std::path::Path;
-use std::time::Duration;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use reqwest::blocking::{multipart, Response};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::blocking::{multipart, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, DATE, USER_AGENT};
 use reqwest::Method;
+use rusqlite::Connection;
 
 use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
 use crate::context::Context;
+use crate::database::DatabaseOperation;
 use crate::error::{ClientError, Error};
-use crate::types::RevisionId;
+use crate::timing::{self, TimingPhase};
+use crate::types::{ClockCheck, RelativeFilePath, RevisionId};
 use crate::util;
 use crate::{
-    remote::RemoteContent,
+    remote::{RemoteContent, RemoteEvent},
     types::{ContentId, ContentType},
 };
 
+// Content label (file name) refused by Tracim validation
+const GENERIC_SCHEMA_VALIDATION_ERR_CODE: u16 = 2001;
 const CONTENT_ALREADY_EXIST_ERR_CODE: u16 = 3002;
+// Content type not allowed in parent folder
+const UNALLOWED_SUBCONTENT_ERR_CODE: u16 = 3003;
+const CONTENT_NOT_FOUND_ERR_CODE: u16 = 1003;
+const FILE_SIZE_OVER_MAX_LIMIT_ERR_CODE: u16 = 3009;
+const FILE_SIZE_OVER_WORKSPACE_EMPTY_SPACE_ERR_CODE: u16 = 3010;
+const FILE_SIZE_OVER_OWNER_EMPTY_SPACE_ERR_CODE: u16 = 3011;
 const DEFAULT_CLIENT_TIMEOUT: u64 = 3600 * 2;
+const DEFAULT_POOL_IDLE_TIMEOUT_S: u64 = 90;
+const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 8;
+const DEFAULT_TCP_KEEPALIVE_S: u64 = 60;
+const CLIENT_TOKEN_HEADER: &str = "X-Tracim-ClientToken";
+const API_KEY_HEADER: &str = "Tracim-Api-Key";
+const API_LOGIN_HEADER: &str = "Tracim-Api-Login";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Paginated<T> {
@@ -51,27 +73,139 @@ impl ParentIdParameter {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum TrashOutcome {
+    Trashed,
+    AlreadyTrashed,
+    NotFound,
+}
+
+// HTTP connections settings. Underlying HTTP client is built by first `Client` and
+// then shared by all clients of the context, so that connections are reused by all
+// threads.
+#[derive(Debug, Clone)]
+pub struct ConnectionPool {
+    // Idle connections are closed after this delay
+    pub idle_timeout: Duration,
+    pub max_idle_per_host: usize,
+    // TCP keep-alive interval, disabled when None
+    pub tcp_keepalive: Option<Duration>,
+    // Ask gzip compressed responses (downloads, contents listing). Uploads are never
+    // compressed : Tracim does not decode compressed request bodies, it would store
+    // compressed bytes as file content.
+    pub gzip: bool,
+    client: Arc<Mutex<Option<reqwest::blocking::Client>>>,
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(DEFAULT_POOL_IDLE_TIMEOUT_S),
+            max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            tcp_keepalive: Some(Duration::from_secs(DEFAULT_TCP_KEEPALIVE_S)),
+            gzip: false,
+            client: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl ConnectionPool {
+    fn client(&self, default_headers: HeaderMap) -> Result<reqwest::blocking::Client, Error> {
+        let mut client = self
+            .client
+            .lock()
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?;
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let new_client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_CLIENT_TIMEOUT))
+            .pool_idle_timeout(self.idle_timeout)
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive)
+            .gzip(self.gzip)
+            .default_headers(default_headers)
+            .build()?;
+        *client = Some(new_client.clone());
+        Ok(new_client)
+    }
+}
+
 pub struct Client {
     context: Context,
     client: reqwest::blocking::Client,
+    auth_headers: HeaderMap,
 }
 
 impl Client {
     pub fn new(context: Context) -> Result<Self, Error> {
         Ok(Self {
+            client: context.connection_pool.client(default_headers(&context)?)?,
+            auth_headers: auth_headers(&context)?,
             context,
-            client: reqwest::blocking::Client::builder()
-                .timeout(Duration::from_secs(DEFAULT_CLIENT_TIMEOUT))
-                .build()?,
         })
     }
 
+    fn request(&self, method: Method, url: String) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .headers(self.auth_headers.clone())
+            .header(CLIENT_TOKEN_HEADER, &self.context.client_token)
+    }
+
+    // Send request, with trace summaries of request and response
+    fn send(&self, request_builder: RequestBuilder) -> Result<Response, ClientError> {
+        let request = request_builder.build()?;
+        self.context.request_pacer.wait();
+        let method = request.method().clone();
+        let url = request.url().clone();
+        log::trace!(
+            "Request {} {} {:?}",
+            method,
+            url,
+            redacted_headers(request.headers())
+        );
+        let started = Instant::now();
+        let response = timing::measure(TimingPhase::Remote, || self.client.execute(request));
+        self.context.metrics.request_duration(started.elapsed());
+        self.context.metrics.set_connected(response.is_ok());
+        let response = response.map_err(|error| {
+            if error.is_connect() || error.is_timeout() {
+                ClientError::UnreachableRemote(format!("{:?}", error))
+            } else {
+                ClientError::from(error)
+            }
+        })?;
+        log::trace!(
+            "Response {} for {} {} ({:?} bytes)",
+            response.status(),
+            method,
+            url,
+            response.content_length()
+        );
+        // Credentials don't expire : they are wrong (or revoked API key)
+        if response.status().as_u16() == 401 {
+            return Err(ClientError::Unauthorized(format!(
+                "Tracim refused credentials of {} for {} {}",
+                self.context.username, method, url
+            )));
+        }
+        Ok(response)
+    }
+
+    fn uploaded(&self, absolute_file_path: &str) {
+        if let Ok(metadata) = fs::metadata(absolute_file_path) {
+            self.context.metrics.uploaded(metadata.len());
+        }
+    }
+
     pub fn create_content(
         &self,
         absolute_file_path: String,
         content_type: ContentType,
         parent_content_id: Option<ContentId>,
-    ) -> Result<(ContentId, RevisionId), ClientError> {
+    ) -> Result<RemoteContent, ClientError> {
         let response = if content_type == ContentType::Folder {
             let url = self.context.workspace_url("contents");
             let mut data = Map::new();
@@ -89,14 +223,7 @@ impl Client {
                 &absolute_file_path,
                 &url
             );
-            self.client
-                .request(Method::POST, url)
-                .basic_auth(
-                    self.context.username.clone(),
-                    Some(self.context.password.clone()),
-                )
-                .json(&data)
-                .send()?
+            self.send(self.request(Method::POST, url).json(&data))?
         } else {
             let mut form = multipart::Form::new();
             if let Some(parent_content_id) = parent_content_id {
@@ -117,36 +244,25 @@ impl Client {
                 &absolute_file_path,
                 &url
             );
-            self.client
-                .request(Method::POST, url)
-                .basic_auth(
-                    self.context.username.clone(),
-                    Some(self.context.password.clone()),
-                )
-                .multipart(form)
-                .send()?
+            self.send(self.request(Method::POST, url).multipart(form))?
         };
 
         let response_status = &response.status().as_u16();
         match response_status {
             200 => {
-                let value = response.json::<Value>()?;
-                let data = value.as_object().ok_or(Error::UnexpectedError(format!(
-                    "Response content not appear to be an object : {:?}",
-                    value
-                )))?;
-                let content_id =
-                    data["content_id"]
-                        .as_i64()
-                        .ok_or(Error::UnexpectedError(format!(
-                            "Response content object do not contains a integer content_id : {:?}",
-                            data
-                        )))? as ContentId;
-                let revision_id = self.get_remote_content(content_id)?.current_revision_id;
-                Ok((content_id, revision_id))
+                // Tracim answer with created content, no need to fetch it again
+                let remote_content = response.json::<RemoteContent>()?;
+                if content_type == ContentType::File {
+                    self.uploaded(&absolute_file_path);
+                }
+                Ok(remote_content)
             }
             400 => {
-                let error_code = match response.json::<Value>()?["code"].as_u64() {
+                let text = response.text()?;
+                if let Some(error) = upload_rejection(&absolute_file_path, &text) {
+                    return Err(error);
+                }
+                let error_code = match response_error_code(&text) {
                     Some(code) => code as u16,
                     None => {
                         return Err(ClientError::AlreadyExistResponseAndFailToFoundIt(
@@ -171,12 +287,25 @@ impl Client {
                             }
                         }
                     }
+                    GENERIC_SCHEMA_VALIDATION_ERR_CODE | UNALLOWED_SUBCONTENT_ERR_CODE => {
+                        Err(ClientError::Rejected(format!(
+                            "Creation of {} refused : {}",
+                            absolute_file_path, text
+                        )))
+                    }
                     _ => Err(ClientError::AlreadyExistResponseAndFailToFoundIt(format!(
                         "Response error code was {}",
                         error_code
                     ))),
                 }
             }
+            403 => {
+                let text = response.text()?;
+                Err(ClientError::ForbiddenResponse(format!(
+                    "Creation of {} refused : {}",
+                    absolute_file_path, text
+                )))
+            }
             _ => {
                 let text = response.text()?;
                 Err(ClientError::UnexpectedResponse(format!(
@@ -196,7 +325,7 @@ impl Client {
         for remote_content in
             self.get_remote_contents(Some(ParentIdParameter::from_value(parent_id)))?
         {
-            if remote_content.filename == file_name {
+            if remote_content.local_name(&self.context) == file_name {
                 return Ok((
                     remote_content.content_id,
                     remote_content.current_revision_id,
@@ -208,6 +337,21 @@ impl Client {
         ))
     }
 
+    // Content of parent with exactly given file name. Looser matches (case, label)
+    // could attach local file to another remote content.
+    pub fn find_by_name(
+        &self,
+        file_name: &str,
+        parent_id: Option<ContentId>,
+    ) -> Result<Option<RemoteContent>, ClientError> {
+        Ok(self
+            .get_remote_contents(Some(ParentIdParameter::from_value(parent_id)))?
+            .into_iter()
+            .find(|remote_content| {
+                remote_content.parent_id == parent_id && remote_content.filename == file_name
+            }))
+    }
+
     pub fn update_content(
         &self,
         absolute_file_path: String,
@@ -239,20 +383,24 @@ impl Client {
             .context
             .workspace_url(&format!("files/{}/raw/{}", content_id, file_name));
 
-        let response = self
-            .client
-            .request(Method::PUT, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .multipart(form)
-            .send()?;
+        let response = self.send(self.request(Method::PUT, url).multipart(form))?;
         match response.status().as_u16() {
             200 | 204 => {
+                self.uploaded(&absolute_file_path);
                 let content = self.get_remote_content(content_id)?;
                 Ok(content.current_revision_id)
             }
+            400 => {
+                let text = response.text()?;
+                Err(
+                    upload_rejection(&absolute_file_path, &text).unwrap_or_else(|| {
+                        ClientError::UnexpectedResponse(format!(
+                            "Response status code was 400 : {}",
+                            text
+                        ))
+                    }),
+                )
+            }
             _ => Err(ClientError::UnexpectedResponse(format!(
                 "Response status code was {}",
                 response.status().as_u16(),
@@ -260,49 +408,136 @@ impl Client {
         }
     }
 
-    pub fn trash_content(&self, content_id: ContentId) -> Result<(), ClientError> {
-        let response = self
-            .client
-            .request(
+    pub fn trash_content(&self, content_id: ContentId) -> Result<TrashOutcome, ClientError> {
+        let response = self.send(
+            self.request(
                 Method::PUT,
                 self.context
                     .workspace_url(&format!("contents/{}/trashed", content_id)),
-            )
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .send()?;
+            ),
+        )?;
 
-        match response.status().as_u16() {
-            204 => Ok(()),
+        let response_status_code = response.status().as_u16();
+        match response_status_code {
+            204 => Ok(TrashOutcome::Trashed),
+            404 => Ok(TrashOutcome::NotFound),
+            400 => {
+                let text = response.text()?;
+                if response_error_code(&text) == Some(CONTENT_NOT_FOUND_ERR_CODE as u64) {
+                    return Ok(TrashOutcome::NotFound);
+                }
+                // Tracim refuse to trash a content which is already in trash
+                if self.get_remote_content(content_id)?.is_deleted {
+                    return Ok(TrashOutcome::AlreadyTrashed);
+                }
+                Err(ClientError::UnexpectedResponse(format!(
+                    "Unexpected response status {} when trashing content {} : {}",
+                    response_status_code, content_id, text,
+                )))
+            }
             _ => Err(ClientError::UnexpectedResponse(format!(
                 "Response status code was {}",
-                response.status().as_u16(),
+                response_status_code,
             ))),
         }
     }
 
     pub fn get_remote_content(&self, content_id: ContentId) -> Result<RemoteContent, ClientError> {
-        let response = self
-            .client
-            .request(
+        let response = self.send(
+            self.request(
                 Method::GET,
                 self.context
                     .workspace_url(&format!("contents/{}", content_id)),
-            )
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .send()?;
+            ),
+        )?;
 
-        Ok(response.json::<RemoteContent>()?)
+        let response_status_code = response.status().as_u16();
+        match response_status_code {
+            200 => Ok(response.json::<RemoteContent>()?),
+            404 => Err(ClientError::NotFoundResponse(format!(
+                "Content {} not found",
+                content_id
+            ))),
+            _ => {
+                let text = response.text()?;
+                Err(ClientError::UnexpectedResponse(format!(
+                    "Unexpected response status {} when getting content {} : {}",
+                    response_status_code, content_id, text,
+                )))
+            }
+        }
+    }
+
+    // Find content id corresponding to local relative path, from index or by
+    // browsing remote when path is not indexed. Nothing is synchronized.
+    pub fn resolve_path(
+        &self,
+        connection: &Connection,
+        relative_path: &str,
+    ) -> Result<Option<ContentId>, Error> {
+        match DatabaseOperation::new(connection).get_content_id_from_path(relative_path.to_string())
+        {
+            Ok(content_id) => return Ok(Some(content_id)),
+            Err(Error::UnIndexedRelativePath(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let mut parent_id = ParentIdParameter::Root;
+        let mut found_content_id = None;
+        for component in Path::new(relative_path).components() {
+            let name = component
+                .as_os_str()
+                .to_str()
+                .ok_or(Error::PathManipulationError(format!(
+                    "Error when manipulate path {:?}",
+                    relative_path
+                )))?;
+            found_content_id = self
+                .get_remote_contents(Some(parent_id))?
+                .into_iter()
+                .find(|content| content.local_name(&self.context) == name)
+                .map(|content| content.content_id);
+            match found_content_id {
+                Some(content_id) => parent_id = ParentIdParameter::Some(content_id),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(found_content_id)
+    }
+
+    // Find local relative path corresponding to content id, from index or by
+    // building it from remote when content is not indexed. Nothing is synchronized.
+    pub fn resolve_content(
+        &self,
+        connection: &Connection,
+        content_id: ContentId,
+    ) -> Result<Option<RelativeFilePath>, Error> {
+        match DatabaseOperation::new(connection).get_path_from_content_id(content_id) {
+            Ok(relative_path) => return Ok(Some(relative_path)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(error) => return Err(Error::from(error)),
+        }
+
+        let remote_content = match self.get_remote_content(content_id) {
+            Ok(remote_content) => remote_content,
+            Err(ClientError::NotFoundResponse(_)) => return Ok(None),
+            Err(error) => return Err(Error::from(error)),
+        };
+        if remote_content.is_deleted {
+            return Ok(None);
+        }
+
+        match self.build_relative_path(&remote_content) {
+            Ok(relative_path) => Ok(Some(relative_path)),
+            Err(ClientError::NotRelevant(_)) => Ok(None),
+            Err(error) => Err(Error::from(error)),
+        }
     }
 
     pub fn build_relative_path(&self, content: &RemoteContent) -> Result<String, ClientError> {
         if let Some(parent_id) = content.parent_id {
-            let mut path_parts: Vec<String> = vec![content.filename.clone()];
+            let mut path_parts: Vec<String> = vec![content.local_name(&self.context)];
             let mut last_seen_parent_id = parent_id;
             loop {
                 log::debug!(
@@ -310,28 +545,32 @@ impl Client {
                     content.content_id,
                     last_seen_parent_id
                 );
-                let response = self
-                    .client
-                    .request(
+                let response = self.send(
+                    self.request(
                         Method::GET,
                         self.context
                             .workspace_url(&format!("contents/{}", last_seen_parent_id)),
-                    )
-                    .basic_auth(
-                        self.context.username.clone(),
-                        Some(self.context.password.clone()),
-                    )
-                    .send()?;
-
-                match response.status().as_u16() {
-                    200 => {},
-                    _ => {
-                        return Err(ClientError::UnexpectedResponse(format!(
-                            "Fail to build relative path for content id {}, response status code was {}",
-                            content.content_id, response.status().as_u16(),
-                        )))
+                    ),
+                )?;
+
+                let response_status_code = response.status().as_u16();
+                if response_status_code != 200 {
+                    // Parent can be deleted while resolving path : its deletion event will
+                    // clean up, so there is nothing to do for this content
+                    let text = response.text()?;
+                    if response_status_code == 404
+                        || response_error_code(&text) == Some(CONTENT_NOT_FOUND_ERR_CODE as u64)
+                    {
+                        return Err(ClientError::NotRelevant(format!(
+                            "Parent content {} of content {} not found, ignore it",
+                            last_seen_parent_id, content.content_id,
+                        )));
                     }
-                };
+                    return Err(ClientError::UnexpectedResponse(format!(
+                        "Fail to build relative path for content id {}, response status code was {}",
+                        content.content_id, response_status_code,
+                    )));
+                }
 
                 let folder = response.json::<RemoteContent>()?;
 
@@ -352,7 +591,7 @@ impl Client {
                     )));
                 }
 
-                path_parts.push(folder.filename);
+                path_parts.push(folder.local_name(&self.context));
                 if let Some(folder_parent_id) = folder.parent_id {
                     last_seen_parent_id = folder_parent_id;
                 } else {
@@ -374,7 +613,7 @@ impl Client {
                 }
             }
         } else {
-            Ok(content.filename.clone())
+            Ok(content.local_name(&self.context))
         }
     }
 
@@ -383,64 +622,83 @@ impl Client {
         content_id: ContentId,
         file_name: String,
     ) -> Result<Response, ClientError> {
-        Ok(self
-            .client
-            .request(
+        let response = self.send(
+            self.request(
                 Method::GET,
                 self.context
                     .workspace_url(&format!("files/{}/raw/{}", content_id, file_name)),
-            )
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .send()?)
+            ),
+        )?;
+
+        let response_status_code = response.status().as_u16();
+        match response_status_code {
+            200 => Ok(response),
+            _ => {
+                let text = response.text()?;
+                Err(ClientError::UnexpectedResponse(format!(
+                    "Unexpected response status {} when downloading content {} : {}",
+                    response_status_code, content_id, text,
+                )))
+            }
+        }
     }
 
+    // Contents of all pages
     pub fn get_remote_contents(
         &self,
         parent_id: Option<ParentIdParameter>,
     ) -> Result<Vec<RemoteContent>, ClientError> {
-        let url = match &parent_id {
-            Some(parent_id) => self.context.workspace_url(&format!(
-                "contents?parent_ids={}",
-                parent_id.to_parameter_value()
-            )),
-            None => self.context.workspace_url("contents"),
-        };
+        let mut contents = vec![];
+        let mut page_token: Option<String> = None;
 
-        let response = self
-            .client
-            .request(Method::GET, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .send()?;
-
-        let status_code = response.status().as_u16();
-        match status_code {
-            200 => Ok(response
-                .json::<Paginated<Vec<RemoteContent>>>()?
-                .items
-                .into_iter()
-                .filter(|c| ContentType::from_str(&c.content_type.as_str()).is_some())
-                .collect::<Vec<RemoteContent>>()),
-            _ => {
-                let text = response.text()?;
-                Err(ClientError::UnexpectedResponse(format!(
-                    "Unexpected response status {} during fetching contents (parent_ids={:?}) : {}",
-                    status_code, parent_id, text
-                )))
+        loop {
+            let mut parameters = vec![];
+            if let Some(parent_id) = &parent_id {
+                parameters.push(format!("parent_ids={}", parent_id.to_parameter_value()));
+            }
+            if let Some(page_token) = &page_token {
+                parameters.push(format!("page_token={}", page_token));
+            }
+            let url = if parameters.is_empty() {
+                self.context.workspace_url("contents")
+            } else {
+                self.context
+                    .workspace_url(&format!("contents?{}", parameters.join("&")))
+            };
+
+            let response = self.send(self.request(Method::GET, url))?;
+
+            let status_code = response.status().as_u16();
+            let page = match status_code {
+                200 => response.json::<Paginated<Vec<RemoteContent>>>()?,
+                _ => {
+                    let text = response.text()?;
+                    return Err(ClientError::UnexpectedResponse(format!(
+                        "Unexpected response status {} during fetching contents (parent_ids={:?}) : {}",
+                        status_code, parent_id, text
+                    )));
+                }
+            };
+
+            contents.extend(
+                page.items
+                    .into_iter()
+                    .filter(|c| ContentType::from_str(c.content_type.as_str()).is_ok()),
+            );
+            if !page.has_next {
+                break;
             }
+            page_token = Some(page.next_page_token);
         }
+
+        Ok(contents)
     }
 
     pub fn move_content(
         &self,
         content_id: ContentId,
         new_parent_id: ParentIdParameter,
-    ) -> Result<(), ClientError> {
+    ) -> Result<Option<RevisionId>, ClientError> {
         let url = self
             .context
             .workspace_url(&format!("contents/{}/move", content_id));
@@ -453,18 +711,15 @@ impl Client {
             "new_workspace_id".to_string(),
             json!(self.context.workspace_id),
         );
-        let response = self
-            .client
-            .request(Method::PUT, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .json(&data)
-            .send()?;
+        let response = self.send(self.request(Method::PUT, url).json(&data))?;
         let response_status_code = response.status().as_u16();
         match response_status_code {
-            200 => Ok(()),
+            // Moved content revision, when Tracim answers it
+            200 => Ok(response
+                .json::<Value>()
+                .ok()
+                .and_then(|value| value["current_revision_id"].as_i64())
+                .map(|revision_id| revision_id as RevisionId)),
             _ => {
                 let text = response.text()?;
                 Err(ClientError::UnexpectedResponse(format!(
@@ -475,11 +730,44 @@ impl Client {
         }
     }
 
+    // Rename content after its local file name. Files carry both a label and a filename
+    // which must stay consistent : label is derived from file name with name strategy,
+    // filename is the file name. Folders only have a label.
     pub fn update_content_file_name(
         &self,
         content_id: ContentId,
         new_file_name: String,
         content_type: ContentType,
+    ) -> Result<RevisionId, ClientError> {
+        let label = self
+            .context
+            .name_strategy
+            .label_from_file_name(&new_file_name, &content_type);
+        let mut data = Map::new();
+        data.insert("label".to_string(), json!(label));
+        if content_type == ContentType::File {
+            data.insert("filename".to_string(), json!(new_file_name));
+        }
+        self.update_content_properties(content_id, content_type, data)
+    }
+
+    // Change label only, file name is kept
+    pub fn update_content_label(
+        &self,
+        content_id: ContentId,
+        label: String,
+        content_type: ContentType,
+    ) -> Result<RevisionId, ClientError> {
+        let mut data = Map::new();
+        data.insert("label".to_string(), json!(label));
+        self.update_content_properties(content_id, content_type, data)
+    }
+
+    fn update_content_properties(
+        &self,
+        content_id: ContentId,
+        content_type: ContentType,
+        data: Map<String, Value>,
     ) -> Result<RevisionId, ClientError> {
         let url = if content_type == ContentType::Folder {
             self.context
@@ -488,17 +776,7 @@ impl Client {
             self.context.workspace_url(&format!("files/{}", content_id))
         };
         log::debug!("Update file {} on remote with url {}", content_id, &url);
-        let mut data = Map::new();
-        data.insert("label".to_string(), json!(new_file_name));
-        let response = self
-            .client
-            .request(Method::PUT, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .json(&data)
-            .send()?;
+        let response = self.send(self.request(Method::PUT, url).json(&data))?;
 
         let response_status_code = response.status().as_u16();
         match response_status_code {
@@ -529,14 +807,7 @@ impl Client {
 
     pub fn get_user_id(&self) -> Result<i32, ClientError> {
         let url = format!("{}auth/whoami", self.context.base_address);
-        let response = self
-            .client
-            .request(Method::GET, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
-            .send()?;
+        let response = self.send(self.request(Method::GET, url))?;
 
         let response_status_code = response.status().as_u16();
         match response_status_code {
@@ -564,6 +835,148 @@ impl Client {
         }
     }
 
+    pub fn get_workspace_label(&self) -> Result<String, ClientError> {
+        let url = format!(
+            "{}workspaces/{}",
+            self.context.base_address, self.context.workspace_id
+        );
+        let response = self.send(self.request(Method::GET, url))?;
+
+        let response_status_code = response.status().as_u16();
+        match response_status_code {
+            200 => {
+                let value = response.json::<Value>()?;
+                let label = value["label"]
+                    .as_str()
+                    .ok_or(Error::UnexpectedError(format!(
+                        "Response content object do not contains a label : {:?}",
+                        value
+                    )))?;
+                Ok(label.to_string())
+            }
+            _ => {
+                let text = response.text()?;
+                Err(ClientError::UnexpectedResponse(format!(
+                    "Unexpected response status {} : {}",
+                    response_status_code, text,
+                )))
+            }
+        }
+    }
+
+    // Local clock minus Tracim server clock (as given by response "Date" header, at
+    // middle of request), none if server do not give it. Precision is about a second.
+    pub fn get_clock_skew_ms(&self) -> Result<Option<i64>, ClientError> {
+        let url = format!("{}auth/whoami", self.context.base_address);
+        let sent_at = util::system_time_to_timestamp(SystemTime::now());
+        let response = self.send(self.request(Method::GET, url))?;
+        let received_at = util::system_time_to_timestamp(SystemTime::now());
+
+        let server_timestamp = response
+            .headers()
+            .get(DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.timestamp_millis());
+        Ok(server_timestamp
+            .map(|server_timestamp| sent_at + (received_at - sent_at) / 2 - server_timestamp))
+    }
+
+    // Clock skew compared to warning threshold of context
+    pub fn check_clock(&self) -> Result<ClockCheck, ClientError> {
+        let warning_ms = self.context.clock_skew_warning_s * 1000;
+        Ok(match self.get_clock_skew_ms()? {
+            Some(clock_skew_ms) if clock_skew_ms.unsigned_abs() > warning_ms => ClockCheck::Skewed(
+                clock_skew_ms,
+                format!(
+                    "Local clock is {} s {} Tracim server clock, check local time settings",
+                    clock_skew_ms.abs() / 1000,
+                    if clock_skew_ms > 0 {
+                        "ahead of"
+                    } else {
+                        "behind"
+                    }
+                ),
+            ),
+            Some(clock_skew_ms) => ClockCheck::InSync(clock_skew_ms),
+            None => ClockCheck::Unknown,
+        })
+    }
+
+    // Id of last event known by Tracim for this user (messages are listed most recent
+    // first)
+    pub fn get_last_event_id(&self, user_id: i32) -> Result<Option<i32>, ClientError> {
+        let url = format!(
+            "{}users/{}/messages?count=1",
+            self.context.base_address, user_id
+        );
+        let response = self.send(self.request(Method::GET, url))?;
+
+        let response_status_code = response.status().as_u16();
+        match response_status_code {
+            200 => Ok(response
+                .json::<Paginated<Vec<RemoteEvent>>>()?
+                .items
+                .iter()
+                .map(|event| event.event_id)
+                .max()),
+            _ => {
+                let text = response.text()?;
+                Err(ClientError::UnexpectedResponse(format!(
+                    "Unexpected response status {} when getting last event : {}",
+                    response_status_code, text,
+                )))
+            }
+        }
+    }
+
+    // Events of this user after given event id, oldest first
+    pub fn get_events_since(
+        &self,
+        user_id: i32,
+        after_event_id: i32,
+    ) -> Result<Vec<RemoteEvent>, ClientError> {
+        let mut events = vec![];
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut url = format!(
+                "{}users/{}/messages?after_event_id={}",
+                self.context.base_address, user_id, after_event_id
+            );
+            if let Some(page_token) = &page_token {
+                url.push_str(&format!("&page_token={}", page_token));
+            }
+            let response = self.send(self.request(Method::GET, url))?;
+
+            let status_code = response.status().as_u16();
+            let page = match status_code {
+                200 => response.json::<Paginated<Vec<RemoteEvent>>>()?,
+                _ => {
+                    let text = response.text()?;
+                    return Err(ClientError::UnexpectedResponse(format!(
+                        "Unexpected response status {} during fetching events after {} : {}",
+                        status_code, after_event_id, text
+                    )));
+                }
+            };
+
+            // Older Tracim ignore the after_event_id parameter
+            events.extend(
+                page.items
+                    .into_iter()
+                    .filter(|event| event.event_id > after_event_id),
+            );
+            if !page.has_next {
+                break;
+            }
+            page_token = Some(page.next_page_token);
+        }
+
+        events.sort_by_key(|event| event.event_id);
+        Ok(events)
+    }
+
     pub async fn get_user_live_messages_response(
         &self,
         user_id: i32,
@@ -572,17 +985,26 @@ impl Client {
             "{}users/{}/live_messages",
             self.context.base_address, user_id
         );
-        let response = reqwest::Client::new()
-            .request(Method::GET, url)
-            .basic_auth(
-                self.context.username.clone(),
-                Some(self.context.password.clone()),
-            )
+        self.context.request_pacer.wait_async().await;
+        log::trace!("Request {} {}", Method::GET, url);
+        // Compressed event stream could be delayed by decoder buffering
+        let request_builder = reqwest::Client::builder()
+            .gzip(false)
+            .default_headers(default_headers(&self.context)?)
+            .build()?
+            .request(Method::GET, url);
+        let response = request_builder
+            .headers(self.auth_headers.clone())
             .send()
             .await?;
+        log::trace!("Response {} for live messages", response.status());
         let response_status_code = response.status().as_u16();
         match response_status_code {
             200 => Ok(response),
+            401 => Err(ClientError::Unauthorized(format!(
+                "Tracim refused credentials of {} for live messages",
+                self.context.username
+            ))),
             _ => {
                 let text = response.text().await?;
                 Err(ClientError::UnexpectedResponse(format!(
@@ -593,3 +1015,356 @@ impl Client {
         }
     }
 }
+
+// User agent and extra headers of context, sent with every request
+pub fn default_headers(context: &Context) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&context.user_agent).map_err(|error| {
+            Error::StartupError(format!(
+                "Invalid user agent {:?} : {}",
+                context.user_agent, error
+            ))
+        })?,
+    );
+    for (name, value) in &context.extra_headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|error| {
+            Error::StartupError(format!("Invalid header name {:?} : {}", name, error))
+        })?;
+        let header_value = HeaderValue::from_str(value).map_err(|error| {
+            Error::StartupError(format!("Invalid value of header {:?} : {}", name, error))
+        })?;
+        headers.append(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+// Credentials sent with every request : API key acting as user, or user password
+fn auth_headers(context: &Context) -> Result<HeaderMap, Error> {
+    let invalid = |error| Error::StartupError(format!("Invalid credentials : {}", error));
+    let mut headers = HeaderMap::new();
+    match &context.api_key {
+        Some(api_key) => {
+            let mut api_key = HeaderValue::from_str(api_key).map_err(invalid)?;
+            api_key.set_sensitive(true);
+            headers.insert(API_KEY_HEADER, api_key);
+            headers.insert(
+                API_LOGIN_HEADER,
+                HeaderValue::from_str(&context.username).map_err(invalid)?,
+            );
+        }
+        None => {
+            let credentials = STANDARD.encode(format!("{}:{}", context.username, context.password));
+            let mut authorization =
+                HeaderValue::from_str(&format!("Basic {}", credentials)).map_err(invalid)?;
+            authorization.set_sensitive(true);
+            headers.insert(AUTHORIZATION, authorization);
+        }
+    }
+    Ok(headers)
+}
+
+// Upload refused for lack of space or because file is too large, from 400 response body
+fn upload_rejection(absolute_file_path: &str, text: &str) -> Option<ClientError> {
+    let message = format!("{} : {}", absolute_file_path, text);
+    match response_error_code(text).map(|code| code as u16) {
+        Some(FILE_SIZE_OVER_WORKSPACE_EMPTY_SPACE_ERR_CODE)
+        | Some(FILE_SIZE_OVER_OWNER_EMPTY_SPACE_ERR_CODE) => {
+            Some(ClientError::QuotaExceeded(message))
+        }
+        Some(FILE_SIZE_OVER_MAX_LIMIT_ERR_CODE) => Some(ClientError::FileTooLarge(message)),
+        _ => None,
+    }
+}
+
+// Tracim error code of an error response body
+fn response_error_code(text: &str) -> Option<u64> {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|value| value["code"].as_u64())
+}
+
+// Headers as printable text, without credentials
+fn redacted_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if name == AUTHORIZATION || name == API_KEY_HEADER {
+                "<redacted>".to_string()
+            } else {
+                value.to_str().unwrap_or("<binary>").to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::{self, StubResponse, TracimStub};
+    use crate::types::NameStrategy;
+
+    #[test]
+    fn clients_of_same_context_reuse_one_connection() {
+        let stub = TracimStub::start();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 1, None, "file.txt", 0),
+            ),
+        );
+        let folder = testing::temp_folder("connection-reuse");
+        let context = testing::context(&stub, &folder);
+
+        for _ in 0..3 {
+            // Each handler or watcher thread build its own client
+            let client = Client::new(context.clone()).unwrap();
+            client.get_remote_content(1).unwrap();
+            client.get_remote_content(1).unwrap();
+        }
+
+        assert_eq!(stub.requests().len(), 6);
+        assert_eq!(stub.connections(), 1);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn find_by_name_requires_exact_file_name_within_parent() {
+        let stub = TracimStub::start();
+        let contents = [
+            testing::remote_content_json(1, 1, Some(9), "Report.txt", 0),
+            testing::remote_content_json(2, 2, Some(9), "report", 0),
+            testing::remote_content_json(3, 3, Some(8), "report.txt", 0),
+            testing::remote_content_json(4, 4, Some(9), "report.txt", 0),
+        ];
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents"),
+            StubResponse::json(
+                200,
+                &format!(
+                    r#"{{"has_next": false, "has_previous": false, "items": [{}],
+                    "next_page_token": "", "per_page": 0, "previous_page_token": ""}}"#,
+                    contents.join(", ")
+                ),
+            ),
+        );
+        let folder = testing::temp_folder("find-by-name");
+        let client = Client::new(testing::context(&stub, &folder)).unwrap();
+
+        let found = client.find_by_name("report.txt", Some(9)).unwrap();
+        let not_found = client.find_by_name("REPORT.TXT", Some(9)).unwrap();
+
+        assert_eq!(
+            found.map(|remote_content| remote_content.content_id),
+            Some(4)
+        );
+        assert!(not_found.is_none());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    fn contents_page(contents: &[String]) -> StubResponse {
+        StubResponse::json(
+            200,
+            &format!(
+                r#"{{"has_next": false, "has_previous": false, "items": [{}],
+                "next_page_token": "", "per_page": 0, "previous_page_token": ""}}"#,
+                contents.join(", ")
+            ),
+        )
+    }
+
+    fn folder_json(content_id: ContentId, parent_id: Option<i32>, name: &str) -> String {
+        let mut folder: serde_json::Value = serde_json::from_str(&testing::remote_content_json(
+            content_id, 1, parent_id, name, 0,
+        ))
+        .unwrap();
+        folder["content_type"] = serde_json::json!("folder");
+        folder["label"] = serde_json::json!(name);
+        folder.to_string()
+    }
+
+    #[test]
+    fn resolve_path_uses_index_then_browses_remote() {
+        let stub = TracimStub::start();
+        // Stub ignores query string : every level lists the same contents
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents"),
+            contents_page(&[
+                folder_json(9, None, "docs"),
+                testing::remote_content_json(4, 4, Some(9), "report.txt", 0),
+            ]),
+        );
+        let folder = testing::temp_folder("resolve-path");
+        let client = Client::new(testing::context(&stub, &folder)).unwrap();
+        let connection = testing::connection();
+        DatabaseOperation::new(&connection)
+            .insert_new_file("indexed.txt".to_string(), 0, 7, 1, None, false)
+            .unwrap();
+
+        let indexed = client.resolve_path(&connection, "indexed.txt").unwrap();
+        assert_eq!(indexed, Some(7));
+        assert!(stub.requests().is_empty());
+
+        let remote = client.resolve_path(&connection, "docs/report.txt").unwrap();
+        let missing = client
+            .resolve_path(&connection, "docs/missing.txt")
+            .unwrap();
+
+        assert_eq!(remote, Some(4));
+        assert_eq!(missing, None);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn resolve_content_uses_index_then_builds_remote_path() {
+        let stub = TracimStub::start();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/4"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(4, 4, Some(9), "report.txt", 0),
+            ),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/9"),
+            StubResponse::json(200, &folder_json(9, None, "docs")),
+        );
+        let mut deleted: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(5, 5, None, "old.txt", 0)).unwrap();
+        deleted["is_deleted"] = serde_json::json!(true);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/5"),
+            StubResponse::json(200, &deleted.to_string()),
+        );
+        let folder = testing::temp_folder("resolve-content");
+        let client = Client::new(testing::context(&stub, &folder)).unwrap();
+        let connection = testing::connection();
+        DatabaseOperation::new(&connection)
+            .insert_new_file("indexed.txt".to_string(), 0, 7, 1, None, false)
+            .unwrap();
+
+        let indexed = client.resolve_content(&connection, 7).unwrap();
+        assert_eq!(indexed, Some("indexed.txt".to_string()));
+        assert!(stub.requests().is_empty());
+
+        assert_eq!(
+            client.resolve_content(&connection, 4).unwrap(),
+            Some("docs/report.txt".to_string())
+        );
+        assert_eq!(client.resolve_content(&connection, 5).unwrap(), None);
+        assert_eq!(client.resolve_content(&connection, 6).unwrap(), None);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn clock_check_compares_skew_with_warning_threshold() {
+        let folder = testing::temp_folder("clock-check");
+        let check = |behind_s: i64| {
+            let stub = TracimStub::start();
+            if behind_s != 0 {
+                testing::server_clock_behind(&stub, behind_s);
+            } else {
+                stub.on(
+                    "GET",
+                    "/api/auth/whoami",
+                    StubResponse::json(200, r#"{"user_id": 1}"#),
+                );
+            }
+            let mut context = testing::context(&stub, &folder);
+            context.clock_skew_warning_s = 60;
+            Client::new(context).unwrap().check_clock().unwrap()
+        };
+
+        assert!(matches!(
+            check(3600),
+            ClockCheck::Skewed(_, warning) if warning.contains("ahead of")
+        ));
+        assert!(matches!(
+            check(-3600),
+            ClockCheck::Skewed(_, warning) if warning.contains("behind")
+        ));
+        assert!(matches!(check(30), ClockCheck::InSync(_)));
+        assert_eq!(check(0), ClockCheck::Unknown);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn renamed_file_sends_strategy_label_and_file_name() {
+        let folder = testing::temp_folder("rename-body");
+        let sent_properties = |name_strategy: NameStrategy, content_type: ContentType| {
+            let stub = TracimStub::start();
+            for path in ["files/1", "folders/1"] {
+                stub.on(
+                    "PUT",
+                    &stub.workspace_path(path),
+                    StubResponse::json(200, r#"{"last_revision_id": 8}"#),
+                );
+            }
+            let mut context = testing::context(&stub, &folder);
+            context.name_strategy = name_strategy;
+            let revision_id = Client::new(context)
+                .unwrap()
+                .update_content_file_name(1, "report.txt".to_string(), content_type)
+                .unwrap();
+            assert_eq!(revision_id, 8);
+            serde_json::from_slice::<Value>(&stub.requests()[0].body).unwrap()
+        };
+
+        assert_eq!(
+            sent_properties(NameStrategy::LabelWithExtension, ContentType::File),
+            json!({"label": "report", "filename": "report.txt"})
+        );
+        assert_eq!(
+            sent_properties(NameStrategy::Filename, ContentType::File),
+            json!({"label": "report.txt", "filename": "report.txt"})
+        );
+        assert_eq!(
+            sent_properties(NameStrategy::LabelWithExtension, ContentType::Folder),
+            json!({"label": "report.txt"})
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn requests_carry_api_key_or_user_credentials() {
+        let folder = testing::temp_folder("credentials");
+        let sent_headers = |api_key: Option<&str>| {
+            let stub = TracimStub::start();
+            let mut context = testing::context(&stub, &folder);
+            context.api_key = api_key.map(str::to_string);
+            // Not found answer is enough to see request headers
+            let _ = Client::new(context).unwrap().get_remote_content(1);
+            stub.requests()[0].headers.clone()
+        };
+
+        let api_key_headers = sent_headers(Some("secret-key"));
+        let password_headers = sent_headers(None);
+
+        assert_eq!(
+            api_key_headers.get("tracim-api-key").map(String::as_str),
+            Some("secret-key")
+        );
+        assert_eq!(
+            api_key_headers.get("tracim-api-login").map(String::as_str),
+            Some("user")
+        );
+        assert!(!api_key_headers.contains_key("authorization"));
+        assert_eq!(
+            password_headers.get("authorization").map(String::as_str),
+            Some("Basic dXNlcjpwYXNzd29yZA==")
+        );
+        assert!(!password_headers.contains_key("tracim-api-key"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
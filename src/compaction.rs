@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::database::DatabaseOperation;
+use crate::operation::OperationalMessage;
+use crate::types::RelativeFilePath;
+
+// Queue accumulated while offline or during a rescan often contains redundant local
+// messages of the same path. They are rewritten before execution :
+//  - modification after creation or modification of the same path is dropped
+//  - creation then deletion of the same path are both dropped
+//  - deletion then creation of the same path become a modification, if path is still
+//    the same kind (see `replaced`)
+// Messages are only rewritten when no message in between concerns the same path, one
+// of its ancestors or descendants. Remote messages and exit are not rewritten and stop
+// rewriting across them. Protected messages (planned ignores) are never rewritten.
+pub fn compact(
+    messages: impl IntoIterator<Item = OperationalMessage>,
+    protected: &[OperationalMessage],
+    kind_unchanged: &dyn Fn(&str) -> bool,
+) -> Vec<OperationalMessage> {
+    let mut compacted: Vec<Option<OperationalMessage>> = vec![];
+    // Position of last message concerning a path, and concerning one of its descendants
+    let mut last_on_path: HashMap<RelativeFilePath, usize> = HashMap::new();
+    let mut last_below_path: HashMap<RelativeFilePath, usize> = HashMap::new();
+    let mut last_barrier: Option<usize> = None;
+
+    for message in messages {
+        let relative_paths = match &message {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path) => vec![relative_path.clone()],
+            OperationalMessage::RenamedLocalFile(before, after) => {
+                vec![before.clone(), after.clone()]
+            }
+            _ => {
+                last_barrier = Some(compacted.len());
+                compacted.push(Some(message));
+                continue;
+            }
+        };
+
+        if relative_paths.len() == 1 && !protected.contains(&message) {
+            let relative_path = &relative_paths[0];
+            let previous = last_on_path
+                .get(relative_path)
+                .copied()
+                .filter(|position| {
+                    last_barrier.is_none_or(|barrier| barrier < *position)
+                        && last_below_path
+                            .get(relative_path)
+                            .is_none_or(|below| below < position)
+                        && ancestors(relative_path).iter().all(|ancestor| {
+                            last_on_path
+                                .get(ancestor)
+                                .is_none_or(|on_ancestor| on_ancestor < position)
+                        })
+                })
+                .filter(|position| {
+                    compacted[*position]
+                        .as_ref()
+                        .is_some_and(|previous| !protected.contains(previous))
+                });
+            let rewritten = previous.and_then(|position| {
+                let previous = compacted[position].as_ref()?;
+                let rewritten = rewrite(previous, &message, kind_unchanged)?;
+                log::debug!("Compact {:?} then {:?}", previous, message);
+                Some((position, rewritten))
+            });
+            if let Some((position, rewritten)) = rewritten {
+                compacted[position] = rewritten;
+                continue;
+            }
+        }
+
+        let position = compacted.len();
+        for relative_path in relative_paths {
+            for ancestor in ancestors(&relative_path) {
+                last_below_path.insert(ancestor, position);
+            }
+            last_on_path.insert(relative_path, position);
+        }
+        compacted.push(Some(message));
+    }
+
+    compacted.into_iter().flatten().collect()
+}
+
+// Replacement of previous message when both can be rewritten as one (None when both
+// are dropped)
+fn rewrite(
+    previous: &OperationalMessage,
+    message: &OperationalMessage,
+    kind_unchanged: &dyn Fn(&str) -> bool,
+) -> Option<Option<OperationalMessage>> {
+    match (previous, message) {
+        (
+            OperationalMessage::NewLocalFile(_) | OperationalMessage::ModifiedLocalFile(_),
+            OperationalMessage::ModifiedLocalFile(_),
+        ) => Some(Some(previous.clone())),
+        (OperationalMessage::NewLocalFile(_), OperationalMessage::DeletedLocalFile(_)) => {
+            Some(None)
+        }
+        (
+            OperationalMessage::DeletedLocalFile(relative_path),
+            OperationalMessage::NewLocalFile(_),
+        ) => replaced(relative_path, kind_unchanged(relative_path)).map(Some),
+        _ => None,
+    }
+}
+
+// Path deleted (or moved aside) then created again is its content modified, as editors
+// save files. It is not when path kind changed : a file replaced by a folder must be
+// trashed and the folder created (and the opposite).
+pub fn replaced(relative_path: &str, kind_unchanged: bool) -> Option<OperationalMessage> {
+    if kind_unchanged {
+        Some(OperationalMessage::ModifiedLocalFile(
+            relative_path.to_string(),
+        ))
+    } else {
+        None
+    }
+}
+
+// Disk path is the same kind (file or folder) as indexed one. Kind is considered
+// unchanged when one is not known (path not indexed, or no longer on disk).
+pub fn kind_unchanged(connection: &Connection, folder_path: &Path, relative_path: &str) -> bool {
+    let database_operation = DatabaseOperation::new(connection);
+    let indexed_is_directory = database_operation
+        .get_content_id_from_path(relative_path.to_string())
+        .ok()
+        .and_then(|content_id| database_operation.get_is_directory(content_id).ok())
+        .flatten();
+    let disk_is_directory = fs::symlink_metadata(folder_path.join(relative_path))
+        .ok()
+        .map(|metadata| metadata.is_dir());
+    match (indexed_is_directory, disk_is_directory) {
+        (Some(indexed_is_directory), Some(disk_is_directory)) => {
+            indexed_is_directory == disk_is_directory
+        }
+        _ => true,
+    }
+}
+
+fn ancestors(relative_path: &str) -> Vec<RelativeFilePath> {
+    Path::new(relative_path)
+        .ancestors()
+        .skip(1)
+        .filter_map(|ancestor| ancestor.to_str())
+        .filter(|ancestor| !ancestor.is_empty())
+        .map(|ancestor| ancestor.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use OperationalMessage::{
+        DeletedLocalFile, ModifiedLocalFile, ModifiedRemoteFile, NewLocalFile, RenamedLocalFile,
+    };
+
+    fn path(relative_path: &str) -> RelativeFilePath {
+        relative_path.to_string()
+    }
+
+    #[test]
+    fn compact_rewrites_redundant_messages_of_a_path() {
+        // Name, messages, protected messages, paths which changed of kind, expected
+        #[allow(clippy::type_complexity)]
+        let cases: Vec<(
+            &str,
+            Vec<OperationalMessage>,
+            Vec<OperationalMessage>,
+            Vec<&str>,
+            Vec<OperationalMessage>,
+        )> = vec![
+            (
+                "modification after creation",
+                vec![NewLocalFile(path("f")), ModifiedLocalFile(path("f"))],
+                vec![],
+                vec![],
+                vec![NewLocalFile(path("f"))],
+            ),
+            (
+                "modification after modification",
+                vec![ModifiedLocalFile(path("f")), ModifiedLocalFile(path("f"))],
+                vec![],
+                vec![],
+                vec![ModifiedLocalFile(path("f"))],
+            ),
+            (
+                "creation then deletion",
+                vec![NewLocalFile(path("f")), DeletedLocalFile(path("f"))],
+                vec![],
+                vec![],
+                vec![],
+            ),
+            (
+                "deletion then creation",
+                vec![DeletedLocalFile(path("f")), NewLocalFile(path("f"))],
+                vec![],
+                vec![],
+                vec![ModifiedLocalFile(path("f"))],
+            ),
+            (
+                "deletion then creation of another kind",
+                vec![DeletedLocalFile(path("f")), NewLocalFile(path("f"))],
+                vec![],
+                vec!["f"],
+                vec![DeletedLocalFile(path("f")), NewLocalFile(path("f"))],
+            ),
+            (
+                "other paths",
+                vec![
+                    NewLocalFile(path("f")),
+                    ModifiedLocalFile(path("g")),
+                    ModifiedLocalFile(path("f")),
+                ],
+                vec![],
+                vec![],
+                vec![NewLocalFile(path("f")), ModifiedLocalFile(path("g"))],
+            ),
+            (
+                "ancestor in between",
+                vec![
+                    DeletedLocalFile(path("a/f")),
+                    NewLocalFile(path("a")),
+                    NewLocalFile(path("a/f")),
+                ],
+                vec![],
+                vec![],
+                vec![
+                    DeletedLocalFile(path("a/f")),
+                    NewLocalFile(path("a")),
+                    NewLocalFile(path("a/f")),
+                ],
+            ),
+            (
+                "descendant in between",
+                vec![
+                    NewLocalFile(path("a")),
+                    NewLocalFile(path("a/f")),
+                    DeletedLocalFile(path("a")),
+                ],
+                vec![],
+                vec![],
+                vec![
+                    NewLocalFile(path("a")),
+                    NewLocalFile(path("a/f")),
+                    DeletedLocalFile(path("a")),
+                ],
+            ),
+            (
+                "rename in between",
+                vec![
+                    NewLocalFile(path("f")),
+                    RenamedLocalFile(path("f"), path("g")),
+                    ModifiedLocalFile(path("f")),
+                ],
+                vec![],
+                vec![],
+                vec![
+                    NewLocalFile(path("f")),
+                    RenamedLocalFile(path("f"), path("g")),
+                    ModifiedLocalFile(path("f")),
+                ],
+            ),
+            (
+                "remote message in between",
+                vec![
+                    NewLocalFile(path("f")),
+                    ModifiedRemoteFile(1),
+                    ModifiedLocalFile(path("f")),
+                ],
+                vec![],
+                vec![],
+                vec![
+                    NewLocalFile(path("f")),
+                    ModifiedRemoteFile(1),
+                    ModifiedLocalFile(path("f")),
+                ],
+            ),
+            (
+                "protected message",
+                vec![NewLocalFile(path("f")), DeletedLocalFile(path("f"))],
+                vec![DeletedLocalFile(path("f"))],
+                vec![],
+                vec![NewLocalFile(path("f")), DeletedLocalFile(path("f"))],
+            ),
+        ];
+
+        for (name, messages, protected, kind_changed, expected) in cases {
+            let compacted = compact(messages, &protected, &|relative_path| {
+                !kind_changed.contains(&relative_path)
+            });
+            assert_eq!(compacted, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn kind_is_compared_between_index_and_disk() {
+        let folder = testing::temp_folder("kind-unchanged");
+        fs::write(folder.join("file"), b"content").unwrap();
+        fs::create_dir(folder.join("folder")).unwrap();
+        fs::create_dir(folder.join("file_became_folder")).unwrap();
+        let connection = testing::connection();
+        for (content_id, (relative_path, is_directory)) in [
+            ("file", false),
+            ("folder", true),
+            ("file_became_folder", false),
+            ("deleted", false),
+        ]
+        .iter()
+        .enumerate()
+        {
+            DatabaseOperation::new(&connection)
+                .insert_new_file(
+                    relative_path.to_string(),
+                    0,
+                    content_id as i32 + 1,
+                    1,
+                    None,
+                    *is_directory,
+                )
+                .unwrap();
+        }
+        let kind_unchanged =
+            |relative_path: &str| kind_unchanged(&connection, &folder, relative_path);
+
+        assert!(kind_unchanged("file"));
+        assert!(kind_unchanged("folder"));
+        assert!(!kind_unchanged("file_became_folder"));
+        assert!(kind_unchanged("deleted"));
+        assert!(kind_unchanged("not_indexed"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
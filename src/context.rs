@@ -1,17 +1,204 @@
-use std::path::Path;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use glob::{Pattern, PatternError};
+use sha2::{Digest, Sha256};
+
+use crate::client::ConnectionPool;
+use crate::database::SynchronousMode;
 use crate::error::Error;
+use crate::event::EventBus;
+use crate::hook::Hooks;
+use crate::metrics::Metrics;
+use crate::pacing::RequestPacer;
+use crate::status::{SharedReport, SharedStatus};
+use crate::types::{
+    AuthorFilter, CaseCollision, ConflictStrategy, ContentId, DownloadExecutablePolicy,
+    FilesystemProfile, LeftoverPolicy, NameStrategy, ParentCreation, RejectionPolicy,
+    RelativeFilePath, RemoteMode,
+};
 use crate::util;
 
+const DEFAULT_TIMESTAMP_TOLERANCE_MS: u64 = 2000;
+const DEFAULT_MAX_NAME_BYTES: usize = 255;
+const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 3;
+const DEFAULT_FAILURE_COOLDOWN_S: u64 = 3600;
+const DEFAULT_LOOP_MAX_OPERATIONS: u32 = 30;
+const DEFAULT_LOOP_WINDOW_S: u64 = 60;
+const DEFAULT_MISSING_FOLDER_TIMEOUT_S: u64 = 300;
+const DEFAULT_LOCAL_POLL_INTERVAL_S: u64 = 60;
+const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 1000;
+const DEFAULT_WATCH_CHANNEL_CAPACITY: usize = 10_000;
+// Network file system profile : timestamps by second, longer stability and debounce
+// windows (a write and its following ones can be seen more than a second apart)
+const NETWORK_TIMESTAMP_GRANULARITY_MS: u64 = 1000;
+const NETWORK_UPLOAD_STABLE_MS: u64 = 3000;
+const NETWORK_WATCH_DEBOUNCE_MS: u64 = 2000;
+const DEFAULT_SLOW_OPERATION_S: u64 = 30;
+const DEFAULT_CLOCK_SKEW_WARNING_S: u64 = 60;
+const DEFAULT_USER_AGENT: &str = concat!("trsync/", env!("CARGO_PKG_VERSION"));
+pub const METADATA_DIR_NAME: &str = ".trsync";
+pub const DATABASE_FILE_NAME: &str = "trsync.db";
+const LEGACY_DATABASE_FILE_NAME: &str = ".trsync.db";
+const DATABASES_DIR_NAME: &str = "databases";
+// Index records its synchronized folder, so that databases of removed folders can be
+// identified
+pub const FOLDER_PATH_META_KEY: &str = "folder_path";
+// Database file and files created next to it by sqlite
+const DATABASE_FILE_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+const TEMP_DIR_NAME: &str = "tmp";
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+const LIVE_STATUS_FILE_NAME: &str = "status.json";
+// Hidden files are ignored by this pattern, unless hidden files synchronization is enabled
+const HIDDEN_FILES_PATTERN: &str = ".*";
+const DEFAULT_IGNORE_PATTERNS: [&str; 4] = [HIDDEN_FILES_PATTERN, "~*", "#*", "*~"];
+
 #[derive(Debug, Clone)]
 pub struct Context {
     pub base_address: String,
     pub username: String,
     pub password: String,
+    // Authenticate with this Tracim API key acting as username, instead of password
+    pub api_key: Option<String>,
     pub folder_path: String,
     pub database_path: String,
     pub workspace_id: i32,
     pub exit_after_sync: bool,
+    // Disk and index timestamps differences under this value are considered as equal
+    pub timestamp_tolerance_ms: u64,
+    // Timestamps are truncated to this granularity before being compared
+    pub timestamp_granularity_ms: u64,
+    // When timestamps differ, compare file checksum with the indexed one before
+    // considering the file as modified
+    pub checksum_fallback: bool,
+    // Never delete anything, on disk or on remote
+    pub disable_deletions: bool,
+    // Attributes changes (Chmod events) are ignored, even if disk file timestamp or size
+    // differs from indexed ones
+    pub disable_chmod_check: bool,
+    // How local file names are derived from remote contents
+    pub name_strategy: NameStrategy,
+    // Longer remote names are truncated on disk
+    pub max_name_bytes: usize,
+    pub database_synchronous: SynchronousMode,
+    // What to do when a file changed both on disk and on remote
+    pub conflict_strategy: ConflictStrategy,
+    // What to do when a new local file parent folder is not on remote
+    pub parent_creation: ParentCreation,
+    // What to do with files not synchronized in a folder deleted on remote
+    pub leftover_policy: LeftoverPolicy,
+    // What to do with a new local file Tracim refuses to create
+    pub rejection_policy: RejectionPolicy,
+    // What to do with permissions of downloaded files, and with executable contents
+    pub download_executable_policy: DownloadExecutablePolicy,
+    // Local paths failing this many consecutive times are skipped until their disk file
+    // changes or cooldown expires (never skipped when 0)
+    pub max_consecutive_failures: u32,
+    pub failure_cooldown_s: u64,
+    // Content modified more than this many times during loop window is considered
+    // as looping between disk and remote, and is paused until restart (never when 0)
+    pub loop_max_operations: u32,
+    pub loop_window_s: u64,
+    // Disk files are uploaded once their size and modification time did not change
+    // during this interval (network and cloud mounts)
+    pub upload_stable_ms: Option<u64>,
+    // Operations longer than this are reported with their time spent by phase
+    pub slow_operation_s: u64,
+    // Local clock differing from Tracim server clock more than this is reported at
+    // startup
+    pub clock_skew_warning_s: u64,
+    // What to do when remote contents paths differ only by case
+    pub case_collision: CaseCollision,
+    pub status: SharedStatus,
+    // Failures and conflicts of this run, summarized at exit
+    pub report: SharedReport,
+    // Contents to not synchronize (to investigate or recover a problematic content),
+    // changed at runtime through trsync handle
+    pub excluded_content_ids: Arc<Mutex<HashSet<ContentId>>>,
+    // Remote contents not indexed yet are taken only if their current revision author
+    // passes this filter (folders are always taken, they hold contents of any author)
+    pub author_filter: AuthorFilter,
+    // Contents to download again at start, whatever their disk state
+    pub force_downloads: Vec<ContentId>,
+    // Disk files to upload again at start, whatever remote state
+    pub force_pushes: Vec<RelativeFilePath>,
+    // Synchronize only one path of hard linked files
+    pub detect_hard_links: bool,
+    // Files or folders with matching name are not synchronized
+    pub ignore_patterns: Vec<Pattern>,
+    pub sync_hidden_files: bool,
+    // Abort startup when a disk path can't be scanned, instead of skipping it
+    pub strict_scan: bool,
+    // Consider a disk file identical to remote content when their sizes are equal,
+    // without downloading remote content to compare checksums
+    pub adopt_by_size: bool,
+    // Synchronize remote labels with "user.tracim.label" extended attribute of disk
+    // files (only with filename name strategy, labels being file names otherwise)
+    pub sync_labels: bool,
+    pub remote_mode: RemoteMode,
+    // At startup, pull only remote changes since last seen Tracim event instead of
+    // listing all remote contents (fallback on full listing if not possible)
+    pub remote_since: bool,
+    // Write a JSON summary of failures and conflicts into this file at exit
+    pub report_file: Option<PathBuf>,
+    // Write health status as JSON into this file
+    pub health_file: Option<PathBuf>,
+    // Serve health status as JSON on this address
+    pub health_address: Option<String>,
+    // Index extended attributes of uploaded files and restore them on download
+    pub preserve_xattrs: bool,
+    pub hooks: Hooks,
+    pub metrics: Metrics,
+    // Limit requests per second made to Tracim (not limited by default)
+    pub request_pacer: RequestPacer,
+    pub connection_pool: ConnectionPool,
+    // Sent with every request, so that gateways and Tracim logs can identify trsync
+    pub user_agent: String,
+    // Additional (name, value) headers sent with every request (auth proxy, tracing)
+    pub extra_headers: Vec<(String, String)>,
+    // Serve Prometheus metrics on this address
+    #[cfg(feature = "metrics")]
+    pub metrics_listen: Option<String>,
+    // Drop remote events authored by synchronizing user, which are echoes of our own
+    // changes only if this account is not used elsewhere (web interface, other machine)
+    pub filter_own_events: bool,
+    // Sent with each request : Tracim echoes it in events caused by these requests
+    pub client_token: String,
+    // Set while remote watcher drops echoes of our own changes
+    pub own_events_filtered: Arc<AtomicBool>,
+    pub events: EventBus,
+    // Operations are not proceed while paused, changes are queued
+    pub paused: Arc<AtomicBool>,
+    // Set when trsync is stopping, so that watchers stop listening
+    pub stopping: Arc<AtomicBool>,
+    // Set while synchronized folder is missing (deleted, unmounted) : operations wait
+    // for it to come back, and synchronization fail after this timeout
+    pub folder_missing: Arc<AtomicBool>,
+    // Set when local watcher woke operational handler up, until handler reads local
+    // watcher channel : handler is woken once for many local watcher messages
+    pub local_changes_signaled: Arc<AtomicBool>,
+    pub missing_folder_timeout_s: u64,
+    // Disk is scanned for changes at this interval when folder can't be entirely
+    // watched (inotify limits reached) or when local polling is enabled
+    pub local_poll_interval_s: u64,
+    // Disk events are held this long to be correlated with following ones (renames,
+    // editors safe save) before being sent to operational handler
+    pub watch_debounce_ms: u64,
+    // When this many local watcher messages wait for operational handler, following
+    // disk events are dropped and disk is scanned again once handler caught up
+    pub watch_channel_capacity: usize,
+    // Disk is scanned for changes periodically, even if folder is watched
+    pub local_polling: bool,
+    // Sets local polling, timestamp granularity, upload stability and watch debounce
+    // windows for the file system of synchronized folder
+    pub filesystem_profile: FilesystemProfile,
+    // Trsync files outside of metadata directory, resolved once paths are set
+    owned_paths: Vec<PathBuf>,
 }
 
 impl Context {
@@ -26,18 +213,336 @@ impl Context {
     ) -> Result<Self, Error> {
         let protocol = if ssl { "https" } else { "http" };
         let base_address = format!("{}://{}/api/", protocol, address);
-        let database_path = util::path_to_string(&Path::new(&folder_path).join(".trsync.db"))?;
-        Ok(Self {
+        let database_path =
+            util::path_to_string(&default_database_path(&folder_path, workspace_id))?;
+        let mut context = Self {
             base_address,
             username,
             password,
+            api_key: None,
             folder_path,
             database_path,
             workspace_id,
             exit_after_sync,
+            timestamp_tolerance_ms: DEFAULT_TIMESTAMP_TOLERANCE_MS,
+            timestamp_granularity_ms: 1,
+            checksum_fallback: false,
+            disable_deletions: false,
+            disable_chmod_check: false,
+            name_strategy: NameStrategy::Filename,
+            max_name_bytes: DEFAULT_MAX_NAME_BYTES,
+            database_synchronous: SynchronousMode::Normal,
+            conflict_strategy: ConflictStrategy::KeepBoth,
+            parent_creation: ParentCreation::Create,
+            leftover_policy: LeftoverPolicy::Remove,
+            rejection_policy: RejectionPolicy::Fail,
+            download_executable_policy: DownloadExecutablePolicy::StripExecutable,
+            max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES,
+            failure_cooldown_s: DEFAULT_FAILURE_COOLDOWN_S,
+            loop_max_operations: DEFAULT_LOOP_MAX_OPERATIONS,
+            loop_window_s: DEFAULT_LOOP_WINDOW_S,
+            upload_stable_ms: None,
+            slow_operation_s: DEFAULT_SLOW_OPERATION_S,
+            clock_skew_warning_s: DEFAULT_CLOCK_SKEW_WARNING_S,
+            case_collision: CaseCollision::Auto,
+            status: SharedStatus::default(),
+            report: SharedReport::default(),
+            excluded_content_ids: Arc::new(Mutex::new(HashSet::new())),
+            author_filter: AuthorFilter::default(),
+            force_downloads: vec![],
+            force_pushes: vec![],
+            detect_hard_links: false,
+            ignore_patterns: DEFAULT_IGNORE_PATTERNS
+                .iter()
+                .map(|pattern| Pattern::new(pattern))
+                .collect::<Result<Vec<Pattern>, PatternError>>()
+                .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?,
+            sync_hidden_files: false,
+            strict_scan: false,
+            adopt_by_size: false,
+            sync_labels: false,
+            remote_mode: RemoteMode::Auto,
+            remote_since: false,
+            report_file: None,
+            health_file: None,
+            health_address: None,
+            preserve_xattrs: false,
+            hooks: Hooks::default(),
+            metrics: Metrics::default(),
+            request_pacer: RequestPacer::default(),
+            connection_pool: ConnectionPool::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            extra_headers: vec![],
+            #[cfg(feature = "metrics")]
+            metrics_listen: None,
+            filter_own_events: false,
+            client_token: new_client_token(),
+            own_events_filtered: Arc::new(AtomicBool::new(false)),
+            events: EventBus::default(),
+            paused: Arc::new(AtomicBool::new(false)),
+            stopping: Arc::new(AtomicBool::new(false)),
+            folder_missing: Arc::new(AtomicBool::new(false)),
+            local_changes_signaled: Arc::new(AtomicBool::new(false)),
+            missing_folder_timeout_s: DEFAULT_MISSING_FOLDER_TIMEOUT_S,
+            local_poll_interval_s: DEFAULT_LOCAL_POLL_INTERVAL_S,
+            watch_debounce_ms: DEFAULT_WATCH_DEBOUNCE_MS,
+            watch_channel_capacity: DEFAULT_WATCH_CHANNEL_CAPACITY,
+            local_polling: false,
+            filesystem_profile: FilesystemProfile::Auto,
+            owned_paths: vec![],
+        };
+        context.resolve_owned_paths();
+        Ok(context)
+    }
+
+    // Directory holding trsync files (database, partial downloads, ...). Nothing
+    // inside it is synchronized.
+    pub fn metadata_dir(&self) -> PathBuf {
+        Path::new(&self.folder_path).join(METADATA_DIR_NAME)
+    }
+
+    pub fn temp_dir(&self) -> PathBuf {
+        self.metadata_dir().join(TEMP_DIR_NAME)
+    }
+
+    // Executable contents downloaded with quarantine policy, at their relative path
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.metadata_dir().join(QUARANTINE_DIR_NAME)
+    }
+
+    // Status of running trsync, read by status command
+    pub fn live_status_path(&self) -> PathBuf {
+        self.metadata_dir().join(LIVE_STATUS_FILE_NAME)
+    }
+
+    pub fn is_ignored_file_name(&self, file_name: &str) -> bool {
+        self.ignore_patterns.iter().any(|pattern| {
+            !(self.sync_hidden_files && pattern.as_str() == HIDDEN_FILES_PATTERN)
+                && pattern.matches(file_name)
+        })
+    }
+
+    // Path is ignored when its file name or one of its parents name is ignored
+    pub fn is_ignored_relative_path(&self, relative_path: &str) -> bool {
+        Path::new(relative_path).components().any(|component| {
+            component
+                .as_os_str()
+                .to_str()
+                .map(|name| self.is_ignored_file_name(name))
+                .unwrap_or(false)
         })
     }
 
+    pub fn is_metadata_path(&self, absolute_path: &Path) -> bool {
+        absolute_path.starts_with(self.metadata_dir())
+    }
+
+    // Files written by trsync outside of metadata directory : database (legacy one too)
+    // and its sqlite files, health and report files (with health temporary file). Each
+    // one is kept as given (made absolute) and canonicalized, so that events of a folder
+    // reached through a symbolic link match too. Must be called again when one of these
+    // paths changes.
+    pub fn resolve_owned_paths(&mut self) {
+        let mut file_paths = vec![];
+        let mut database_paths = vec![PathBuf::from(&self.database_path)];
+        database_paths.extend(self.previous_database_paths());
+        for database_path in &database_paths {
+            for suffix in &DATABASE_FILE_SUFFIXES {
+                let mut file_path = database_path.clone().into_os_string();
+                file_path.push(suffix);
+                file_paths.push(PathBuf::from(file_path));
+            }
+        }
+        let health_temp_file = self
+            .health_file
+            .as_ref()
+            .map(|health_file| health_file.with_extension("tmp"));
+        file_paths.extend(
+            self.health_file
+                .iter()
+                .chain(health_temp_file.iter())
+                .chain(self.report_file.iter())
+                .cloned(),
+        );
+
+        let current_dir = env::current_dir().ok();
+        let mut owned_paths = vec![];
+        for file_path in file_paths {
+            let absolute_path = match &current_dir {
+                Some(current_dir) => current_dir.join(&file_path),
+                None => file_path,
+            };
+            // Files may not exist yet, but their directory does
+            let canonical_path = absolute_path
+                .parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .zip(absolute_path.file_name())
+                .map(|(parent, file_name)| parent.join(file_name));
+            for owned_path in Some(absolute_path).into_iter().chain(canonical_path) {
+                if !owned_paths.contains(&owned_path) {
+                    owned_paths.push(owned_path);
+                }
+            }
+        }
+        self.owned_paths = owned_paths;
+    }
+
+    pub fn owned_paths(&self) -> &[PathBuf] {
+        &self.owned_paths
+    }
+
+    // Trsync files are never synchronized, whatever ignore patterns are
+    pub fn is_owned_path(&self, absolute_path: &Path) -> bool {
+        self.is_metadata_path(absolute_path)
+            || self
+                .owned_paths
+                .iter()
+                .any(|owned_path| owned_path == absolute_path)
+    }
+
+    // Database locations of previous trsync versions (or default location when database
+    // path is given), moved to database path at start
+    fn previous_database_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.metadata_dir().join(DATABASE_FILE_NAME),
+            Path::new(&self.folder_path).join(LEGACY_DATABASE_FILE_NAME),
+        ]
+        .into_iter()
+        .filter(|previous_path| previous_path != Path::new(&self.database_path))
+        .collect()
+    }
+
+    // Create metadata and database directories, and move database of previous trsync
+    // versions to database path
+    pub fn prepare_metadata_dir(&self) -> Result<(), Error> {
+        fs::create_dir_all(self.temp_dir())?;
+        if let Some(database_dir) = Path::new(&self.database_path).parent() {
+            fs::create_dir_all(database_dir)?;
+        }
+
+        if Path::new(&self.database_path).exists() {
+            return Ok(());
+        }
+        if let Some(previous_path) = self
+            .previous_database_paths()
+            .into_iter()
+            .find(|previous_path| previous_path.exists())
+        {
+            for suffix in &DATABASE_FILE_SUFFIXES {
+                let mut file_path = previous_path.clone().into_os_string();
+                file_path.push(suffix);
+                let file_path = PathBuf::from(file_path);
+                if file_path.exists() {
+                    let path = format!("{}{}", self.database_path, suffix);
+                    log::info!("Move database file {:?} into {:?}", file_path, path);
+                    util::move_file(&file_path, Path::new(&path))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Commands not knowing workspace id (status, exclusions) use the database of
+    // synchronized folder, whatever its workspace (or previous location)
+    pub fn use_folder_database(&mut self) -> Result<(), Error> {
+        if Path::new(&self.database_path).exists() {
+            return Ok(());
+        }
+
+        let mut database_paths = folder_databases(&self.folder_path);
+        database_paths.extend(self.previous_database_paths());
+        if let Some(database_path) = database_paths
+            .into_iter()
+            .find(|database_path| database_path.exists())
+        {
+            self.database_path = util::path_to_string(&database_path)?;
+        }
+        Ok(())
+    }
+
+    // No download is running before operational handler start, so all partial files
+    // are leftovers of a previous crash
+    pub fn clean_partial_files(&self) -> Result<(), Error> {
+        for entry in fs::read_dir(self.temp_dir())? {
+            let path = entry?.path();
+            log::info!("Remove partial file {:?}", path);
+            if let Err(error) = fs::remove_file(&path) {
+                log::error!("Unable to remove partial file {:?} : {:?}", path, error)
+            }
+        }
+
+        Ok(())
+    }
+
+    // Network file systems don't report disk events and store timestamps by second :
+    // poll disk, compare timestamps by second and wait longer for files to be stable.
+    // Settings are only raised, never lowered.
+    pub fn apply_filesystem_profile(&mut self) {
+        let network = match self.filesystem_profile {
+            FilesystemProfile::Local => return,
+            FilesystemProfile::Network => true,
+            FilesystemProfile::Auto => {
+                match util::network_filesystem_type(Path::new(&self.folder_path)) {
+                    Some(filesystem_type) => {
+                        log::info!(
+                            "Synchronized folder is on a {} network file system",
+                            filesystem_type
+                        );
+                        true
+                    }
+                    None => false,
+                }
+            }
+        };
+        if !network {
+            return;
+        }
+
+        self.local_polling = true;
+        self.timestamp_granularity_ms = self
+            .timestamp_granularity_ms
+            .max(NETWORK_TIMESTAMP_GRANULARITY_MS);
+        self.upload_stable_ms = Some(
+            self.upload_stable_ms
+                .unwrap_or_default()
+                .max(NETWORK_UPLOAD_STABLE_MS),
+        );
+        self.watch_debounce_ms = self.watch_debounce_ms.max(NETWORK_WATCH_DEBOUNCE_MS);
+        log::info!(
+            "Network file system profile : search disk changes every {} seconds, compare \
+             timestamps by {} ms, upload files stable for {} ms and hold disk events {} ms",
+            self.local_poll_interval_s,
+            self.timestamp_granularity_ms,
+            self.upload_stable_ms.unwrap_or_default(),
+            self.watch_debounce_ms
+        );
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst) || self.is_folder_missing()
+    }
+
+    pub fn is_folder_missing(&self) -> bool {
+        self.folder_missing.load(Ordering::SeqCst)
+    }
+
+    // Labels are file names with label name strategies
+    pub fn labels_synchronized(&self) -> bool {
+        self.sync_labels && self.name_strategy == NameStrategy::Filename
+    }
+
+    pub fn is_excluded_content(&self, content_id: ContentId) -> bool {
+        self.excluded_content_ids
+            .lock()
+            .map(|excluded_content_ids| excluded_content_ids.contains(&content_id))
+            .unwrap_or(false)
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
     pub fn workspace_url(&self, suffix: &str) -> String {
         format!(
             "{}workspaces/{}/{}",
@@ -45,3 +550,176 @@ impl Context {
         )
     }
 }
+
+// Index is kept out of synchronized folder, where a bulk operation could delete it : in
+// user data directory, one per workspace and folder. Without user data directory, it is
+// kept in metadata directory.
+pub fn default_database_path(folder_path: &str, workspace_id: i32) -> PathBuf {
+    match databases_dir() {
+        Some(databases_dir) => {
+            databases_dir.join(format!("{}-{}.db", workspace_id, folder_hash(folder_path)))
+        }
+        None => Path::new(folder_path)
+            .join(METADATA_DIR_NAME)
+            .join(DATABASE_FILE_NAME),
+    }
+}
+
+// Directory of default databases : XDG data directory (Linux), application support
+// (macOS) or local application data (Windows)
+pub fn databases_dir() -> Option<PathBuf> {
+    let home_dir = env::var_os("HOME")
+        .filter(|home_dir| !home_dir.is_empty())
+        .map(PathBuf::from);
+    let data_dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA")
+            .filter(|data_dir| !data_dir.is_empty())
+            .map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir.map(|home_dir| home_dir.join("Library").join("Application Support"))
+    } else {
+        // Relative XDG_DATA_HOME is invalid and ignored
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|data_dir| data_dir.is_absolute())
+            .or_else(|| home_dir.map(|home_dir| home_dir.join(".local").join("share")))
+    };
+    data_dir.map(|data_dir| data_dir.join("trsync").join(DATABASES_DIR_NAME))
+}
+
+// Default databases of a synchronized folder, whatever their workspace
+fn folder_databases(folder_path: &str) -> Vec<PathBuf> {
+    let suffix = format!("-{}.db", folder_hash(folder_path));
+    let mut database_paths: Vec<PathBuf> = databases_dir()
+        .and_then(|databases_dir| fs::read_dir(databases_dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| file_name.ends_with(&suffix))
+        })
+        .collect();
+    database_paths.sort();
+    database_paths
+}
+
+fn folder_hash(folder_path: &str) -> String {
+    Sha256::digest(folder_path.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Token identifying this trsync process
+fn new_client_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("trsync-{}-{}", std::process::id(), nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    #[test]
+    fn owned_paths_match_given_and_canonical_forms() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("owned");
+        let mut context = testing::context(&stub, &folder);
+        fs::create_dir(folder.join("real")).unwrap();
+        let database_path = folder
+            .join("real")
+            .join("..")
+            .join("real")
+            .join("trsync.db");
+        context.database_path = util::path_to_string(&database_path).unwrap();
+        context.health_file = Some(folder.join("real").join("health.json"));
+
+        context.resolve_owned_paths();
+
+        let canonical_folder = folder.canonicalize().unwrap();
+        for owned_path in [
+            database_path.clone(),
+            canonical_folder.join("real").join("trsync.db"),
+            canonical_folder.join("real").join("trsync.db-wal"),
+            canonical_folder.join("real").join("health.tmp"),
+        ] {
+            assert!(context.is_owned_path(&owned_path), "{:?}", owned_path);
+        }
+        assert!(!context.is_owned_path(&canonical_folder.join("real").join("file.txt")));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn network_profile_raises_windows() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("network-profile");
+        let mut context = testing::context(&stub, &folder);
+        context.filesystem_profile = FilesystemProfile::Network;
+
+        context.apply_filesystem_profile();
+
+        assert!(context.local_polling);
+        assert_eq!(
+            context.timestamp_granularity_ms,
+            NETWORK_TIMESTAMP_GRANULARITY_MS
+        );
+        assert_eq!(context.upload_stable_ms, Some(NETWORK_UPLOAD_STABLE_MS));
+        assert_eq!(context.watch_debounce_ms, NETWORK_WATCH_DEBOUNCE_MS);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn default_database_path_derives_from_workspace_and_folder() {
+        let database_path = default_database_path("/home/user/Tracim", 42);
+        let other_folder = default_database_path("/home/user/Tracim2", 42);
+        let other_workspace = default_database_path("/home/user/Tracim", 43);
+
+        let file_name = database_path.file_name().unwrap().to_str().unwrap();
+        match databases_dir() {
+            Some(databases_dir) => {
+                assert_eq!(database_path.parent(), Some(databases_dir.as_path()));
+                assert_eq!(
+                    file_name,
+                    format!("42-{}.db", folder_hash("/home/user/Tracim"))
+                );
+                assert_eq!(folder_hash("/home/user/Tracim").len(), 16);
+                assert_ne!(database_path, other_folder);
+                assert_ne!(database_path, other_workspace);
+            }
+            None => assert_eq!(
+                database_path,
+                Path::new("/home/user/Tracim")
+                    .join(METADATA_DIR_NAME)
+                    .join(DATABASE_FILE_NAME)
+            ),
+        }
+        assert_eq!(
+            database_path,
+            default_database_path("/home/user/Tracim", 42)
+        );
+    }
+
+    #[test]
+    fn metadata_paths_are_inside_metadata_dir_only() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("metadata-paths");
+        let mut context = testing::context(&stub, &folder);
+        context.sync_hidden_files = true;
+
+        assert!(context.is_metadata_path(&folder.join(METADATA_DIR_NAME)));
+        assert!(context.is_metadata_path(&context.temp_dir().join("partial")));
+        assert!(context.is_owned_path(&context.quarantine_dir().join("a.sh")));
+        assert!(!context.is_metadata_path(&folder.join(format!("{}2", METADATA_DIR_NAME))));
+        assert!(!context.is_metadata_path(&folder.join("docs").join(METADATA_DIR_NAME)));
+        assert!(!context.is_ignored_relative_path(".hidden.txt"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
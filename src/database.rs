@@ -1,17 +1,70 @@
-use rusqlite::{params, Connection};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use rusqlite::{params, Connection, ErrorCode, OpenFlags, Params, Row};
 
 use crate::{
     error::Error,
+    timing::{self, TimingPhase},
     types::{ContentId, LastModifiedTimestamp, RelativeFilePath, RevisionId},
+    util::{self, Xattrs},
 };
 
+const DATABASE_BUSY_TIMEOUT_MS: u64 = 5000;
+const DATABASE_LOCKED_RETRIES: u32 = 5;
+const DATABASE_LOCKED_RETRY_DELAY_MS: u64 = 200;
+
+// Value of sqlite "synchronous" pragma. Normal is safe in WAL mode and avoid a disk
+// sync on each transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SynchronousMode {
+    Off,
+    Normal,
+    Full,
+}
+
+impl FromStr for SynchronousMode {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "normal" => Ok(Self::Normal),
+            "full" => Ok(Self::Full),
+            _ => Err(format!(
+                "Unknown synchronous mode '{}' (expected off, normal or full)",
+                str_
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SynchronousMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SynchronousMode::Off => write!(f, "OFF"),
+            SynchronousMode::Normal => write!(f, "NORMAL"),
+            SynchronousMode::Full => write!(f, "FULL"),
+        }
+    }
+}
+
 pub struct Database {
     database_file_path: String,
+    synchronous: SynchronousMode,
 }
 
 impl Database {
-    pub fn new(database_file_path: String) -> Self {
-        Self { database_file_path }
+    pub fn new(database_file_path: String, synchronous: SynchronousMode) -> Self {
+        Self {
+            database_file_path,
+            synchronous,
+        }
     }
 
     pub fn with_new_connection<F>(&self, f: F) -> Result<(), Error>
@@ -19,20 +72,207 @@ impl Database {
         F: FnOnce(Connection) -> Result<(), Error>,
     {
         let connection = Connection::open(self.database_file_path.clone())?;
+        // Wait for other connections (other threads or processes) to release their locks
+        connection.busy_timeout(Duration::from_millis(DATABASE_BUSY_TIMEOUT_MS))?;
+        // WAL let readers and writer work concurrently. Its "-wal" and "-shm" files are
+        // created next to database file.
+        let journal_mode =
+            connection.query_row("PRAGMA journal_mode=WAL", [], |row| row.get::<_, String>(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            log::warn!(
+                "Unable to enable WAL mode on database, journal mode is {}",
+                journal_mode
+            );
+        }
+        connection.execute_batch(&format!("PRAGMA synchronous={}", self.synchronous))?;
         f(connection)?;
         Ok(())
     }
+
+    // Connection which never write, usable while another trsync instance is running
+    pub fn with_read_only_connection<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(Connection) -> Result<(), Error>,
+    {
+        let connection = Connection::open_with_flags(
+            self.database_file_path.clone(),
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )?;
+        connection.busy_timeout(Duration::from_millis(DATABASE_BUSY_TIMEOUT_MS))?;
+        f(connection)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub relative_path: RelativeFilePath,
+    pub last_modified_timestamp: LastModifiedTimestamp,
+    pub content_id: ContentId,
+    pub revision_id: RevisionId,
 }
 
+impl IndexEntry {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            relative_path: row.get(0)?,
+            last_modified_timestamp: row.get(1)?,
+            content_id: row.get(2)?,
+            revision_id: row.get(3)?,
+        })
+    }
+}
+
+// Conflict which was not automatically resolved
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub relative_path: RelativeFilePath,
+    // Disk file state when conflict was detected
+    pub last_modified_timestamp: Option<LastModifiedTimestamp>,
+    pub checksum: Option<String>,
+    // Remote content state when conflict was detected
+    pub content_id: ContentId,
+    pub revision_id: RevisionId,
+    pub detected_timestamp: LastModifiedTimestamp,
+}
+
+impl Conflict {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            relative_path: row.get(0)?,
+            last_modified_timestamp: row.get(1)?,
+            checksum: row.get(2)?,
+            content_id: row.get(3)?,
+            revision_id: row.get(4)?,
+            detected_timestamp: row.get(5)?,
+        })
+    }
+}
+
+// Path which operations keep failing with a non retryable error
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub relative_path: RelativeFilePath,
+    pub error: String,
+    // Consecutive failures (a success forget the failure)
+    pub count: u32,
+    pub last_attempt_timestamp: LastModifiedTimestamp,
+    // Disk file state at last failure
+    pub last_modified_timestamp: Option<LastModifiedTimestamp>,
+    pub checksum: Option<String>,
+}
+
+impl Failure {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            relative_path: row.get(0)?,
+            error: row.get(1)?,
+            count: row.get(2)?,
+            last_attempt_timestamp: row.get(3)?,
+            last_modified_timestamp: row.get(4)?,
+            checksum: row.get(5)?,
+        })
+    }
+}
+
+// New local file Tracim refused to create, skipped until its disk file changes
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub relative_path: RelativeFilePath,
+    pub reason: String,
+    pub rejected_timestamp: LastModifiedTimestamp,
+    // Disk file state when rejected
+    pub last_modified_timestamp: Option<LastModifiedTimestamp>,
+    pub checksum: Option<String>,
+}
+
+impl Rejection {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            relative_path: row.get(0)?,
+            reason: row.get(1)?,
+            rejected_timestamp: row.get(2)?,
+            last_modified_timestamp: row.get(3)?,
+            checksum: row.get(4)?,
+        })
+    }
+}
+
+// Local change made while remote was unreachable, waiting to be replayed
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub kind: String,
+    pub relative_path: RelativeFilePath,
+    // Rename destination
+    pub new_relative_path: Option<RelativeFilePath>,
+}
+
+impl JournalEntry {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            kind: row.get(0)?,
+            relative_path: row.get(1)?,
+            new_relative_path: row.get(2)?,
+        })
+    }
+}
+
+// Entries matching a prefix are the prefix itself and all its descendants. An empty
+// prefix match all entries.
+const PREFIX_CONDITION: &str =
+    "(?1 = '' OR relative_path = ?1 OR substr(relative_path, 1, length(?1) + 1) = ?1 || '/')";
+
 pub struct DatabaseOperation<'d> {
     connection: &'d Connection,
 }
 
 impl<'d> DatabaseOperation<'d> {
-    pub fn new<'a>(connection: &'d Connection) -> Self {
+    pub fn new(connection: &'d Connection) -> Self {
         Self { connection }
     }
 
+    // Execute a write statement, retrying it when database is busy or locked after
+    // busy timeout expired
+    fn execute<P: Params + Copy>(&self, sql: &str, params: P) -> Result<usize, rusqlite::Error> {
+        let mut attempt = 0;
+        loop {
+            match timing::measure(TimingPhase::Database, || {
+                self.connection.execute(sql, params)
+            }) {
+                Err(rusqlite::Error::SqliteFailure(sqlite_error, _))
+                    if (sqlite_error.code == ErrorCode::DatabaseBusy
+                        || sqlite_error.code == ErrorCode::DatabaseLocked)
+                        && attempt < DATABASE_LOCKED_RETRIES =>
+                {
+                    attempt += 1;
+                    log::warn!(
+                        "Database is locked, retry statement ({}/{})",
+                        attempt,
+                        DATABASE_LOCKED_RETRIES
+                    );
+                    thread::sleep(Duration::from_millis(DATABASE_LOCKED_RETRY_DELAY_MS));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Run given index changes in a single transaction : all are committed, or none if
+    // one fails. Changes run in the enclosing transaction, if any.
+    pub fn with_transaction<T, E: From<rusqlite::Error>>(
+        &self,
+        f: impl FnOnce(&DatabaseOperation) -> Result<T, E>,
+    ) -> Result<T, E> {
+        if !self.connection.is_autocommit() {
+            return f(self);
+        }
+
+        let transaction = self.connection.unchecked_transaction()?;
+        let result = f(&DatabaseOperation::new(&transaction))?;
+        transaction.commit()?;
+        Ok(result)
+    }
+
     pub fn create_tables(&self) -> Result<(), rusqlite::Error> {
         self.connection
             .execute(
@@ -40,12 +280,147 @@ impl<'d> DatabaseOperation<'d> {
                 relative_path TEXT PRIMARY KEY,
                 last_modified_timestamp INTEGER NOT NULL,
                 content_id INTEGER NOT NULL,
-                revision_id INTEGER NOT NULL
+                revision_id INTEGER NOT NULL,
+                checksum TEXT,
+                label TEXT
             );
             CREATE UNIQUE INDEX IF NOT EXISTS idx_local_relative_path ON local (relative_path);
             CREATE UNIQUE INDEX IF NOT EXISTS idx_local_remote_content_id ON local (remote_content_id);",
                 [],
             )?;
+        self.ensure_column("file", "checksum", "TEXT")?;
+        self.ensure_column("file", "label", "TEXT")?;
+        // NULL for files indexed by older trsync versions : unknown
+        self.ensure_column("file", "is_directory", "INTEGER")?;
+        // Remote content type ("file", "folder", "html-document", ...), NULL when unknown
+        self.ensure_column("file", "content_type", "TEXT")?;
+        // Byte size of synchronized content, NULL when unknown (older trsync versions)
+        self.ensure_column("file", "size", "INTEGER")?;
+        // Relative path with case folded, to find paths differing only by case
+        self.ensure_column("file", "folded_relative_path", "TEXT")?;
+        self.connection.execute(
+            "CREATE INDEX IF NOT EXISTS idx_file_folded_relative_path ON file (folded_relative_path)",
+            [],
+        )?;
+        self.fold_unfolded_relative_paths()?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS conflict (
+                relative_path TEXT PRIMARY KEY,
+                last_modified_timestamp INTEGER,
+                checksum TEXT,
+                content_id INTEGER NOT NULL,
+                revision_id INTEGER NOT NULL,
+                detected_timestamp INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS xattr (
+                content_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (content_id, name)
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS failure (
+                relative_path TEXT PRIMARY KEY,
+                error TEXT NOT NULL,
+                count INTEGER NOT NULL,
+                last_attempt_timestamp INTEGER NOT NULL,
+                last_modified_timestamp INTEGER,
+                checksum TEXT
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS rejection (
+                relative_path TEXT PRIMARY KEY,
+                reason TEXT NOT NULL,
+                rejected_timestamp INTEGER NOT NULL,
+                last_modified_timestamp INTEGER,
+                checksum TEXT
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS exclusion (
+                relative_path TEXT PRIMARY KEY,
+                excluded_timestamp INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.connection.execute(
+            "CREATE TABLE IF NOT EXISTS journal (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                relative_path TEXT NOT NULL,
+                new_relative_path TEXT
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // Add column to table created by an older trsync version
+    fn ensure_column(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        if !columns.iter().any(|column_| column_ == column) {
+            log::info!("Add column {} to {} table", column, table);
+            self.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+
+    // Fold paths indexed without their folded path (by an older trsync version, or
+    // just moved)
+    fn fold_unfolded_relative_paths(&self) -> Result<(), rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT relative_path FROM file WHERE folded_relative_path IS NULL")?;
+        let relative_paths = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<RelativeFilePath>, rusqlite::Error>>()?;
+        if relative_paths.is_empty() {
+            return Ok(());
+        }
+        log::debug!("Fold case of {} indexed path(s)", relative_paths.len());
+        self.with_transaction(|database_operation| {
+            database_operation.fold_relative_paths(&relative_paths)
+        })
+    }
+
+    fn fold_relative_paths(
+        &self,
+        relative_paths: &[RelativeFilePath],
+    ) -> Result<(), rusqlite::Error> {
+        for relative_path in relative_paths {
+            self.execute(
+                "UPDATE file SET folded_relative_path = ?1 WHERE relative_path = ?2",
+                params![util::fold_case(relative_path), relative_path],
+            )?;
+        }
         Ok(())
     }
 
@@ -61,6 +436,24 @@ impl<'d> DatabaseOperation<'d> {
         }
     }
 
+    // Indexed path equal to given one when ignoring case, but not exactly equal. Case
+    // is folded with util rule rather than SQLite NOCASE, which only folds ASCII.
+    pub fn get_relative_path_ignoring_case(
+        &self,
+        relative_path: &str,
+    ) -> Result<Option<RelativeFilePath>, rusqlite::Error> {
+        match self.connection.query_row(
+            "SELECT relative_path FROM file WHERE folded_relative_path = ?1 AND relative_path != ?2
+            ORDER BY relative_path LIMIT 1",
+            params![util::fold_case(relative_path), relative_path],
+            |row| row.get(0),
+        ) {
+            Ok(indexed_relative_path) => Ok(Some(indexed_relative_path)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
     pub fn relative_path_is_known(
         &self,
         relative_path: &RelativeFilePath,
@@ -105,11 +498,11 @@ impl<'d> DatabaseOperation<'d> {
         &self,
         content_id: ContentId,
     ) -> Result<String, rusqlite::Error> {
-        Ok(self.connection.query_row::<String, _, _>(
+        self.connection.query_row::<String, _, _>(
             "SELECT relative_path FROM file WHERE content_id = ?",
             params![content_id],
             |row| row.get(0),
-        )?)
+        )
     }
 
     pub fn insert_new_file(
@@ -118,6 +511,8 @@ impl<'d> DatabaseOperation<'d> {
         last_modified_timestamp: LastModifiedTimestamp,
         content_id: ContentId,
         revision_id: RevisionId,
+        checksum: Option<String>,
+        is_directory: bool,
     ) -> Result<(), rusqlite::Error> {
         log::debug!(
             "Insert new file with path {:?} and timestamp {}",
@@ -125,22 +520,18 @@ impl<'d> DatabaseOperation<'d> {
             last_modified_timestamp,
         );
 
-        match self.connection
+        match self
             .execute(
-                "INSERT INTO file (relative_path, last_modified_timestamp, content_id, revision_id) VALUES (?1, ?2, ?3, ?4)",
-                params![relative_path, last_modified_timestamp, content_id, revision_id],
+                "INSERT INTO file (relative_path, last_modified_timestamp, content_id, revision_id, checksum, is_directory, folded_relative_path) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![relative_path, last_modified_timestamp, content_id, revision_id, checksum, is_directory, util::fold_case(&relative_path)],
             ) {
                 Ok(_) => {},
                 Err(error) => {
                     match &error {
                         rusqlite::Error::SqliteFailure(sqlite_error, message) => {
                             match sqlite_error.code {
-                                rusqlite::ErrorCode::ConstraintViolation => {
-                                    if message == &Some("UNIQUE constraint failed: file.relative_path".to_string()) {
-                                        log::debug!("File with path {:?} already exists", relative_path);
-                                    } else {
-                                        return Err(error)
-                                    }
+                                rusqlite::ErrorCode::ConstraintViolation if message == &Some("UNIQUE constraint failed: file.relative_path".to_string()) => {
+                                    log::debug!("File with path {:?} already exists", relative_path);
                                 },
                                 _ => return Err(error),
                             }
@@ -163,19 +554,126 @@ impl<'d> DatabaseOperation<'d> {
             last_modified_timestamp
         );
 
-        self.connection.execute(
+        self.execute(
             "UPDATE file SET last_modified_timestamp = ?1 WHERE relative_path = ?2",
             params![last_modified_timestamp, relative_path],
         )?;
         Ok(())
     }
 
-    pub fn get_last_modified_timestamp(&self, relative_path: &str) -> Result<u64, rusqlite::Error> {
-        Ok(self.connection.query_row::<u64, _, _>(
+    pub fn get_last_modified_timestamp(
+        &self,
+        relative_path: &str,
+    ) -> Result<LastModifiedTimestamp, rusqlite::Error> {
+        self.connection.query_row::<LastModifiedTimestamp, _, _>(
             "SELECT last_modified_timestamp FROM file WHERE relative_path = ?",
             params![relative_path],
             |row| row.get(0),
-        )?)
+        )
+    }
+
+    pub fn get_checksum(&self, relative_path: &str) -> Result<Option<String>, rusqlite::Error> {
+        self.connection.query_row::<Option<String>, _, _>(
+            "SELECT checksum FROM file WHERE relative_path = ?",
+            params![relative_path],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn update_checksum(
+        &self,
+        relative_path: String,
+        checksum: Option<String>,
+    ) -> Result<(), rusqlite::Error> {
+        log::debug!("Update checksum of {:?} with {:?}", relative_path, checksum);
+
+        self.execute(
+            "UPDATE file SET checksum = ?1 WHERE relative_path = ?2",
+            params![checksum, relative_path],
+        )?;
+        Ok(())
+    }
+
+    // None when unknown, also when index of an older trsync version is read without
+    // migration (status)
+    pub fn get_size(&self, relative_path: &str) -> Result<Option<u64>, rusqlite::Error> {
+        match self.connection.query_row::<Option<u64>, _, _>(
+            "SELECT size FROM file WHERE relative_path = ?",
+            params![relative_path],
+            |row| row.get(0),
+        ) {
+            Err(rusqlite::Error::SqliteFailure(_, Some(message)))
+                if message.starts_with("no such column") =>
+            {
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+
+    pub fn update_size(
+        &self,
+        relative_path: String,
+        size: Option<u64>,
+    ) -> Result<(), rusqlite::Error> {
+        log::debug!("Update size of {:?} with {:?}", relative_path, size);
+
+        self.execute(
+            "UPDATE file SET size = ?1 WHERE relative_path = ?2",
+            params![size, relative_path],
+        )?;
+        Ok(())
+    }
+
+    // Indexed path was a directory when synchronized (None if indexed by an older
+    // trsync version)
+    pub fn get_is_directory(&self, content_id: ContentId) -> Result<Option<bool>, rusqlite::Error> {
+        self.connection.query_row(
+            "SELECT is_directory FROM file WHERE content_id = ?",
+            params![content_id],
+            |row| row.get(0),
+        )
+    }
+
+    // Remote content type at last synchronization from remote (None if not known)
+    pub fn get_content_type(
+        &self,
+        content_id: ContentId,
+    ) -> Result<Option<String>, rusqlite::Error> {
+        self.connection.query_row(
+            "SELECT content_type FROM file WHERE content_id = ?",
+            params![content_id],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn update_content_type(
+        &self,
+        content_id: ContentId,
+        content_type: &str,
+    ) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "UPDATE file SET content_type = ?1 WHERE content_id = ?2",
+            params![content_type, content_id],
+        )?;
+        Ok(())
+    }
+
+    // Remote label at last synchronization of labels
+    pub fn get_label(&self, content_id: ContentId) -> Result<Option<String>, rusqlite::Error> {
+        self.connection.query_row(
+            "SELECT label FROM file WHERE content_id = ?",
+            params![content_id],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn update_label(&self, content_id: ContentId, label: &str) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "UPDATE file SET label = ?1 WHERE content_id = ?2",
+            params![label, content_id],
+        )?;
+        Ok(())
     }
 
     pub fn update_revision_id(
@@ -189,58 +687,895 @@ impl<'d> DatabaseOperation<'d> {
             revision_id
         );
 
-        self.connection.execute(
+        self.execute(
             "UPDATE file SET revision_id = ?1 WHERE relative_path = ?2",
             params![revision_id, relative_path],
         )?;
         Ok(())
     }
 
-    pub fn update_relative_path(
+    // Indexed file now match another remote content (extended attributes follow it)
+    pub fn replace_content_id(
         &self,
-        content_id: ContentId,
-        relative_path: RelativeFilePath,
+        old_content_id: ContentId,
+        new_content_id: ContentId,
+        new_revision_id: RevisionId,
     ) -> Result<(), rusqlite::Error> {
         log::debug!(
-            "Update relative path relative path of content {:?} with {:?}",
-            content_id,
-            relative_path
+            "Replace content {:?} by content {:?}",
+            old_content_id,
+            new_content_id
         );
 
-        self.connection.execute(
-            "UPDATE file SET relative_path = ?1 WHERE content_id = ?2",
-            params![relative_path, content_id],
+        self.execute(
+            "UPDATE file SET content_id = ?1, revision_id = ?2 WHERE content_id = ?3",
+            params![new_content_id, new_revision_id, old_content_id],
+        )?;
+        self.execute(
+            "UPDATE xattr SET content_id = ?1 WHERE content_id = ?2",
+            params![new_content_id, old_content_id],
         )?;
         Ok(())
     }
 
+    // Move an entry (with its descendants) and set its revision at once : a moved content
+    // is never indexed with a path and a revision which do not go together
+    pub fn move_entry(
+        &self,
+        content_id: ContentId,
+        new_relative_path: &str,
+        revision_id: RevisionId,
+    ) -> Result<(), rusqlite::Error> {
+        log::debug!(
+            "Move entry of content {:?} to {:?} at revision {:?}",
+            content_id,
+            new_relative_path,
+            revision_id
+        );
+
+        self.with_transaction(|database_operation| {
+            let old_relative_path = database_operation.get_path_from_content_id(content_id)?;
+            database_operation.move_relative_path(&old_relative_path, new_relative_path)?;
+            database_operation.execute(
+                "UPDATE file SET revision_id = ?1 WHERE content_id = ?2",
+                params![revision_id, content_id],
+            )?;
+            Ok(())
+        })
+    }
+
+    // Replace relative path prefix of an entry and its descendants
+    pub fn move_relative_path(
+        &self,
+        old_relative_path: &str,
+        new_relative_path: &str,
+    ) -> Result<(), rusqlite::Error> {
+        log::debug!(
+            "Move relative path {:?} to {:?}",
+            old_relative_path,
+            new_relative_path
+        );
+
+        self.with_transaction(|database_operation| {
+            database_operation.execute(
+                &format!(
+                    "UPDATE file SET relative_path = ?2 || substr(relative_path, length(?1) + 1), folded_relative_path = NULL WHERE {}",
+                    PREFIX_CONDITION
+                ),
+                params![old_relative_path, new_relative_path],
+            )?;
+            database_operation.fold_unfolded_relative_paths()
+        })
+    }
+
+    // Unindex a content, with failure and rejection recorded for its path
     pub fn delete_file(&self, content_id: ContentId) -> Result<(), rusqlite::Error> {
-        self.connection.execute(
-            "DELETE FROM file WHERE content_id = ?1",
+        self.with_transaction(|database_operation| {
+            for table in ["failure", "rejection"] {
+                database_operation.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE relative_path IN (SELECT relative_path FROM file WHERE content_id = ?1)",
+                        table
+                    ),
+                    params![content_id],
+                )?;
+            }
+            database_operation.execute(
+                "DELETE FROM file WHERE content_id = ?1",
+                params![content_id],
+            )?;
+            database_operation.execute(
+                "DELETE FROM xattr WHERE content_id = ?1",
+                params![content_id],
+            )?;
+            Ok(())
+        })
+    }
+
+    // Unindex an entry and its descendants
+    pub fn delete_relative_path(&self, relative_path: &str) -> Result<(), rusqlite::Error> {
+        self.with_transaction(|database_operation| {
+            database_operation.execute(
+                &format!(
+                    "DELETE FROM xattr WHERE content_id IN (SELECT content_id FROM file WHERE {})",
+                    PREFIX_CONDITION
+                ),
+                params![relative_path],
+            )?;
+            database_operation.execute(
+                &format!("DELETE FROM file WHERE {}", PREFIX_CONDITION),
+                params![relative_path],
+            )?;
+            Ok(())
+        })
+    }
+
+    // Replace indexed extended attributes of content
+    pub fn set_xattrs(
+        &self,
+        content_id: ContentId,
+        xattrs: &[(String, Vec<u8>)],
+    ) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "DELETE FROM xattr WHERE content_id = ?1",
             params![content_id],
         )?;
+        for (name, value) in xattrs {
+            self.execute(
+                "INSERT INTO xattr (content_id, name, value) VALUES (?1, ?2, ?3)",
+                params![content_id, name, value],
+            )?;
+        }
         Ok(())
     }
 
-    pub fn get_relative_paths(&self) -> Result<Vec<String>, rusqlite::Error> {
-        let mut relative_paths = vec![];
-        let mut stmt = self.connection.prepare("SELECT relative_path FROM file")?;
-        let local_iter = stmt.query_map([], |row| Ok(row.get(0)?))?;
-        for result in local_iter {
-            let relative_path: String = result?;
-            relative_paths.push(relative_path);
+    pub fn get_xattrs(&self, content_id: ContentId) -> Result<Xattrs, rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT name, value FROM xattr WHERE content_id = ? ORDER BY name")?;
+        let xattrs = stmt
+            .query_map(params![content_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Xattrs, rusqlite::Error>>()?;
+        Ok(xattrs)
+    }
+
+    pub fn insert_conflict(&self, conflict: &Conflict) -> Result<(), rusqlite::Error> {
+        log::debug!("Insert conflict {:?}", conflict);
+
+        self.execute(
+            "INSERT OR REPLACE INTO conflict (relative_path, last_modified_timestamp, checksum, content_id, revision_id, detected_timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                conflict.relative_path,
+                conflict.last_modified_timestamp,
+                conflict.checksum,
+                conflict.content_id,
+                conflict.revision_id,
+                conflict.detected_timestamp
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_conflict(&self, relative_path: &str) -> Result<Option<Conflict>, rusqlite::Error> {
+        match self.connection.query_row(
+            "SELECT relative_path, last_modified_timestamp, checksum, content_id, revision_id, detected_timestamp FROM conflict WHERE relative_path = ?",
+            params![relative_path],
+            Conflict::from_row,
+        ) {
+            Ok(conflict) => Ok(Some(conflict)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error),
         }
-        Ok(relative_paths)
+    }
+
+    // Database created by previous trsync versions can miss recent tables
+    pub fn table_exists(&self, table_name: &str) -> Result<bool, rusqlite::Error> {
+        self.connection.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+            params![table_name],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_conflicts(&self) -> Result<Vec<Conflict>, rusqlite::Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT relative_path, last_modified_timestamp, checksum, content_id, revision_id, detected_timestamp FROM conflict ORDER BY detected_timestamp",
+        )?;
+        let conflicts = stmt
+            .query_map([], Conflict::from_row)?
+            .collect::<Result<Vec<Conflict>, rusqlite::Error>>()?;
+        Ok(conflicts)
+    }
+
+    pub fn delete_conflict(&self, relative_path: &str) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "DELETE FROM conflict WHERE relative_path = ?1",
+            params![relative_path],
+        )?;
+        Ok(())
+    }
+
+    // Count one more failure of path, with disk file state at this failure
+    pub fn record_failure(
+        &self,
+        relative_path: &str,
+        error: &str,
+        last_modified_timestamp: Option<LastModifiedTimestamp>,
+        checksum: Option<String>,
+        last_attempt_timestamp: LastModifiedTimestamp,
+    ) -> Result<u32, rusqlite::Error> {
+        let count = self
+            .get_failure(relative_path)?
+            .map(|failure| failure.count)
+            .unwrap_or(0)
+            + 1;
+        self.execute(
+            "INSERT OR REPLACE INTO failure (relative_path, error, count, last_attempt_timestamp, last_modified_timestamp, checksum) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                relative_path,
+                error,
+                count,
+                last_attempt_timestamp,
+                last_modified_timestamp,
+                checksum
+            ],
+        )?;
+        Ok(count)
+    }
+
+    pub fn get_failure(&self, relative_path: &str) -> Result<Option<Failure>, rusqlite::Error> {
+        match self.connection.query_row(
+            "SELECT relative_path, error, count, last_attempt_timestamp, last_modified_timestamp, checksum FROM failure WHERE relative_path = ?",
+            params![relative_path],
+            Failure::from_row,
+        ) {
+            Ok(failure) => Ok(Some(failure)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn get_failures(&self) -> Result<Vec<Failure>, rusqlite::Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT relative_path, error, count, last_attempt_timestamp, last_modified_timestamp, checksum FROM failure ORDER BY relative_path",
+        )?;
+        let failures = stmt
+            .query_map([], Failure::from_row)?
+            .collect::<Result<Vec<Failure>, rusqlite::Error>>()?;
+        Ok(failures)
+    }
+
+    pub fn delete_failure(&self, relative_path: &str) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "DELETE FROM failure WHERE relative_path = ?1",
+            params![relative_path],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_rejection(&self, rejection: &Rejection) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "INSERT OR REPLACE INTO rejection (relative_path, reason, rejected_timestamp, last_modified_timestamp, checksum) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                rejection.relative_path,
+                rejection.reason,
+                rejection.rejected_timestamp,
+                rejection.last_modified_timestamp,
+                rejection.checksum
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_rejection(&self, relative_path: &str) -> Result<Option<Rejection>, rusqlite::Error> {
+        match self.connection.query_row(
+            "SELECT relative_path, reason, rejected_timestamp, last_modified_timestamp, checksum FROM rejection WHERE relative_path = ?",
+            params![relative_path],
+            Rejection::from_row,
+        ) {
+            Ok(rejection) => Ok(Some(rejection)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn get_rejections(&self) -> Result<Vec<Rejection>, rusqlite::Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT relative_path, reason, rejected_timestamp, last_modified_timestamp, checksum FROM rejection ORDER BY relative_path",
+        )?;
+        let rejections = stmt
+            .query_map([], Rejection::from_row)?
+            .collect::<Result<Vec<Rejection>, rusqlite::Error>>()?;
+        Ok(rejections)
+    }
+
+    pub fn delete_rejection(&self, relative_path: &str) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "DELETE FROM rejection WHERE relative_path = ?1",
+            params![relative_path],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_exclusion(
+        &self,
+        relative_path: &str,
+        excluded_timestamp: LastModifiedTimestamp,
+    ) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "INSERT OR REPLACE INTO exclusion (relative_path, excluded_timestamp) VALUES (?1, ?2)",
+            params![relative_path, excluded_timestamp],
+        )?;
+        Ok(())
+    }
+
+    // Return true if path was excluded
+    pub fn delete_exclusion(&self, relative_path: &str) -> Result<bool, rusqlite::Error> {
+        Ok(self.execute(
+            "DELETE FROM exclusion WHERE relative_path = ?1",
+            params![relative_path],
+        )? > 0)
+    }
+
+    pub fn get_exclusions(&self) -> Result<Vec<RelativeFilePath>, rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT relative_path FROM exclusion ORDER BY relative_path")?;
+        let exclusions = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<RelativeFilePath>, rusqlite::Error>>()?;
+        Ok(exclusions)
+    }
+
+    // Path is excluded, or is inside an excluded folder
+    pub fn is_excluded(&self, relative_path: &str) -> Result<bool, rusqlite::Error> {
+        self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM exclusion WHERE relative_path = ?1 OR substr(?1, 1, length(relative_path) + 1) = relative_path || '/')",
+            params![relative_path],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>, rusqlite::Error> {
+        match self.connection.query_row(
+            "SELECT value FROM meta WHERE key = ?",
+            params![key],
+            |row| row.get(0),
+        ) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+        self.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_meta(&self, key: &str) -> Result<(), rusqlite::Error> {
+        self.execute("DELETE FROM meta WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    pub fn append_journal(&self, entry: &JournalEntry) -> Result<(), rusqlite::Error> {
+        log::debug!("Append to journal {:?}", entry);
+
+        self.execute(
+            "INSERT INTO journal (kind, relative_path, new_relative_path) VALUES (?1, ?2, ?3)",
+            params![entry.kind, entry.relative_path, entry.new_relative_path],
+        )?;
+        Ok(())
+    }
+
+    // Journal entries, in the order they were appended
+    pub fn get_journal(&self) -> Result<Vec<JournalEntry>, rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT kind, relative_path, new_relative_path FROM journal ORDER BY id")?;
+        let entries = stmt
+            .query_map([], JournalEntry::from_row)?
+            .collect::<Result<Vec<JournalEntry>, rusqlite::Error>>()?;
+        Ok(entries)
+    }
+
+    pub fn clear_journal(&self) -> Result<(), rusqlite::Error> {
+        self.execute("DELETE FROM journal", [])?;
+        Ok(())
+    }
+
+    // Give known paths one by one to given function, while rows are read : paths are
+    // never all loaded in memory. Iteration stops at first error. Paths are sorted, so
+    // that parents come before their children.
+    pub fn for_each_relative_path<E: From<rusqlite::Error>>(
+        &self,
+        mut callback: impl FnMut(RelativeFilePath) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT relative_path FROM file ORDER BY relative_path")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            callback(row.get(0)?)?;
+        }
+        Ok(())
     }
 
     pub fn get_content_ids(&self) -> Result<Vec<ContentId>, rusqlite::Error> {
         let mut content_ids = vec![];
         let mut stmt = self.connection.prepare("SELECT content_id FROM file")?;
-        let local_iter = stmt.query_map([], |row| Ok(row.get(0)?))?;
+        let local_iter = stmt.query_map([], |row| row.get(0))?;
         for result in local_iter {
             let content_id: i32 = result?;
             content_ids.push(content_id)
         }
         Ok(content_ids)
     }
+
+    // Indexed revision of each content
+    pub fn get_revisions(&self) -> Result<HashMap<ContentId, RevisionId>, rusqlite::Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT content_id, revision_id FROM file")?;
+        let revisions = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        revisions
+    }
+
+    pub fn get_relative_paths(&self) -> Result<Vec<RelativeFilePath>, rusqlite::Error> {
+        let mut relative_paths = vec![];
+        let mut stmt = self.connection.prepare("SELECT relative_path FROM file")?;
+        let local_iter = stmt.query_map([], |row| row.get(0))?;
+        for result in local_iter {
+            let relative_path: String = result?;
+            relative_paths.push(relative_path)
+        }
+        Ok(relative_paths)
+    }
+
+    pub fn count_entries(&self, prefix: &str) -> Result<u64, rusqlite::Error> {
+        self.connection.query_row::<u64, _, _>(
+            &format!("SELECT COUNT(*) FROM file WHERE {}", PREFIX_CONDITION),
+            params![prefix],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn list_entries_paged(
+        &self,
+        prefix: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<IndexEntry>, Error> {
+        check_page_size(limit)?;
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT relative_path, last_modified_timestamp, content_id, revision_id FROM file
+            WHERE {} ORDER BY relative_path LIMIT ?2 OFFSET ?3",
+            PREFIX_CONDITION
+        ))?;
+        let entries = stmt
+            .query_map(params![prefix, limit, offset], IndexEntry::from_row)?
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+
+    // Direct children of an indexed folder
+    pub fn get_children(&self, relative_path: &str) -> Result<Vec<IndexEntry>, rusqlite::Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT relative_path, last_modified_timestamp, content_id, revision_id FROM file
+            WHERE substr(relative_path, 1, length(?1) + 1) = ?1 || '/'
+            AND instr(substr(relative_path, length(?1) + 2), '/') = 0
+            ORDER BY relative_path",
+        )?;
+        let entries = stmt
+            .query_map(params![relative_path], IndexEntry::from_row)?
+            .collect();
+        entries
+    }
+
+    pub fn entries_cursor(&self, prefix: &str, page_size: u64) -> Result<EntriesCursor<'d>, Error> {
+        check_page_size(page_size)?;
+        Ok(EntriesCursor {
+            connection: self.connection,
+            prefix: prefix.to_string(),
+            page_size,
+            last_relative_path: None,
+            page: VecDeque::new(),
+            exhausted: false,
+        })
+    }
+}
+
+// Empty pages would end a paged listing before its first entry
+fn check_page_size(page_size: u64) -> Result<(), Error> {
+    if page_size == 0 {
+        return Err(Error::UnexpectedError(
+            "Index entries page size must be greater than 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Iterate over index entries by loading them page by page. Pages are selected from
+// the last seen relative path, so entries inserted or deleted during iteration
+// don't shift the next pages.
+pub struct EntriesCursor<'d> {
+    connection: &'d Connection,
+    prefix: String,
+    page_size: u64,
+    last_relative_path: Option<RelativeFilePath>,
+    page: VecDeque<IndexEntry>,
+    exhausted: bool,
+}
+
+impl<'d> EntriesCursor<'d> {
+    fn load_next_page(&mut self) -> Result<(), rusqlite::Error> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT relative_path, last_modified_timestamp, content_id, revision_id FROM file
+            WHERE {} AND (?2 IS NULL OR relative_path > ?2) ORDER BY relative_path LIMIT ?3",
+            PREFIX_CONDITION
+        ))?;
+        let entries = stmt
+            .query_map(
+                params![self.prefix, self.last_relative_path, self.page_size],
+                IndexEntry::from_row,
+            )?
+            .collect::<Result<Vec<IndexEntry>, rusqlite::Error>>()?;
+
+        if (entries.len() as u64) < self.page_size {
+            self.exhausted = true;
+        }
+        if let Some(last_entry) = entries.last() {
+            self.last_relative_path = Some(last_entry.relative_path.clone());
+        }
+        self.page.extend(entries);
+        Ok(())
+    }
+}
+
+impl<'d> Iterator for EntriesCursor<'d> {
+    type Item = Result<IndexEntry, rusqlite::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.exhausted {
+            if let Err(error) = self.load_next_page() {
+                self.exhausted = true;
+                return Some(Err(error));
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::*;
+    use crate::{testing::connection, util};
+
+    #[test]
+    fn timestamps_round_trip_through_index() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        let times = [
+            UNIX_EPOCH - Duration::from_secs(3600),
+            UNIX_EPOCH,
+            UNIX_EPOCH + Duration::from_millis(1_634_300_000_123),
+            // Year 9999, beyond 32 bits seconds
+            UNIX_EPOCH + Duration::from_millis(253_402_300_799_999),
+        ];
+
+        for (content_id, time) in times.iter().enumerate() {
+            let relative_path = format!("file_{}.txt", content_id);
+            let timestamp = util::system_time_to_timestamp(*time);
+            database_operation
+                .insert_new_file(
+                    relative_path.clone(),
+                    timestamp,
+                    content_id as ContentId,
+                    1,
+                    None,
+                    false,
+                )
+                .unwrap();
+
+            assert_eq!(
+                database_operation
+                    .get_last_modified_timestamp(&relative_path)
+                    .unwrap(),
+                timestamp
+            );
+        }
+
+        assert_eq!(
+            database_operation
+                .get_last_modified_timestamp("file_0.txt")
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            database_operation
+                .get_last_modified_timestamp("file_3.txt")
+                .unwrap(),
+            253_402_300_799_999
+        );
+        assert!(util::system_time_to_timestamp(SystemTime::now()) > 0);
+    }
+
+    #[test]
+    fn relative_path_ignoring_case_folds_non_ascii_letters() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("Été/Noël.txt".to_string(), 0, 1, 1, None, false)
+            .unwrap();
+
+        assert_eq!(
+            database_operation
+                .get_relative_path_ignoring_case("été/noël.txt")
+                .unwrap(),
+            Some("Été/Noël.txt".to_string())
+        );
+        assert_eq!(
+            database_operation
+                .get_relative_path_ignoring_case("Été/Noël.txt")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn deleted_file_leaves_no_failure_nor_rejection() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        for (content_id, relative_path) in [(1, "deleted.txt"), (2, "kept.txt")] {
+            database_operation
+                .insert_new_file(relative_path.to_string(), 0, content_id, 1, None, false)
+                .unwrap();
+            database_operation
+                .record_failure(relative_path, "error", None, None, 0)
+                .unwrap();
+            database_operation
+                .insert_rejection(&Rejection {
+                    relative_path: relative_path.to_string(),
+                    reason: "refused".to_string(),
+                    rejected_timestamp: 0,
+                    last_modified_timestamp: None,
+                    checksum: None,
+                })
+                .unwrap();
+        }
+
+        database_operation.delete_file(1).unwrap();
+
+        assert!(database_operation
+            .get_failure("deleted.txt")
+            .unwrap()
+            .is_none());
+        assert!(database_operation
+            .get_rejection("deleted.txt")
+            .unwrap()
+            .is_none());
+        assert!(database_operation
+            .get_failure("kept.txt")
+            .unwrap()
+            .is_some());
+        assert!(database_operation
+            .get_rejection("kept.txt")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn failing_transaction_leaves_index_unchanged() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("kept.txt".to_string(), 0, 1, 1, None, false)
+            .unwrap();
+
+        let result: Result<(), rusqlite::Error> =
+            database_operation.with_transaction(|database_operation| {
+                database_operation.insert_new_file("new.txt".to_string(), 0, 2, 1, None, false)?;
+                database_operation.update_revision_id("kept.txt".to_string(), 2)?;
+                // Nested transaction runs in the enclosing one
+                database_operation.with_transaction(|database_operation| {
+                    database_operation.set_meta("key", "value")?;
+                    database_operation.execute("INSERT INTO missing_table VALUES (1)", [])
+                })?;
+                Ok(())
+            });
+
+        assert!(result.is_err());
+        assert!(connection.is_autocommit());
+        assert!(!database_operation
+            .relative_path_is_known(&"new.txt".to_string())
+            .unwrap());
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            1
+        );
+        assert_eq!(database_operation.get_meta("key").unwrap(), None);
+    }
+
+    #[test]
+    fn succeeding_transaction_is_committed() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+
+        database_operation
+            .with_transaction(|database_operation| {
+                database_operation.insert_new_file("new.txt".to_string(), 0, 2, 1, None, false)?;
+                database_operation.set_meta("key", "value")
+            })
+            .unwrap();
+
+        assert!(connection.is_autocommit());
+        assert!(database_operation
+            .relative_path_is_known(&"new.txt".to_string())
+            .unwrap());
+        assert_eq!(
+            database_operation.get_meta("key").unwrap(),
+            Some("value".to_string())
+        );
+    }
+
+    #[test]
+    fn moved_entry_leaves_one_consistent_row() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("folder".to_string(), 0, 1, 1, None, true)
+            .unwrap();
+        database_operation
+            .insert_new_file("folder/file.txt".to_string(), 0, 2, 1, None, false)
+            .unwrap();
+
+        database_operation.move_entry(1, "moved", 5).unwrap();
+
+        let rows = |content_id: ContentId| -> Vec<(String, RevisionId)> {
+            let mut stmt = connection
+                .prepare("SELECT relative_path, revision_id FROM file WHERE content_id = ?")
+                .unwrap();
+            stmt.query_map(params![content_id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        assert_eq!(rows(1), vec![("moved".to_string(), 5)]);
+        assert_eq!(rows(2), vec![("moved/file.txt".to_string(), 1)]);
+        assert_eq!(database_operation.count_entries("").unwrap(), 2);
+        assert_eq!(database_operation.count_entries("folder").unwrap(), 0);
+    }
+
+    #[test]
+    fn relative_path_ignoring_case_follows_moves_and_older_index() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("Folder".to_string(), 0, 1, 1, None, true)
+            .unwrap();
+        database_operation
+            .insert_new_file("Folder/File.txt".to_string(), 0, 2, 1, None, false)
+            .unwrap();
+        database_operation.move_entry(1, "Moved", 2).unwrap();
+        // Index written by an older trsync version has no folded path
+        database_operation
+            .insert_new_file("Other.txt".to_string(), 0, 3, 1, None, false)
+            .unwrap();
+        connection
+            .execute(
+                "UPDATE file SET folded_relative_path = NULL WHERE relative_path = 'Other.txt'",
+                [],
+            )
+            .unwrap();
+        database_operation.create_tables().unwrap();
+
+        for (relative_path, indexed_relative_path) in [
+            ("moved/file.txt", Some("Moved/File.txt")),
+            ("folder/file.txt", None),
+            ("OTHER.TXT", Some("Other.txt")),
+        ] {
+            assert_eq!(
+                database_operation
+                    .get_relative_path_ignoring_case(relative_path)
+                    .unwrap()
+                    .as_deref(),
+                indexed_relative_path
+            );
+        }
+    }
+
+    fn insert_entries(database_operation: &DatabaseOperation, relative_paths: &[&str]) {
+        for (content_id, relative_path) in relative_paths.iter().enumerate() {
+            database_operation
+                .insert_new_file(
+                    relative_path.to_string(),
+                    0,
+                    content_id as ContentId + 1,
+                    1,
+                    None,
+                    false,
+                )
+                .unwrap();
+        }
+    }
+
+    fn relative_paths(entries: &[IndexEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.relative_path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn empty_pages_are_rejected() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        insert_entries(&database_operation, &["a.txt"]);
+
+        assert!(matches!(
+            database_operation.list_entries_paged("", 0, 0),
+            Err(Error::UnexpectedError(_))
+        ));
+        assert!(matches!(
+            database_operation.entries_cursor("", 0),
+            Err(Error::UnexpectedError(_))
+        ));
+    }
+
+    #[test]
+    fn paged_entries_follow_prefix_and_offset() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        insert_entries(
+            &database_operation,
+            &["docs", "docs/a.txt", "docs/b.txt", "docs2/c.txt", "e.txt"],
+        );
+
+        let first_page = database_operation.list_entries_paged("docs", 0, 2).unwrap();
+        let second_page = database_operation.list_entries_paged("docs", 2, 2).unwrap();
+        let all = database_operation.list_entries_paged("", 0, 10).unwrap();
+
+        assert_eq!(relative_paths(&first_page), vec!["docs", "docs/a.txt"]);
+        assert_eq!(relative_paths(&second_page), vec!["docs/b.txt"]);
+        assert_eq!(all.len(), 5);
+    }
+
+    #[test]
+    fn entries_cursor_loads_pages_from_last_seen_path() {
+        let connection = connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        insert_entries(
+            &database_operation,
+            &[
+                "docs",
+                "docs/a.txt",
+                "docs/b.txt",
+                "docs/c.txt",
+                "docs2/d.txt",
+            ],
+        );
+
+        let mut cursor = database_operation.entries_cursor("docs", 2).unwrap();
+        let mut seen = vec![];
+        for _ in 0..2 {
+            seen.push(cursor.next().unwrap().unwrap());
+        }
+        // Entries before the last seen one don't shift the next pages
+        database_operation.delete_file(1).unwrap();
+        seen.extend(cursor.map(|entry| entry.unwrap()));
+
+        assert_eq!(
+            relative_paths(&seen),
+            vec!["docs", "docs/a.txt", "docs/b.txt", "docs/c.txt"]
+        );
+    }
 }
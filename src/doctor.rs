@@ -0,0 +1,510 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{Connection, OpenFlags};
+use walkdir::WalkDir;
+
+use crate::{
+    client::Client,
+    context::{self, Context, FOLDER_PATH_META_KEY, METADATA_DIR_NAME},
+    database::DatabaseOperation,
+    error::Error,
+    local::LocalSync,
+    operation::OperationalMessage,
+    types::ClockCheck,
+};
+
+const DOCTOR_PROBE_FILE_NAME: &str = "doctor.probe";
+// Ignored paths given as example in findings
+const IGNORED_EXAMPLES_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Ok => write!(f, "ok"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} : {}", self.severity, self.check, self.message)
+    }
+}
+
+// Diagnose common misconfigurations (folder, index, credentials, workspace, clock,
+// ignore patterns) without synchronizing anything
+pub struct Doctor {
+    context: Context,
+    findings: Vec<Finding>,
+}
+
+impl Doctor {
+    pub fn new(mut context: Context) -> Self {
+        context.resolve_owned_paths();
+        Self {
+            context,
+            findings: vec![],
+        }
+    }
+
+    pub fn run(mut self) -> Vec<Finding> {
+        if self.check_folder() {
+            self.check_nested_folders();
+            self.check_database();
+            self.check_ignored_files();
+        }
+        self.check_stale_databases();
+        // Workspace and clock checks need to be authenticated
+        if self.check_credentials() {
+            self.check_workspace();
+            self.check_clock();
+        }
+        self.findings
+    }
+
+    fn found(&mut self, severity: Severity, check: &'static str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            check,
+            message,
+        })
+    }
+
+    fn check_folder(&mut self) -> bool {
+        let folder_path = Path::new(&self.context.folder_path);
+        if !folder_path.is_dir() {
+            self.found(
+                Severity::Error,
+                "folder",
+                format!("{:?} is not an existing directory", folder_path),
+            );
+            return false;
+        }
+
+        // Probe is written in metadata directory, which is never synchronized. When it
+        // does not exist yet, creating it is the probe : nothing is written among
+        // synchronized files.
+        let metadata_dir = self.context.metadata_dir();
+        let (probe_dir, probed) = if metadata_dir.is_dir() {
+            let probe_path = metadata_dir.join(DOCTOR_PROBE_FILE_NAME);
+            let probed = fs::write(&probe_path, b"").and_then(|_| fs::remove_file(&probe_path));
+            (metadata_dir, probed)
+        } else {
+            let probed = fs::create_dir(&metadata_dir).and_then(|_| fs::remove_dir(&metadata_dir));
+            (folder_path.to_path_buf(), probed)
+        };
+        match probed {
+            Ok(_) => {
+                self.found(
+                    Severity::Ok,
+                    "folder",
+                    format!("{:?} is writable", folder_path),
+                );
+                true
+            }
+            Err(error) => {
+                self.found(
+                    Severity::Error,
+                    "folder",
+                    format!(
+                        "{:?} is not writable ({}), check its permissions",
+                        probe_dir, error
+                    ),
+                );
+                false
+            }
+        }
+    }
+
+    // Metadata (partial downloads, index) inside another synchronized folder would be
+    // synchronized by the other one
+    fn check_nested_folders(&mut self) {
+        let folder_path = Path::new(&self.context.folder_path).to_path_buf();
+        for ancestor in folder_path.ancestors().skip(1) {
+            if Self::is_synchronized_folder(ancestor) {
+                self.found(
+                    Severity::Error,
+                    "index location",
+                    format!(
+                        "Folder is inside {:?}, already synchronized by trsync : its \
+                         metadata would be synchronized too, choose a folder outside of it",
+                        ancestor
+                    ),
+                );
+                return;
+            }
+        }
+
+        let nested: Vec<PathBuf> = WalkDir::new(&folder_path)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !self.context.is_owned_path(entry.path()))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.file_type().is_dir() && Self::is_synchronized_folder(entry.path())
+            })
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+        if nested.is_empty() {
+            self.found(
+                Severity::Ok,
+                "index location",
+                "No other trsync folder around this one".to_string(),
+            );
+        } else {
+            self.found(
+                Severity::Error,
+                "index location",
+                format!(
+                    "Folder contains folders synchronized by another trsync : {:?}, their \
+                     metadata would be synchronized too",
+                    nested
+                ),
+            );
+        }
+    }
+
+    fn is_synchronized_folder(path: &Path) -> bool {
+        path.join(METADATA_DIR_NAME).is_dir()
+    }
+
+    fn check_database(&mut self) {
+        if !Path::new(&self.context.database_path).exists() {
+            self.found(
+                Severity::Ok,
+                "index",
+                "No index yet, it will be created by first synchronization".to_string(),
+            );
+            return;
+        }
+
+        let checked = Connection::open_with_flags(
+            &self.context.database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .and_then(|connection| {
+            connection.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0))
+        });
+        match checked {
+            Ok(result) if result == "ok" => self.found(
+                Severity::Ok,
+                "index",
+                format!("{} is readable and consistent", self.context.database_path),
+            ),
+            Ok(result) => self.found(
+                Severity::Error,
+                "index",
+                format!(
+                    "{} is corrupted ({}), remove it to rebuild index at next synchronization",
+                    self.context.database_path, result
+                ),
+            ),
+            Err(error) => self.found(
+                Severity::Error,
+                "index",
+                format!(
+                    "{} is unusable ({}), check its permissions or remove it",
+                    self.context.database_path, error
+                ),
+            ),
+        }
+    }
+
+    // Databases in user data directory are kept when their folder is removed
+    fn check_stale_databases(&mut self) {
+        let databases_dir = match context::databases_dir() {
+            Some(databases_dir) if databases_dir.is_dir() => databases_dir,
+            _ => return,
+        };
+
+        let mut stale = vec![];
+        for entry in WalkDir::new(&databases_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extension == "db")
+            })
+        {
+            let folder_path =
+                Connection::open_with_flags(entry.path(), OpenFlags::SQLITE_OPEN_READ_ONLY)
+                    .and_then(|connection| {
+                        DatabaseOperation::new(&connection).get_meta(FOLDER_PATH_META_KEY)
+                    });
+            // Databases of previous versions don't know their folder
+            if let Ok(Some(folder_path)) = folder_path {
+                if !Path::new(&folder_path).is_dir() {
+                    stale.push(format!("{:?} (of {:?})", entry.path(), folder_path));
+                }
+            }
+        }
+        if stale.is_empty() {
+            self.found(
+                Severity::Ok,
+                "stale indexes",
+                format!("No index of removed folder in {:?}", databases_dir),
+            );
+        } else {
+            self.found(
+                Severity::Warning,
+                "stale indexes",
+                format!(
+                    "Indexes of removed folders, remove them if these folders are no longer \
+                     synchronized : {}",
+                    stale.join(", ")
+                ),
+            );
+        }
+    }
+
+    // Compare disk files with what a local sync, against an empty index, would upload
+    fn check_ignored_files(&mut self) {
+        let synchronized = match self.dry_local_sync() {
+            Ok(synchronized) => synchronized,
+            Err(error) => {
+                self.found(
+                    Severity::Error,
+                    "ignored files",
+                    format!("Unable to scan folder : {:?}", error),
+                );
+                return;
+            }
+        };
+
+        let folder_path = Path::new(&self.context.folder_path).to_path_buf();
+        let ignored: Vec<String> = WalkDir::new(&folder_path)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !self.context.is_owned_path(entry.path()))
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&folder_path)
+                    .ok()
+                    .and_then(|relative_path| relative_path.to_str())
+                    .map(|relative_path| relative_path.to_string())
+            })
+            // Content of an ignored folder is not listed by itself
+            .filter(|relative_path| {
+                self.context.is_ignored_relative_path(relative_path)
+                    && !Path::new(relative_path)
+                        .parent()
+                        .and_then(|parent| parent.to_str())
+                        .map(|parent| self.context.is_ignored_relative_path(parent))
+                        .unwrap_or(false)
+            })
+            .collect();
+
+        if synchronized == 0 && !ignored.is_empty() {
+            self.found(
+                Severity::Error,
+                "ignored files",
+                format!(
+                    "Every local file is ignored (for example {:?}), check ignore patterns {:?}",
+                    &ignored[..ignored.len().min(IGNORED_EXAMPLES_COUNT)],
+                    self.context
+                        .ignore_patterns
+                        .iter()
+                        .map(|pattern| pattern.as_str())
+                        .collect::<Vec<&str>>()
+                ),
+            );
+        } else if !ignored.is_empty() {
+            self.found(
+                Severity::Warning,
+                "ignored files",
+                format!(
+                    "{} local file(s) and folder(s) synchronized, {} ignored (for example {:?})",
+                    synchronized,
+                    ignored.len(),
+                    &ignored[..ignored.len().min(IGNORED_EXAMPLES_COUNT)]
+                ),
+            );
+        } else {
+            self.found(
+                Severity::Ok,
+                "ignored files",
+                format!(
+                    "{} local file(s) and folder(s) synchronized, none ignored",
+                    synchronized
+                ),
+            );
+        }
+    }
+
+    fn dry_local_sync(&self) -> Result<usize, Error> {
+        let connection = Connection::open_in_memory()?;
+        DatabaseOperation::new(&connection).create_tables()?;
+        Ok(LocalSync::new_read_only(self.context.clone(), connection)?
+            .changes()?
+            .iter()
+            .filter(|message| matches!(message, OperationalMessage::NewLocalFile(_)))
+            .count())
+    }
+
+    fn check_credentials(&mut self) -> bool {
+        match Client::new(self.context.clone()).and_then(|client| Ok(client.get_user_id()?)) {
+            Ok(_) => {
+                self.found(
+                    Severity::Ok,
+                    "credentials",
+                    format!(
+                        "Authenticated as {} on {}",
+                        self.context.username, self.context.base_address
+                    ),
+                );
+                true
+            }
+            Err(Error::RemoteUnreachable(reason)) => {
+                self.found(
+                    Severity::Error,
+                    "credentials",
+                    format!(
+                        "Unable to reach {} ({}), check Tracim address and --no-ssl",
+                        self.context.base_address, reason
+                    ),
+                );
+                false
+            }
+            Err(error) => {
+                let secret = match self.context.api_key {
+                    Some(_) => "API key",
+                    None => "password",
+                };
+                self.found(
+                    Severity::Error,
+                    "credentials",
+                    format!(
+                        "Unable to authenticate as {} ({:?}), check username and {}",
+                        self.context.username, error, secret
+                    ),
+                );
+                false
+            }
+        }
+    }
+
+    fn check_workspace(&mut self) {
+        match Client::new(self.context.clone()).and_then(|client| Ok(client.get_workspace_label()?))
+        {
+            Ok(label) => self.found(
+                Severity::Ok,
+                "workspace",
+                format!("Workspace {} is {:?}", self.context.workspace_id, label),
+            ),
+            Err(error) => self.found(
+                Severity::Error,
+                "workspace",
+                format!(
+                    "Workspace {} is not accessible ({:?}), check workspace id and that user \
+                     is member of it",
+                    self.context.workspace_id, error
+                ),
+            ),
+        }
+    }
+
+    fn check_clock(&mut self) {
+        match Client::new(self.context.clone()).and_then(|client| Ok(client.check_clock()?)) {
+            Ok(ClockCheck::Skewed(_, warning)) => self.found(Severity::Warning, "clock", warning),
+            Ok(ClockCheck::InSync(clock_skew_ms)) => self.found(
+                Severity::Ok,
+                "clock",
+                format!("Local clock skew is {} ms", clock_skew_ms),
+            ),
+            Ok(ClockCheck::Unknown) => self.found(
+                Severity::Warning,
+                "clock",
+                "Tracim server do not give its clock, skew can't be checked".to_string(),
+            ),
+            Err(error) => self.found(
+                Severity::Warning,
+                "clock",
+                format!("Unable to compare clocks ({:?})", error),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    fn doctor(stub: &TracimStub, folder: &Path) -> Doctor {
+        Doctor::new(testing::context(stub, folder))
+    }
+
+    #[test]
+    fn folder_probe_writes_nothing_among_synchronized_files() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("doctor-probe");
+        let mut doctor = doctor(&stub, &folder);
+        fs::remove_dir_all(doctor.context.metadata_dir()).unwrap();
+
+        assert!(doctor.check_folder());
+
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 0);
+        assert_eq!(doctor.findings[0].severity, Severity::Ok);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn folder_probe_is_removed_from_metadata_dir() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("doctor-metadata-probe");
+        let mut doctor = doctor(&stub, &folder);
+
+        assert!(doctor.check_folder());
+
+        assert!(!doctor
+            .context
+            .metadata_dir()
+            .join(DOCTOR_PROBE_FILE_NAME)
+            .exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn clock_check_is_the_startup_one() {
+        let stub = TracimStub::start();
+        testing::server_clock_behind(&stub, 3600);
+        let folder = testing::temp_folder("doctor-clock");
+        let mut doctor = doctor(&stub, &folder);
+
+        doctor.check_clock();
+
+        let finding = &doctor.findings[0];
+        assert_eq!(finding.severity, Severity::Warning);
+        assert!(
+            finding.message.starts_with("Local clock is 3") && finding.message.contains("ahead of"),
+            "{}",
+            finding.message
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -6,10 +6,21 @@ use crate::types::{AbsoluteFilePath, ContentId, RevisionId};
 pub enum ClientError {
     InputFileError(AbsoluteFilePath),
     RequestError(String),
+    // Tracim can't be reached (connection refused, timeout, ...)
+    UnreachableRemote(String),
     UnexpectedResponse(String),
     AlreadyExistResponse(ContentId, RevisionId),
     AlreadyExistResponseAndFailToFoundIt(String),
     NotFoundResponse(String),
+    ForbiddenResponse(String),
+    // Tracim refused credentials (password or API key)
+    Unauthorized(String),
+    // Upload refused because workspace (or its owner) has not enough space left
+    QuotaExceeded(String),
+    // Upload refused because file is over Tracim maximum file size
+    FileTooLarge(String),
+    // Creation refused because Tracim does not allow content name or type
+    Rejected(String),
     DecodingResponseError(String),
     NotRelevant(String),
 }
@@ -35,6 +46,9 @@ impl fmt::Display for ClientError {
             ClientError::RequestError(message) => {
                 format!("Error when making request : {}", message)
             }
+            ClientError::UnreachableRemote(message) => {
+                format!("Remote unreachable : {}", message)
+            }
             ClientError::UnexpectedResponse(message) => {
                 format!("UnExpected response : {}", message)
             }
@@ -46,6 +60,11 @@ impl fmt::Display for ClientError {
                 message
             ),
             ClientError::NotFoundResponse(message) => format!("Not found : {}", message),
+            ClientError::ForbiddenResponse(message) => format!("Forbidden : {}", message),
+            ClientError::Unauthorized(message) => format!("Unauthorized : {}", message),
+            ClientError::QuotaExceeded(message) => format!("Quota exceeded : {}", message),
+            ClientError::FileTooLarge(message) => format!("File too large : {}", message),
+            ClientError::Rejected(message) => format!("Rejected : {}", message),
             ClientError::DecodingResponseError(message) => format!("Decoding error : {}", message),
             ClientError::NotRelevant(message) => format!("Note : {}", message),
         };
@@ -56,12 +75,36 @@ impl fmt::Display for ClientError {
 #[derive(Debug)]
 pub enum Error {
     FailToCreateContentOnRemote(String),
+    // Tracim refuse to create this folder (user rights, workspace settings, ...)
+    RemoteFolderCreationForbidden(String),
+    // Parent folder is not on remote and parent creation is rejected
+    MissingRemoteParent(String),
     FailToCreateContentOnLocal(String),
+    IncompleteDownload(String),
+    // Disk is full, operation should be retried once space is freed
+    NoSpaceLeft(String),
+    // Disk refuse the write (read only directory, etc), operation will not succeed by retrying
+    PermissionDenied(String),
     UnIndexedRelativePath(String),
     UnexpectedError(String),
     PathCastingError(String),
     PathManipulationError(String),
     StartupError(String),
+    // Synchronized folder disappeared and did not come back
+    FolderMissing(String),
+    // Inotify watches (or instances) limit reached, folder can't be entirely watched
+    WatchLimitExceeded(String),
+    // Tracim can't be reached, local changes are journaled until it is back
+    RemoteUnreachable(String),
+    // Uploads are blocked until space is freed on remote
+    QuotaExceeded(String),
+    FileTooLarge(String),
+    // Tracim does not allow content name or type
+    Rejected(String),
+    // Tracim refused credentials, retrying is useless
+    Unauthorized(String),
+    // Disk file changed since previous stability probe, upload is deferred
+    FileUnstable(String),
     NotRelevant(String),
 }
 
@@ -69,15 +112,31 @@ impl Error {
     pub fn level(&self) -> log::Level {
         match self {
             Error::NotRelevant(_) => log::Level::Debug,
+            Error::NoSpaceLeft(_)
+            | Error::RemoteUnreachable(_)
+            | Error::FileTooLarge(_)
+            | Error::Rejected(_)
+            | Error::WatchLimitExceeded(_) => log::Level::Warn,
+            // Reported once when uploads become blocked
+            Error::QuotaExceeded(_) | Error::FileUnstable(_) => log::Level::Info,
             _ => log::Level::Error,
         }
     }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::IncompleteDownload(_) | Error::NoSpaceLeft(_))
+    }
 }
 
 impl From<ClientError> for Error {
     fn from(err: ClientError) -> Self {
         match err {
             ClientError::NotRelevant(message) => Error::NotRelevant(message),
+            ClientError::UnreachableRemote(message) => Error::RemoteUnreachable(message),
+            ClientError::QuotaExceeded(message) => Error::QuotaExceeded(message),
+            ClientError::FileTooLarge(message) => Error::FileTooLarge(message),
+            ClientError::Rejected(message) => Error::Rejected(message),
+            ClientError::Unauthorized(message) => Error::Unauthorized(message),
             _ => Error::UnexpectedError(format!("{:?}", err)),
         }
     }
@@ -124,3 +183,9 @@ impl From<reqwest::Error> for Error {
         Error::UnexpectedError(format!("reqwest error {:?}", error))
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::UnexpectedError(format!("json error {:?}", error))
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use crate::{operation::OperationalMessage, types::RelativeFilePath};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncEvent {
+    // Operation succeed. Path is the one of the synchronized file, when still known.
+    Synced(OperationalMessage, Option<RelativeFilePath>),
+    // File changed both on disk and on remote
+    Conflict(RelativeFilePath),
+    // Operation failed (retryable failures are reported on each attempt)
+    Error(OperationalMessage, String),
+    // Remote refused an upload for lack of space : uploads are blocked until space is
+    // freed (reported once)
+    QuotaExceeded(String),
+}
+
+// Dispatch synchronization events to subscribers. Subscribers which dropped their
+// receiver are forgotten on next event.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    senders: Arc<Mutex<Vec<Sender<SyncEvent>>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> Receiver<SyncEvent> {
+        let (sender, receiver) = channel();
+        if let Ok(mut senders) = self.senders.lock() {
+            senders.push(sender);
+        }
+        receiver
+    }
+
+    pub fn publish(&self, event: SyncEvent) {
+        if let Ok(mut senders) = self.senders.lock() {
+            senders.retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
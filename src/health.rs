@@ -0,0 +1,318 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    context::Context,
+    error::Error,
+    status::{Phase, SharedStatus, Status},
+    util,
+};
+
+// Connections are answered one by one : a client sending nothing must not block others
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+// Status files are written at most once per interval, changes made meanwhile are
+// written at its end
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Expose synchronization status to supervisors : as a JSON file rewritten on changes
+// (at most once per report interval), and/or as a JSON HTTP response (status code 503
+// until trsync watches changes).
+// Status is also always written into metadata directory for status command.
+#[derive(Clone)]
+pub struct HealthReporter {
+    status: SharedStatus,
+    file_paths: Vec<PathBuf>,
+    schedule: Arc<Mutex<ReportSchedule>>,
+}
+
+#[derive(Default)]
+struct ReportSchedule {
+    last_written: Option<Instant>,
+    // A write is planned at the end of current interval
+    deferred: bool,
+}
+
+impl HealthReporter {
+    pub fn new(context: &Context) -> Self {
+        let mut file_paths = vec![context.live_status_path()];
+        file_paths.extend(context.health_file.clone());
+        Self {
+            status: context.status.clone(),
+            file_paths,
+            schedule: Arc::new(Mutex::new(ReportSchedule::default())),
+        }
+    }
+
+    // Phase changes are written at once : process can exit right after
+    pub fn set_phase(&self, phase: Phase) {
+        if let Ok(mut status) = self.status.lock() {
+            status.phase = phase;
+        }
+        if let Ok(mut schedule) = self.schedule.lock() {
+            schedule.last_written = Some(Instant::now());
+        }
+        self.write();
+    }
+
+    // Phase becomes watching once startup changes are proceed
+    pub fn startup_finished(&self) {
+        let changed = match self.status.lock() {
+            Ok(mut status) if status.phase == Phase::Synchronizing => {
+                status.phase = Phase::Watching;
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            self.report();
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if let Ok(mut status) = self.status.lock() {
+            status.paused = paused;
+        }
+        self.report();
+    }
+
+    pub fn set_clock_skew(&self, clock_skew_ms: i64) {
+        if let Ok(mut status) = self.status.lock() {
+            status.clock_skew_ms = Some(clock_skew_ms);
+        }
+        self.report();
+    }
+
+    pub fn operation_succeed(&self) {
+        if let Ok(mut status) = self.status.lock() {
+            status.last_success_timestamp = Some(util::system_time_to_timestamp(SystemTime::now()));
+        }
+        self.report();
+    }
+
+    pub fn operation_failed(&self, error: String) {
+        if let Ok(mut status) = self.status.lock() {
+            status.errors += 1;
+            status.last_error = Some(error);
+        }
+        self.report();
+    }
+
+    // Written with next report, as operation outcome is reported right after
+    pub fn operation_timed(&self, kind: &str, duration: Duration) {
+        if let Ok(mut status) = self.status.lock() {
+            status
+                .timings
+                .entry(kind.to_string())
+                .or_default()
+                .record(duration);
+        }
+    }
+
+    pub fn set_queue_depth(&self, queue_depth: usize) {
+        let changed = match self.status.lock() {
+            Ok(mut status) => {
+                let changed = status.queue_depth != queue_depth;
+                status.queue_depth = queue_depth;
+                changed
+            }
+            Err(_) => false,
+        };
+        if changed {
+            self.report();
+        }
+    }
+
+    // Write status files, or plan to write them at the end of current interval
+    pub fn report(&self) {
+        let delay = match self.schedule.lock() {
+            // Planned write will include this change
+            Ok(schedule) if schedule.deferred => return,
+            Ok(mut schedule) => match schedule.last_written.map(|written| written.elapsed()) {
+                Some(elapsed) if elapsed < REPORT_INTERVAL => {
+                    schedule.deferred = true;
+                    Some(REPORT_INTERVAL - elapsed)
+                }
+                _ => {
+                    schedule.last_written = Some(Instant::now());
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        match delay {
+            Some(delay) => {
+                let reporter = self.clone();
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    if let Ok(mut schedule) = reporter.schedule.lock() {
+                        schedule.deferred = false;
+                        schedule.last_written = Some(Instant::now());
+                    }
+                    reporter.write();
+                });
+            }
+            None => self.write(),
+        }
+    }
+
+    // Files are replaced at once so that readers never see a partially written file
+    fn write(&self) {
+        let content = match self.status.lock() {
+            Ok(status) => status_json(&status).to_string(),
+            Err(error) => {
+                log::error!("Unable to read status : {:?}", error);
+                return;
+            }
+        };
+
+        for file_path in &self.file_paths {
+            let temp_file_path = file_path.with_extension("tmp");
+            if let Err(error) = fs::write(&temp_file_path, &content)
+                .and_then(|_| fs::rename(&temp_file_path, file_path))
+            {
+                log::error!("Unable to write status file {:?} : {:?}", file_path, error)
+            }
+        }
+    }
+
+    // Answer status to each connection on given address, in a dedicated thread
+    pub fn serve(&self, address: &str) -> Result<(), Error> {
+        let listener = TcpListener::bind(address).map_err(|error| {
+            Error::StartupError(format!(
+                "Unable to listen health address {} : {:?}",
+                address, error
+            ))
+        })?;
+        log::info!("Serve health status on {}", address);
+
+        let status = self.status.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(error) = answer(stream, &status) {
+                            log::debug!("Error when answering health request : {:?}", error)
+                        }
+                    }
+                    Err(error) => log::error!("Health connection failed : {:?}", error),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn answer(mut stream: TcpStream, status: &SharedStatus) -> Result<(), Error> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    // Request content is not relevant, read it only to not reset connection
+    let mut buffer = [0; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    let (ready, body) = match status.lock() {
+        Ok(status) => (
+            status.phase == Phase::Watching,
+            status_json(&status).to_string(),
+        ),
+        Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
+    };
+    let status_line = if ready {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_line,
+        body.len(),
+        body
+    )?;
+
+    Ok(())
+}
+
+fn status_json(status: &Status) -> Value {
+    json!({
+        "phase": status.phase.to_string(),
+        "last_success_timestamp": status.last_success_timestamp,
+        "queue_depth": status.queue_depth,
+        "errors": status.errors,
+        "last_error": status.last_error,
+        "resolved_conflicts": status.resolved_conflicts,
+        "paused": status.paused,
+        "timings": status.timings,
+        "clock_skew_ms": status.clock_skew_ms,
+        "updated_timestamp": util::system_time_to_timestamp(SystemTime::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    #[test]
+    fn silent_client_does_not_block_health_requests() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("health");
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        HealthReporter::new(&testing::context(&stub, &folder))
+            .serve(&address)
+            .unwrap();
+
+        let _silent = TcpStream::connect(&address).unwrap();
+        let mut stream = TcpStream::connect(&address).unwrap();
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT * 5)).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line).unwrap();
+
+        assert!(status_line.starts_with("HTTP/1.1 503"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    fn written_errors(context: &Context) -> u64 {
+        let content = fs::read_to_string(context.live_status_path()).unwrap();
+        serde_json::from_str::<Value>(&content).unwrap()["errors"]
+            .as_u64()
+            .unwrap()
+    }
+
+    #[test]
+    fn status_file_is_written_at_most_once_per_interval() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("health-throttle");
+        let context = testing::context(&stub, &folder);
+        let reporter = HealthReporter::new(&context);
+
+        reporter.operation_failed("first".to_string());
+        for error in ["second", "third"] {
+            reporter.operation_failed(error.to_string());
+        }
+        let written_at_once = written_errors(&context);
+        thread::sleep(REPORT_INTERVAL + Duration::from_millis(500));
+        let written_after_interval = written_errors(&context);
+
+        assert_eq!(written_at_once, 1);
+        assert_eq!(written_after_interval, 3);
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{context::Context, types::RelativeFilePath};
+
+const DEFAULT_HOOK_TIMEOUT_S: u64 = 60;
+const HOOK_REAP_INTERVAL_MS: u64 = 100;
+// Same hook is not run again for the same path during this delay. Files written by
+// a hook into the workspace are synchronized like any other change : this prevent
+// a hook reacting to its own changes to loop quickly.
+const HOOK_DEBOUNCE_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookEvent {
+    AfterDownload,
+    AfterUpload,
+    OnConflict,
+    OnError,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookEvent::AfterDownload => write!(f, "after-download"),
+            HookEvent::AfterUpload => write!(f, "after-upload"),
+            HookEvent::OnConflict => write!(f, "on-conflict"),
+            HookEvent::OnError => write!(f, "on-error"),
+        }
+    }
+}
+
+// Shell commands run on synchronization events. They receive event details as
+// TRSYNC_* environment variables.
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    pub after_download: Option<String>,
+    pub after_upload: Option<String>,
+    pub on_conflict: Option<String>,
+    pub on_error: Option<String>,
+    // Hooks still running after this delay are killed
+    pub timeout: Duration,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            after_download: None,
+            after_upload: None,
+            on_conflict: None,
+            on_error: None,
+            timeout: Duration::from_secs(DEFAULT_HOOK_TIMEOUT_S),
+        }
+    }
+}
+
+impl Hooks {
+    fn command(&self, event: HookEvent) -> Option<&String> {
+        match event {
+            HookEvent::AfterDownload => self.after_download.as_ref(),
+            HookEvent::AfterUpload => self.after_upload.as_ref(),
+            HookEvent::OnConflict => self.on_conflict.as_ref(),
+            HookEvent::OnError => self.on_error.as_ref(),
+        }
+    }
+}
+
+// Run hooks without waiting for them : each started hook is reaped by its own thread
+pub struct HookRunner {
+    context: Context,
+    last_runs: HashMap<(HookEvent, Option<RelativeFilePath>), Instant>,
+}
+
+impl HookRunner {
+    pub fn new(context: Context) -> Self {
+        Self {
+            context,
+            last_runs: HashMap::new(),
+        }
+    }
+
+    // Hook failures are logged but never fail the operation
+    pub fn run(
+        &mut self,
+        event: HookEvent,
+        operation: &str,
+        relative_path: Option<RelativeFilePath>,
+        error: Option<String>,
+    ) {
+        let command = match self.context.hooks.command(event) {
+            Some(command) => command.clone(),
+            None => return,
+        };
+
+        let now = Instant::now();
+        self.last_runs.retain(|_, last_run| {
+            now.duration_since(*last_run) < Duration::from_millis(HOOK_DEBOUNCE_MS)
+        });
+        let key = (event, relative_path.clone());
+        if self.last_runs.contains_key(&key) {
+            log::info!(
+                "Hook {} already run for {:?} recently, skip it",
+                event,
+                relative_path
+            );
+            return;
+        }
+        self.last_runs.insert(key, now);
+
+        log::debug!("Run hook {} for {:?} : {}", event, relative_path, command);
+        let child = shell_command(&command)
+            .current_dir(&self.context.folder_path)
+            .env("TRSYNC_EVENT", event.to_string())
+            .env("TRSYNC_OPERATION", operation)
+            .env("TRSYNC_RELATIVE_PATH", relative_path.unwrap_or_default())
+            .env("TRSYNC_WORKSPACE_ID", self.context.workspace_id.to_string())
+            .env("TRSYNC_FOLDER_PATH", &self.context.folder_path)
+            .env("TRSYNC_ERROR", error.unwrap_or_default())
+            .spawn();
+        match child {
+            Ok(child) => {
+                let timeout = self.context.hooks.timeout;
+                thread::spawn(move || reap(child, event, timeout));
+            }
+            Err(error) => log::error!("Unable to run hook {} : {:?}", event, error),
+        }
+    }
+}
+
+fn reap(mut child: Child, event: HookEvent, timeout: Duration) {
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return,
+            Ok(Some(status)) => {
+                log::error!("Hook {} failed with {}", event, status);
+                return;
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                log::error!("Hook {} still running after {:?}, kill it", event, timeout);
+                if let Err(error) = child.kill().and_then(|_| child.wait()) {
+                    log::error!("Unable to kill hook {} : {:?}", event, error)
+                }
+                return;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(HOOK_REAP_INTERVAL_MS)),
+            Err(error) => {
+                log::error!("Unable to wait hook {} : {:?}", event, error);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut command_ = Command::new("cmd");
+    command_.args(["/C", command]);
+    command_
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut command_ = Command::new("sh");
+    command_.args(["-c", command]);
+    command_
+}
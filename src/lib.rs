@@ -0,0 +1,629 @@
+extern crate notify;
+
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::client::Client;
+use crate::context::{Context, FOLDER_PATH_META_KEY};
+use crate::database::{Database, DatabaseOperation};
+use crate::error::Error;
+use crate::event::SyncEvent;
+use crate::health::HealthReporter;
+use crate::local::{LocalSync, LocalWatcher};
+use crate::operation::{clear_conflict, OperationalHandler, OperationalMessage};
+use crate::remote::{PollingRemoteWatcher, RemoteSync, RemoteWatcher};
+use crate::status::{Phase, Status};
+use crate::types::{ClockCheck, ContentId, RelativeFilePath, RemoteMode};
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod client;
+pub mod compaction;
+pub mod context;
+pub mod database;
+pub mod doctor;
+pub mod error;
+pub mod event;
+pub mod health;
+pub mod hook;
+pub mod local;
+pub mod metrics;
+pub mod operation;
+pub mod pacing;
+pub mod remote;
+pub mod status;
+#[cfg(test)]
+mod testing;
+pub mod timing;
+pub mod types;
+pub mod util;
+
+/// Entry point to embed trsync in another application :
+///
+/// ```no_run
+/// use trsync::{context::Context, Trsync};
+///
+/// # fn main() -> Result<(), trsync::error::Error> {
+/// let context = Context::new(
+///     true,
+///     "tracim.example.com".to_string(),
+///     "user".to_string(),
+///     "password".to_string(),
+///     "/home/user/Tracim".to_string(),
+///     1,
+///     false,
+/// )?;
+/// let handle = Trsync::start(context)?;
+/// let events = handle.subscribe();
+/// // ...
+/// # drop(events);
+/// handle.stop()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Threads (startup syncs, watchers and operational handler) are owned by the
+/// returned handle.
+pub struct Trsync;
+
+impl Trsync {
+    /// Prepare metadata directory and index, then start synchronization threads.
+    /// Events produced before subscribing to the handle can be received by
+    /// subscribing to `context.events` before calling this function.
+    pub fn start(mut context: Context) -> Result<TrsyncHandle, Error> {
+        // Refuse invalid request headers before anything starts
+        client::default_headers(&context)?;
+        context.apply_filesystem_profile();
+
+        // Prepare main channel, and bounded local watcher one (local watcher wakes
+        // operational handler up through main channel)
+        let (operational_sender, operational_receiver) = channel();
+        let (local_watcher_sender, local_watcher_receiver) =
+            sync_channel(context.watch_channel_capacity.max(1));
+
+        // Requested forced downloads and pushes are the first operations proceed
+        for content_id in &context.force_downloads {
+            operational_sender
+                .send(OperationalMessage::ForceDownload(*content_id))
+                .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?;
+        }
+        for relative_path in &context.force_pushes {
+            operational_sender
+                .send(OperationalMessage::ForcePush(relative_path.clone()))
+                .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?;
+        }
+
+        // Initialize database if needed
+        log::info!("Initialize index");
+        context.prepare_metadata_dir()?;
+        context.resolve_owned_paths();
+        context.clean_partial_files()?;
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let database_operation = DatabaseOperation::new(&connection);
+            database_operation.create_tables()?;
+            database_operation.set_meta(FOLDER_PATH_META_KEY, &context.folder_path)?;
+            Ok(())
+        })?;
+
+        let health = HealthReporter::new(&context);
+        if let Some(health_address) = &context.health_address {
+            health.serve(health_address)?;
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics_listen) = &context.metrics_listen {
+            context.metrics.serve(&context, metrics_listen)?;
+        }
+        check_clock_skew(&context, &health);
+        health.set_phase(Phase::Synchronizing);
+        warn_owned_paths_in_folder(&context);
+
+        log::info!("Start synchronization");
+
+        // Watchers start while startup syncs are running, so that no change is lost between
+        // disk scan and watching. A change made during startup can then be reported by both
+        // local sync and local watcher : operational handler only upload disk content which
+        // is not already indexed, so the second message do nothing.
+        log::info!("Start watchers");
+
+        // Start local watcher
+        let local_watcher_context = context.clone();
+        let mut local_watcher = LocalWatcher::new(
+            local_watcher_context.clone(),
+            local_watcher_sender,
+            operational_sender.clone(),
+        )?;
+        let local_handle = spawn(&context, &operational_sender, move || {
+            if !local_watcher_context.exit_after_sync {
+                local_watcher.listen(local_watcher_context.folder_path.clone())
+            } else {
+                Ok(())
+            }
+        });
+
+        // Start remote watcher
+        let remote_watcher_operational_sender = operational_sender.clone();
+        let remote_watcher_context = context.clone();
+        let remote_handle = spawn(&context, &operational_sender, move || {
+            if !remote_watcher_context.exit_after_sync {
+                remote_watch(remote_watcher_context, remote_watcher_operational_sender)
+            } else {
+                Ok(())
+            }
+        });
+
+        // Operational handler start once startup syncs are finished
+        let operational_context = context.clone();
+        let operational_sender_ = operational_sender.clone();
+        let operational_handle = spawn(&context, &operational_sender, move || {
+            synchronize(operational_context.clone(), operational_sender_)?;
+            Database::new(
+                operational_context.database_path.clone(),
+                operational_context.database_synchronous.clone(),
+            )
+            .with_new_connection(|connection| {
+                OperationalHandler::new(operational_context, connection)?
+                    .listen(operational_receiver, local_watcher_receiver);
+                Ok(())
+            })
+        });
+
+        Ok(TrsyncHandle {
+            context,
+            operational_sender,
+            health,
+            threads: vec![
+                ("local watcher", local_handle),
+                ("remote watcher", remote_handle),
+                ("operational handler", operational_handle),
+            ],
+        })
+    }
+}
+
+/// Control a started trsync. Dropping the handle without calling `stop` or `wait`
+/// let threads run.
+pub struct TrsyncHandle {
+    context: Context,
+    operational_sender: Sender<OperationalMessage>,
+    health: HealthReporter,
+    threads: Vec<(&'static str, JoinHandle<Result<(), Error>>)>,
+}
+
+impl TrsyncHandle {
+    /// Stop proceeding operations. Changes are still watched and proceed on resume.
+    pub fn pause(&self) {
+        log::info!("Pause synchronization");
+        self.context.paused.store(true, Ordering::SeqCst);
+        self.health.set_paused(true);
+    }
+
+    /// Proceed operations again, changes watched while paused included
+    pub fn resume(&self) {
+        log::info!("Resume synchronization");
+        self.context.paused.store(false, Ordering::SeqCst);
+        self.health.set_paused(false);
+    }
+
+    /// Stop synchronizing given content, until it is included again
+    pub fn exclude_content(&self, content_id: ContentId) -> Result<(), Error> {
+        log::info!("Exclude content {} from synchronization", content_id);
+        self.context
+            .excluded_content_ids
+            .lock()
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+            .insert(content_id);
+        Ok(())
+    }
+
+    /// Synchronize given content again. Changes made while it was excluded are proceed.
+    pub fn include_content(&self, content_id: ContentId) -> Result<(), Error> {
+        log::info!("Include content {} in synchronization again", content_id);
+        let was_excluded = self
+            .context
+            .excluded_content_ids
+            .lock()
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+            .remove(&content_id);
+        if !was_excluded {
+            return Ok(());
+        }
+
+        let mut relative_path = None;
+        Database::new(
+            self.context.database_path.clone(),
+            self.context.database_synchronous.clone(),
+        )
+        .with_read_only_connection(|connection| {
+            relative_path =
+                match DatabaseOperation::new(&connection).get_path_from_content_id(content_id) {
+                    Ok(relative_path) => Some(relative_path),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                    Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
+                };
+            Ok(())
+        })?;
+
+        let messages = match relative_path {
+            Some(relative_path) => vec![
+                OperationalMessage::ModifiedLocalFile(relative_path),
+                OperationalMessage::ModifiedRemoteFile(content_id),
+            ],
+            None => vec![OperationalMessage::NewRemoteFile(content_id)],
+        };
+        for message in messages {
+            self.operational_sender
+                .send(message)
+                .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?;
+        }
+        Ok(())
+    }
+
+    /// Snapshot of synchronization status
+    pub fn status(&self) -> Result<Status, Error> {
+        Ok(self
+            .context
+            .status
+            .lock()
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+            .clone())
+    }
+
+    /// Download remote content again, replacing its disk file whatever its state. Folder
+    /// content is downloaded again, recursively.
+    pub fn force_download(&self, content_id: ContentId) -> Result<(), Error> {
+        log::info!("Request force download of content {}", content_id);
+        self.operational_sender
+            .send(OperationalMessage::ForceDownload(content_id))
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))
+    }
+
+    /// Same as force download, for the remote content of an indexed path
+    pub fn force_download_path(&self, relative_path: RelativeFilePath) -> Result<(), Error> {
+        let mut content_id = None;
+        Database::new(
+            self.context.database_path.clone(),
+            self.context.database_synchronous.clone(),
+        )
+        .with_read_only_connection(|connection| {
+            content_id =
+                Some(DatabaseOperation::new(&connection).get_content_id_from_path(relative_path)?);
+            Ok(())
+        })?;
+        match content_id {
+            Some(content_id) => self.force_download(content_id),
+            None => Err(Error::UnexpectedError(
+                "Unable to read index to force download".to_string(),
+            )),
+        }
+    }
+
+    /// Upload disk file again, replacing remote content whatever its state. Indexed
+    /// folder content is uploaded again, recursively.
+    pub fn force_upload(&self, relative_path: RelativeFilePath) -> Result<(), Error> {
+        log::info!("Request force upload of {:?}", relative_path);
+        self.operational_sender
+            .send(OperationalMessage::ForcePush(relative_path))
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))
+    }
+
+    /// Mark a conflict left to user as resolved : disk file, as the user left it, is
+    /// synchronized again against the remote revision which was conflicting
+    pub fn resolve_conflict(&self, relative_path: RelativeFilePath) -> Result<(), Error> {
+        log::info!("Resolve conflict of {:?}", relative_path);
+        Database::new(
+            self.context.database_path.clone(),
+            self.context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            clear_conflict(&connection, &self.operational_sender, relative_path)
+        })
+    }
+
+    /// Receive synchronization events from now
+    pub fn subscribe(&self) -> Receiver<SyncEvent> {
+        self.context.events.subscribe()
+    }
+
+    /// Stop watchers and operational handler, then wait for them. Operations not yet
+    /// proceed will be found again by startup syncs of next start.
+    pub fn stop(self) -> Result<(), Error> {
+        log::info!("Stop synchronization");
+        self.interrupt();
+        self.wait()
+    }
+
+    /// Wait for threads to finish (after synchronization when exit after sync is
+    /// enabled, on stop or on failure). When a thread fail, others are stopped and
+    /// first error is returned.
+    pub fn wait(self) -> Result<(), Error> {
+        let mut result = Ok(());
+        for (name, handle) in self.threads {
+            let thread_result = handle
+                .join()
+                .unwrap_or_else(|_| Err(Error::UnexpectedError(format!("{} panicked", name))));
+            if let Err(error) = thread_result {
+                log::error!("{} failed : {:?}", name, error);
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+
+        self.health.set_phase(Phase::Stopped);
+        result
+    }
+
+    fn interrupt(&self) {
+        interrupt(&self.context, &self.operational_sender)
+    }
+}
+
+fn interrupt(context: &Context, operational_sender: &Sender<OperationalMessage>) {
+    context.stopping.store(true, Ordering::SeqCst);
+    if let Err(error) = operational_sender.send(OperationalMessage::Exit) {
+        log::debug!("Operational handler already stopped : {}", error)
+    }
+}
+
+// Spawn a trsync thread : when it fails, other threads are stopped
+fn spawn<F>(
+    context: &Context,
+    operational_sender: &Sender<OperationalMessage>,
+    f: F,
+) -> JoinHandle<Result<(), Error>>
+where
+    F: FnOnce() -> Result<(), Error> + Send + 'static,
+{
+    let context = context.clone();
+    let operational_sender = operational_sender.clone();
+    thread::spawn(move || {
+        let result = f();
+        if result.is_err() {
+            interrupt(&context, &operational_sender);
+        }
+        result
+    })
+}
+
+// Startup syncs : send changes made since last run, on disk and on remote
+fn synchronize(
+    context: Context,
+    operational_sender: Sender<OperationalMessage>,
+) -> Result<(), Error> {
+    // First, start local sync to know changes since last start. They are journaled and
+    // replayed by operational handler when it starts.
+    let local_sync_context = context.clone();
+    let local_sync_handle = thread::spawn(move || local_sync(local_sync_context));
+
+    // Second, start remote sync to know remote changes since last run
+    let remote_sync_operational_sender = operational_sender.clone();
+    let remote_sync_context = context.clone();
+    let remote_sync_handle =
+        thread::spawn(move || remote_sync(remote_sync_context, remote_sync_operational_sender));
+
+    // Wait end of local and remote  sync
+    log::info!("Wait synchronizations to finish their jobs");
+    let local_sync_result = local_sync_handle
+        .join()
+        .expect("Fail to join local sync handler");
+    let remote_sync_result = remote_sync_handle
+        .join()
+        .expect("Fail to join remote sync handler");
+
+    if let Err(error) = &local_sync_result {
+        log::error!("Local sync failed: {:?}", error);
+    }
+    if let Err(error) = &remote_sync_result {
+        log::error!("Remote sync failed: {:?}", error);
+    }
+    if local_sync_result.is_err() || remote_sync_result.is_err() {
+        return Err(Error::StartupError(
+            "Synchronization fail, interrupt now".to_string(),
+        ));
+    }
+
+    if context.exit_after_sync {
+        log::info!("Synchronization finished");
+        operational_sender.send(OperationalMessage::Exit).unwrap();
+    } else {
+        log::info!("Synchronization finished, start changes resolver");
+    }
+
+    Ok(())
+}
+
+// Trsync files inside workspace folder are not synchronized, which user may not expect
+fn warn_owned_paths_in_folder(context: &Context) {
+    for owned_path in context.owned_paths() {
+        if owned_path.starts_with(&context.folder_path)
+            && !context.is_metadata_path(owned_path)
+            && owned_path.exists()
+        {
+            log::warn!(
+                "Trsync file {:?} is inside synchronized folder, it will not be synchronized",
+                owned_path
+            );
+        }
+    }
+}
+
+// Disk modification times are only compared with each other, but a wrong local clock
+// make them misleading (conflicted copies names, times shown by file managers)
+fn check_clock_skew(context: &Context, health: &HealthReporter) {
+    match Client::new(context.clone()).and_then(|client| Ok(client.check_clock()?)) {
+        Ok(ClockCheck::Skewed(clock_skew_ms, warning)) => {
+            health.set_clock_skew(clock_skew_ms);
+            log::warn!("{}", warning);
+        }
+        Ok(ClockCheck::InSync(clock_skew_ms)) => {
+            health.set_clock_skew(clock_skew_ms);
+            log::debug!("Local clock skew is {} ms", clock_skew_ms);
+        }
+        Ok(ClockCheck::Unknown) => log::debug!("Tracim server do not give its clock"),
+        Err(error) => log::debug!(
+            "Unable to compare local clock with Tracim server clock : {:?}",
+            error
+        ),
+    }
+}
+
+fn local_sync(local_sync_context: Context) -> Result<(), Error> {
+    Database::new(
+        local_sync_context.database_path.clone(),
+        local_sync_context.database_synchronous.clone(),
+    )
+    .with_new_connection(|connection| {
+        LocalSync::new(local_sync_context, connection)?.sync()?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+fn remote_sync(
+    remote_sync_context: Context,
+    remote_sync_operational_sender: Sender<OperationalMessage>,
+) -> Result<(), Error> {
+    Database::new(
+        remote_sync_context.database_path.clone(),
+        remote_sync_context.database_synchronous.clone(),
+    )
+    .with_new_connection(|connection| {
+        RemoteSync::new(
+            remote_sync_context,
+            connection,
+            remote_sync_operational_sender,
+        )?
+        .pull()?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+fn remote_watch(
+    remote_watcher_context: Context,
+    remote_watcher_operational_sender: Sender<OperationalMessage>,
+) -> Result<(), Error> {
+    match remote_watcher_context.remote_mode {
+        RemoteMode::Live => {
+            RemoteWatcher::new(remote_watcher_context, remote_watcher_operational_sender).listen()
+        }
+        RemoteMode::Polling => {
+            PollingRemoteWatcher::new(remote_watcher_context, remote_watcher_operational_sender)
+                .listen()
+        }
+        RemoteMode::Auto => {
+            match RemoteWatcher::new(
+                remote_watcher_context.clone(),
+                remote_watcher_operational_sender.clone(),
+            )
+            .listen()
+            {
+                Ok(()) if remote_watcher_context.is_stopping() => return Ok(()),
+                // Stream ended without trsync stopping : remote changes would be missed
+                Ok(()) => log::warn!("Live messages stream ended, fallback on polling"),
+                Err(error) => log::warn!(
+                    "Live messages not available ({:?}), fallback on polling",
+                    error
+                ),
+            }
+            PollingRemoteWatcher::new(remote_watcher_context, remote_watcher_operational_sender)
+                .listen()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    fn measured_clock_skew_ms(behind_s: i64) -> Option<i64> {
+        let stub = TracimStub::start();
+        testing::server_clock_behind(&stub, behind_s);
+        let folder = testing::temp_folder("clock-skew");
+        let context = testing::context(&stub, &folder);
+
+        check_clock_skew(&context, &HealthReporter::new(&context));
+
+        let clock_skew_ms = context.status.lock().unwrap().clock_skew_ms;
+        fs::remove_dir_all(folder).unwrap();
+        clock_skew_ms
+    }
+
+    fn handle(context: Context, operational_sender: Sender<OperationalMessage>) -> TrsyncHandle {
+        TrsyncHandle {
+            health: HealthReporter::new(&context),
+            context,
+            operational_sender,
+            threads: vec![],
+        }
+    }
+
+    #[test]
+    fn included_content_is_synchronized_again() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("include-content");
+        let mut context = testing::context(&stub, &folder);
+        context.database_path = folder.join("index.db").to_str().unwrap().to_string();
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let operation = DatabaseOperation::new(&connection);
+            operation.create_tables()?;
+            operation.insert_new_file("file.txt".to_string(), 1, 1, 1, None, false)?;
+            Ok(())
+        })
+        .unwrap();
+        let (operational_sender, operational_receiver) = channel();
+        let handle = handle(context.clone(), operational_sender);
+
+        handle.exclude_content(1).unwrap();
+        handle.exclude_content(2).unwrap();
+        let excluded = context.is_excluded_content(1) && context.is_excluded_content(2);
+        handle.include_content(1).unwrap();
+        handle.include_content(2).unwrap();
+        // Content which was not excluded has nothing to catch up
+        handle.include_content(3).unwrap();
+        let messages: Vec<OperationalMessage> = operational_receiver.try_iter().collect();
+
+        assert!(excluded);
+        assert!(!context.is_excluded_content(1));
+        assert!(!context.is_excluded_content(2));
+        assert_eq!(
+            messages,
+            vec![
+                OperationalMessage::ModifiedLocalFile("file.txt".to_string()),
+                OperationalMessage::ModifiedRemoteFile(1),
+                OperationalMessage::NewRemoteFile(2),
+            ]
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn clock_skew_is_measured_from_server_date() {
+        // Server date has a second precision
+        let ahead_ms = measured_clock_skew_ms(3600).unwrap();
+        let behind_ms = measured_clock_skew_ms(-3600).unwrap();
+        let in_sync_ms = measured_clock_skew_ms(0).unwrap();
+
+        assert!((3_598_000..=3_602_000).contains(&ahead_ms), "{}", ahead_ms);
+        assert!(
+            (-3_602_000..=-3_598_000).contains(&behind_ms),
+            "{}",
+            behind_ms
+        );
+        assert!(in_sync_ms.abs() <= 2000, "{}", in_sync_ms);
+    }
+}
@@ -1,54 +1,297 @@
-use crate::DatabaseOperation;
 use notify::DebouncedEvent;
-use notify::{watcher, RecursiveMode, Watcher};
+use notify::{watcher, RecommendedWatcher, RecursiveMode, Watcher};
 use rusqlite::Connection;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::Sender;
-use std::time::{Duration, UNIX_EPOCH};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::time::{Duration, Instant};
 use walkdir::{DirEntry, WalkDir};
 
+use crate::compaction;
+use crate::context::Context;
+use crate::database::{Database, DatabaseOperation};
 use crate::error::Error;
 use crate::operation::OperationalMessage;
+use crate::types::{LastModifiedTimestamp, RelativeFilePath};
 use crate::util;
 
+// Disk scan position is saved (with changes found until it) every this number of entries
+const SCAN_CHECKPOINT_INTERVAL: usize = 500;
+const SCAN_CHECKPOINT_KEY: &str = "local_scan_checkpoint";
+// Last indexed path checked for deletion, once disk scan is complete
+const DELETION_CHECKPOINT_KEY: &str = "local_deletion_checkpoint";
+// Errors given by inotify when max_user_watches or max_user_instances limit is reached
+const WATCHES_LIMIT_ERRNO: i32 = 28;
+const INSTANCES_LIMIT_ERRNO: i32 = 24;
+// While messages wait for room in local watcher channel, they are sent again at this interval
+const BACKLOG_RETRY_MS: u64 = 100;
+
+// Watcher is kept with its receiver : events stop when it is dropped. Sender is kept
+// too, so that receiver only times out when there is no watcher.
+struct Watch {
+    _watcher: Option<RecommendedWatcher>,
+    _sender: Sender<DebouncedEvent>,
+    receiver: Receiver<DebouncedEvent>,
+    // Set when folder is not entirely watched : disk is then scanned at this time
+    next_poll: Option<Instant>,
+}
+
+// Local watcher messages go through a bounded channel, and operational handler is woken
+// up through main channel. Watcher never blocks (disk events would pile up in notify
+// channel) : when operational handler is late, message which found channel full is kept,
+// following ones are dropped and disk is scanned again once kept ones are sent.
 pub struct LocalWatcher {
-    operational_sender: Sender<OperationalMessage>,
+    context: Context,
+    operational_sender: SyncSender<OperationalMessage>,
+    wakeup_sender: Sender<OperationalMessage>,
+    backlog: VecDeque<OperationalMessage>,
+    rescan_needed: bool,
     workspace_folder_path: PathBuf,
+    correlator: EventCorrelator,
+    // Opened on first index read, and kept for next ones
+    index_connection: Option<Connection>,
 }
 
 impl LocalWatcher {
     pub fn new(
-        operational_sender: Sender<OperationalMessage>,
-        workspace_folder_path: String,
+        context: Context,
+        operational_sender: SyncSender<OperationalMessage>,
+        wakeup_sender: Sender<OperationalMessage>,
     ) -> Result<Self, Error> {
         Ok(Self {
+            workspace_folder_path: fs::canonicalize(&context.folder_path)?,
+            correlator: EventCorrelator::new(Duration::from_millis(context.watch_debounce_ms)),
+            context,
             operational_sender,
-            workspace_folder_path: fs::canonicalize(&workspace_folder_path)?,
+            wakeup_sender,
+            backlog: VecDeque::new(),
+            rescan_needed: false,
+            index_connection: None,
         })
     }
 
+    // Watch is bound to watched folder inode : when synchronized folder is deleted or
+    // unmounted, operations are paused until it comes back, then it is watched again
+    // and changes made meanwhile are searched like at startup.
     pub fn listen(&mut self, path: String) -> Result<(), Error> {
-        let (inotify_sender, inotify_receiver) = channel();
-        let mut inotify_watcher = watcher(inotify_sender, Duration::from_secs(1))?;
-        inotify_watcher.watch(path, RecursiveMode::Recursive)?;
+        let mut watch = self.watch(&path)?;
+        let mut folder_id = util::folder_id(Path::new(&path));
+        let mut missing_since: Option<Instant> = None;
 
-        loop {
-            match inotify_receiver.recv() {
-                Ok(event) => match self.digest_event(&event) {
-                    Err(error) => {
+        while !self.context.is_stopping() {
+            let mut timeout = self
+                .correlator
+                .next_due_in()
+                .unwrap_or(Duration::from_secs(1))
+                .min(Duration::from_secs(1));
+            if !self.backlog.is_empty() {
+                timeout = timeout.min(Duration::from_millis(BACKLOG_RETRY_MS));
+            }
+            match watch.receiver.recv_timeout(timeout) {
+                Ok(event) => {
+                    if let Err(error) = self.digest_event(&event) {
                         log::error!("Error when digest event {:?} : {:?}", &event, error)
                     }
-                    _ => {}
-                },
+                }
+                Err(RecvTimeoutError::Timeout) => {}
                 Err(e) => log::error!("Watch error: {:?}", e),
             }
+            for message in self.correlator.take_due() {
+                self.send(message)?;
+            }
+            self.flush_backlog()?;
+
+            if let Some(next_poll) = watch.next_poll {
+                if Instant::now() >= next_poll && !self.context.is_folder_missing() {
+                    log::debug!("Search changes of not watched folder parts");
+                    self.search_changes()?;
+                    watch.next_poll = Some(Instant::now() + self.poll_interval());
+                }
+            }
+
+            let current_folder_id = util::folder_id(Path::new(&path));
+            match missing_since {
+                None if current_folder_id != folder_id => {
+                    log::warn!(
+                        "Synchronized folder {:?} disappeared, pause synchronization until it is back",
+                        path
+                    );
+                    self.context.folder_missing.store(true, Ordering::SeqCst);
+                    missing_since = Some(Instant::now());
+                }
+                None => {}
+                Some(_) if self.is_synchronized_folder(folder_id, current_folder_id) => {
+                    log::info!("Synchronized folder {:?} is back, watch it again", path);
+                    // Previous watcher (and its events) are dropped when replaced
+                    watch = self.watch(&path)?;
+                    folder_id = current_folder_id;
+                    self.workspace_folder_path = fs::canonicalize(&path)?;
+                    // Index came back with the folder, it may be another file
+                    self.index_connection = None;
+                    self.resync()?;
+                    missing_since = None;
+                    self.context.folder_missing.store(false, Ordering::SeqCst);
+                }
+                Some(since)
+                    if since.elapsed()
+                        > Duration::from_secs(self.context.missing_folder_timeout_s) =>
+                {
+                    return Err(Error::FolderMissing(format!(
+                        "Synchronized folder {:?} is missing since {} seconds",
+                        path,
+                        since.elapsed().as_secs()
+                    )));
+                }
+                Some(_) => {}
+            }
         }
+
+        Ok(())
     }
 
-    pub fn digest_event(&self, event: &DebouncedEvent) -> Result<(), Error> {
-        log::debug!("Local event: {:?}", event);
+    // Directory found at synchronized folder path is the synchronized folder if it is the
+    // same directory (moved away then back), or if it holds trsync metadata directory
+    // (mounted again). Else, as an empty mount point, it must not be taken for a folder
+    // whose files were deleted.
+    fn is_synchronized_folder(
+        &self,
+        folder_id: Option<(u64, u64)>,
+        current_folder_id: Option<(u64, u64)>,
+    ) -> bool {
+        current_folder_id.is_some()
+            && (current_folder_id == folder_id || self.context.metadata_dir().is_dir())
+    }
+
+    // When inotify limits are reached, folder parts watched before are still watched,
+    // and changes of the whole folder are searched periodically
+    fn watch(&self, path: &str) -> Result<Watch, Error> {
+        let (inotify_sender, inotify_receiver) = channel();
+        let mut watch = Watch {
+            _watcher: None,
+            _sender: inotify_sender.clone(),
+            receiver: inotify_receiver,
+            next_poll: None,
+        };
+        let debounce = Duration::from_millis(self.context.watch_debounce_ms);
+        let watched = watcher(inotify_sender, debounce).and_then(|inotify_watcher| {
+            watch
+                ._watcher
+                .insert(inotify_watcher)
+                .watch(path, RecursiveMode::Recursive)
+        });
+
+        match watched.map_err(|error| watch_error(path, error)) {
+            // Events may not be reported at all (network file systems)
+            Ok(_) if self.context.local_polling => {
+                watch.next_poll = Some(Instant::now() + self.poll_interval());
+            }
+            Ok(_) => {}
+            Err(Error::WatchLimitExceeded(message)) => {
+                log::warn!(
+                    "{}. Search changes every {} seconds instead",
+                    message,
+                    self.poll_interval().as_secs()
+                );
+                watch.next_poll = Some(Instant::now() + self.poll_interval());
+            }
+            Err(error) => return Err(error),
+        }
+        Ok(watch)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.context.local_poll_interval_s.max(1))
+    }
+
+    // Changes made while synchronized folder was missing are found by comparing disk
+    // and index. Index can be in synchronized folder : if it did not come back with it,
+    // disk content can't be compared to anything. An empty folder is rather an empty
+    // mount point than a folder whose files were all deleted : deleting all indexed
+    // contents is left to a restart.
+    fn resync(&mut self) -> Result<(), Error> {
+        if !Path::new(&self.context.database_path).exists() {
+            return Err(Error::FolderMissing(format!(
+                "Synchronized folder {:?} is back without its index, restart synchronization",
+                self.context.folder_path
+            )));
+        }
+        if self.folder_is_empty()?
+            && DatabaseOperation::new(self.index_connection()?).count_entries("")? > 0
+        {
+            return Err(Error::FolderMissing(format!(
+                "Synchronized folder {:?} is back empty while its index is not, restart \
+                 synchronization if its files were deleted",
+                self.context.folder_path
+            )));
+        }
+
+        log::info!("Search changes made while synchronized folder was missing");
+        self.search_changes()
+    }
+
+    // Metadata directory content is not synchronized content
+    fn folder_is_empty(&self) -> Result<bool, Error> {
+        for entry in fs::read_dir(&self.workspace_folder_path)? {
+            if !self.context.is_metadata_path(&entry?.path()) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Changes found replace dropped and kept messages : they are all kept, then sent as
+    // channel has room for them
+    fn search_changes(&mut self) -> Result<(), Error> {
+        let context = self.context.clone();
+        let mut changes = vec![];
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            changes = LocalSync::new(context, connection)?.changes()?;
+            Ok(())
+        })?;
+        self.rescan_needed = false;
+        self.backlog = changes.into();
+        self.flush_backlog()
+    }
+
+    // Messages are held by correlator, and sent once correlation window elapsed
+    pub fn digest_event(&mut self, event: &DebouncedEvent) -> Result<(), Error> {
+        log::trace!("Raw local event: {:?}", event);
+
+        // Files moved into metadata directory are no longer synchronized
+        if let DebouncedEvent::Rename(absolute_source_path, absolute_dest_path) = event {
+            if !self.context.is_metadata_path(absolute_source_path)
+                && self.context.is_metadata_path(absolute_dest_path)
+            {
+                // Only creations can replace a path, kind is not relevant
+                self.correlator.push(
+                    OperationalMessage::DeletedLocalFile(util::path_to_string(
+                        absolute_source_path.strip_prefix(&self.workspace_folder_path)?,
+                    )?),
+                    &|_| true,
+                );
+                return Ok(());
+            }
+        }
+
+        // Metadata directory content (database, partial downloads, ...) is never synchronized
+        if self.is_trsync_event(event) {
+            return Ok(());
+        }
+
+        // Content of a deleted or unmounted synchronized folder is not deleted content
+        if self.context.is_folder_missing() || !self.workspace_folder_path.is_dir() {
+            log::debug!("Synchronized folder is missing, ignore {:?}", event);
+            return Ok(());
+        }
 
         let messages: Vec<OperationalMessage> = match event {
             DebouncedEvent::Create(absolute_path) => {
@@ -76,6 +319,31 @@ impl LocalWatcher {
                     )?,
                 )]
             }
+            // Extended attributes changes (as label) are reported as Chmod
+            DebouncedEvent::Chmod(absolute_path)
+                if self.context.labels_synchronized() && absolute_path.is_file() =>
+            {
+                vec![OperationalMessage::ModifiedLocalFile(util::path_to_string(
+                    absolute_path.strip_prefix(&self.workspace_folder_path)?,
+                )?)]
+            }
+            // Some tools (rsync --inplace, editors on CIFS) write content which is only
+            // reported as Chmod : it is a modification if disk file differs from index
+            DebouncedEvent::Chmod(absolute_path)
+                if !self.context.disable_chmod_check && absolute_path.is_file() =>
+            {
+                let relative_path =
+                    util::path_to_string(absolute_path.strip_prefix(&self.workspace_folder_path)?)?;
+                if self.differs_from_index(&relative_path, absolute_path)? {
+                    vec![OperationalMessage::ModifiedLocalFile(relative_path)]
+                } else {
+                    log::trace!(
+                        "Attributes of {:?} changed but not its content",
+                        relative_path
+                    );
+                    vec![]
+                }
+            }
             // Ignore these
             DebouncedEvent::NoticeWrite(_)
             | DebouncedEvent::NoticeRemove(_)
@@ -90,96 +358,552 @@ impl LocalWatcher {
             }
         };
 
+        log::debug!("Local event give messages {:?}", messages);
+        if messages.is_empty() {
+            return Ok(());
+        }
+        // Replacements depend on the kind of indexed paths
+        if let Err(error) = self.index_connection() {
+            log::error!("Unable to read index to correlate events : {:?}", error);
+        }
+        let connection = self.index_connection.as_ref();
+        let folder_path = &self.workspace_folder_path;
         for message in messages {
-            match self.operational_sender.send(message) {
-                Ok(_) => (),
-                Err(err) => {
-                    log::error!(
-                        "Error when send operational message from local watcher : {}",
-                        err
-                    )
-                }
+            self.correlator.push(message, &|relative_path| {
+                connection.is_none_or(|connection| {
+                    compaction::kind_unchanged(connection, folder_path, relative_path)
+                })
+            });
+        }
+
+        Ok(())
+    }
+
+    // Disk file timestamp or size differs from indexed ones (checksum is left to operational
+    // handler). Not indexed file is not considered : its creation was reported.
+    fn differs_from_index(
+        &mut self,
+        relative_path: &str,
+        absolute_path: &Path,
+    ) -> Result<bool, Error> {
+        let metadata = fs::metadata(absolute_path)?;
+        let disk_last_modified_timestamp = util::system_time_to_timestamp(metadata.modified()?);
+        let database_operation = DatabaseOperation::new(self.index_connection()?);
+        let last_modified_timestamp =
+            match database_operation.get_last_modified_timestamp(relative_path) {
+                Ok(last_modified_timestamp) => last_modified_timestamp,
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+                Err(error) => return Err(Error::from(error)),
             };
+        let indexed_size = database_operation.get_size(relative_path)?;
+        Ok(!util::timestamps_match(
+            disk_last_modified_timestamp,
+            last_modified_timestamp,
+            self.context.timestamp_granularity_ms,
+            self.context.timestamp_tolerance_ms,
+        ) || indexed_size.is_some_and(|indexed_size| indexed_size != metadata.len()))
+    }
+
+    // Watcher only reads index : a read only connection never holds a write lock
+    fn index_connection(&mut self) -> Result<&Connection, Error> {
+        if self.index_connection.is_none() {
+            let index_connection = &mut self.index_connection;
+            Database::new(
+                self.context.database_path.clone(),
+                self.context.database_synchronous.clone(),
+            )
+            .with_read_only_connection(|connection| {
+                *index_connection = Some(connection);
+                Ok(())
+            })?;
+        }
+        self.index_connection
+            .as_ref()
+            .ok_or_else(|| Error::UnexpectedError("Index connection not opened".to_string()))
+    }
+
+    fn send(&mut self, message: OperationalMessage) -> Result<(), Error> {
+        if !self.backlog.is_empty() {
+            if !self.rescan_needed {
+                log::warn!("Operational handler is late, search changes once it caught up");
+                self.rescan_needed = true;
+            }
+            return Ok(());
         }
+        match self.operational_sender.try_send(message) {
+            Ok(()) => self.wake_handler(),
+            Err(TrySendError::Full(message)) => self.backlog.push_back(message),
+            Err(TrySendError::Disconnected(message)) => log::error!(
+                "Error when send operational message from local watcher : {:?}",
+                message
+            ),
+        }
+        Ok(())
+    }
 
+    // Send kept messages while channel has room, then search changes of dropped ones
+    fn flush_backlog(&mut self) -> Result<(), Error> {
+        while let Some(message) = self.backlog.pop_front() {
+            match self.operational_sender.try_send(message) {
+                Ok(()) => self.wake_handler(),
+                Err(TrySendError::Full(message)) => {
+                    self.backlog.push_front(message);
+                    return Ok(());
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    log::error!("Local watcher channel is disconnected, drop kept messages");
+                    self.backlog.clear();
+                    return Ok(());
+                }
+            }
+        }
+        if self.rescan_needed && !self.context.is_folder_missing() {
+            log::info!("Search changes dropped while operational handler was late");
+            self.search_changes()?;
+        }
         Ok(())
     }
+
+    // Handler is woken up once until it reads local watcher channel
+    fn wake_handler(&self) {
+        if !self
+            .context
+            .local_changes_signaled
+            .swap(true, Ordering::SeqCst)
+        {
+            if let Err(error) = self.wakeup_sender.send(OperationalMessage::LocalChanges) {
+                log::error!("Error when wake operational handler up : {}", error)
+            }
+        }
+    }
+
+    // Downloads are written in metadata directory before being moved to their target,
+    // so moves from metadata directory (or other trsync files) are ignored too
+    fn is_trsync_event(&self, event: &DebouncedEvent) -> bool {
+        let absolute_path = match event {
+            DebouncedEvent::Create(absolute_path)
+            | DebouncedEvent::Write(absolute_path)
+            | DebouncedEvent::Remove(absolute_path)
+            | DebouncedEvent::Rename(absolute_path, _) => absolute_path,
+            _ => return false,
+        };
+
+        self.context.is_owned_path(absolute_path)
+    }
+}
+
+// Inotify limits errors are reported with the setting to raise
+fn watch_error(path: &str, error: notify::Error) -> Error {
+    let setting = match &error {
+        notify::Error::Io(io_error) => match io_error.raw_os_error() {
+            Some(WATCHES_LIMIT_ERRNO) => "max_user_watches",
+            Some(INSTANCES_LIMIT_ERRNO) => "max_user_instances",
+            _ => return Error::from(error),
+        },
+        _ => return Error::from(error),
+    };
+    Error::WatchLimitExceeded(format!(
+        "Inotify {} limit reached when watching {:?}, so it is not entirely watched : \
+         raise it with \"sysctl fs.inotify.{}=<value>\" as root (and in /etc/sysctl.conf \
+         to keep it after reboot)",
+        setting, path, setting
+    ))
+}
+
+// Editors "safe save" replace a file by another one : a temporary file renamed over the
+// original (LibreOffice), or the original moved aside before being written again (vim
+// without backup copy). Watched as deletion then creation, the remote content would be
+// trashed and created again, losing its revisions and comments. Messages of the same
+// path seen in the correlation window (debounce period) are collapsed into a
+// modification, with the same rule as queue compaction (see `compaction::replaced`).
+#[derive(Debug)]
+struct EventCorrelator {
+    held: VecDeque<(Instant, OperationalMessage)>,
+    window: Duration,
+}
+
+impl EventCorrelator {
+    fn new(window: Duration) -> Self {
+        Self {
+            held: VecDeque::new(),
+            window,
+        }
+    }
+
+    fn push(&mut self, message: OperationalMessage, kind_unchanged: &dyn Fn(&str) -> bool) {
+        let now = Instant::now();
+        // Temporary file not yet sent is never synchronized
+        if let OperationalMessage::DeletedLocalFile(relative_path) = &message {
+            if self.forget_created(relative_path) {
+                return;
+            }
+        }
+        match &message {
+            // File deleted then created again, or moved aside then created again
+            OperationalMessage::NewLocalFile(relative_path) => {
+                let replaced = compaction::replaced(relative_path, kind_unchanged(relative_path));
+                if let (Some(position), Some(replaced)) =
+                    (self.held_deletion(relative_path), replaced.clone())
+                {
+                    log::debug!(
+                        "{:?} replaced by a new file, consider it modified",
+                        relative_path
+                    );
+                    self.held[position].1 = replaced;
+                    return;
+                }
+                if let (Some((position, after)), Some(replaced)) =
+                    (self.held_rename_from(relative_path), replaced)
+                {
+                    log::debug!(
+                        "{:?} moved aside then written, consider it modified",
+                        relative_path
+                    );
+                    self.held[position].1 = replaced;
+                    self.held
+                        .push_back((now, OperationalMessage::NewLocalFile(after)));
+                    return;
+                }
+            }
+            // Temporary file renamed over a deleted file
+            OperationalMessage::RenamedLocalFile(before, after) => {
+                if let (Some(position), Some(replaced)) = (
+                    self.held_deletion(after),
+                    compaction::replaced(after, kind_unchanged(after)),
+                ) {
+                    log::debug!("{:?} replaced by {:?}, consider it modified", after, before);
+                    self.held[position].1 = replaced;
+                    // Temporary file no longer exist : forget it if its creation is not
+                    // yet sent, else it must be deleted
+                    if !self.forget_created(before) {
+                        self.held
+                            .push_back((now, OperationalMessage::DeletedLocalFile(before.clone())));
+                    }
+                    return;
+                }
+            }
+            _ => {}
+        }
+        self.held.push_back((now, message));
+    }
+
+    fn held_deletion(&self, relative_path: &str) -> Option<usize> {
+        self.held.iter().position(|(_, held)| {
+            matches!(held, OperationalMessage::DeletedLocalFile(path) if path == relative_path)
+        })
+    }
+
+    fn held_creation(&self, relative_path: &str) -> Option<usize> {
+        self.held.iter().position(|(_, held)| {
+            matches!(held, OperationalMessage::NewLocalFile(path) if path == relative_path)
+        })
+    }
+
+    // Forget held creation of given path, with its modifications held after it. Return
+    // false if its creation is not held.
+    fn forget_created(&mut self, relative_path: &str) -> bool {
+        let position = match self.held_creation(relative_path) {
+            Some(position) => position,
+            None => return false,
+        };
+        let mut index = 0;
+        self.held.retain(|(_, held)| {
+            let forgotten = index >= position
+                && matches!(
+                    held,
+                    OperationalMessage::NewLocalFile(path)
+                    | OperationalMessage::ModifiedLocalFile(path) if path == relative_path
+                );
+            index += 1;
+            !forgotten
+        });
+        true
+    }
+
+    // Position and destination of a held rename of given path
+    fn held_rename_from(&self, relative_path: &str) -> Option<(usize, RelativeFilePath)> {
+        self.held
+            .iter()
+            .enumerate()
+            .find_map(|(position, (_, held))| match held {
+                OperationalMessage::RenamedLocalFile(before, after) if before == relative_path => {
+                    Some((position, after.clone()))
+                }
+                _ => None,
+            })
+    }
+
+    // Messages held longer than correlation window, in their order
+    fn take_due(&mut self) -> Vec<OperationalMessage> {
+        let window = self.window;
+        let mut due = vec![];
+        while let Some((held_at, _)) = self.held.front() {
+            if held_at.elapsed() < window {
+                break;
+            }
+            if let Some((_, message)) = self.held.pop_front() {
+                due.push(message);
+            }
+        }
+        due
+    }
+
+    fn next_due_in(&self) -> Option<Duration> {
+        self.held
+            .front()
+            .map(|(held_at, _)| self.window.saturating_sub(held_at.elapsed()))
+    }
 }
 
 // Represent known local files. When trsync start, it use this index to compare
 // with real local files state and produce change messages.
 pub struct LocalSync {
+    context: Context,
     connection: Connection,
     path: PathBuf,
-    operational_sender: Sender<OperationalMessage>,
+    // Only compute changes, without touching disk or database
+    read_only: bool,
 }
 
 impl LocalSync {
-    pub fn new(
-        connection: Connection,
-        path: String,
-        operational_sender: Sender<OperationalMessage>,
-    ) -> Result<Self, Error> {
+    pub fn new(context: Context, connection: Connection) -> Result<Self, Error> {
         Ok(Self {
+            path: fs::canonicalize(&context.folder_path)?,
+            context,
             connection,
-            path: fs::canonicalize(&path)?,
-            operational_sender,
+            read_only: false,
+        })
+    }
+
+    pub fn new_read_only(context: Context, connection: Connection) -> Result<Self, Error> {
+        Ok(Self {
+            read_only: true,
+            ..Self::new(context, connection)?
         })
     }
 
+    // Changes are journaled while scanning, with scan position : an interrupted scan
+    // resume after its last checkpoint instead of walking whole disk again.
     pub fn sync(&self) -> Result<(), Error> {
+        let mut changes = vec![];
+        // When interrupted while checking deleted files, disk scan was complete. Paths it
+        // skipped are not known anymore, but unreadable paths are never found deleted.
+        let skipped_paths = if DatabaseOperation::new(&self.connection)
+            .get_meta(DELETION_CHECKPOINT_KEY)?
+            .is_some()
+        {
+            vec![]
+        } else {
+            let skipped_paths = self.changes_from_disk(&mut changes, true)?;
+            self.journal_changes(&mut changes, Some((DELETION_CHECKPOINT_KEY, "")))?;
+            skipped_paths
+        };
+        self.changes_from_db(&mut changes, &skipped_paths, true)?;
+        // Scan is complete, next one will start from scratch
+        self.journal_changes(&mut changes, None)
+    }
+
+    // Differences between disk and index, as operational messages
+    pub fn changes(&self) -> Result<Vec<OperationalMessage>, Error> {
+        let mut changes = vec![];
         // Look at disk files and compare to db
-        self.sync_from_disk();
+        let skipped_paths = self.changes_from_disk(&mut changes, false)?;
         // TODO : look ate db to search deleted files
-        self.sync_from_db()?;
+        self.changes_from_db(&mut changes, &skipped_paths, false)?;
 
-        Ok(())
+        Ok(changes)
     }
 
-    fn sync_from_disk(&self) {
-        WalkDir::new(&self.path)
+    // Journal changes found since previous checkpoint and save scan position (disk scan
+    // or deletion checkpoint key with its value, or clear them), in the same transaction
+    fn journal_changes(
+        &self,
+        changes: &mut Vec<OperationalMessage>,
+        position: Option<(&str, &str)>,
+    ) -> Result<(), Error> {
+        DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+            for message in changes.drain(..) {
+                if let Some(entry) = message.to_journal_entry() {
+                    database_operation.append_journal(&entry)?;
+                }
+            }
+            for key in [SCAN_CHECKPOINT_KEY, DELETION_CHECKPOINT_KEY] {
+                match position {
+                    Some((position_key, position)) if position_key == key => {
+                        database_operation.set_meta(key, position)?
+                    }
+                    _ => database_operation.delete_meta(key)?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    // Entries are walked sorted by name, so walk order is path order : entries until
+    // checkpoint were scanned by interrupted scan
+    fn scanned_before(&self, entry: &DirEntry, checkpoint: &Option<PathBuf>) -> bool {
+        match (checkpoint, entry.path().strip_prefix(&self.path)) {
+            (Some(checkpoint), Ok(relative_path)) => {
+                relative_path != Path::new("") && relative_path <= checkpoint.as_path()
+            }
+            _ => false,
+        }
+    }
+
+    // Folders scanned by interrupted scan are not walked again, except ancestors of
+    // checkpoint (and all folders when hard links are detected, to know them all)
+    fn skip_walk(&self, entry: &DirEntry, checkpoint: &Option<PathBuf>) -> bool {
+        if self.context.detect_hard_links || !self.scanned_before(entry, checkpoint) {
+            return false;
+        }
+        match (checkpoint, entry.path().strip_prefix(&self.path)) {
+            (Some(checkpoint), Ok(relative_path)) => !checkpoint.starts_with(relative_path),
+            _ => false,
+        }
+    }
+
+    // Return paths which can't be scanned (unreadable folders, ...). Their content is
+    // unknown : it must not be considered as deleted. With strict scan, first of them
+    // abort the scan instead.
+    fn changes_from_disk(
+        &self,
+        changes: &mut Vec<OperationalMessage>,
+        checkpointed: bool,
+    ) -> Result<Vec<PathBuf>, Error> {
+        // Whole content would be seen as deleted
+        if !self.path.is_dir() {
+            return Err(Error::FolderMissing(format!(
+                "Synchronized folder {:?} is missing",
+                self.path
+            )));
+        }
+
+        let mut seen_hard_links = HashMap::new();
+        let mut skipped_paths = vec![];
+        let checkpoint = if checkpointed {
+            DatabaseOperation::new(&self.connection)
+                .get_meta(SCAN_CHECKPOINT_KEY)?
+                .map(PathBuf::from)
+        } else {
+            None
+        };
+        if let Some(checkpoint) = &checkpoint {
+            log::info!("Resume interrupted disk scan after {:?}", checkpoint);
+        }
+        let mut scanned = 0;
+        // Walk is depth first with directories yielded before their content, sorted by
+        // name : order is reproducible and parents are always created before children
+        let entries = WalkDir::new(&self.path)
+            .sort_by_file_name()
             .into_iter()
-            .filter_entry(|e| !self.ignore_entry(e))
-            .for_each(|dir_entry| match &dir_entry {
-                Ok(dir_entry_) => match self.sync_disk_file(&dir_entry_) {
-                    Ok(_) => {}
-                    Err(error) => {
-                        log::error!("Fail to sync disk file {:?} : {:?}", dir_entry_, error);
+            .filter_entry(|e| !self.ignore_entry(e) && !self.skip_walk(e, &checkpoint))
+            .filter(|dir_entry| match dir_entry {
+                Ok(dir_entry_) => !self.is_hard_link_duplicate(dir_entry_, &mut seen_hard_links),
+                Err(_) => true,
+            });
+        for dir_entry in entries {
+            match &dir_entry {
+                Ok(dir_entry_) => {
+                    if self.scanned_before(dir_entry_, &checkpoint) {
+                        continue;
+                    }
+                    match self.disk_file_change(dir_entry_) {
+                        Ok(Some(change)) => changes.push(change),
+                        Ok(None) => {}
+                        Err(error) => {
+                            log::error!("Fail to sync disk file {:?} : {:?}", dir_entry_, error);
+                        }
+                    }
+                    scanned += 1;
+                    if checkpointed && scanned % SCAN_CHECKPOINT_INTERVAL == 0 {
+                        let relative_path = dir_entry_.path().strip_prefix(&self.path)?;
+                        self.journal_changes(
+                            changes,
+                            Some((SCAN_CHECKPOINT_KEY, &util::path_to_string(relative_path)?)),
+                        )?;
                     }
-                },
+                }
                 Err(error) => {
-                    log::error!("Fail to walk on dir {:?} : {}", &dir_entry, error)
+                    let path = error.path().unwrap_or(&self.path).to_path_buf();
+                    let description = walk_error_description(error);
+                    if self.context.strict_scan {
+                        return Err(Error::StartupError(format!(
+                            "Unable to scan {:?} : {}",
+                            path, description
+                        )));
+                    }
+                    log::warn!("Unable to scan {:?} ({}), skip it", path, description);
+                    skipped_paths.push(path);
                 }
-            })
+            }
+        }
+
+        if !skipped_paths.is_empty() {
+            log::warn!(
+                "Disk scan skipped {} path(s), their changes will not be synchronized : {:?}",
+                skipped_paths.len(),
+                skipped_paths
+            );
+        }
+        Ok(skipped_paths)
     }
 
-    fn ignore_entry(&self, entry: &DirEntry) -> bool {
-        // TODO : patterns from config object
-        if let Some(file_name) = entry.path().file_name() {
-            if let Some(file_name_) = file_name.to_str() {
-                let file_name_as_str = format!("{}", file_name_);
-                if file_name_as_str.starts_with(".")
-                    || file_name_as_str.starts_with("~")
-                    || file_name_as_str.starts_with("#")
-                {
-                    return true;
-                }
+    // When enabled, only first seen path of hard linked files is synchronized
+    fn is_hard_link_duplicate(
+        &self,
+        entry: &DirEntry,
+        seen_hard_links: &mut HashMap<(u64, u64), PathBuf>,
+    ) -> bool {
+        if !self.context.detect_hard_links {
+            return false;
+        }
+
+        let hard_link_id = match entry.metadata().ok().and_then(|m| util::hard_link_id(&m)) {
+            Some(hard_link_id) => hard_link_id,
+            None => return false,
+        };
+        match seen_hard_links.get(&hard_link_id) {
+            Some(first_path) => {
+                log::warn!(
+                    "{:?} is a hard link to {:?}, ignore it",
+                    entry.path(),
+                    first_path
+                );
+                true
+            }
+            None => {
+                seen_hard_links.insert(hard_link_id, entry.path().to_path_buf());
+                false
             }
         }
+    }
+
+    fn ignore_entry(&self, entry: &DirEntry) -> bool {
+        if self.context.is_owned_path(entry.path()) {
+            return true;
+        }
 
-        false
+        // Workspace folder itself is never ignored
+        if entry.depth() == 0 {
+            return false;
+        }
+
+        entry
+            .file_name()
+            .to_str()
+            .map(|file_name| self.context.is_ignored_file_name(file_name))
+            .unwrap_or(false)
     }
 
-    fn sync_disk_file(&self, entry: &DirEntry) -> Result<(), Error> {
+    fn disk_file_change(&self, entry: &DirEntry) -> Result<Option<OperationalMessage>, Error> {
         let relative_path = entry.path().strip_prefix(&self.path)?;
         // TODO : prevent sync root with more clean way
         if relative_path == Path::new("") {
-            return Ok(());
+            return Ok(None);
         }
 
         let metadata = fs::metadata(self.path.join(relative_path))?;
-        let disk_last_modified_timestamp =
-            metadata.modified()?.duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let disk_last_modified_timestamp = util::system_time_to_timestamp(metadata.modified()?);
 
         match DatabaseOperation::new(&self.connection).get_last_modified_timestamp(
             relative_path
@@ -189,61 +913,589 @@ impl LocalSync {
                     relative_path
                 )))?,
         ) {
+            // Known folder : its modification time changes with its children, which are
+            // synchronized by themselves. Empty or not, a folder has nothing to upload.
+            Ok(_) if metadata.is_dir() => Ok(None),
             Ok(last_modified_timestamp) => {
                 // Known file (check if have been modified)
-                if disk_last_modified_timestamp != last_modified_timestamp {
-                    match self
-                        .operational_sender
-                        .send(OperationalMessage::ModifiedLocalFile(util::path_to_string(
-                            relative_path,
-                        )?)) {
-                        Err(error) => {
-                            log::error!("Fail to send operational message : {:?}", error)
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // Unknown file
-                match self
-                    .operational_sender
-                    .send(OperationalMessage::NewLocalFile(util::path_to_string(
-                        relative_path,
-                    )?)) {
-                    Err(error) => {
-                        log::error!("Fail to send operational message : {:?}", error)
-                    }
-                    _ => {}
+                if (!util::timestamps_match(
+                    disk_last_modified_timestamp,
+                    last_modified_timestamp,
+                    self.context.timestamp_granularity_ms,
+                    self.context.timestamp_tolerance_ms,
+                ) && !self.same_checksum(
+                    relative_path,
+                    &metadata,
+                    disk_last_modified_timestamp,
+                )?) || self.label_changed(relative_path, metadata.is_dir())?
+                {
+                    return Ok(Some(OperationalMessage::ModifiedLocalFile(
+                        util::path_to_string(relative_path)?,
+                    )));
                 }
+                Ok(None)
             }
-            Err(error) => {
-                return Err(Error::UnexpectedError(format!(
-                    "Error when reading database for synchronize disk file : {:?}",
-                    error
-                )))
-            }
+            // Unknown file
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(Some(
+                OperationalMessage::NewLocalFile(util::path_to_string(relative_path)?),
+            )),
+            Err(error) => Err(Error::UnexpectedError(format!(
+                "Error when reading database for synchronize disk file : {:?}",
+                error
+            ))),
+        }
+    }
+
+    // When enabled, or when disk file size is the indexed one, compare disk file checksum
+    // with indexed one. If they are equals, update indexed timestamp to not compute
+    // checksum on next start. A disk file of another size is modified, without computing
+    // its checksum (unknown indexed size is not used).
+    fn same_checksum(
+        &self,
+        relative_path: &Path,
+        metadata: &fs::Metadata,
+        disk_last_modified_timestamp: LastModifiedTimestamp,
+    ) -> Result<bool, Error> {
+        if metadata.is_dir() {
+            return Ok(false);
+        }
+
+        let relative_path_string = util::path_to_string(relative_path)?;
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let same_size = match database_operation.get_size(&relative_path_string)? {
+            Some(indexed_size) if indexed_size != metadata.len() => return Ok(false),
+            Some(_) => true,
+            None => false,
+        };
+        if !self.context.checksum_fallback && !same_size {
+            return Ok(false);
+        }
+        let indexed_checksum = match database_operation.get_checksum(&relative_path_string)? {
+            Some(indexed_checksum) => indexed_checksum,
+            None => return Ok(false),
         };
 
-        Ok(())
+        if util::file_checksum(&self.path.join(relative_path))? == indexed_checksum {
+            log::debug!(
+                "Timestamp of {:?} changed but not its content",
+                relative_path
+            );
+            if !self.read_only {
+                database_operation.update_last_modified_timestamp(
+                    relative_path_string,
+                    disk_last_modified_timestamp,
+                )?;
+            }
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    // Label set on disk file since last synchronization of labels
+    fn label_changed(&self, relative_path: &Path, is_directory: bool) -> Result<bool, Error> {
+        if !self.context.labels_synchronized() || is_directory {
+            return Ok(false);
+        }
+
+        let local_label = match util::read_label(&self.path.join(relative_path))? {
+            Some(local_label) => local_label,
+            None => return Ok(false),
+        };
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let content_id =
+            database_operation.get_content_id_from_path(util::path_to_string(relative_path)?)?;
+        Ok(database_operation.get_label(content_id)? != Some(local_label))
     }
 
-    fn sync_from_db(&self) -> Result<(), Error> {
-        let relative_paths = DatabaseOperation::new(&self.connection).get_relative_paths()?;
-        for relative_path in &relative_paths {
-            if !self.path.join(&relative_path).exists() {
-                match self
-                    .operational_sender
-                    .send(OperationalMessage::DeletedLocalFile(relative_path.clone()))
+    // Indexed paths are checked while rows are read. When checkpointed, deleted files
+    // are journaled as they are found, with last checked path to resume after it.
+    fn changes_from_db(
+        &self,
+        changes: &mut Vec<OperationalMessage>,
+        skipped_paths: &[PathBuf],
+        checkpointed: bool,
+    ) -> Result<(), Error> {
+        let checkpoint = if checkpointed {
+            DatabaseOperation::new(&self.connection).get_meta(DELETION_CHECKPOINT_KEY)?
+        } else {
+            None
+        };
+        if let Some(checkpoint) = checkpoint.as_ref().filter(|c| !c.is_empty()) {
+            log::info!(
+                "Resume interrupted deleted files check after {:?}",
+                checkpoint
+            );
+        }
+        let mut checked = 0;
+        DatabaseOperation::new(&self.connection).for_each_relative_path(
+            |relative_path| -> Result<(), Error> {
+                // Paths are read sorted, as they were by interrupted check
+                if checkpoint
+                    .as_ref()
+                    .is_some_and(|checkpoint| relative_path <= *checkpoint)
                 {
-                    Err(error) => {
-                        log::error!("Fail to send operational message : {}", error)
+                    return Ok(());
+                }
+                let absolute_path = self.path.join(&relative_path);
+                if !skipped_paths
+                    .iter()
+                    .any(|skipped_path| absolute_path.starts_with(skipped_path))
+                {
+                    // Only a missing file is deleted, not a file which can't be read
+                    match fs::symlink_metadata(&absolute_path) {
+                        Ok(_) => {}
+                        Err(error) if error.kind() == io::ErrorKind::NotFound => changes
+                            .push(OperationalMessage::DeletedLocalFile(relative_path.clone())),
+                        Err(error) => {
+                            log::warn!(
+                                "Unable to check {:?} ({}), do not consider it as deleted",
+                                absolute_path,
+                                error
+                            )
+                        }
                     }
-                    _ => {}
                 }
-            }
+                checked += 1;
+                if checkpointed && checked % SCAN_CHECKPOINT_INTERVAL == 0 {
+                    self.journal_changes(
+                        changes,
+                        Some((DELETION_CHECKPOINT_KEY, relative_path.as_str())),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+// Walk error with its OS error code, when there is one
+fn walk_error_description(error: &walkdir::Error) -> String {
+    match error
+        .io_error()
+        .and_then(|io_error| io_error.raw_os_error())
+    {
+        Some(errno) => format!("{} (errno {})", error, errno),
+        None => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::sync_channel;
+
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    // Local sync of an empty folder whose index knows given missing paths
+    fn local_sync_with_missing(
+        stub: &TracimStub,
+        name: &str,
+        relative_paths: &[String],
+    ) -> LocalSync {
+        let folder = testing::temp_folder(name);
+        let connection = testing::connection();
+        for (content_id, relative_path) in relative_paths.iter().enumerate() {
+            DatabaseOperation::new(&connection)
+                .insert_new_file(
+                    relative_path.clone(),
+                    0,
+                    content_id as i32 + 1,
+                    1,
+                    None,
+                    false,
+                )
+                .unwrap();
         }
+        LocalSync::new(testing::context(stub, &folder), connection).unwrap()
+    }
 
-        Ok(())
+    fn journaled_paths(local_sync: &LocalSync) -> Vec<RelativeFilePath> {
+        DatabaseOperation::new(&local_sync.connection)
+            .get_journal()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.relative_path)
+            .collect()
+    }
+
+    #[test]
+    fn deleted_files_are_journaled_while_index_is_read() {
+        let stub = TracimStub::start();
+        let relative_paths: Vec<String> = (0..SCAN_CHECKPOINT_INTERVAL + 1)
+            .map(|i| format!("file-{:04}.txt", i))
+            .collect();
+        let local_sync = local_sync_with_missing(&stub, "deletions-journal", &relative_paths);
+        let mut changes = vec![];
+
+        local_sync.changes_from_db(&mut changes, &[], true).unwrap();
+
+        assert_eq!(
+            journaled_paths(&local_sync),
+            relative_paths[..SCAN_CHECKPOINT_INTERVAL]
+        );
+        assert_eq!(
+            changes,
+            vec![OperationalMessage::DeletedLocalFile(
+                relative_paths[SCAN_CHECKPOINT_INTERVAL].clone()
+            )]
+        );
+        assert_eq!(
+            DatabaseOperation::new(&local_sync.connection)
+                .get_meta(DELETION_CHECKPOINT_KEY)
+                .unwrap(),
+            Some(relative_paths[SCAN_CHECKPOINT_INTERVAL - 1].clone())
+        );
+        fs::remove_dir_all(&local_sync.context.folder_path).unwrap();
+    }
+
+    #[test]
+    fn interrupted_deleted_files_check_resumes_after_checkpoint() {
+        let stub = TracimStub::start();
+        let relative_paths = vec![
+            "a.txt".to_string(),
+            "b.txt".to_string(),
+            "c.txt".to_string(),
+        ];
+        let local_sync = local_sync_with_missing(&stub, "deletions-resume", &relative_paths);
+        DatabaseOperation::new(&local_sync.connection)
+            .set_meta(DELETION_CHECKPOINT_KEY, "b.txt")
+            .unwrap();
+
+        local_sync.sync().unwrap();
+
+        assert_eq!(journaled_paths(&local_sync), vec!["c.txt".to_string()]);
+        assert_eq!(
+            DatabaseOperation::new(&local_sync.connection)
+                .get_meta(DELETION_CHECKPOINT_KEY)
+                .unwrap(),
+            None
+        );
+        fs::remove_dir_all(&local_sync.context.folder_path).unwrap();
+    }
+
+    #[test]
+    fn interrupted_disk_scan_resumes_after_checkpoint() {
+        let stub = TracimStub::start();
+        let local_sync = local_sync_with_missing(&stub, "scan-resume", &[]);
+        let folder = PathBuf::from(&local_sync.context.folder_path);
+        for relative_path in ["a/1.txt", "a/2.txt", "b/3.txt", "c.txt"] {
+            let absolute_path = folder.join(relative_path);
+            fs::create_dir_all(absolute_path.parent().unwrap()).unwrap();
+            fs::write(absolute_path, b"content").unwrap();
+        }
+        DatabaseOperation::new(&local_sync.connection)
+            .set_meta(SCAN_CHECKPOINT_KEY, "a/1.txt")
+            .unwrap();
+        let mut changes = vec![];
+
+        local_sync.changes_from_disk(&mut changes, true).unwrap();
+
+        assert_eq!(
+            changes,
+            ["a/2.txt", "b", "b/3.txt", "c.txt"]
+                .iter()
+                .map(|relative_path| OperationalMessage::NewLocalFile(relative_path.to_string()))
+                .collect::<Vec<_>>()
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn watch_limit_errors_name_the_setting_to_raise() {
+        let watches = watch_error(
+            "/folder",
+            notify::Error::Io(io::Error::from_raw_os_error(28)),
+        );
+        let instances = watch_error(
+            "/folder",
+            notify::Error::Io(io::Error::from_raw_os_error(24)),
+        );
+        let other = watch_error(
+            "/folder",
+            notify::Error::Io(io::Error::from_raw_os_error(13)),
+        );
+
+        assert!(
+            matches!(watches, Error::WatchLimitExceeded(message) if message.contains("fs.inotify.max_user_watches"))
+        );
+        assert!(
+            matches!(instances, Error::WatchLimitExceeded(message) if message.contains("fs.inotify.max_user_instances"))
+        );
+        assert!(!matches!(other, Error::WatchLimitExceeded(_)));
+    }
+
+    #[test]
+    fn metadata_files_are_not_synchronized_with_hidden_files() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("hidden-metadata");
+        let mut context = testing::context(&stub, &folder);
+        context.sync_hidden_files = true;
+        fs::write(context.temp_dir().join("partial"), b"partial").unwrap();
+        fs::write(context.metadata_dir().join("ignore"), b"*.tmp").unwrap();
+        fs::write(folder.join(".hidden.txt"), b"hidden").unwrap();
+        fs::write(folder.join("visible.txt"), b"visible").unwrap();
+        let local_sync = LocalSync::new(context, testing::connection()).unwrap();
+
+        let mut changes = local_sync.changes().unwrap();
+        changes.sort_by_key(|message| format!("{:?}", message));
+
+        assert_eq!(
+            changes,
+            vec![
+                OperationalMessage::NewLocalFile(".hidden.txt".to_string()),
+                OperationalMessage::NewLocalFile("visible.txt".to_string()),
+            ]
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn chmod_events_read_index_with_one_kept_connection() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("chmod-connection");
+        let mut context = testing::context(&stub, &folder);
+        let database_path = context.metadata_dir().join("index.db");
+        context.database_path = database_path.to_str().unwrap().to_string();
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let database_operation = DatabaseOperation::new(&connection);
+            database_operation.create_tables()?;
+            database_operation.insert_new_file("file.txt".to_string(), 0, 1, 1, None, false)?;
+            Ok(())
+        })
+        .unwrap();
+        fs::write(folder.join("file.txt"), b"written in place").unwrap();
+        let (operational_sender, _operational_receiver) = sync_channel(1);
+        let mut local_watcher =
+            LocalWatcher::new(context, operational_sender, channel().0).unwrap();
+        let event = DebouncedEvent::Chmod(local_watcher.workspace_folder_path.join("file.txt"));
+
+        local_watcher.digest_event(&event).unwrap();
+        // A new connection could no longer open index
+        fs::remove_file(&database_path).unwrap();
+        local_watcher.digest_event(&event).unwrap();
+
+        assert_eq!(
+            local_watcher
+                .correlator
+                .held
+                .iter()
+                .map(|(_, message)| message.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                OperationalMessage::ModifiedLocalFile("file.txt".to_string()),
+                OperationalMessage::ModifiedLocalFile("file.txt".to_string()),
+            ]
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Local watcher of a folder holding indexed "file.txt", whose index is out of folder
+    fn watcher_with_indexed_file(
+        stub: &TracimStub,
+        name: &str,
+    ) -> (LocalWatcher, Receiver<OperationalMessage>, PathBuf, PathBuf) {
+        let folder = testing::temp_folder(name);
+        let index_folder = testing::temp_folder(&format!("{}-index", name));
+        let mut context = testing::context(stub, &folder);
+        context.database_path = util::path_to_string(&index_folder.join("index.db")).unwrap();
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let database_operation = DatabaseOperation::new(&connection);
+            database_operation.create_tables()?;
+            database_operation.insert_new_file("file.txt".to_string(), 0, 1, 1, None, false)?;
+            Ok(())
+        })
+        .unwrap();
+        fs::write(folder.join("file.txt"), b"content").unwrap();
+        let (operational_sender, operational_receiver) = sync_channel(16);
+        let local_watcher = LocalWatcher::new(context, operational_sender, channel().0).unwrap();
+        (local_watcher, operational_receiver, folder, index_folder)
+    }
+
+    #[test]
+    fn late_handler_does_not_block_watcher() {
+        let stub = TracimStub::start();
+        let (mut local_watcher, _, folder, index_folder) =
+            watcher_with_indexed_file(&stub, "late-handler");
+        let (operational_sender, operational_receiver) = sync_channel(1);
+        local_watcher.operational_sender = operational_sender;
+        fs::write(folder.join("dropped.txt"), b"content").unwrap();
+
+        for name in ["sent.txt", "kept.txt", "dropped.txt"] {
+            local_watcher
+                .send(OperationalMessage::NewLocalFile(name.to_string()))
+                .unwrap();
+        }
+        let sent = operational_receiver.try_iter().collect::<Vec<_>>();
+        local_watcher.flush_backlog().unwrap();
+        let flushed = operational_receiver.try_iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            sent,
+            vec![OperationalMessage::NewLocalFile("sent.txt".to_string())]
+        );
+        assert_eq!(
+            flushed,
+            vec![OperationalMessage::NewLocalFile("kept.txt".to_string())]
+        );
+        assert!(local_watcher
+            .context
+            .local_changes_signaled
+            .load(Ordering::SeqCst));
+        // Dropped message is found again by disk scan, waiting for room in channel
+        assert!(!local_watcher.rescan_needed);
+        assert!(local_watcher
+            .backlog
+            .contains(&OperationalMessage::NewLocalFile("dropped.txt".to_string())));
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(index_folder).unwrap();
+    }
+
+    #[test]
+    fn recreated_empty_folder_is_not_resynchronized() {
+        let stub = TracimStub::start();
+        let (mut local_watcher, operational_receiver, folder, index_folder) =
+            watcher_with_indexed_file(&stub, "recreated-empty");
+        fs::remove_dir_all(&folder).unwrap();
+        fs::create_dir(&folder).unwrap();
+
+        let resynced = local_watcher.resync();
+
+        assert!(matches!(resynced, Err(Error::FolderMissing(_))));
+        assert!(operational_receiver.try_iter().next().is_none());
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(index_folder).unwrap();
+    }
+
+    #[test]
+    fn emptied_folder_is_not_resynchronized() {
+        let stub = TracimStub::start();
+        let (mut local_watcher, operational_receiver, folder, index_folder) =
+            watcher_with_indexed_file(&stub, "emptied");
+        fs::remove_file(folder.join("file.txt")).unwrap();
+
+        let resynced = local_watcher.resync();
+
+        assert!(matches!(resynced, Err(Error::FolderMissing(_))));
+        assert!(operational_receiver.try_iter().next().is_none());
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(index_folder).unwrap();
+    }
+
+    #[test]
+    fn folder_back_with_files_is_resynchronized() {
+        let stub = TracimStub::start();
+        let (mut local_watcher, operational_receiver, folder, index_folder) =
+            watcher_with_indexed_file(&stub, "back-with-files");
+        fs::write(folder.join("new.txt"), b"new").unwrap();
+
+        local_watcher.resync().unwrap();
+
+        assert!(operational_receiver
+            .try_iter()
+            .any(|message| message == OperationalMessage::NewLocalFile("new.txt".to_string())));
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(index_folder).unwrap();
+    }
+
+    #[test]
+    fn folder_replaced_by_an_empty_one_is_still_missing() {
+        let stub = TracimStub::start();
+        let (mut local_watcher, operational_receiver, folder, index_folder) =
+            watcher_with_indexed_file(&stub, "replaced-empty");
+        let context = local_watcher.context.clone();
+        let away = folder.with_extension("away");
+        let path = util::path_to_string(&folder).unwrap();
+        let listening = std::thread::spawn(move || local_watcher.listen(path));
+        std::thread::sleep(Duration::from_millis(500));
+
+        // Like an unmount, which leaves an empty mount point
+        fs::rename(&folder, &away).unwrap();
+        fs::create_dir(&folder).unwrap();
+        std::thread::sleep(Duration::from_secs(3));
+        let missing = context.is_folder_missing();
+        context.stopping.store(true, Ordering::SeqCst);
+        listening.join().unwrap().unwrap();
+
+        assert!(missing);
+        assert!(!operational_receiver
+            .try_iter()
+            .any(|message| matches!(message, OperationalMessage::DeletedLocalFile(_))));
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(away).unwrap();
+        fs::remove_dir_all(index_folder).unwrap();
+    }
+
+    // Messages sent by a correlator without window, for events of given paths kind
+    fn correlated(
+        messages: Vec<OperationalMessage>,
+        kind_unchanged: &dyn Fn(&str) -> bool,
+    ) -> Vec<OperationalMessage> {
+        let mut correlator = EventCorrelator::new(Duration::ZERO);
+        for message in messages {
+            correlator.push(message, kind_unchanged);
+        }
+        correlator.take_due()
+    }
+
+    #[test]
+    fn libreoffice_save_is_a_modification() {
+        // Content written in a temporary file, original deleted, then temporary file
+        // renamed to original name
+        let messages = vec![
+            OperationalMessage::NewLocalFile("lu1234.tmp".to_string()),
+            OperationalMessage::ModifiedLocalFile("lu1234.tmp".to_string()),
+            OperationalMessage::DeletedLocalFile("report.odt".to_string()),
+            OperationalMessage::RenamedLocalFile(
+                "lu1234.tmp".to_string(),
+                "report.odt".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            correlated(messages, &|_| true),
+            vec![OperationalMessage::ModifiedLocalFile(
+                "report.odt".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn vim_save_is_a_modification() {
+        // Original moved aside, content written at original name, then moved aside
+        // file deleted
+        let messages = vec![
+            OperationalMessage::RenamedLocalFile("notes.txt".to_string(), "notes.txt~".to_string()),
+            OperationalMessage::NewLocalFile("notes.txt".to_string()),
+            OperationalMessage::DeletedLocalFile("notes.txt~".to_string()),
+        ];
+
+        assert_eq!(
+            correlated(messages, &|_| true),
+            vec![OperationalMessage::ModifiedLocalFile(
+                "notes.txt".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn file_replaced_by_a_folder_is_not_a_modification() {
+        let messages = vec![
+            OperationalMessage::DeletedLocalFile("path".to_string()),
+            OperationalMessage::NewLocalFile("path".to_string()),
+        ];
+
+        assert_eq!(
+            correlated(messages.clone(), &|relative_path| relative_path != "path"),
+            messages
+        );
     }
 }
@@ -1,28 +1,28 @@
 use env_logger::Env;
-use error::Error;
-use operation::OperationalMessage;
+use glob::Pattern;
+use log::LevelFilter;
 use structopt::StructOpt;
-extern crate notify;
-use log;
-
-use std::sync::mpsc::{channel, Sender};
-use std::{env, thread};
-
-use crate::context::Context;
-use crate::database::{Database, DatabaseOperation};
-use crate::local::{LocalSync, LocalWatcher};
-use crate::operation::OperationalHandler;
-use crate::remote::{RemoteSync, RemoteWatcher};
-
-pub mod client;
-pub mod context;
-pub mod database;
-pub mod error;
-pub mod local;
-pub mod operation;
-pub mod remote;
-pub mod types;
-pub mod util;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use trsync::client::Client;
+use trsync::context::Context;
+use trsync::database::{Database, DatabaseOperation, SynchronousMode};
+use trsync::doctor::{Doctor, Severity};
+use trsync::error::Error;
+use trsync::hook::Hooks;
+use trsync::pacing::RequestPacer;
+use trsync::remote::RemoteTree;
+use trsync::status::{RunReport, StatusSnapshot, WorkspaceStatus, EXIT_CODE_FATAL};
+use trsync::types::{
+    AuthorFilter, CaseCollision, ConflictStrategy, DownloadExecutablePolicy, FilesystemProfile,
+    LeftoverPolicy, NameStrategy, ParentCreation, RejectionPolicy, RemoteMode, RequestHeader,
+};
+use trsync::{util, Trsync};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "basic")]
@@ -45,180 +45,725 @@ pub struct Opt {
     #[structopt(name = "--env-var-pass", long, short)]
     env_var_pass: Option<String>,
 
+    #[structopt(name = "--env-var-api-key", long)]
+    env_var_api_key: Option<String>,
+
     #[structopt(name = "--exit-after-sync", long)]
     exit_after_sync: bool,
+
+    #[structopt(name = "--timestamp-tolerance-ms", long, default_value = "2000")]
+    timestamp_tolerance_ms: u64,
+
+    #[structopt(name = "--checksum-fallback", long)]
+    checksum_fallback: bool,
+
+    #[structopt(name = "--no-delete", long)]
+    no_delete: bool,
+
+    #[structopt(name = "--no-chmod-check", long)]
+    no_chmod_check: bool,
+
+    #[structopt(name = "--name-strategy", long, default_value = "filename")]
+    name_strategy: NameStrategy,
+
+    #[structopt(name = "--max-name-bytes", long, default_value = "255")]
+    max_name_bytes: usize,
+
+    #[structopt(name = "--db-synchronous", long, default_value = "normal")]
+    db_synchronous: SynchronousMode,
+
+    #[structopt(name = "--conflict-strategy", long, default_value = "keep-both")]
+    conflict_strategy: ConflictStrategy,
+
+    #[structopt(name = "--parent-creation", long, default_value = "create")]
+    parent_creation: ParentCreation,
+
+    #[structopt(name = "--leftover-files", long, default_value = "remove")]
+    leftover_policy: LeftoverPolicy,
+
+    #[structopt(name = "--rejection-policy", long, default_value = "fail")]
+    rejection_policy: RejectionPolicy,
+
+    #[structopt(
+        name = "--download-executable-policy",
+        long,
+        default_value = "strip-executable"
+    )]
+    download_executable_policy: DownloadExecutablePolicy,
+
+    #[structopt(name = "--max-consecutive-failures", long, default_value = "3")]
+    max_consecutive_failures: u32,
+
+    #[structopt(name = "--failure-cooldown-s", long, default_value = "3600")]
+    failure_cooldown_s: u64,
+
+    #[structopt(name = "--loop-max-operations", long, default_value = "30")]
+    loop_max_operations: u32,
+
+    #[structopt(name = "--loop-window-s", long, default_value = "60")]
+    loop_window_s: u64,
+
+    #[structopt(name = "--upload-stable-ms", long)]
+    upload_stable_ms: Option<u64>,
+
+    #[structopt(name = "--slow-operation-s", long, default_value = "30")]
+    slow_operation_s: u64,
+
+    #[structopt(name = "--clock-skew-warning-s", long, default_value = "60")]
+    clock_skew_warning_s: u64,
+
+    #[structopt(name = "--missing-folder-timeout-s", long, default_value = "300")]
+    missing_folder_timeout_s: u64,
+
+    #[structopt(name = "--local-poll-interval-s", long, default_value = "60")]
+    local_poll_interval_s: u64,
+
+    #[structopt(name = "--watch-debounce-ms", long, default_value = "1000")]
+    watch_debounce_ms: u64,
+
+    #[structopt(name = "--watch-channel-capacity", long, default_value = "10000")]
+    watch_channel_capacity: usize,
+
+    #[structopt(name = "--database-path", long, parse(from_os_str))]
+    database_path: Option<std::path::PathBuf>,
+
+    #[structopt(name = "--filesystem-profile", long, default_value = "auto")]
+    filesystem_profile: FilesystemProfile,
+
+    #[structopt(name = "--case-collision", long, default_value = "auto")]
+    case_collision: CaseCollision,
+
+    #[structopt(name = "--exclude-content-id", long)]
+    exclude_content_ids: Vec<i32>,
+
+    #[structopt(name = "--allow-author-id", long)]
+    allow_author_ids: Vec<i32>,
+
+    #[structopt(name = "--deny-author-id", long)]
+    deny_author_ids: Vec<i32>,
+
+    #[structopt(name = "--force-download", long)]
+    force_downloads: Vec<i32>,
+
+    #[structopt(name = "--force-push", long)]
+    force_pushes: Vec<String>,
+
+    #[structopt(name = "--detect-hardlinks", long)]
+    detect_hardlinks: bool,
+
+    #[structopt(name = "--ignore-pattern", long)]
+    ignore_patterns: Vec<Pattern>,
+
+    #[structopt(name = "--sync-hidden-files", long)]
+    sync_hidden_files: bool,
+
+    #[structopt(name = "--strict-scan", long)]
+    strict_scan: bool,
+
+    #[structopt(name = "--adopt-by-size", long)]
+    adopt_by_size: bool,
+
+    #[structopt(name = "--sync-labels", long)]
+    sync_labels: bool,
+
+    #[structopt(name = "--remote-mode", long, default_value = "auto")]
+    remote_mode: RemoteMode,
+
+    #[structopt(name = "--since", long)]
+    remote_since: bool,
+
+    #[structopt(name = "--report-file", long, parse(from_os_str))]
+    report_file: Option<std::path::PathBuf>,
+
+    #[structopt(name = "--health-file", long, parse(from_os_str))]
+    health_file: Option<std::path::PathBuf>,
+
+    #[structopt(name = "--health-address", long)]
+    health_address: Option<String>,
+
+    #[structopt(name = "--preserve-xattrs", long)]
+    preserve_xattrs: bool,
+
+    #[structopt(name = "--hook-after-download", long)]
+    hook_after_download: Option<String>,
+
+    #[structopt(name = "--hook-after-upload", long)]
+    hook_after_upload: Option<String>,
+
+    #[structopt(name = "--hook-on-conflict", long)]
+    hook_on_conflict: Option<String>,
+
+    #[structopt(name = "--hook-on-error", long)]
+    hook_on_error: Option<String>,
+
+    #[structopt(name = "--hook-timeout-s", long, default_value = "60")]
+    hook_timeout_s: u64,
+
+    #[structopt(name = "--user-agent", long)]
+    user_agent: Option<String>,
+
+    #[structopt(name = "--header", long)]
+    headers: Vec<RequestHeader>,
+
+    #[structopt(name = "--max-requests-per-second", long)]
+    max_requests_per_second: Option<f64>,
+
+    #[structopt(name = "--pool-idle-timeout-s", long, default_value = "90")]
+    pool_idle_timeout_s: u64,
+
+    #[structopt(name = "--pool-max-idle-per-host", long, default_value = "8")]
+    pool_max_idle_per_host: usize,
+
+    #[structopt(name = "--tcp-keepalive-s", long, default_value = "60")]
+    tcp_keepalive_s: u64,
+
+    #[structopt(name = "--compress-transfers", long)]
+    compress_transfers: bool,
+
+    #[structopt(name = "--filter-own-events", long)]
+    filter_own_events: bool,
+
+    #[cfg(feature = "metrics")]
+    #[structopt(name = "--metrics-listen", long)]
+    metrics_listen: Option<String>,
+
+    #[structopt(short = "v", parse(from_occurrences))]
+    verbose: u8,
+
+    #[structopt(name = "--quiet", short, long)]
+    quiet: bool,
+
+    #[structopt(name = "--log-level", long)]
+    log_level: Option<LevelFilter>,
 }
 
-fn local_sync(
-    local_sync_context: Context,
-    local_sync_operational_sender: Sender<OperationalMessage>,
-) -> Result<(), Error> {
-    Database::new(local_sync_context.database_path.clone()).with_new_connection(|connection| {
-        LocalSync::new(
-            connection,
-            local_sync_context.folder_path.clone(),
-            local_sync_operational_sender,
-        )?
-        .sync()?;
-        Ok(())
-    })?;
+#[derive(StructOpt, Debug)]
+#[structopt(name = "status")]
+pub struct StatusOpt {
+    #[structopt(parse(from_os_str))]
+    path: std::path::PathBuf,
 
-    Ok(())
+    #[structopt(name = "tracim_address")]
+    tracim_address: Option<String>,
+
+    #[structopt(name = "workspace_id")]
+    workspace_id: Option<i32>,
+
+    #[structopt(name = "username")]
+    username: Option<String>,
+
+    #[structopt(
+        name = "--remote",
+        long,
+        requires_all = &["tracim_address", "workspace_id", "username"]
+    )]
+    remote: bool,
+
+    #[structopt(name = "--no-ssl", short, long)]
+    no_ssl: bool,
+
+    #[structopt(name = "--env-var-pass", long, short)]
+    env_var_pass: Option<String>,
+
+    #[structopt(name = "--env-var-api-key", long)]
+    env_var_api_key: Option<String>,
+
+    #[structopt(name = "--json", long)]
+    json: bool,
+
+    #[structopt(name = "--database-path", long, parse(from_os_str))]
+    database_path: Option<std::path::PathBuf>,
 }
 
-fn remote_sync(
-    remote_sync_context: Context,
-    remote_sync_operational_sender: Sender<OperationalMessage>,
-) -> Result<(), Error> {
-    Database::new(remote_sync_context.database_path.clone()).with_new_connection(|connection| {
-        RemoteSync::new(
-            remote_sync_context,
-            connection,
-            remote_sync_operational_sender,
-        )?
-        .sync()?;
-        Ok(())
-    })?;
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ls-remote")]
+pub struct LsRemoteOpt {
+    #[structopt(name = "tracim_address")]
+    tracim_address: String,
 
+    #[structopt(name = "workspace_id")]
+    workspace_id: i32,
+
+    #[structopt(name = "username")]
+    username: String,
+
+    #[structopt(name = "--no-ssl", short, long)]
+    no_ssl: bool,
+
+    #[structopt(name = "--env-var-pass", long, short)]
+    env_var_pass: Option<String>,
+
+    #[structopt(name = "--env-var-api-key", long)]
+    env_var_api_key: Option<String>,
+
+    #[structopt(name = "--json", long)]
+    json: bool,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "doctor")]
+pub struct DoctorOpt {
+    #[structopt(parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    #[structopt(name = "tracim_address")]
+    tracim_address: String,
+
+    #[structopt(name = "workspace_id")]
+    workspace_id: i32,
+
+    #[structopt(name = "username")]
+    username: String,
+
+    #[structopt(name = "--no-ssl", short, long)]
+    no_ssl: bool,
+
+    #[structopt(name = "--env-var-pass", long, short)]
+    env_var_pass: Option<String>,
+
+    #[structopt(name = "--env-var-api-key", long)]
+    env_var_api_key: Option<String>,
+
+    #[structopt(name = "--ignore-pattern", long)]
+    ignore_patterns: Vec<Pattern>,
+
+    #[structopt(name = "--sync-hidden-files", long)]
+    sync_hidden_files: bool,
+
+    #[structopt(name = "--clock-skew-warning-s", long, default_value = "60")]
+    clock_skew_warning_s: u64,
+
+    #[structopt(name = "--database-path", long, parse(from_os_str))]
+    database_path: Option<std::path::PathBuf>,
+}
+
+// Ask password by input or get it from env var
+fn password(env_var_pass: Option<String>) -> Result<String, Error> {
+    if let Some(env_var_pass) = env_var_pass {
+        match env::var(&env_var_pass) {
+            Ok(password) => Ok(password),
+            Err(_) => Err(Error::UnexpectedError(format!(
+                "No en var set for name {}",
+                &env_var_pass
+            ))),
+        }
+    } else {
+        Ok(rpassword::read_password_from_tty(Some(
+            "Tracim user password ? ",
+        ))?)
+    }
+}
+
+// Given database path, or database of synchronized folder for commands which may not
+// know workspace id
+fn use_database(context: &mut Context, database_path: Option<PathBuf>) -> Result<(), Error> {
+    match database_path {
+        Some(database_path) => context.database_path = util::path_to_string(&database_path)?,
+        None => context.use_folder_database()?,
+    }
     Ok(())
 }
 
-fn main() -> Result<(), Error> {
-    // Initialize static things
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-    let opt = Opt::from_args();
+// Tracim API key from env var, used instead of password
+fn api_key(env_var_api_key: Option<String>) -> Result<Option<String>, Error> {
+    env_var_api_key
+        .map(|env_var_api_key| {
+            env::var(&env_var_api_key).map_err(|_| {
+                Error::UnexpectedError(format!("No env var set for name {}", &env_var_api_key))
+            })
+        })
+        .transpose()
+}
 
-    // Digest input folder to watch
-    log::info!("Prepare to sync {:?}", &opt.path);
+#[derive(StructOpt, Debug)]
+#[structopt(name = "exclude")]
+pub struct ExclusionOpt {
+    #[structopt(parse(from_os_str))]
+    path: std::path::PathBuf,
+
+    #[structopt(parse(from_os_str))]
+    excluded_path: std::path::PathBuf,
+
+    #[structopt(name = "--database-path", long, parse(from_os_str))]
+    database_path: Option<std::path::PathBuf>,
+}
+
+// Exclude path (relative to synchronized folder, or absolute inside it) and its
+// descendants from synchronization, or include it again. Its index is kept : changes made meanwhile are synchronized at next start.
+fn exclusion(opt: ExclusionOpt, excluded: bool) -> Result<(), Error> {
     let folder_path = util::canonicalize_to_string(&opt.path)?;
+    let relative_path = util::path_to_string(
+        opt.excluded_path
+            .strip_prefix(&folder_path)
+            .or_else(|_| opt.excluded_path.strip_prefix("/"))
+            .unwrap_or(&opt.excluded_path),
+    )?
+    .trim_end_matches('/')
+    .to_string();
+    if relative_path.is_empty() {
+        return Err(Error::StartupError(
+            "Synchronized folder itself can't be excluded".to_string(),
+        ));
+    }
+    let mut context = Context::new(
+        false,
+        "".to_string(),
+        "".to_string(),
+        "".to_string(),
+        folder_path,
+        0,
+        true,
+    )?;
+    use_database(&mut context, opt.database_path)?;
+    if !Path::new(&context.database_path).exists() {
+        return Err(Error::StartupError(format!(
+            "No trsync database found at {}",
+            context.database_path
+        )));
+    }
 
-    // Ask password by input or get it from env var
-    let password = if let Some(env_var_pass) = opt.env_var_pass {
-        match env::var(&env_var_pass) {
-            Ok(password) => password,
-            Err(_) => {
-                return Err(Error::UnexpectedError(format!(
-                    "No en var set for name {}",
-                    &env_var_pass
-                )))
-            }
+    Database::new(
+        context.database_path.clone(),
+        context.database_synchronous.clone(),
+    )
+    .with_new_connection(|connection| {
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation.create_tables()?;
+        if excluded {
+            database_operation.insert_exclusion(
+                &relative_path,
+                util::system_time_to_timestamp(SystemTime::now()),
+            )?;
+            log::info!("{:?} is excluded from synchronization", relative_path);
+        } else if database_operation.delete_exclusion(&relative_path)? {
+            log::info!(
+                "{:?} is included again, it will be synchronized at next start",
+                relative_path
+            );
+        } else {
+            log::warn!("{:?} was not excluded", relative_path);
         }
+        Ok(())
+    })
+}
+
+// Print differences between index, disk and optionally remote, without synchronizing
+fn status(opt: StatusOpt) -> Result<(), Error> {
+    let folder_path = util::canonicalize_to_string(&opt.path)?;
+    let remote = opt.remote;
+    let workspace_id = opt.workspace_id;
+    let json = opt.json;
+    let api_key = api_key(opt.env_var_api_key)?;
+    let password = if remote && api_key.is_none() {
+        password(opt.env_var_pass)?
     } else {
-        rpassword::read_password_from_tty(Some("Tracim user password ? "))?
+        "".to_string()
     };
+    let mut context = Context::new(
+        !opt.no_ssl,
+        opt.tracim_address.unwrap_or_default(),
+        opt.username.unwrap_or_default(),
+        password,
+        folder_path,
+        opt.workspace_id.unwrap_or_default(),
+        true,
+    )?;
+    context.api_key = api_key;
+    context.apply_filesystem_profile();
+    use_database(&mut context, opt.database_path)?;
+    if !Path::new(&context.database_path).exists() {
+        return Err(Error::StartupError(format!(
+            "No trsync database found at {}",
+            context.database_path
+        )));
+    }
 
-    // Prepare context object
-    let context = Context::new(
+    let database = Database::new(
+        context.database_path.clone(),
+        context.database_synchronous.clone(),
+    );
+    database.with_read_only_connection(|connection| {
+        let indexed = DatabaseOperation::new(&connection).count_entries("")?;
+        let workspace_status = WorkspaceStatus::new(context.clone(), connection, remote)?;
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(&StatusSnapshot::new(
+                    &context,
+                    workspace_id,
+                    indexed,
+                    &workspace_status,
+                    remote
+                )?)?
+            );
+        } else {
+            print!("{}", workspace_status);
+        }
+        Ok(())
+    })
+}
+
+// Print remote workspace tree, without local folder or database
+fn ls_remote(opt: LsRemoteOpt) -> Result<(), Error> {
+    let api_key = api_key(opt.env_var_api_key)?;
+    let password = match api_key {
+        Some(_) => "".to_string(),
+        None => password(opt.env_var_pass)?,
+    };
+    let mut context = Context::new(
         !opt.no_ssl,
         opt.tracim_address,
         opt.username,
         password,
-        folder_path,
+        "".to_string(),
         opt.workspace_id,
-        opt.exit_after_sync,
+        true,
     )?;
+    context.api_key = api_key;
+    let tree = RemoteTree::new(Client::new(context)?.get_remote_contents(None)?);
+    if opt.json {
+        println!("{}", tree.to_json());
+    } else {
+        print!("{}", tree);
+    }
 
-    // Prepare main channel
-    let (operational_sender, operational_receiver) = channel();
+    Ok(())
+}
 
-    // Initialize database if needed
-    log::info!("Initialize index");
-    Database::new(context.database_path.clone()).with_new_connection(|connection| {
-        DatabaseOperation::new(&connection).create_tables()?;
-        Ok(())
-    })?;
-
-    log::info!("Start synchronization");
-
-    // First, start local sync to know changes since last start
-    let local_sync_operational_sender = operational_sender.clone();
-    let local_sync_context = context.clone();
-    let local_sync_handle =
-        thread::spawn(move || local_sync(local_sync_context, local_sync_operational_sender));
-
-    // Second, start remote sync to know remote changes since last run
-    let remote_sync_operational_sender = operational_sender.clone();
-    let remote_sync_context = context.clone();
-    let remote_sync_handle =
-        thread::spawn(move || remote_sync(remote_sync_context, remote_sync_operational_sender));
-
-    log::info!("Start watchers");
-
-    // Start local watcher
-    let local_watcher_operational_sender = operational_sender.clone();
-    let local_watcher_context = context.clone();
-    let mut local_watcher = LocalWatcher::new(
-        local_watcher_operational_sender,
-        local_watcher_context.folder_path.clone(),
+// Print findings of configuration checks, fail if one of them is an error
+fn doctor(opt: DoctorOpt) -> Result<(), Error> {
+    // Missing folder is a finding, not a failure
+    let folder_path = util::canonicalize_to_string(&opt.path)
+        .unwrap_or_else(|_| opt.path.to_string_lossy().to_string());
+    let api_key = api_key(opt.env_var_api_key)?;
+    let password = match api_key {
+        Some(_) => "".to_string(),
+        None => password(opt.env_var_pass)?,
+    };
+    let mut context = Context::new(
+        !opt.no_ssl,
+        opt.tracim_address,
+        opt.username,
+        password,
+        folder_path,
+        opt.workspace_id,
+        true,
     )?;
-    let local_handle = thread::spawn(move || {
-        if !local_watcher_context.exit_after_sync {
-            local_watcher.listen(local_watcher_context.folder_path.clone())
-        } else {
-            Ok(())
+    context.api_key = api_key;
+    context.ignore_patterns.extend(opt.ignore_patterns);
+    context.sync_hidden_files = opt.sync_hidden_files;
+    context.clock_skew_warning_s = opt.clock_skew_warning_s;
+    use_database(&mut context, opt.database_path)?;
+
+    let findings = Doctor::new(context).run();
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(Error::StartupError(format!("{} check(s) failed", errors)));
+    }
+
+    Ok(())
+}
+
+// Dependencies (http client, ...) only log warnings, trsync modules log at given level.
+// RUST_LOG, when set, replaces this configuration.
+fn init_logger(trsync_level: LevelFilter) {
+    env_logger::Builder::from_env(
+        Env::default().default_filter_or(format!("warn,trsync={}", trsync_level)),
+    )
+    .init();
+}
+
+fn main() -> Result<(), Error> {
+    // Subcommands are dispatched before parsing synchronization arguments
+    match env::args().nth(1).as_deref() {
+        Some("status") => {
+            init_logger(LevelFilter::Info);
+            return status(StatusOpt::from_iter(env::args().skip(1)));
         }
-    });
-
-    // Start remote watcher
-    let remote_watcher_operational_sender = operational_sender.clone();
-    let remote_watcher_context = context.clone();
-    let mut remote_watcher = RemoteWatcher::new(context.clone(), remote_watcher_operational_sender);
-    let remote_handle = thread::spawn(move || {
-        if !remote_watcher_context.exit_after_sync {
-            remote_watcher.listen()
-        } else {
-            Ok(())
+        Some("doctor") => {
+            init_logger(LevelFilter::Warn);
+            return doctor(DoctorOpt::from_iter(env::args().skip(1)));
+        }
+        Some("exclude") => {
+            init_logger(LevelFilter::Info);
+            return exclusion(ExclusionOpt::from_iter(env::args().skip(1)), true);
+        }
+        Some("include") => {
+            init_logger(LevelFilter::Info);
+            return exclusion(ExclusionOpt::from_iter(env::args().skip(1)), false);
+        }
+        Some("ls-remote") => {
+            init_logger(LevelFilter::Info);
+            return ls_remote(LsRemoteOpt::from_iter(env::args().skip(1)));
         }
-    });
+        _ => {}
+    }
 
-    // FIXME BS NOW : il faut check si il y a une erreur quelque soit le thread qui plante ne premier !
-    // Wait end of local and remote  sync
-    log::info!("Wait synchronizations to finish their jobs");
-    let local_sync_result = local_sync_handle
-        .join()
-        .expect("Fail to join local sync handler");
-    let remote_sync_result = remote_sync_handle
-        .join()
-        .expect("Fail to join remote sync handler");
+    let opt = Opt::from_args();
+    init_logger(opt.log_level.unwrap_or(match (opt.quiet, opt.verbose) {
+        (true, _) => LevelFilter::Warn,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }));
+
+    // Errors preventing synchronization to start or continue are fatal : they have their
+    // own exit code, and are written in report file
+    let report_file = opt.report_file.clone();
+    let context = match synchronization_context(opt) {
+        Ok(context) => context,
+        Err(error) => exit_fatal(report_file.as_deref(), &RunReport::default(), error),
+    };
 
-    if let Err(error) = &local_sync_result {
-        log::error!("Local sync failed: {:?}", error);
+    let result = Trsync::start(context.clone()).and_then(|handle| handle.wait());
+    let report = match context.report.lock() {
+        Ok(report) => report.clone(),
+        Err(error) => exit_fatal(
+            context.report_file.as_deref(),
+            &RunReport::default(),
+            Error::UnexpectedError(format!("{:?}", error)),
+        ),
+    };
+    if let Err(error) = result {
+        exit_fatal(context.report_file.as_deref(), &report, error);
     }
-    if let Err(error) = &remote_sync_result {
-        log::error!("Remote sync failed: {:?}", error);
+    if let Some(report_file) = &context.report_file {
+        if let Err(error) = report.write(report_file, None) {
+            log::error!("{:?}", error);
+        }
     }
-    if local_sync_result.is_err() || remote_sync_result.is_err() {
-        return Err(Error::StartupError(format!(
-            "Synchronization fail, interrupt now"
-        )));
+
+    if let Ok(status) = context.status.lock() {
+        log::info!("Resolved conflicts : {}", status.resolved_conflicts);
     }
+    log::info!(
+        "Exit application with {} failure(s) and {} conflict(s)",
+        report.failures.len(),
+        report.conflicts.len()
+    );
+    process::exit(report.exit_code());
+}
 
-    if context.exit_after_sync {
-        log::info!("Synchronization finished");
-        operational_sender.send(OperationalMessage::Exit).unwrap();
-    } else {
-        log::info!("Synchronization finished, start changes resolver");
+fn synchronization_context(opt: Opt) -> Result<Context, Error> {
+    // Digest input folder to watch
+    log::info!("Prepare to sync {:?}", &opt.path);
+    let folder_path = util::canonicalize_to_string(&opt.path)?;
+    let api_key = api_key(opt.env_var_api_key)?;
+    let password = match api_key {
+        Some(_) => "".to_string(),
+        None => password(opt.env_var_pass)?,
+    };
+
+    // Prepare context object
+    let mut context = Context::new(
+        !opt.no_ssl,
+        opt.tracim_address,
+        opt.username,
+        password,
+        folder_path,
+        opt.workspace_id,
+        opt.exit_after_sync,
+    )?;
+    context.api_key = api_key;
+    context.timestamp_tolerance_ms = opt.timestamp_tolerance_ms;
+    context.checksum_fallback = opt.checksum_fallback;
+    context.disable_deletions = opt.no_delete;
+    context.disable_chmod_check = opt.no_chmod_check;
+    context.name_strategy = opt.name_strategy;
+    context.max_name_bytes = opt.max_name_bytes;
+    context.database_synchronous = opt.db_synchronous;
+    context.conflict_strategy = opt.conflict_strategy;
+    context.parent_creation = opt.parent_creation;
+    context.leftover_policy = opt.leftover_policy;
+    context.rejection_policy = opt.rejection_policy;
+    context.download_executable_policy = opt.download_executable_policy;
+    context.max_consecutive_failures = opt.max_consecutive_failures;
+    context.failure_cooldown_s = opt.failure_cooldown_s;
+    context.loop_max_operations = opt.loop_max_operations;
+    context.loop_window_s = opt.loop_window_s;
+    context.upload_stable_ms = opt.upload_stable_ms;
+    context.slow_operation_s = opt.slow_operation_s;
+    context.clock_skew_warning_s = opt.clock_skew_warning_s;
+    context.case_collision = opt.case_collision;
+    context.missing_folder_timeout_s = opt.missing_folder_timeout_s;
+    context.local_poll_interval_s = opt.local_poll_interval_s;
+    context.watch_debounce_ms = opt.watch_debounce_ms;
+    context.watch_channel_capacity = opt.watch_channel_capacity;
+    context.filesystem_profile = opt.filesystem_profile;
+    if let Some(database_path) = &opt.database_path {
+        context.database_path = util::path_to_string(database_path)?;
+    }
+    context.excluded_content_ids =
+        Arc::new(Mutex::new(opt.exclude_content_ids.into_iter().collect()));
+    context.author_filter = AuthorFilter {
+        allowed: opt.allow_author_ids.into_iter().collect(),
+        denied: opt.deny_author_ids.into_iter().collect(),
+    };
+    context.force_downloads = opt.force_downloads;
+    context.force_pushes = opt
+        .force_pushes
+        .iter()
+        .map(|relative_path| relative_path.trim_start_matches('/').to_string())
+        .collect();
+    context.detect_hard_links = opt.detect_hardlinks;
+    context.ignore_patterns.extend(opt.ignore_patterns);
+    context.sync_hidden_files = opt.sync_hidden_files;
+    context.strict_scan = opt.strict_scan;
+    context.adopt_by_size = opt.adopt_by_size;
+    context.sync_labels = opt.sync_labels;
+    if context.sync_labels && !context.labels_synchronized() {
+        log::warn!("Labels are file names with this name strategy, --sync-labels is ignored");
+    }
+    context.remote_mode = opt.remote_mode;
+    context.remote_since = opt.remote_since;
+    context.report_file = opt.report_file;
+    context.health_file = opt.health_file;
+    context.health_address = opt.health_address;
+    context.preserve_xattrs = opt.preserve_xattrs;
+    context.hooks = Hooks {
+        after_download: opt.hook_after_download,
+        after_upload: opt.hook_after_upload,
+        on_conflict: opt.hook_on_conflict,
+        on_error: opt.hook_on_error,
+        timeout: Duration::from_secs(opt.hook_timeout_s),
+    };
+    context.request_pacer = RequestPacer::new(opt.max_requests_per_second);
+    if let Some(user_agent) = opt.user_agent {
+        context.user_agent = user_agent;
+    }
+    context.extra_headers = opt
+        .headers
+        .into_iter()
+        .map(|header| (header.name, header.value))
+        .collect();
+    context.connection_pool.idle_timeout = Duration::from_secs(opt.pool_idle_timeout_s);
+    context.connection_pool.max_idle_per_host = opt.pool_max_idle_per_host;
+    context.connection_pool.tcp_keepalive = match opt.tcp_keepalive_s {
+        0 => None,
+        tcp_keepalive_s => Some(Duration::from_secs(tcp_keepalive_s)),
+    };
+    context.connection_pool.gzip = opt.compress_transfers;
+    context.filter_own_events = opt.filter_own_events;
+    #[cfg(feature = "metrics")]
+    {
+        context.metrics_listen = opt.metrics_listen;
     }
 
-    // Operational
-    let operational_context = context.clone();
-    let operational_handle = thread::spawn(move || {
-        Database::new(context.database_path.clone()).with_new_connection(|connection| {
-            OperationalHandler::new(operational_context, connection)?.listen(operational_receiver);
-            Ok(())
-        })
-    });
-
-    local_handle
-        .join()
-        .expect("Fail to join local listener handler")?;
-    remote_handle
-        .join()
-        .expect("Fail to join remote listener handler")?;
-    operational_handle
-        .join()
-        .expect("Fail to join operational handler")?;
-
-    log::info!("Exit application");
-    Ok(())
+    Ok(context)
+}
+
+fn exit_fatal(report_file: Option<&Path>, report: &RunReport, error: Error) -> ! {
+    if let Some(report_file) = report_file {
+        if let Err(write_error) = report.write(report_file, Some(&error)) {
+            log::error!("{:?}", write_error);
+        }
+    }
+    log::error!("Synchronization failed : {:?}", error);
+    process::exit(EXIT_CODE_FATAL);
 }
@@ -0,0 +1,312 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "metrics")]
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+#[cfg(feature = "metrics")]
+use crate::{
+    context::Context,
+    database::{Database, DatabaseOperation},
+    error::Error,
+};
+
+// Connections are answered one by one : a client sending nothing must not block others
+#[cfg(feature = "metrics")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Counters and gauges shared by handler, watchers and client. Recording is cheap, so
+// it is always done : only the exporter depends on "metrics" feature.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<MetricsInner>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    // Count of proceed operations by (kind, outcome)
+    operations: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    uploaded_bytes: AtomicU64,
+    downloaded_bytes: AtomicU64,
+    queue_depth: AtomicU64,
+    last_remote_event: Mutex<Option<Instant>>,
+    // Last request to Tracim reached it
+    connected: AtomicBool,
+    // Count and total seconds of proceed operations by kind
+    operation_durations: Mutex<BTreeMap<&'static str, (u64, f64)>>,
+    requests: AtomicU64,
+    requests_ms: AtomicU64,
+}
+
+impl Metrics {
+    pub fn operation(&self, kind: &'static str, outcome: &'static str) {
+        if let Ok(mut operations) = self.inner.operations.lock() {
+            *operations.entry((kind, outcome)).or_insert(0) += 1;
+        }
+    }
+
+    pub fn operation_duration(&self, kind: &'static str, duration: Duration) {
+        if let Ok(mut operation_durations) = self.inner.operation_durations.lock() {
+            let (count, seconds) = operation_durations.entry(kind).or_insert((0, 0.0));
+            *count += 1;
+            *seconds += duration.as_secs_f64();
+        }
+    }
+
+    pub fn request_duration(&self, duration: Duration) {
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .requests_ms
+            .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn uploaded(&self, bytes: u64) {
+        self.inner
+            .uploaded_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn downloaded(&self, bytes: u64) {
+        self.inner
+            .downloaded_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn set_queue_depth(&self, queue_depth: usize) {
+        self.inner
+            .queue_depth
+            .store(queue_depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn remote_event(&self) {
+        if let Ok(mut last_remote_event) = self.inner.last_remote_event.lock() {
+            *last_remote_event = Some(Instant::now());
+        }
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.inner.connected.store(connected, Ordering::Relaxed);
+    }
+
+    // Metrics in Prometheus text format
+    pub fn render(&self, open_conflicts: Option<usize>) -> String {
+        let mut text = String::new();
+
+        text.push_str("# HELP trsync_operations_total Proceed operations by kind and outcome\n");
+        text.push_str("# TYPE trsync_operations_total counter\n");
+        if let Ok(operations) = self.inner.operations.lock() {
+            for ((kind, outcome), count) in operations.iter() {
+                let _ = writeln!(
+                    text,
+                    "trsync_operations_total{{kind=\"{}\",outcome=\"{}\"}} {}",
+                    kind, outcome, count
+                );
+            }
+        }
+
+        text.push_str(
+            "# HELP trsync_operation_duration_seconds Duration of proceed operations by kind\n",
+        );
+        text.push_str("# TYPE trsync_operation_duration_seconds summary\n");
+        if let Ok(operation_durations) = self.inner.operation_durations.lock() {
+            for (kind, (count, seconds)) in operation_durations.iter() {
+                let _ = writeln!(
+                    text,
+                    "trsync_operation_duration_seconds_sum{{kind=\"{}\"}} {}",
+                    kind, seconds
+                );
+                let _ = writeln!(
+                    text,
+                    "trsync_operation_duration_seconds_count{{kind=\"{}\"}} {}",
+                    kind, count
+                );
+            }
+        }
+
+        text.push_str(
+            "# HELP trsync_request_duration_seconds Duration of requests to Tracim, until response headers\n",
+        );
+        text.push_str("# TYPE trsync_request_duration_seconds summary\n");
+        let _ = writeln!(
+            text,
+            "trsync_request_duration_seconds_sum {}",
+            self.inner.requests_ms.load(Ordering::Relaxed) as f64 / 1000.0
+        );
+        let _ = writeln!(
+            text,
+            "trsync_request_duration_seconds_count {}",
+            self.inner.requests.load(Ordering::Relaxed)
+        );
+
+        let mut metric = |name: &str, type_: &str, help: &str, value: String| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} {}", name, type_);
+            let _ = writeln!(text, "{} {}", name, value);
+        };
+        metric(
+            "trsync_uploaded_bytes_total",
+            "counter",
+            "Bytes of uploaded files",
+            self.inner
+                .uploaded_bytes
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        metric(
+            "trsync_downloaded_bytes_total",
+            "counter",
+            "Bytes of downloaded files",
+            self.inner
+                .downloaded_bytes
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        metric(
+            "trsync_queue_depth",
+            "gauge",
+            "Operations waiting to be proceed, including retries",
+            self.inner.queue_depth.load(Ordering::Relaxed).to_string(),
+        );
+        if let Some(last_remote_event) = self
+            .inner
+            .last_remote_event
+            .lock()
+            .ok()
+            .and_then(|last_remote_event| *last_remote_event)
+        {
+            metric(
+                "trsync_last_remote_event_age_seconds",
+                "gauge",
+                "Seconds since last remote event",
+                last_remote_event.elapsed().as_secs_f64().to_string(),
+            );
+        }
+        if let Some(open_conflicts) = open_conflicts {
+            metric(
+                "trsync_open_conflicts",
+                "gauge",
+                "Conflicts waiting for a manual resolution",
+                open_conflicts.to_string(),
+            );
+        }
+        metric(
+            "trsync_connected",
+            "gauge",
+            "1 if last request reached Tracim, else 0",
+            (self.inner.connected.load(Ordering::Relaxed) as u8).to_string(),
+        );
+
+        text
+    }
+
+    // Answer metrics to each connection on given address, in a dedicated thread
+    #[cfg(feature = "metrics")]
+    pub fn serve(&self, context: &Context, address: &str) -> Result<(), Error> {
+        let listener = TcpListener::bind(address).map_err(|error| {
+            Error::StartupError(format!(
+                "Unable to listen metrics address {} : {:?}",
+                address, error
+            ))
+        })?;
+        log::info!("Serve metrics on {}", address);
+
+        let metrics = self.clone();
+        let context = context.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(error) = answer(stream, &metrics, &context) {
+                            log::debug!("Error when answering metrics request : {:?}", error)
+                        }
+                    }
+                    Err(error) => log::error!("Metrics connection failed : {:?}", error),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn answer(mut stream: TcpStream, metrics: &Metrics, context: &Context) -> Result<(), Error> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    // Request content is not relevant, read it only to not reset connection
+    let mut buffer = [0; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    let body = metrics.render(open_conflicts(context));
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+
+    Ok(())
+}
+
+#[cfg(feature = "metrics")]
+fn open_conflicts(context: &Context) -> Option<usize> {
+    let mut open_conflicts = None;
+    let result = Database::new(
+        context.database_path.clone(),
+        context.database_synchronous.clone(),
+    )
+    .with_read_only_connection(|connection| {
+        open_conflicts = Some(DatabaseOperation::new(&connection).get_conflicts()?.len());
+        Ok(())
+    });
+    if let Err(error) = result {
+        log::debug!("Unable to count conflicts : {:?}", error)
+    }
+    open_conflicts
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader},
+    };
+
+    use super::*;
+    use crate::testing::{self, TracimStub};
+
+    #[test]
+    fn silent_client_does_not_block_metrics_requests() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("metrics");
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        Metrics::default()
+            .serve(&testing::context(&stub, &folder), &address)
+            .unwrap();
+
+        let _silent = TcpStream::connect(&address).unwrap();
+        let mut stream = TcpStream::connect(&address).unwrap();
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT * 5)).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line).unwrap();
+
+        assert!(status_line.starts_with("HTTP/1.1 200"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -1,22 +1,58 @@
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
     fs::{self, File},
     io,
     path::Path,
-    sync::mpsc::Receiver,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    time::{Duration, Instant, SystemTime},
 };
 
 use rusqlite::Connection;
+use walkdir::WalkDir;
 
 use crate::{
-    client::{Client, ParentIdParameter},
+    client::{Client, ParentIdParameter, TrashOutcome},
+    compaction,
     context::Context,
-    database::DatabaseOperation,
+    database::{Conflict, Database, DatabaseOperation, IndexEntry, JournalEntry, Rejection},
     error::{ClientError, Error},
-    types::{ContentId, ContentType, RelativeFilePath},
+    event::SyncEvent,
+    health::HealthReporter,
+    hook::{HookEvent, HookRunner},
+    remote::{self, RemoteContent, RemoteSync},
+    timing::{self, TimingPhase},
+    types::{
+        CaseCollision, ConflictStrategy, ContentId, ContentType, DownloadExecutablePolicy,
+        LastModifiedTimestamp, LeftoverPolicy, NameStrategy, ParentCreation, RejectionPolicy,
+        RelativeFilePath, RevisionId,
+    },
     util,
 };
 
-#[derive(Debug, PartialEq)]
+// Retryable failures are retried with an exponential backoff
+const RETRY_BASE_DELAY_MS: u64 = 5_000;
+const RETRY_MAX_DELAY_MS: u64 = 300_000;
+const RETRY_MAX_ATTEMPTS: u32 = 8;
+// Wait duration of incoming messages when no retry is planned
+const IDLE_WAIT_MS: u64 = 60_000;
+// Wait duration of incoming messages while paused, before checking pause again
+const PAUSE_WAIT_MS: u64 = 500;
+// Delay between two reachability checks while remote is unreachable
+const OFFLINE_PROBE_MS: u64 = 10_000;
+// Delay before retrying uploads blocked by quota, when no deletion freed space meanwhile
+const QUOTA_PROBE_MS: u64 = 300_000;
+// Messages taken from channel at once, before compacting queue
+const MAX_DRAINED_MESSAGES: usize = 10_000;
+// Queue is compacted again once it doubled since last compaction (and is at least this
+// long) : compacting after each drain would cost the whole queue each time
+const COMPACTION_MIN_QUEUE_LENGTH: usize = 64;
+// Revision of an adopted disk file which differs from its remote content : no revision
+// is common to disk and remote until the conflict is resolved
+const UNRESOLVED_REVISION_ID: RevisionId = 0;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum OperationalMessage {
     // Local files messages
     NewLocalFile(RelativeFilePath),
@@ -26,9 +62,94 @@ pub enum OperationalMessage {
     // Remote files messages
     NewRemoteFile(ContentId),
     ModifiedRemoteFile(ContentId),
+    MovedRemoteFile(ContentId),
     DeletedRemoteFile(ContentId),
+    RelocatedRemoteFile(ContentId, ContentId), // vanished, appeared
+    // Write remote content on disk whatever disk and index states are (recovery)
+    ForceDownload(ContentId),
+    // Upload disk file whatever remote and index states are (recovery)
+    ForcePush(RelativeFilePath),
     // Internal messages
     Exit,
+    // Local watcher sent messages in its channel (wakes handler waiting on main channel)
+    LocalChanges,
+}
+
+impl OperationalMessage {
+    pub fn is_remote(&self) -> bool {
+        matches!(
+            self,
+            OperationalMessage::NewRemoteFile(_)
+                | OperationalMessage::ModifiedRemoteFile(_)
+                | OperationalMessage::MovedRemoteFile(_)
+                | OperationalMessage::DeletedRemoteFile(_)
+                | OperationalMessage::RelocatedRemoteFile(_, _)
+                | OperationalMessage::ForceDownload(_)
+        )
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OperationalMessage::NewLocalFile(_) => "NewLocalFile",
+            OperationalMessage::ModifiedLocalFile(_) => "ModifiedLocalFile",
+            OperationalMessage::DeletedLocalFile(_) => "DeletedLocalFile",
+            OperationalMessage::RenamedLocalFile(_, _) => "RenamedLocalFile",
+            OperationalMessage::NewRemoteFile(_) => "NewRemoteFile",
+            OperationalMessage::ModifiedRemoteFile(_) => "ModifiedRemoteFile",
+            OperationalMessage::MovedRemoteFile(_) => "MovedRemoteFile",
+            OperationalMessage::DeletedRemoteFile(_) => "DeletedRemoteFile",
+            OperationalMessage::RelocatedRemoteFile(_, _) => "RelocatedRemoteFile",
+            OperationalMessage::ForceDownload(_) => "ForceDownload",
+            OperationalMessage::ForcePush(_) => "ForcePush",
+            OperationalMessage::Exit => "Exit",
+            OperationalMessage::LocalChanges => "LocalChanges",
+        }
+    }
+
+    // Only local messages and forced operations are journaled : remote changes are
+    // found again by recovery
+    pub fn to_journal_entry(&self) -> Option<JournalEntry> {
+        let (relative_path, new_relative_path) = match self {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path)
+            // Forced push would not be found again by recovery
+            | OperationalMessage::ForcePush(relative_path) => (relative_path.clone(), None),
+            OperationalMessage::RenamedLocalFile(before_relative_path, after_relative_path) => (
+                before_relative_path.clone(),
+                Some(after_relative_path.clone()),
+            ),
+            // Forced download would not be found again by recovery either. Content may
+            // not be indexed, its id is journaled in place of a path.
+            OperationalMessage::ForceDownload(content_id) => (content_id.to_string(), None),
+            _ => return None,
+        };
+        Some(JournalEntry {
+            kind: self.kind().to_string(),
+            relative_path,
+            new_relative_path,
+        })
+    }
+
+    fn from_journal_entry(entry: &JournalEntry) -> Option<Self> {
+        let relative_path = entry.relative_path.clone();
+        match (entry.kind.as_str(), &entry.new_relative_path) {
+            ("NewLocalFile", None) => Some(OperationalMessage::NewLocalFile(relative_path)),
+            ("ModifiedLocalFile", None) => {
+                Some(OperationalMessage::ModifiedLocalFile(relative_path))
+            }
+            ("DeletedLocalFile", None) => Some(OperationalMessage::DeletedLocalFile(relative_path)),
+            ("ForcePush", None) => Some(OperationalMessage::ForcePush(relative_path)),
+            ("ForceDownload", None) => relative_path
+                .parse()
+                .ok()
+                .map(OperationalMessage::ForceDownload),
+            ("RenamedLocalFile", Some(new_relative_path)) => Some(
+                OperationalMessage::RenamedLocalFile(relative_path, new_relative_path.clone()),
+            ),
+            _ => None,
+        }
+    }
 }
 
 // TODO : Manage a flag set to true when program start to indicate to manage conflicts.
@@ -39,463 +160,5154 @@ pub struct OperationalHandler {
     connection: Connection,
     client: Client,
     ignore_messages: Vec<OperationalMessage>,
+    queue: VecDeque<OperationalMessage>,
+    retries: Vec<PlannedRetry>,
+    health: HealthReporter,
+    hooks: HookRunner,
+    // Creation messages of ancestors created for their descendants during current batch
+    // (until queue is empty). Many descendants of a new folder can arrive together :
+    // the folder is created once, and its own creation message is then skipped.
+    created_ancestors: HashSet<OperationalMessage>,
+    // Remote can't be reached : local messages are journaled until it is reachable again
+    offline: bool,
+    next_probe: Instant,
+    // Case collision policy, resolved for synchronized folder file system
+    case_collision: CaseCollision,
+    // Remote refused an upload for lack of space : uploads wait here until a deletion
+    // succeeds or probe delay elapsed
+    quota_exceeded: bool,
+    quota_blocked: Vec<OperationalMessage>,
+    next_quota_probe: Instant,
+    // Size and modification time of disk files waiting to be stable before upload, with
+    // the instant they were first seen with them
+    stability_probes: HashMap<RelativeFilePath, (u64, SystemTime, Instant)>,
+    // Recent successful modifications by content, alternately local and remote ones, to
+    // detect contents endlessly bouncing between disk and remote
+    recent_operations: HashMap<ContentId, VecDeque<(Instant, &'static str, bool)>>,
+    // Contents found looping : they are not synchronized until restart or forced
+    // download (or upload)
+    looping_content_ids: HashSet<ContentId>,
+    // Remote changes pulled at startup are not all proceed yet. Cleared without commit
+    // when one of them is lost (remote unreachable, failure) : they are pulled again at
+    // next startup.
+    remote_cursor_pending: bool,
+    // Queue length after last compaction, lowered as messages are taken
+    compacted_queue_length: usize,
+}
+
+struct PlannedRetry {
+    message: OperationalMessage,
+    attempt: u32,
+    due: Instant,
+    // Postponed until disk file is stable, not after a failure
+    deferred: bool,
 }
 
 impl OperationalHandler {
     pub fn new(context: Context, connection: Connection) -> Result<Self, Error> {
+        let case_collision = match context.case_collision {
+            CaseCollision::Auto if util::is_case_insensitive_dir(&context.metadata_dir()) => {
+                CaseCollision::Rename
+            }
+            CaseCollision::Auto => CaseCollision::Keep,
+            ref case_collision => case_collision.clone(),
+        };
         Ok(Self {
             context: context.clone(),
             connection,
+            health: HealthReporter::new(&context),
+            hooks: HookRunner::new(context.clone()),
+            created_ancestors: HashSet::new(),
             client: Client::new(context)?,
             ignore_messages: vec![],
+            queue: VecDeque::new(),
+            retries: vec![],
+            offline: false,
+            next_probe: Instant::now(),
+            case_collision,
+            quota_exceeded: false,
+            quota_blocked: vec![],
+            next_quota_probe: Instant::now(),
+            stability_probes: HashMap::new(),
+            recent_operations: HashMap::new(),
+            looping_content_ids: HashSet::new(),
+            remote_cursor_pending: true,
+            compacted_queue_length: 0,
         })
     }
 
     fn ignore_message(&mut self, message: &OperationalMessage) -> Result<bool, Error> {
-        // TODO : For local files, ignore some patterns given by config : eg. ".*", "*~"
-        if self.ignore_messages.contains(&message) {
+        // Echoes of our own remote changes are dropped by remote watcher : planned remote
+        // ignores would never be consumed and could hide a later change of someone else
+        if self.context.own_events_filtered.load(Ordering::SeqCst) {
+            self.ignore_messages.retain(|x| !x.is_remote());
+        }
+
+        // Planned ignores match on all message values (both paths for renames)
+        if self.ignore_messages.contains(message) {
             self.ignore_messages.retain(|x| *x != *message);
-            log::debug!("Ignore message (planned ignore) : {:?}", &message);
-            return Ok(true);
+            if self.is_own_remote_modification(message)? {
+                log::debug!("Ignore message (planned ignore) : {:?}", &message);
+                return Ok(true);
+            }
         };
 
+        if self.excluded_content(message)? {
+            log::info!("Ignore message (excluded content) : {:?}", &message);
+            return Ok(true);
+        }
+
+        // Nothing in metadata directory (nor other trsync file) is synchronized
+        if let OperationalMessage::NewLocalFile(relative_path)
+        | OperationalMessage::ModifiedLocalFile(relative_path)
+        | OperationalMessage::DeletedLocalFile(relative_path)
+        | OperationalMessage::RenamedLocalFile(relative_path, _)
+        | OperationalMessage::ForcePush(relative_path) = message
+        {
+            if self
+                .context
+                .is_owned_path(&Path::new(&self.context.folder_path).join(relative_path))
+            {
+                log::debug!("Ignore message (trsync file) : {:?}", &message);
+                return Ok(true);
+            }
+        }
+
+        if self.conflicted(message)? {
+            log::info!("Ignore message (unresolved conflict) : {:?}", &message);
+            return Ok(true);
+        }
+
         Ok(match message {
             OperationalMessage::NewLocalFile(relative_path)
             | OperationalMessage::ModifiedLocalFile(relative_path)
             | OperationalMessage::DeletedLocalFile(relative_path) => {
-                util::string_path_file_name(relative_path)?.starts_with(".")
-                    | util::string_path_file_name(relative_path)?.ends_with("~")
+                self.context.is_ignored_relative_path(relative_path)
             }
             _ => false,
         })
     }
 
-    pub fn listen(&mut self, receiver: Receiver<OperationalMessage>) {
-        // TODO : Why loop is required ?!
+    // Remote modification planned to be ignored is our own change only if remote
+    // revision is still the one we indexed after our change
+    fn is_own_remote_modification(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        let content_id = match message {
+            OperationalMessage::ModifiedRemoteFile(content_id) => *content_id,
+            _ => return Ok(true),
+        };
+
+        let indexed_revision_id = match DatabaseOperation::new(&self.connection)
+            .get_revision_id_from_content_id(content_id)
+        {
+            Ok(indexed_revision_id) => indexed_revision_id,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(true),
+            Err(error) => return Err(Error::from(error)),
+        };
+        let remote_revision_id = self
+            .client
+            .get_remote_content(content_id)?
+            .current_revision_id;
+        if remote_revision_id != indexed_revision_id {
+            log::info!(
+                "Remote {} is at revision {} instead of expected {}, do not ignore {:?}",
+                content_id,
+                remote_revision_id,
+                indexed_revision_id,
+                message
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    // Message concern a file with an unresolved conflict
+    fn conflicted(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let relative_path = match message {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path)
+            | OperationalMessage::RenamedLocalFile(relative_path, _) => relative_path.clone(),
+            OperationalMessage::NewRemoteFile(content_id)
+            | OperationalMessage::ModifiedRemoteFile(content_id)
+            | OperationalMessage::MovedRemoteFile(content_id)
+            | OperationalMessage::DeletedRemoteFile(content_id)
+            | OperationalMessage::RelocatedRemoteFile(content_id, _) => {
+                match database_operation.get_path_from_content_id(*content_id) {
+                    Ok(relative_path) => relative_path,
+                    Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+                    Err(error) => return Err(Error::from(error)),
+                }
+            }
+            // Forced download (or push) is the way to leave conflict by taking remote (or
+            // disk) content
+            OperationalMessage::ForceDownload(_)
+            | OperationalMessage::ForcePush(_)
+            | OperationalMessage::Exit
+            | OperationalMessage::LocalChanges => return Ok(false),
+        };
+
+        Ok(database_operation.get_conflict(&relative_path)?.is_some())
+    }
+
+    // Message concern a content excluded by user
+    fn excluded_content(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        if self
+            .context
+            .excluded_content_ids
+            .lock()
+            .map(|excluded_content_ids| excluded_content_ids.is_empty())
+            .unwrap_or(true)
+        {
+            return Ok(false);
+        }
+
+        let content_id = match message {
+            OperationalMessage::NewRemoteFile(content_id)
+            | OperationalMessage::ModifiedRemoteFile(content_id)
+            | OperationalMessage::MovedRemoteFile(content_id)
+            | OperationalMessage::DeletedRemoteFile(content_id)
+            | OperationalMessage::RelocatedRemoteFile(_, content_id)
+            | OperationalMessage::ForceDownload(content_id) => Some(*content_id),
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path)
+            | OperationalMessage::RenamedLocalFile(relative_path, _)
+            | OperationalMessage::ForcePush(relative_path) => {
+                match DatabaseOperation::new(&self.connection)
+                    .get_content_id_from_path(relative_path.clone())
+                {
+                    Ok(content_id) => Some(content_id),
+                    Err(Error::UnIndexedRelativePath(_)) => None,
+                    Err(error) => return Err(error),
+                }
+            }
+            OperationalMessage::Exit | OperationalMessage::LocalChanges => None,
+        };
+
+        Ok(content_id
+            .map(|content_id| self.context.is_excluded_content(content_id))
+            .unwrap_or(false))
+    }
+
+    // Messages come from main channel (startup syncs, remote watcher, handle) and from
+    // bounded local watcher channel. Local watcher wakes handler up through main channel.
+    pub fn listen(
+        &mut self,
+        receiver: Receiver<OperationalMessage>,
+        local_receiver: Receiver<OperationalMessage>,
+    ) {
+        // Local changes journaled by startup local sync, or by a previous run stopped while
+        // remote was unreachable, are replayed after startup remote sync messages (which
+        // act as recovery)
+        self.queue.extend(receiver.try_iter().filter(is_operation));
+        self.context
+            .local_changes_signaled
+            .store(false, Ordering::SeqCst);
+        self.queue.extend(local_receiver.try_iter());
+        match self.take_journal() {
+            Ok(messages) => {
+                if !messages.is_empty() {
+                    log::info!("Replay {} journaled local change(s)", messages.len());
+                }
+                let position = self
+                    .queue
+                    .iter()
+                    .position(|message| *message == OperationalMessage::Exit)
+                    .unwrap_or(self.queue.len());
+                for (i, message) in messages.into_iter().enumerate() {
+                    self.queue.insert(position + i, message);
+                }
+            }
+            Err(error) => log::error!("Unable to read journal : {:?}", error),
+        }
+        self.compact_queue();
+
         loop {
-            for message in receiver.recv() {
-                if match self.ignore_message(&message) {
+            // Received messages are queued here to know how many are waiting, and to
+            // compact them with already queued ones
+            self.compacted_queue_length = self.compacted_queue_length.min(self.queue.len());
+            let drained = drain(
+                &receiver,
+                &local_receiver,
+                &self.context.local_changes_signaled,
+                MAX_DRAINED_MESSAGES,
+            );
+            if !drained.is_empty() {
+                self.queue.extend(drained);
+                self.compact_grown_queue();
+            }
+            let queue_depth = self.queue.len() + self.retries.len();
+            self.health.set_queue_depth(queue_depth);
+            self.context.metrics.set_queue_depth(queue_depth);
+            // Startup messages were all sent before handler start
+            if queue_depth == 0 {
+                self.health.startup_finished();
+                self.created_ancestors.clear();
+                self.commit_remote_cursor();
+            }
+
+            // Pending operations are dropped, startup syncs will find them again
+            if self.context.is_stopping() {
+                log::info!("Stop operational handler");
+                return;
+            }
+            if self.context.is_paused() {
+                match receive(
+                    &receiver,
+                    &local_receiver,
+                    &self.context.local_changes_signaled,
+                    Duration::from_millis(PAUSE_WAIT_MS),
+                ) {
+                    Ok(message) => self.queue.push_back(message),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                continue;
+            }
+
+            if self.offline && Instant::now() >= self.next_probe {
+                self.probe_remote();
+            }
+            if self.quota_exceeded && Instant::now() >= self.next_quota_probe {
+                self.release_quota_blocked("probe delay elapsed");
+            }
+
+            let (message, attempt) = match self.pop_due_retry() {
+                Some(retry) => (retry.message, retry.attempt),
+                None => match self.queue.pop_front() {
+                    Some(message) => (message, 0),
+                    None => match receive(
+                        &receiver,
+                        &local_receiver,
+                        &self.context.local_changes_signaled,
+                        self.next_wait_delay(),
+                    ) {
+                        Ok(message) => (message, 0),
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.health.report();
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    },
+                },
+            };
+
+            if self.created_ancestors.contains(&message) {
+                log::debug!("Ignore message (created as ancestor) : {:?}", &message);
+                self.context.metrics.operation(message.kind(), "ignored");
+                continue;
+            }
+            // A deleted ancestor can be created again during the same batch
+            match &message {
+                OperationalMessage::DeletedLocalFile(relative_path) => {
+                    self.created_ancestors
+                        .remove(&OperationalMessage::NewLocalFile(relative_path.clone()));
+                }
+                OperationalMessage::DeletedRemoteFile(content_id) => {
+                    self.created_ancestors
+                        .remove(&OperationalMessage::NewRemoteFile(*content_id));
+                }
+                _ => {}
+            }
+
+            if self.offline && message != OperationalMessage::Exit {
+                self.journal_message(message);
+                continue;
+            }
+
+            // Retried messages already passed ignore checks
+            if attempt == 0
+                && match self.ignore_message(&message) {
                     Ok(true) => true,
                     Err(error) => {
                         log::error!("Error when trying to know if ignore {:?}", error);
                         false
                     }
                     Ok(false) => false,
-                } {
-                    continue;
                 }
+            {
+                self.context.metrics.operation(message.kind(), "ignored");
+                continue;
+            }
+            if attempt == 0
+                && self
+                    .skip_excluded(&message)
+                    .and_then(|skip| Ok(skip || self.skip_rejected(&message)?))
+                    .and_then(|skip| Ok(skip || self.skip_failing(&message)?))
+                    .and_then(|skip| Ok(skip || self.skip_looping(&message)?))
+                    .unwrap_or_else(|error| {
+                        log::error!("Error when trying to know if skip {:?}", error);
+                        false
+                    })
+            {
+                self.context.metrics.operation(message.kind(), "skipped");
+                continue;
+            }
+            if self.quota_exceeded && self.is_file_upload(&message) {
+                let error =
+                    Error::QuotaExceeded("Upload blocked until remote space is freed".into());
+                self.block_by_quota(message, &error);
+                continue;
+            }
 
-                log::info!("Operation : {:?}", &message);
+            log::info!("Operation : {:?}", &message);
 
-                let return_ = match &message {
-                    // Local changes
-                    OperationalMessage::NewLocalFile(relative_path) => {
-                        self.new_local_file(relative_path.clone())
-                    }
-                    OperationalMessage::ModifiedLocalFile(relative_path) => {
-                        self.modified_local_file(relative_path.clone())
-                    }
-                    OperationalMessage::DeletedLocalFile(relative_path) => {
-                        self.deleted_local_file(relative_path.clone())
-                    }
-                    OperationalMessage::RenamedLocalFile(
-                        before_relative_path,
-                        after_relative_path,
-                    ) => self.renamed_local_file(
+            let started = Instant::now();
+            timing::take_phases();
+            let return_ = match &message {
+                // Local changes
+                OperationalMessage::NewLocalFile(relative_path) => {
+                    self.new_local_file(relative_path.clone())
+                }
+                OperationalMessage::ModifiedLocalFile(relative_path) => {
+                    self.modified_local_file(relative_path.clone())
+                }
+                OperationalMessage::DeletedLocalFile(relative_path) => {
+                    self.forget_stability_probes(relative_path);
+                    self.deleted_local_file(relative_path.clone())
+                }
+                OperationalMessage::RenamedLocalFile(before_relative_path, after_relative_path) => {
+                    self.forget_stability_probes(before_relative_path);
+                    self.renamed_local_file(
                         before_relative_path.clone(),
                         after_relative_path.clone(),
-                    ),
-                    // Remote changes
-                    OperationalMessage::NewRemoteFile(content_id) => {
-                        self.new_remote_file(*content_id)
+                    )
+                }
+                // Remote changes
+                OperationalMessage::NewRemoteFile(content_id) => self.new_remote_file(*content_id),
+                OperationalMessage::ModifiedRemoteFile(content_id) => {
+                    self.modified_remote_file(*content_id)
+                }
+                OperationalMessage::MovedRemoteFile(content_id) => {
+                    self.moved_remote_file(*content_id)
+                }
+                OperationalMessage::DeletedRemoteFile(content_id) => {
+                    self.deleted_remote_file(*content_id)
+                }
+                OperationalMessage::RelocatedRemoteFile(old_content_id, new_content_id) => {
+                    self.relocated_remote_file(*old_content_id, *new_content_id)
+                }
+                OperationalMessage::ForceDownload(content_id) => self.force_download(*content_id),
+                OperationalMessage::ForcePush(relative_path) => {
+                    self.force_push(relative_path.clone())
+                }
+                // Wake up messages are never queued
+                OperationalMessage::LocalChanges => continue,
+                OperationalMessage::Exit => {
+                    // Files still being written are uploaded before exiting
+                    if let Some(due) = self
+                        .retries
+                        .iter()
+                        .filter(|retry| retry.deferred)
+                        .map(|retry| retry.due)
+                        .max()
+                    {
+                        log::info!("Wait for files being written before exit");
+                        self.retries.push(PlannedRetry {
+                            message: OperationalMessage::Exit,
+                            attempt: attempt.max(1),
+                            due,
+                            deferred: false,
+                        });
+                        continue;
+                    }
+                    if !self.retries.is_empty() {
+                        log::warn!(
+                            "Exit with {} operation(s) waiting for retry",
+                            self.retries.len()
+                        );
                     }
-                    OperationalMessage::ModifiedRemoteFile(content_id) => {
-                        self.modified_remote_file(*content_id)
+                    if !self.quota_blocked.is_empty() {
+                        log::warn!(
+                            "Exit with {} upload(s) blocked by remote quota",
+                            self.quota_blocked.len()
+                        );
                     }
-                    OperationalMessage::DeletedRemoteFile(content_id) => {
-                        self.deleted_remote_file(*content_id)
+                    if let Ok(mut report) = self.context.report.lock() {
+                        report.pending = self.retries.len() + self.quota_blocked.len();
                     }
-                    OperationalMessage::Exit => return (),
-                };
+                    if self.retries.is_empty() {
+                        self.commit_remote_cursor();
+                    }
+                    return;
+                }
+            };
 
-                match return_ {
-                    Err(err) => {
-                        log::log!(err.level(), "Error when {:?} : {:?}", message, err)
+            self.record_timing(&message, started.elapsed());
+            match &return_ {
+                Ok(_) => {
+                    self.context.metrics.operation(message.kind(), "success");
+                    self.health.operation_succeed();
+                    let relative_path = self.message_relative_path(&message);
+                    self.forget_failure(&relative_path);
+                    self.track_loop(&message, &relative_path);
+                    if let Ok(mut report) = self.context.report.lock() {
+                        report.succeeded(&Self::report_key(&message, &relative_path));
+                    }
+                    if self.quota_exceeded
+                        && matches!(
+                            message,
+                            OperationalMessage::DeletedLocalFile(_)
+                                | OperationalMessage::DeletedRemoteFile(_)
+                        )
+                    {
+                        self.release_quota_blocked("content deleted");
+                    }
+                    self.context
+                        .events
+                        .publish(SyncEvent::Synced(message.clone(), relative_path.clone()));
+                    self.run_success_hook(&message, relative_path);
+                }
+                Err(Error::NotRelevant(_)) => {
+                    self.context
+                        .metrics
+                        .operation(message.kind(), "not_relevant");
+                }
+                Err(Error::RemoteUnreachable(reason)) => {
+                    self.context
+                        .metrics
+                        .operation(message.kind(), "unreachable");
+                    self.go_offline(reason);
+                    self.journal_message(message);
+                    continue;
+                }
+                Err(Error::FileUnstable(reason)) => {
+                    self.context.metrics.operation(message.kind(), "deferred");
+                    log::info!("Defer {:?} : {}", message, reason);
+                    self.defer_until_stable(message, attempt);
+                    continue;
+                }
+                Err(error @ Error::QuotaExceeded(reason)) => {
+                    self.context
+                        .metrics
+                        .operation(message.kind(), "quota_exceeded");
+                    self.go_over_quota(reason);
+                    self.block_by_quota(message, error);
+                    continue;
+                }
+                Err(error) => {
+                    self.context.metrics.operation(message.kind(), "error");
+                    let error = format!("{:?}", error);
+                    self.health.operation_failed(error.clone());
+                    self.context
+                        .events
+                        .publish(SyncEvent::Error(message.clone(), error));
+                }
+            }
+            if let Err(err) = return_ {
+                if message.is_remote()
+                    && !matches!(err, Error::NotRelevant(_))
+                    && !(err.is_retryable() && attempt < RETRY_MAX_ATTEMPTS)
+                {
+                    self.remote_cursor_pending = false;
+                }
+                if err.is_retryable() && attempt < RETRY_MAX_ATTEMPTS {
+                    self.plan_retry(message, attempt + 1, err);
+                } else if err.is_retryable() {
+                    log::error!(
+                        "Give up {:?} after {} attempts : {:?}",
+                        message,
+                        attempt + 1,
+                        err
+                    );
+                    self.record_failure(&message, &err);
+                    self.report_failure(&message, &err);
+                    self.run_error_hook(&message, &err);
+                } else {
+                    log::log!(err.level(), "Error when {:?} : {:?}", message, err);
+                    if !matches!(err, Error::NotRelevant(_)) {
+                        self.record_failure(&message, &err);
+                        self.report_failure(&message, &err);
+                        self.run_error_hook(&message, &err);
                     }
-                    _ => {}
                 }
             }
         }
     }
 
-    fn new_local_file(&mut self, relative_path: String) -> Result<(), Error> {
-        // Prevent known bug : new local file is sometime an existing file
-        if DatabaseOperation::new(&self.connection).relative_path_is_known(&relative_path)? {
-            return self.modified_local_file(relative_path.clone());
+    // Local path which keeps failing is skipped, until its disk file changed or cooldown
+    // expired. Remote changes are never skipped : a new revision can fix the failure.
+    fn skip_failing(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        if self.context.max_consecutive_failures == 0 || message.is_remote() {
+            return Ok(false);
         }
-
-        // Grab file infos
-        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
-        let parent_id = match file_infos.parent_id(&self.connection) {
-            Ok(parent_id) => parent_id,
-            Err(error) => match error {
-                // Parent is currently not indexed
-                Error::UnIndexedRelativePath(parent_relative_path) => {
-                    self.new_local_file(parent_relative_path.clone())?;
-                    Some(
-                        DatabaseOperation::new(&self.connection)
-                            .get_content_id_from_path(parent_relative_path)?,
-                    )
-                }
-                _ => return Err(error),
-            },
+        let relative_path = match self.message_relative_path(message) {
+            Some(relative_path) => relative_path,
+            None => return Ok(false),
+        };
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let failure = match database_operation.get_failure(&relative_path)? {
+            Some(failure) if failure.count >= self.context.max_consecutive_failures => failure,
+            _ => return Ok(false),
         };
 
-        // Create it on remote
+        let elapsed_ms =
+            util::system_time_to_timestamp(SystemTime::now()) - failure.last_attempt_timestamp;
+        if elapsed_ms >= (self.context.failure_cooldown_s * 1000) as LastModifiedTimestamp {
+            log::info!("Cooldown of failing {:?} expired, try again", relative_path);
+            return Ok(false);
+        }
+        // Checksum is only computed when timestamp differs
+        if self.disk_timestamp(&relative_path)? != failure.last_modified_timestamp
+            && (failure.checksum.is_none()
+                || self.disk_state(&relative_path)?.1 != failure.checksum)
+        {
+            log::info!("Failing {:?} changed on disk, try again", relative_path);
+            database_operation.delete_failure(&relative_path)?;
+            return Ok(false);
+        }
+
         log::debug!(
-            "Create remote content with disk file {:?}",
-            &file_infos.absolute_path
+            "Skip {:?} ({} consecutive failures, last one : {})",
+            message,
+            failure.count,
+            failure.error
         );
-        let (content_id, revision_id) = match self.client.create_content(
-            file_infos.absolute_path,
-            file_infos.content_type.clone(),
-            parent_id,
-        ) {
-            Ok((content_id, revision_id)) => {
-                // Prepare to ignore remote create event
-                self.ignore_messages
-                    .push(OperationalMessage::NewRemoteFile(content_id));
-                // Tracim generate additional modified event when it is a file
-                if file_infos.content_type == ContentType::File {
-                    self.ignore_messages
-                        .push(OperationalMessage::ModifiedRemoteFile(content_id));
-                }
-                (content_id, revision_id)
+        Ok(true)
+    }
+
+    // Content found looping is skipped (in both directions) until restart. Forced
+    // download and upload are the way to leave the loop by taking one side.
+    fn skip_looping(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        if self.looping_content_ids.is_empty()
+            || matches!(
+                message,
+                OperationalMessage::ForceDownload(_) | OperationalMessage::ForcePush(_)
+            )
+        {
+            return Ok(false);
+        }
+        let content_id = match self.message_content_id(message)? {
+            Some(content_id) => content_id,
+            None => return Ok(false),
+        };
+        if self.looping_content_ids.contains(&content_id) {
+            log::debug!("Skip {:?} (content {} is looping)", message, content_id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // Remember successful modification of message content and pause content if it went
+    // back and forth between disk and remote too many times during loop window. Following
+    // modifications from the same side (user saving a file again and again) count once.
+    fn track_loop(
+        &mut self,
+        message: &OperationalMessage,
+        relative_path: &Option<RelativeFilePath>,
+    ) {
+        if self.context.loop_max_operations == 0 {
+            return;
+        }
+        let content_id = match self.message_content_id(message) {
+            Ok(Some(content_id)) => content_id,
+            Ok(None) => return,
+            Err(error) => {
+                log::error!("Unable to track loop of {:?} : {:?}", message, error);
+                return;
             }
-            Err(ClientError::AlreadyExistResponse(existing_content_id, existing_revision_id)) => {
-                (existing_content_id, existing_revision_id)
+        };
+        if matches!(
+            message,
+            OperationalMessage::ForceDownload(_) | OperationalMessage::ForcePush(_)
+        ) {
+            if self.looping_content_ids.remove(&content_id) {
+                log::info!("Content {} forced, resume its synchronization", content_id);
             }
-            Err(err) => {
-                return Err(Error::FailToCreateContentOnRemote(format!(
-                    "Fail to create new local file on remote : {:?}",
-                    err
-                )))
+            self.recent_operations.remove(&content_id);
+            return;
+        }
+        // Loops are made of modifications : creations (during a clone for example) are
+        // not tracked, so tracked contents stay few
+        if !matches!(
+            message,
+            OperationalMessage::ModifiedLocalFile(_)
+                | OperationalMessage::RenamedLocalFile(_, _)
+                | OperationalMessage::ModifiedRemoteFile(_)
+                | OperationalMessage::MovedRemoteFile(_)
+        ) {
+            return;
+        }
+
+        let now = Instant::now();
+        let window = Duration::from_secs(self.context.loop_window_s);
+        let operations = self.recent_operations.entry(content_id).or_default();
+        if operations
+            .back()
+            .is_some_and(|(_, _, remote)| *remote == message.is_remote())
+        {
+            operations.pop_back();
+        }
+        operations.push_back((now, message.kind(), message.is_remote()));
+        while operations
+            .front()
+            .is_some_and(|(at, _, _)| now.duration_since(*at) > window)
+        {
+            operations.pop_front();
+        }
+        self.recent_operations.retain(|_, operations| {
+            operations
+                .back()
+                .is_some_and(|(at, _, _)| now.duration_since(*at) <= window)
+        });
+
+        let operations = match self.recent_operations.get(&content_id) {
+            Some(operations) if operations.len() > self.context.loop_max_operations as usize => {
+                operations
             }
+            _ => return,
         };
-
-        // Update database
-        DatabaseOperation::new(&self.connection).insert_new_file(
-            file_infos.relative_path,
-            file_infos.last_modified_timestamp,
+        let kinds = operations
+            .iter()
+            .map(|(_, kind, _)| *kind)
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let reason = format!(
+            "Content {} ({:?}) modified alternately on disk and remote {} times in less \
+            than {} seconds ({}), it looks like a synchronization loop : pause it until \
+            restart or forced download (or upload)",
             content_id,
-            revision_id,
-        )?;
+            relative_path,
+            operations.len(),
+            self.context.loop_window_s,
+            kinds
+        );
+        log::warn!("{}", reason);
+        self.health.operation_failed(reason.clone());
+        if let Ok(mut report) = self.context.report.lock() {
+            report.failed(Self::report_key(message, relative_path), reason);
+        }
+        self.looping_content_ids.insert(content_id);
+        self.recent_operations.remove(&content_id);
+    }
 
-        Ok(())
+    // Content concerned by message, none if it is not (or no more) indexed
+    fn message_content_id(&self, message: &OperationalMessage) -> Result<Option<ContentId>, Error> {
+        match message {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path)
+            | OperationalMessage::RenamedLocalFile(_, relative_path)
+            | OperationalMessage::ForcePush(relative_path) => {
+                match DatabaseOperation::new(&self.connection)
+                    .get_content_id_from_path(relative_path.clone())
+                {
+                    Ok(content_id) => Ok(Some(content_id)),
+                    Err(Error::UnIndexedRelativePath(_)) => Ok(None),
+                    Err(error) => Err(error),
+                }
+            }
+            OperationalMessage::NewRemoteFile(content_id)
+            | OperationalMessage::ModifiedRemoteFile(content_id)
+            | OperationalMessage::MovedRemoteFile(content_id)
+            | OperationalMessage::DeletedRemoteFile(content_id)
+            | OperationalMessage::RelocatedRemoteFile(_, content_id)
+            | OperationalMessage::ForceDownload(content_id) => Ok(Some(*content_id)),
+            OperationalMessage::Exit | OperationalMessage::LocalChanges => Ok(None),
+        }
     }
 
-    fn modified_local_file(&mut self, relative_path: RelativeFilePath) -> Result<(), Error> {
+    // Excluded paths (and their descendants) are not synchronized in any direction, their
+    // index is kept as is so that changes are found at startup once they are included
+    fn skip_excluded(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        let mut relative_paths: Vec<RelativeFilePath> =
+            self.message_relative_path(message).into_iter().collect();
+        if let OperationalMessage::RenamedLocalFile(before_relative_path, _) = message {
+            relative_paths.push(before_relative_path.clone());
+        }
         let database_operation = DatabaseOperation::new(&self.connection);
+        for relative_path in relative_paths {
+            if database_operation.is_excluded(&relative_path)? {
+                log::debug!("Skip {:?} ({:?} is excluded)", message, relative_path);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
 
-        // Grab file infos
-        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
-        let content_id =
-            database_operation.get_content_id_from_path(file_infos.relative_path.clone())?;
+    // New local file rejected by Tracim is skipped until its disk file changed
+    fn skip_rejected(&self, message: &OperationalMessage) -> Result<bool, Error> {
+        if message.is_remote() {
+            return Ok(false);
+        }
+        let relative_path = match self.message_relative_path(message) {
+            Some(relative_path) => relative_path,
+            None => return Ok(false),
+        };
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let rejection = match database_operation.get_rejection(&relative_path)? {
+            Some(rejection) => rejection,
+            None => return Ok(false),
+        };
 
-        // Prepare to ignore remote create event
-        self.ignore_messages
-            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+        // Checksum is only computed when timestamp differs
+        if self.disk_timestamp(&relative_path)? != rejection.last_modified_timestamp
+            && (rejection.checksum.is_none()
+                || self.disk_state(&relative_path)?.1 != rejection.checksum)
+        {
+            log::info!("Rejected {:?} changed on disk, try again", relative_path);
+            database_operation.delete_rejection(&relative_path)?;
+            return Ok(false);
+        }
 
-        // Update file on remote
-        log::debug!("Update remote remote {}", content_id);
-        let revision_id = self.client.update_content(
-            file_infos.absolute_path,
-            file_infos.file_name,
-            file_infos.content_type,
-            content_id,
-        )?;
+        log::debug!(
+            "Skip {:?} (rejected by Tracim : {})",
+            message,
+            rejection.reason
+        );
+        Ok(true)
+    }
 
-        // Update database
-        database_operation.update_last_modified_timestamp(
-            file_infos.relative_path.clone(),
-            file_infos.last_modified_timestamp,
-        )?;
-        database_operation.update_revision_id(file_infos.relative_path, revision_id)?;
+    // Remember failure of local message path, with disk file state, to skip it later
+    fn record_failure(&self, message: &OperationalMessage, error: &Error) {
+        if self.context.max_consecutive_failures == 0 || message.is_remote() {
+            return;
+        }
+        let relative_path = match self.message_relative_path(message) {
+            Some(relative_path) => relative_path,
+            None => return,
+        };
 
-        Ok(())
+        // Failure can be caused by an unreadable file, which then have no checksum
+        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+        match self
+            .disk_timestamp(&relative_path)
+            .and_then(|last_modified_timestamp| {
+                let checksum = last_modified_timestamp
+                    .and_then(|_| util::file_checksum(&util::long_path(&absolute_path)).ok());
+                Ok(DatabaseOperation::new(&self.connection).record_failure(
+                    &relative_path,
+                    &format!("{:?}", error),
+                    last_modified_timestamp,
+                    checksum,
+                    util::system_time_to_timestamp(SystemTime::now()),
+                )?)
+            }) {
+            Ok(count) if count == self.context.max_consecutive_failures => log::warn!(
+                "{:?} failed {} consecutive times, skip it until it changes (or during {}s)",
+                relative_path,
+                count,
+                self.context.failure_cooldown_s
+            ),
+            Ok(_) => {}
+            Err(error) => log::error!(
+                "Unable to record failure of {:?} : {:?}",
+                relative_path,
+                error
+            ),
+        }
     }
 
-    fn deleted_local_file(&mut self, relative_path: String) -> Result<(), Error> {
-        let database_operation = DatabaseOperation::new(&self.connection);
-
-        // Grab file infos
-        let content_id = database_operation.get_content_id_from_path(relative_path)?;
+    // Messages without path (unknown remote content) are reported by themselves
+    fn report_key(
+        message: &OperationalMessage,
+        relative_path: &Option<RelativeFilePath>,
+    ) -> String {
+        relative_path
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", message))
+    }
 
-        // Delete on remote
-        log::debug!("Delete remote {}", content_id);
-        self.client.trash_content(content_id)?;
+    fn report_failure(&self, message: &OperationalMessage, error: &Error) {
+        let key = Self::report_key(message, &self.message_relative_path(message));
+        if let Ok(mut report) = self.context.report.lock() {
+            report.failed(key, format!("{:?}", error));
+        }
+    }
 
-        // Prepare to ignore remote trashed event
-        self.ignore_messages
-            .push(OperationalMessage::DeletedRemoteFile(content_id));
-
-        // Update database
-        database_operation.delete_file(content_id)?;
-
-        Ok(())
+    fn forget_failure(&self, relative_path: &Option<RelativeFilePath>) {
+        if let Some(relative_path) = relative_path {
+            if let Err(error) =
+                DatabaseOperation::new(&self.connection).delete_failure(relative_path)
+            {
+                log::error!(
+                    "Unable to forget failure of {:?} : {:?}",
+                    relative_path,
+                    error
+                )
+            }
+        }
     }
 
-    fn renamed_local_file(
-        &mut self,
-        before_relative_path: String,
-        after_relative_path: String,
-    ) -> Result<(), Error> {
-        let before_parent_relative_path = Path::new(&before_relative_path).parent();
-        let after_parent_relative_path = Path::new(&after_relative_path).parent();
-        let content_id = DatabaseOperation::new(&self.connection)
-            .get_content_id_from_path(before_relative_path.clone())?;
-        let file_infos = util::FileInfos::from(
-            self.context.folder_path.clone(),
-            after_relative_path.clone(),
-        )?;
+    // Disk file modification timestamp, none for folders and missing files
+    fn disk_timestamp(&self, relative_path: &str) -> Result<Option<LastModifiedTimestamp>, Error> {
+        let absolute_path = Path::new(&self.context.folder_path).join(relative_path);
+        if !absolute_path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(util::system_time_to_timestamp(
+            fs::metadata(util::long_path(&absolute_path))?.modified()?,
+        )))
+    }
 
-        // Prepare to ignore remote trashed event
-        self.ignore_messages
-            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+    // Disk file modification timestamp and checksum, none for folders and missing files
+    fn disk_state(
+        &self,
+        relative_path: &str,
+    ) -> Result<(Option<LastModifiedTimestamp>, Option<String>), Error> {
+        let absolute_path = Path::new(&self.context.folder_path).join(relative_path);
+        match self.disk_timestamp(relative_path)? {
+            Some(last_modified_timestamp) => Ok((
+                Some(last_modified_timestamp),
+                Some(util::file_checksum(&util::long_path(&absolute_path))?),
+            )),
+            None => Ok((None, None)),
+        }
+    }
 
-        // If path changes
-        if before_parent_relative_path != after_parent_relative_path {
-            log::debug!(
-                "Path of {:?} change for {:?}",
-                &before_parent_relative_path,
-                &after_parent_relative_path
-            );
-            // If path changes for a folder
-            if let Some(after_parent_relative_path_) = after_parent_relative_path {
-                let after_parent_relative_path_str =
-                    util::path_to_string(after_parent_relative_path_)?;
-                match DatabaseOperation::new(&self.connection)
-                    .get_content_id_from_path(after_parent_relative_path_str.clone())
-                {
-                    // New parent folder is indexed, update remote with it
-                    Ok(after_parent_content_id) => self.client.move_content(
-                        content_id,
-                        ParentIdParameter::Some(after_parent_content_id),
-                    )?,
-                    // New parent folder is not indexed, create it on remote
-                    Err(Error::UnIndexedRelativePath(_)) => {
-                        self.new_local_file(after_parent_relative_path_str.clone())?;
-                        let after_parent_content_id = DatabaseOperation::new(&self.connection)
-                            .get_content_id_from_path(after_parent_relative_path_str.clone())?;
-                        self.client.move_content(
-                            content_id,
-                            ParentIdParameter::Some(after_parent_content_id),
-                        )?
-                    }
-                    Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
-                }
-            // Or change for root
-            } else {
-                self.client
-                    .move_content(content_id, ParentIdParameter::Root)?
+    // Path concerned by message, as indexed after the operation for remote messages
+    fn message_relative_path(&self, message: &OperationalMessage) -> Option<RelativeFilePath> {
+        match message {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path)
+            | OperationalMessage::DeletedLocalFile(relative_path)
+            | OperationalMessage::RenamedLocalFile(_, relative_path)
+            | OperationalMessage::ForcePush(relative_path) => Some(relative_path.clone()),
+            OperationalMessage::NewRemoteFile(content_id)
+            | OperationalMessage::ModifiedRemoteFile(content_id)
+            | OperationalMessage::MovedRemoteFile(content_id)
+            | OperationalMessage::DeletedRemoteFile(content_id)
+            | OperationalMessage::RelocatedRemoteFile(_, content_id)
+            | OperationalMessage::ForceDownload(content_id) => {
+                DatabaseOperation::new(&self.connection)
+                    .get_path_from_content_id(*content_id)
+                    .ok()
             }
+            OperationalMessage::Exit | OperationalMessage::LocalChanges => None,
         }
+    }
 
-        let before_file_name = util::string_path_file_name(&before_relative_path)?;
-        let after_file_name = util::string_path_file_name(&after_relative_path)?;
+    // Hooks only concern files : folders are neither downloaded nor uploaded
+    fn run_success_hook(
+        &mut self,
+        message: &OperationalMessage,
+        relative_path: Option<RelativeFilePath>,
+    ) {
+        let event = match message {
+            OperationalMessage::NewRemoteFile(_)
+            | OperationalMessage::ModifiedRemoteFile(_)
+            | OperationalMessage::ForceDownload(_) => HookEvent::AfterDownload,
+            OperationalMessage::NewLocalFile(_)
+            | OperationalMessage::ModifiedLocalFile(_)
+            | OperationalMessage::ForcePush(_) => HookEvent::AfterUpload,
+            _ => return,
+        };
+        let is_file = relative_path
+            .as_ref()
+            .map(|relative_path| {
+                Path::new(&self.context.folder_path)
+                    .join(relative_path)
+                    .is_file()
+            })
+            .unwrap_or(false);
+        if is_file {
+            self.hooks.run(event, message.kind(), relative_path, None);
+        }
+    }
 
-        // Rename file name if changes
-        if before_file_name != after_file_name {
-            log::debug!(
-                "Rename remote {} from {:?} to {:?}",
-                content_id,
-                before_file_name,
-                after_file_name
+    // Error hook runs once per failed operation : when it is given up, not on each retry
+    fn run_error_hook(&mut self, message: &OperationalMessage, error: &Error) {
+        self.hooks.run(
+            HookEvent::OnError,
+            message.kind(),
+            self.message_relative_path(message),
+            Some(format!("{:?}", error)),
+        );
+    }
+
+    // Durations are kept in status and metrics. Slow operations are reported with time
+    // spent in each measured phase, the rest being disk reads, checksums, etc.
+    fn record_timing(&self, message: &OperationalMessage, elapsed: Duration) {
+        let phases = timing::take_phases();
+        self.context
+            .metrics
+            .operation_duration(message.kind(), elapsed);
+        self.health.operation_timed(message.kind(), elapsed);
+
+        if elapsed >= Duration::from_secs(self.context.slow_operation_s) {
+            let elapsed_ms = elapsed.as_millis() as u64;
+            log::warn!(
+                "Slow operation {:?} took {} ms (remote {} ms, disk write {} ms, database {} ms, other {} ms)",
+                message,
+                elapsed_ms,
+                phases.remote_ms,
+                phases.disk_ms,
+                phases.database_ms,
+                elapsed_ms
+                    .saturating_sub(phases.remote_ms + phases.disk_ms + phases.database_ms)
             );
-            self.client.update_content_file_name(
-                content_id,
-                after_file_name,
-                file_infos.content_type,
-            )?;
         }
+    }
 
-        DatabaseOperation::new(&self.connection)
-            .update_relative_path(content_id, after_relative_path.clone())?;
-        let remote_content = self.client.get_remote_content(content_id)?;
-        DatabaseOperation::new(&self.connection)
-            .update_revision_id(after_relative_path, remote_content.current_revision_id)?;
-
-        Ok(())
+    fn plan_retry(&mut self, message: OperationalMessage, attempt: u32, error: Error) {
+        let delay_ms = RETRY_BASE_DELAY_MS
+            .saturating_mul(2u64.saturating_pow(attempt - 1))
+            .min(RETRY_MAX_DELAY_MS);
+        log::warn!(
+            "Error when {:?} : {:?}, retry in {}s (attempt {})",
+            message,
+            error,
+            delay_ms / 1000,
+            attempt
+        );
+        self.retries.push(PlannedRetry {
+            message,
+            attempt,
+            due: Instant::now() + Duration::from_millis(delay_ms),
+            deferred: false,
+        });
     }
 
-    fn new_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
-        // Grab file infos
-        let remote_content = self.client.get_remote_content(content_id)?;
-        let relative_path = self.client.build_relative_path(&remote_content)?;
-        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+    // Message is proceed again after probe interval. It already passed ignore checks,
+    // and deferrals do not count as attempts.
+    fn defer_until_stable(&mut self, message: OperationalMessage, attempt: u32) {
+        let interval_ms = self.context.upload_stable_ms.unwrap_or_default();
+        self.retries.push(PlannedRetry {
+            message,
+            attempt: attempt.max(1),
+            due: Instant::now() + Duration::from_millis(interval_ms),
+            deferred: true,
+        });
+    }
 
-        // Prepare to ignore new local file
-        self.ignore_messages
-            .push(OperationalMessage::NewLocalFile(relative_path.clone()));
+    // Network and cloud mounts show files before their content is fully written. With
+    // stability probes, a disk file is uploaded only once its size and modification time
+    // did not change for at least one interval.
+    fn ensure_stable(&mut self, file_infos: &util::FileInfos) -> Result<(), Error> {
+        let interval_ms = match self.context.upload_stable_ms {
+            Some(interval_ms) if !file_infos.is_directory => interval_ms,
+            _ => return Ok(()),
+        };
 
-        // Check tree before create new file
-        if let Some(parent_id) = remote_content.parent_id {
-            // If parent content id is unknown, folder is not on disk
-            if !DatabaseOperation::new(&self.connection).content_id_is_known(parent_id)? {
-                // Use recursive to create this parent and possible parents parent
-                log::debug!("Parent of {:?} is unknown, ensure it", &absolute_path);
-                self.new_remote_file(parent_id)?;
+        let metadata = fs::metadata(util::long_path(Path::new(&file_infos.absolute_path)))?;
+        let state = (metadata.len(), metadata.modified()?);
+        let now = Instant::now();
+        match self.stability_probes.get(&file_infos.relative_path) {
+            Some((size, modified, seen)) if (*size, *modified) == state => {
+                if now.duration_since(*seen) >= Duration::from_millis(interval_ms) {
+                    self.stability_probes.remove(&file_infos.relative_path);
+                    return Ok(());
+                }
+            }
+            _ => {
+                self.stability_probes
+                    .insert(file_infos.relative_path.clone(), (state.0, state.1, now));
             }
         }
+        Err(Error::FileUnstable(format!(
+            "Disk file {:?} may still be written ({} bytes)",
+            file_infos.relative_path, state.0
+        )))
+    }
 
-        // Write file/folder on disk
-        if remote_content.content_type == "folder" {
-            log::debug!("Create disk folder {:?}", &absolute_path);
-            match fs::create_dir_all(&absolute_path) {
-                Ok(_) => {}
-                Err(error) => {
-                    let level = util::io_error_to_log_level(&error);
-                    log::log!(
-                        level,
-                        "Error during creation of {:?} : {}",
-                        absolute_path,
-                        error
-                    )
-                }
-            }
+    // Deleted or moved disk files (and their descendants) will not be probed again
+    fn forget_stability_probes(&mut self, relative_path: &str) {
+        self.stability_probes.retain(|probed_relative_path, _| {
+            !Path::new(probed_relative_path).starts_with(relative_path)
+        });
+    }
+
+    fn pop_due_retry(&mut self) -> Option<PlannedRetry> {
+        let now = Instant::now();
+        let position = self.retries.iter().position(|retry| retry.due <= now)?;
+        Some(self.retries.remove(position))
+    }
+
+    // Wait incoming messages until next retry, or next reachability check when offline
+    fn next_wait_delay(&self) -> Duration {
+        let now = Instant::now();
+        let delay = self
+            .retries
+            .iter()
+            .map(|retry| retry.due.saturating_duration_since(now))
+            .min()
+            .unwrap_or(Duration::from_millis(IDLE_WAIT_MS));
+        let delay = if self.quota_exceeded {
+            delay.min(self.next_quota_probe.saturating_duration_since(now))
         } else {
-            let mut response = self
-                .client
-                .get_file_content_response(remote_content.content_id, remote_content.filename)?;
-            log::debug!("Create disk file {:?}", &absolute_path);
-            let mut out = File::create(absolute_path)?;
-            io::copy(&mut response, &mut out)?;
+            delay
+        };
+        if self.offline {
+            delay.min(self.next_probe.saturating_duration_since(now))
+        } else {
+            delay
         }
+    }
 
-        // Update database
-        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
-        let content = self.client.get_remote_content(content_id)?;
-        DatabaseOperation::new(&self.connection).insert_new_file(
-            file_infos.relative_path,
-            file_infos.last_modified_timestamp,
-            content_id,
-            content.current_revision_id,
-        )?;
-
-        Ok(())
+    // Only files consume quota
+    fn is_file_upload(&self, message: &OperationalMessage) -> bool {
+        match message {
+            OperationalMessage::NewLocalFile(relative_path)
+            | OperationalMessage::ModifiedLocalFile(relative_path) => {
+                Path::new(&self.context.folder_path)
+                    .join(relative_path)
+                    .is_file()
+            }
+            _ => false,
+        }
     }
 
-    fn modified_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
-        let database_operation = DatabaseOperation::new(&self.connection);
+    fn go_over_quota(&mut self, reason: &str) {
+        if !self.quota_exceeded {
+            log::warn!(
+                "Remote quota exceeded, uploads are blocked until space is freed : {}",
+                reason
+            );
+            self.context
+                .events
+                .publish(SyncEvent::QuotaExceeded(reason.to_string()));
+            self.quota_exceeded = true;
+        }
+        self.next_quota_probe = Instant::now() + Duration::from_millis(QUOTA_PROBE_MS);
+    }
 
-        // Grab file infos
-        let remote_content = self.client.get_remote_content(content_id)?;
-        let relative_path = self.client.build_relative_path(&remote_content)?;
-        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+    // Blocked paths are visible as failing paths until they are tried again
+    fn block_by_quota(&mut self, message: OperationalMessage, error: &Error) {
+        if !self.quota_blocked.contains(&message) {
+            self.record_failure(&message, error);
+            log::debug!("Block {:?} until remote space is freed", message);
+            self.quota_blocked.push(message);
+        }
+    }
 
-        // TODO : use enum for content_type
-        if remote_content.content_type == "folder" {
-            // TODO : manage case where file doesn't exist (in db and on disk)
-            let relative_path =
-                DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
-            let old_absolute_path = Path::new(&self.context.folder_path).join(relative_path);
-            let new_absolute_path = old_absolute_path
-                .parent()
-                .ok_or(Error::PathManipulationError(format!(
-                    "Unable to get parent folder of {:?}",
-                    old_absolute_path
-                )))?
-                .join(remote_content.filename);
+    // Blocked uploads are tried again first. If quota is still exceeded, first one fails
+    // and the others are blocked again.
+    fn release_quota_blocked(&mut self, reason: &str) {
+        log::info!(
+            "Retry {} upload(s) blocked by remote quota ({})",
+            self.quota_blocked.len(),
+            reason
+        );
+        self.quota_exceeded = false;
+        for message in self.quota_blocked.drain(..).rev().collect::<Vec<_>>() {
+            self.forget_failure(&self.message_relative_path(&message));
+            self.queue.push_front(message);
+        }
+    }
 
-            log::info!(
-                "Rename disk folder {:?} into {:?}",
-                &old_absolute_path,
-                &new_absolute_path
+    fn go_offline(&mut self, reason: &str) {
+        if !self.offline {
+            log::warn!(
+                "Remote unreachable, journal local changes until it is reachable again : {}",
+                reason
             );
-            fs::rename(old_absolute_path, &new_absolute_path)?;
-            // Prepare to ignore modified local file
-            let new_relative_path =
-                util::path_to_string(new_absolute_path.strip_prefix(&self.context.folder_path)?)?;
-            self.ignore_messages
-                .push(OperationalMessage::ModifiedLocalFile(new_relative_path));
-            return Ok(());
+            self.offline = true;
+            // Remote changes are not journaled
+            self.remote_cursor_pending = false;
         }
+        self.next_probe = Instant::now() + Duration::from_millis(OFFLINE_PROBE_MS);
+    }
 
-        // Manage renamed case
-        let current_relative_path =
-            DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
-        let file_infos =
-            util::FileInfos::from(self.context.folder_path.clone(), current_relative_path)?;
-        if remote_content.filename != file_infos.file_name {
-            log::debug!(
-                "Rename {} into {:?}",
-                file_infos.absolute_path,
-                &absolute_path
-            );
-            match fs::rename(file_infos.absolute_path, &absolute_path) {
-                Ok(_) => {
-                    DatabaseOperation::new(&self.connection)
-                        .update_relative_path(content_id, relative_path.clone())?
-                    // TODO : manage local event rename by ignoring renamed event
+    fn commit_remote_cursor(&mut self) {
+        if !self.remote_cursor_pending {
+            return;
+        }
+        self.remote_cursor_pending = false;
+        if let Err(error) = remote::commit_remote_cursor(&self.connection) {
+            log::error!("Unable to remember last pulled remote event : {:?}", error);
+        }
+    }
+
+    // Journal is persisted in index, so that local changes made while offline survive
+    // a crash or a stop
+    fn journal_message(&self, message: OperationalMessage) {
+        match message.to_journal_entry() {
+            Some(entry) => {
+                if let Err(error) = DatabaseOperation::new(&self.connection).append_journal(&entry)
+                {
+                    log::error!("Unable to journal {:?} : {:?}", message, error)
                 }
-                Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
             }
+            None => log::debug!(
+                "Drop {:?} while remote is unreachable, recovery will find it again",
+                message
+            ),
         }
+    }
 
-        // Prepare to ignore modified local file
-        self.ignore_messages
-            .push(OperationalMessage::ModifiedLocalFile(relative_path.clone()));
+    fn probe_remote(&mut self) {
+        match self.client.get_user_id() {
+            Ok(_) => self.recover(),
+            Err(error) => {
+                log::debug!("Remote still unreachable : {}", error);
+                self.next_probe = Instant::now() + Duration::from_millis(OFFLINE_PROBE_MS);
+            }
+        }
+    }
 
-        // Write file on disk
-        let mut response = self
-            .client
-            .get_file_content_response(content_id, remote_content.filename)?;
-        // TODO : Manage case where file don't exist on disk
-        log::debug!(
-            "Update disk file {:?} with content {}",
-            &absolute_path,
-            content_id,
-        );
-        let mut out = File::create(absolute_path)?;
-        io::copy(&mut response, &mut out)?;
+    // Remote is reachable again : remote changes missed meanwhile are searched like at
+    // startup and proceed first, then journaled local changes are replayed in their
+    // order. Both go through usual conflict detection, so a file changed on both sides
+    // become a conflict instead of being overwritten.
+    fn recover(&mut self) {
+        log::info!("Remote reachable again, search missed remote changes");
+        let (sender, receiver) = channel();
+        let context = self.context.clone();
+        if let Err(error) = Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| RemoteSync::new(context, connection, sender)?.pull())
+        {
+            log::warn!("Unable to search missed remote changes : {:?}", error);
+            self.next_probe = Instant::now() + Duration::from_millis(OFFLINE_PROBE_MS);
+            return;
+        }
 
-        // Update database
-        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
-        database_operation.update_last_modified_timestamp(
-            file_infos.relative_path.clone(),
-            file_infos.last_modified_timestamp,
-        )?;
-        database_operation
-            .update_revision_id(file_infos.relative_path, remote_content.current_revision_id)?;
+        let journaled = match self.take_journal() {
+            Ok(journaled) => journaled,
+            Err(error) => {
+                log::error!("Unable to read journal : {:?}", error);
+                return;
+            }
+        };
+        log::info!("Replay {} journaled local change(s)", journaled.len());
+        self.offline = false;
 
-        Ok(())
+        let mut queue: VecDeque<OperationalMessage> =
+            receiver.try_iter().filter(is_operation).collect();
+        queue.extend(journaled);
+        queue.append(&mut self.queue);
+        self.queue = queue;
+        self.compact_queue();
     }
 
-    fn deleted_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
+    fn compact_grown_queue(&mut self) {
+        if self.queue.len() >= COMPACTION_MIN_QUEUE_LENGTH
+            && self.queue.len() >= self.compacted_queue_length * 2
+        {
+            self.compact_queue();
+        }
+    }
+
+    fn compact_queue(&mut self) {
+        let length = self.queue.len();
+        let connection = &self.connection;
+        let folder_path = Path::new(&self.context.folder_path);
+        let compacted = compaction::compact(
+            self.queue.drain(..),
+            &self.ignore_messages,
+            &|relative_path| compaction::kind_unchanged(connection, folder_path, relative_path),
+        );
+        self.queue = compacted.into();
+        self.compacted_queue_length = self.queue.len();
+        if self.queue.len() < length {
+            log::debug!(
+                "Queue compacted from {} to {} message(s)",
+                length,
+                self.queue.len()
+            );
+        }
+    }
+
+    // Journal is emptied once its messages are queued. A crash before they are proceed
+    // is covered by startup local sync, which find these changes on disk.
+    fn take_journal(&self) -> Result<Vec<OperationalMessage>, Error> {
         let database_operation = DatabaseOperation::new(&self.connection);
+        let messages = database_operation
+            .get_journal()?
+            .iter()
+            .filter_map(|entry| {
+                let message = OperationalMessage::from_journal_entry(entry);
+                if message.is_none() {
+                    log::warn!("Ignore unknown journal entry {:?}", entry);
+                }
+                message
+            })
+            .collect();
+        database_operation.clear_journal()?;
+        Ok(messages)
+    }
 
-        // Grab file infos (from local index, remote content has name changes)
+    fn new_local_file(&mut self, relative_path: String) -> Result<(), Error> {
+        // Prevent known bug : new local file is sometime an existing file
+        if DatabaseOperation::new(&self.connection).relative_path_is_known(&relative_path)? {
+            // Folder was already created for a descendant or from its parent content,
+            // there is nothing to upload for it
+            if Path::new(&self.context.folder_path)
+                .join(&relative_path)
+                .is_dir()
+            {
+                log::debug!("Folder {:?} is already created", relative_path);
+                return Ok(());
+            }
+            return self.modified_local_file(relative_path.clone());
+        }
 
-        let relative_path =
-            DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
-        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        // A case only rename can be seen as a new file, especially on case insensitive
+        // file systems. Indexed path is renamed only if its disk file no longer exists
+        // with its exact name, so that files only differing by case are both kept.
+        if let Some(indexed_relative_path) = DatabaseOperation::new(&self.connection)
+            .get_relative_path_ignoring_case(&relative_path)?
+        {
+            let indexed_file_name = util::string_path_file_name(&indexed_relative_path)?;
+            let indexed_disk_file_name = util::disk_file_name(
+                &Path::new(&self.context.folder_path).join(&indexed_relative_path),
+            )?;
+            if indexed_disk_file_name.as_ref() != Some(&indexed_file_name) {
+                log::info!(
+                    "{:?} is a case only rename of {:?}",
+                    relative_path,
+                    indexed_relative_path
+                );
+                return self.renamed_local_file(indexed_relative_path, relative_path);
+            }
+        }
 
-        // Prepare to ignore deleted local file
-        self.ignore_messages
-            .push(OperationalMessage::DeletedLocalFile(
+        // Grab file infos
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        // Hard link created while running is not seen by startup scan
+        if let Some(linked_relative_path) = self.indexed_hard_link(&file_infos)? {
+            log::warn!(
+                "{:?} is a hard link to {:?}, ignore it",
                 file_infos.relative_path,
-            ));
-
-        // Delete disk file
-        log::debug!("Remove disk file {:?}", &file_infos.absolute_path);
-        if file_infos.is_directory {
-            fs::remove_dir_all(&file_infos.absolute_path)?;
-        } else {
-            fs::remove_file(&file_infos.absolute_path)?;
+                linked_relative_path
+            );
+            return Err(Error::NotRelevant(format!(
+                "{:?} is a hard link to already synchronized {:?}",
+                file_infos.relative_path, linked_relative_path
+            )));
+        }
+        self.ensure_stable(&file_infos)?;
+        let parent_id = match file_infos.parent_id(&self.connection) {
+            Ok(parent_id) => parent_id,
+            Err(error) => match error {
+                // Parent is currently not indexed
+                Error::UnIndexedRelativePath(parent_relative_path) => {
+                    if self.context.parent_creation == ParentCreation::Reject {
+                        return Err(Error::MissingRemoteParent(format!(
+                            "Parent {:?} of {:?} is not on remote and parent creation is rejected",
+                            parent_relative_path, file_infos.relative_path
+                        )));
+                    }
+                    log::debug!(
+                        "Parent {:?} of {:?} is not on remote, create it",
+                        parent_relative_path,
+                        file_infos.relative_path
+                    );
+                    self.new_local_file(parent_relative_path.clone())?;
+                    self.created_ancestors
+                        .insert(OperationalMessage::NewLocalFile(
+                            parent_relative_path.clone(),
+                        ));
+                    Some(
+                        DatabaseOperation::new(&self.connection)
+                            .get_content_id_from_path(parent_relative_path)?,
+                    )
+                }
+                _ => return Err(error),
+            },
         };
 
-        database_operation.delete_file(content_id)?;
+        let checksum = file_infos.checksum()?;
+        let xattrs = self.xattrs_to_index(&file_infos)?;
 
-        Ok(())
+        // Create it on remote
+        log::debug!(
+            "Create remote content with disk file {:?}",
+            &file_infos.absolute_path
+        );
+        let (content_id, revision_id) = match self.client.create_content(
+            file_infos.absolute_path.clone(),
+            file_infos.content_type.clone(),
+            parent_id,
+        ) {
+            Ok(remote_content) => {
+                let content_id = remote_content.content_id;
+                // Prepare to ignore remote create event
+                self.ignore_messages
+                    .push(OperationalMessage::NewRemoteFile(content_id));
+                // Tracim generate additional modified event when it is a file
+                if file_infos.content_type == ContentType::File {
+                    self.ignore_messages
+                        .push(OperationalMessage::ModifiedRemoteFile(content_id));
+                }
+                let revision_id = self
+                    .align_remote_label(&remote_content, &file_infos.file_name)?
+                    .unwrap_or(remote_content.current_revision_id);
+                (content_id, revision_id)
+            }
+            Err(ClientError::AlreadyExistResponse(existing_content_id, existing_revision_id)) => {
+                // No remote create event will come for an existing content
+                let revision_id = self
+                    .reconcile_existing(&file_infos, existing_content_id, &checksum)?
+                    .unwrap_or(existing_revision_id);
+                (existing_content_id, revision_id)
+            }
+            Err(ClientError::AlreadyExistResponseAndFailToFoundIt(message)) => {
+                // Colliding content was not matched while handling Tracim response : list
+                // parent folder again to find it by name, then proceed as if it was found
+                let existing = self
+                    .client
+                    .find_by_name(&file_infos.file_name, parent_id)?
+                    .ok_or_else(|| {
+                        Error::FailToCreateContentOnRemote(format!(
+                            "Content {:?} already exist on remote but can't be found : {}",
+                            file_infos.relative_path, message
+                        ))
+                    })?;
+                log::info!(
+                    "Found existing remote content {} for {:?} after creation refused ({})",
+                    existing.content_id,
+                    file_infos.relative_path,
+                    message
+                );
+                let revision_id = self
+                    .reconcile_existing(&file_infos, existing.content_id, &checksum)?
+                    .unwrap_or(existing.current_revision_id);
+                (existing.content_id, revision_id)
+            }
+            Err(ClientError::ForbiddenResponse(message))
+                if file_infos.content_type == ContentType::Folder =>
+            {
+                return Err(Error::RemoteFolderCreationForbidden(format!(
+                    "Tracim refuse to create folder {:?} : {}",
+                    file_infos.relative_path, message
+                )))
+            }
+            Err(ClientError::UnreachableRemote(message)) => {
+                return Err(Error::RemoteUnreachable(message))
+            }
+            Err(ClientError::QuotaExceeded(message)) => return Err(Error::QuotaExceeded(message)),
+            Err(ClientError::FileTooLarge(message)) => return Err(Error::FileTooLarge(message)),
+            Err(ClientError::Rejected(message)) => {
+                return self.rejected_local_file(file_infos, checksum, message)
+            }
+            Err(err) => {
+                return Err(Error::FailToCreateContentOnRemote(format!(
+                    "Fail to create new local file on remote : {:?}",
+                    err
+                )))
+            }
+        };
+
+        // Update database
+        DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+            if let Some(xattrs) = xattrs {
+                database_operation.set_xattrs(content_id, &xattrs)?;
+            }
+            database_operation.insert_new_file(
+                file_infos.relative_path.clone(),
+                file_infos.last_modified_timestamp,
+                content_id,
+                revision_id,
+                checksum,
+                file_infos.is_directory,
+            )?;
+            database_operation.update_size(file_infos.relative_path.clone(), file_infos.size)
+        })?;
+
+        // A folder moved in from outside synchronized folder comes with its own create
+        // event only : its content is queued from disk. Descendants already reported by
+        // their own events are then found known and left untouched.
+        if file_infos.is_directory {
+            self.queue_disk_children(&file_infos)?;
+        }
+
+        // Label set on disk file before its creation on remote is pushed
+        if self.context.labels_synchronized() && !file_infos.is_directory {
+            let remote_content = self.client.get_remote_content(content_id)?;
+            DatabaseOperation::new(&self.connection)
+                .update_label(content_id, &remote_content.label)?;
+            let local_label = self.local_label(&file_infos.relative_path)?;
+            self.sync_label(&file_infos.relative_path, &remote_content, local_label)?;
+        }
+
+        Ok(())
+    }
+
+    // New local file refused by Tracim (name or type not allowed), according to
+    // rejection policy
+    fn rejected_local_file(
+        &mut self,
+        file_infos: util::FileInfos,
+        checksum: Option<String>,
+        reason: String,
+    ) -> Result<(), Error> {
+        match self.context.rejection_policy {
+            RejectionPolicy::Fail => return Err(Error::Rejected(reason)),
+            RejectionPolicy::Skip => {}
+            RejectionPolicy::RenameAndRetry => {
+                // Retried once : a sanitized name is sanitized again as itself
+                let sanitized_file_name = util::sanitized_file_name(&file_infos.file_name);
+                let sanitized_relative_path = util::path_to_string(
+                    &Path::new(&file_infos.relative_path).with_file_name(&sanitized_file_name),
+                )?;
+                let sanitized_absolute_path =
+                    Path::new(&self.context.folder_path).join(&sanitized_relative_path);
+                if sanitized_file_name != file_infos.file_name && !sanitized_absolute_path.exists()
+                {
+                    log::info!(
+                        "Tracim refused {:?}, rename it {:?} and retry : {}",
+                        file_infos.relative_path,
+                        sanitized_relative_path,
+                        reason
+                    );
+                    self.rename_disk_file(&file_infos.relative_path, &sanitized_relative_path)?;
+                    return self.new_local_file(sanitized_relative_path);
+                }
+            }
+        }
+
+        log::warn!(
+            "Tracim refused {:?}, skip it until it changes : {}",
+            file_infos.relative_path,
+            reason
+        );
+        DatabaseOperation::new(&self.connection).insert_rejection(&Rejection {
+            relative_path: file_infos.relative_path.clone(),
+            reason,
+            rejected_timestamp: util::system_time_to_timestamp(SystemTime::now()),
+            last_modified_timestamp: self.disk_timestamp(&file_infos.relative_path)?,
+            checksum,
+        })?;
+        Err(Error::NotRelevant(format!(
+            "{:?} is rejected by Tracim",
+            file_infos.relative_path
+        )))
+    }
+
+    // Queue creation of folder disk children not already queued (as by startup sync),
+    // before exit message if any
+    fn queue_disk_children(&mut self, file_infos: &util::FileInfos) -> Result<(), Error> {
+        let mut file_names = fs::read_dir(&file_infos.absolute_path)?
+            .map(|entry| Ok(entry?.file_name()))
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        file_names.sort();
+
+        let mut position = self
+            .queue
+            .iter()
+            .position(|message| *message == OperationalMessage::Exit)
+            .unwrap_or(self.queue.len());
+        for file_name in file_names {
+            let message = OperationalMessage::NewLocalFile(util::path_to_string(
+                &Path::new(&file_infos.relative_path).join(file_name),
+            )?);
+            if !self.queue.contains(&message) {
+                self.queue.insert(position, message);
+                position += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Local file matched an already existing remote content. If their content differ,
+    // upload local file as a new revision of it.
+    fn reconcile_existing(
+        &mut self,
+        file_infos: &util::FileInfos,
+        content_id: ContentId,
+        checksum: &Option<String>,
+    ) -> Result<Option<RevisionId>, Error> {
+        if file_infos.is_directory {
+            return Ok(None);
+        }
+
+        let remote_content = self.client.get_remote_content(content_id)?;
+        if self.identical_to_remote(file_infos, checksum, &remote_content)? {
+            log::debug!(
+                "Existing remote content {} is identical to {:?}",
+                content_id,
+                file_infos.relative_path
+            );
+            return Ok(None);
+        }
+
+        log::info!(
+            "Existing remote content {} differs from {:?}, upload it as new revision",
+            content_id,
+            file_infos.relative_path
+        );
+        // Prepare to ignore remote modified event
+        self.ignore_messages
+            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+        Ok(Some(self.client.update_content(
+            file_infos.absolute_path.clone(),
+            remote_content.filename,
+            file_infos.content_type.clone(),
+            content_id,
+        )?))
+    }
+
+    // Remote content checksum is not exposed by Tracim : metadata (size, then indexed
+    // revision) is compared first, and remote content is read (not written on disk)
+    // only when it cannot tell, unless sizes are trusted
+    fn identical_to_remote(
+        &self,
+        file_infos: &util::FileInfos,
+        checksum: &Option<String>,
+        remote_content: &RemoteContent,
+    ) -> Result<bool, Error> {
+        let local_size = fs::metadata(util::long_path(Path::new(&file_infos.absolute_path)))?.len();
+        match remote_content.size {
+            Some(remote_size) if remote_size != local_size => return Ok(false),
+            Some(_) if self.context.adopt_by_size => return Ok(true),
+            _ => {}
+        }
+        if checksum.is_some() && self.indexed_checksum_at_revision(remote_content)? == *checksum {
+            return Ok(true);
+        }
+
+        let remote_checksum = util::reader_checksum(&mut self.client.get_file_content_response(
+            remote_content.content_id,
+            remote_content.filename.clone(),
+        )?)?;
+        Ok(Some(remote_checksum) == *checksum)
+    }
+
+    // Checksum indexed for remote content when the index knows its current revision
+    fn indexed_checksum_at_revision(
+        &self,
+        remote_content: &RemoteContent,
+    ) -> Result<Option<String>, Error> {
+        let database_operation = DatabaseOperation::new(&self.connection);
+        if !database_operation.content_id_is_known(remote_content.content_id)?
+            || database_operation.get_revision_id_from_content_id(remote_content.content_id)?
+                != remote_content.current_revision_id
+        {
+            return Ok(None);
+        }
+        let relative_path =
+            database_operation.get_path_from_content_id(remote_content.content_id)?;
+        Ok(database_operation.get_checksum(&relative_path)?)
+    }
+
+    // Indexed path of another hard link to given disk file, when hard links are detected
+    fn indexed_hard_link(
+        &self,
+        file_infos: &util::FileInfos,
+    ) -> Result<Option<RelativeFilePath>, Error> {
+        if !self.context.detect_hard_links {
+            return Ok(None);
+        }
+        let hard_link_id = match fs::metadata(util::long_path(Path::new(&file_infos.absolute_path)))
+            .ok()
+            .and_then(|metadata| util::hard_link_id(&metadata))
+        {
+            Some(hard_link_id) => hard_link_id,
+            None => return Ok(None),
+        };
+
+        for relative_path in DatabaseOperation::new(&self.connection).get_relative_paths()? {
+            let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+            if fs::metadata(util::long_path(&absolute_path))
+                .ok()
+                .and_then(|metadata| util::hard_link_id(&metadata))
+                == Some(hard_link_id)
+            {
+                return Ok(Some(relative_path));
+            }
+        }
+        Ok(None)
+    }
+
+    // Tracim compute label from uploaded file name, which can give a different local
+    // name depending on name strategy. Update label when it is the case, so that
+    // building local name back from remote content give the same local file name.
+    fn align_remote_label(
+        &mut self,
+        remote_content: &RemoteContent,
+        file_name: &str,
+    ) -> Result<Option<RevisionId>, Error> {
+        let content_id = remote_content.content_id;
+        if remote_content.local_name(&self.context) == file_name {
+            return Ok(None);
+        }
+
+        let content_type = ContentType::from_str(&remote_content.content_type).map_err(|_| {
+            Error::UnexpectedError(format!(
+                "Unknown content type {} for content {}",
+                remote_content.content_type, content_id
+            ))
+        })?;
+        log::debug!("Update remote {} name to {:?}", content_id, file_name);
+        // Prepare to ignore remote modified event
+        self.ignore_messages
+            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+        Ok(Some(self.client.update_content_file_name(
+            content_id,
+            file_name.to_string(),
+            content_type,
+        )?))
+    }
+
+    fn modified_local_file(&mut self, relative_path: RelativeFilePath) -> Result<(), Error> {
+        // Grab file infos
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        // Folder modification time changes with its children, which are synchronized by
+        // themselves : a folder has no content to update
+        if file_infos.is_directory {
+            return Err(Error::NotRelevant(format!(
+                "{:?} is a folder, nothing to upload",
+                file_infos.relative_path
+            )));
+        }
+        let content_id = DatabaseOperation::new(&self.connection)
+            .get_content_id_from_path(file_infos.relative_path.clone())?;
+
+        // Conflict only when both disk and remote diverged from the indexed revision
+        let disk_changed = self.disk_file_changed(&file_infos)?;
+        if disk_changed {
+            self.ensure_stable(&file_infos)?;
+        }
+        let remote_content = self.client.get_remote_content(content_id)?;
+        let known_revision_id =
+            DatabaseOperation::new(&self.connection).get_revision_id_from_content_id(content_id)?;
+        let remote_changed = known_revision_id != remote_content.current_revision_id;
+        let local_label = self.local_label(&file_infos.relative_path)?;
+        let relative_path = file_infos.relative_path.clone();
+
+        match (disk_changed, remote_changed) {
+            // Local sync and local watcher can both report the same change at startup :
+            // once the first message uploaded it, disk content is the indexed one
+            (false, false) => {
+                log::debug!(
+                    "Disk file {:?} is already synchronized, nothing to upload",
+                    file_infos.relative_path
+                );
+            }
+            (true, false) => self.upload_file(content_id, file_infos)?,
+            (false, true) => self.write_remote_file(&remote_content, file_infos.relative_path)?,
+            (true, true) => {
+                return self.resolve_conflict(file_infos.relative_path, &remote_content)
+            }
+        }
+
+        self.sync_label(&relative_path, &remote_content, local_label)
+    }
+
+    // Upload disk file as new revision of remote content and index it
+    fn upload_file(
+        &mut self,
+        content_id: ContentId,
+        file_infos: util::FileInfos,
+    ) -> Result<(), Error> {
+        let database_operation = DatabaseOperation::new(&self.connection);
+
+        // Prepare to ignore remote create event
+        self.ignore_messages
+            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+
+        let checksum = file_infos.checksum()?;
+        let xattrs = self.xattrs_to_index(&file_infos)?;
+        let file_name = self.upload_file_name(content_id, &file_infos)?;
+
+        // Update file on remote
+        log::debug!("Update remote remote {}", content_id);
+        let revision_id = self.client.update_content(
+            file_infos.absolute_path,
+            file_name,
+            file_infos.content_type,
+            content_id,
+        )?;
+
+        // Update database
+        let relative_path = file_infos.relative_path;
+        let last_modified_timestamp = file_infos.last_modified_timestamp;
+        let size = file_infos.size;
+        database_operation.with_transaction(|database_operation| {
+            if let Some(xattrs) = xattrs {
+                database_operation.set_xattrs(content_id, &xattrs)?;
+            }
+            database_operation
+                .update_last_modified_timestamp(relative_path.clone(), last_modified_timestamp)?;
+            database_operation.update_checksum(relative_path.clone(), checksum)?;
+            database_operation.update_size(relative_path.clone(), size)?;
+            database_operation.update_revision_id(relative_path, revision_id)
+        })?;
+
+        Ok(())
+    }
+
+    // Tracim names the new revision with uploaded file name. Disk name can be derived from
+    // remote content name (label name strategy, name truncated for disk, name
+    // disambiguated from a case collision) : remote content file name is then kept, so
+    // that uploading never renames it.
+    fn upload_file_name(
+        &self,
+        content_id: ContentId,
+        file_infos: &util::FileInfos,
+    ) -> Result<String, Error> {
+        let derived = file_infos.content_type == ContentType::File
+            && (self.context.name_strategy != NameStrategy::Filename
+                || util::may_be_limited_file_name(
+                    &file_infos.file_name,
+                    self.context.max_name_bytes,
+                )
+                || util::has_case_collision_suffix(&file_infos.file_name, content_id));
+        if !derived {
+            return Ok(file_infos.file_name.clone());
+        }
+        Ok(self.client.get_remote_content(content_id)?.filename)
+    }
+
+    // Tracim can't hold extended attributes, so they are kept in index to be
+    // restored when the file is downloaded again
+    fn xattrs_to_index(&self, file_infos: &util::FileInfos) -> Result<Option<util::Xattrs>, Error> {
+        if !self.context.preserve_xattrs {
+            return Ok(None);
+        }
+
+        Ok(Some(file_infos.xattrs()?))
+    }
+
+    // Disk file content differs from the content of its indexed revision. Content is
+    // hashed only when size or timestamp differ from indexed ones.
+    fn disk_file_changed(&self, file_infos: &util::FileInfos) -> Result<bool, Error> {
+        let database_operation = DatabaseOperation::new(&self.connection);
+        // Different size is a change, without computing checksum
+        if let (Some(indexed_size), Some(size)) = (
+            database_operation.get_size(&file_infos.relative_path)?,
+            file_infos.size,
+        ) {
+            if indexed_size != size {
+                return Ok(true);
+            }
+        }
+        let indexed_last_modified_timestamp =
+            database_operation.get_last_modified_timestamp(&file_infos.relative_path)?;
+        if util::timestamps_match(
+            file_infos.last_modified_timestamp,
+            indexed_last_modified_timestamp,
+            self.context.timestamp_granularity_ms,
+            self.context.timestamp_tolerance_ms,
+        ) {
+            return Ok(false);
+        }
+
+        // Timestamp can change without content change
+        Ok(!self.disk_content_is_indexed(file_infos)?)
+    }
+
+    // Disk file content is exactly the indexed one
+    fn disk_content_is_indexed(&self, file_infos: &util::FileInfos) -> Result<bool, Error> {
+        match DatabaseOperation::new(&self.connection).get_checksum(&file_infos.relative_path)? {
+            Some(indexed_checksum) => Ok(file_infos.checksum()? == Some(indexed_checksum)),
+            None => Ok(false),
+        }
+    }
+
+    // File changed both on disk and on remote since last synchronization
+    fn resolve_conflict(
+        &mut self,
+        relative_path: RelativeFilePath,
+        remote_content: &RemoteContent,
+    ) -> Result<(), Error> {
+        let content_id = remote_content.content_id;
+        let known_revision_id =
+            DatabaseOperation::new(&self.connection).get_revision_id_from_content_id(content_id)?;
+        self.context
+            .events
+            .publish(SyncEvent::Conflict(relative_path.clone()));
+        self.hooks.run(
+            HookEvent::OnConflict,
+            "Conflict",
+            Some(relative_path.clone()),
+            None,
+        );
+
+        match self.context.conflict_strategy {
+            ConflictStrategy::LocalWins => {
+                log::info!(
+                    "Conflict on {:?} : keep disk file, discard remote revision {} (known revision was {})",
+                    relative_path,
+                    remote_content.current_revision_id,
+                    known_revision_id
+                );
+                let file_infos =
+                    util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+                self.upload_file(content_id, file_infos)?;
+            }
+            ConflictStrategy::RemoteWins => {
+                log::info!(
+                    "Conflict on {:?} : take remote revision {}, discard disk changes made since revision {}",
+                    relative_path,
+                    remote_content.current_revision_id,
+                    known_revision_id
+                );
+                self.write_remote_file(remote_content, relative_path)?;
+            }
+            ConflictStrategy::KeepBoth => {
+                if let Err(error) =
+                    self.keep_both(relative_path.clone(), remote_content, known_revision_id)
+                {
+                    self.record_conflict(relative_path, remote_content)?;
+                    return Err(error);
+                }
+            }
+            ConflictStrategy::Manual => {
+                log::info!(
+                    "Conflict on {:?} : record it, remote revision is {} (known revision was {})",
+                    relative_path,
+                    remote_content.current_revision_id,
+                    known_revision_id
+                );
+                return self.record_conflict(relative_path, remote_content);
+            }
+        }
+
+        self.context
+            .status
+            .lock()
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?
+            .resolved_conflicts += 1;
+        Ok(())
+    }
+
+    // Keep disk file as a conflicted copy and take remote revision
+    fn keep_both(
+        &mut self,
+        relative_path: RelativeFilePath,
+        remote_content: &RemoteContent,
+        known_revision_id: RevisionId,
+    ) -> Result<(), Error> {
+        let copy_relative_path = util::conflicted_copy_path(&relative_path)?;
+        log::info!(
+            "Conflict on {:?} : keep disk file as {:?}, take remote revision {} (known revision was {})",
+            relative_path,
+            copy_relative_path,
+            remote_content.current_revision_id,
+            known_revision_id
+        );
+        // Copy is created below as new file
+        self.rename_disk_file(&relative_path, &copy_relative_path)?;
+        self.write_remote_file(remote_content, relative_path)?;
+        self.new_local_file(copy_relative_path)?;
+        Ok(())
+    }
+
+    // Conflict is not resolved : remember it and stop synchronizing this file
+    fn record_conflict(
+        &mut self,
+        relative_path: RelativeFilePath,
+        remote_content: &RemoteContent,
+    ) -> Result<(), Error> {
+        let (last_modified_timestamp, checksum) = self.disk_state(&relative_path)?;
+
+        if let Ok(mut report) = self.context.report.lock() {
+            report.conflicted(relative_path.clone(), remote_content.content_id);
+        }
+        DatabaseOperation::new(&self.connection).insert_conflict(&Conflict {
+            relative_path,
+            last_modified_timestamp,
+            checksum,
+            content_id: remote_content.content_id,
+            revision_id: remote_content.current_revision_id,
+            detected_timestamp: util::system_time_to_timestamp(SystemTime::now()),
+        })?;
+        Ok(())
+    }
+
+    fn deleted_local_file(&mut self, relative_path: String) -> Result<(), Error> {
+        // Keep index entry to stay consistent with remote which still have this content
+        if self.context.disable_deletions {
+            log::info!(
+                "Deletions are disabled, do not delete {:?} on remote",
+                relative_path
+            );
+            return Ok(());
+        }
+
+        let database_operation = DatabaseOperation::new(&self.connection);
+
+        // Grab file infos. Path can be no longer indexed when its file was renamed
+        // (case only rename seen as new file at startup for example)
+        let content_id = match database_operation.get_content_id_from_path(relative_path) {
+            Ok(content_id) => content_id,
+            Err(Error::UnIndexedRelativePath(relative_path)) => {
+                return Err(Error::NotRelevant(format!(
+                    "Deleted file {:?} is not indexed",
+                    relative_path
+                )))
+            }
+            Err(error) => return Err(error),
+        };
+
+        // Delete on remote
+        log::debug!("Delete remote {}", content_id);
+        match self.client.trash_content(content_id)? {
+            TrashOutcome::Trashed => {
+                // Prepare to ignore remote trashed event
+                self.ignore_messages
+                    .push(OperationalMessage::DeletedRemoteFile(content_id));
+            }
+            // No remote trashed event will come
+            TrashOutcome::AlreadyTrashed | TrashOutcome::NotFound => {
+                log::debug!("Remote {} is already deleted", content_id);
+            }
+        }
+
+        // Update database
+        database_operation.delete_file(content_id)?;
+
+        Ok(())
+    }
+
+    fn renamed_local_file(
+        &mut self,
+        before_relative_path: String,
+        after_relative_path: String,
+    ) -> Result<(), Error> {
+        // Case insensitive file systems can report a case only rename with same paths,
+        // real new name is the disk one
+        let after_relative_path = if before_relative_path == after_relative_path {
+            let absolute_path = Path::new(&self.context.folder_path).join(&after_relative_path);
+            match util::disk_file_name(&absolute_path)? {
+                Some(disk_file_name) => util::path_to_string(
+                    &Path::new(&after_relative_path).with_file_name(disk_file_name),
+                )?,
+                None => after_relative_path,
+            }
+        } else {
+            after_relative_path
+        };
+        if before_relative_path == after_relative_path {
+            log::debug!(
+                "Rename of {:?} do not change its path",
+                before_relative_path
+            );
+            return Ok(());
+        }
+
+        let before_parent_relative_path = Path::new(&before_relative_path).parent();
+        let after_parent_relative_path = Path::new(&after_relative_path).parent();
+        let content_id = match DatabaseOperation::new(&self.connection)
+            .get_content_id_from_path(before_relative_path.clone())
+        {
+            Ok(content_id) => content_id,
+            // Source was never indexed (ignored file, event missed while not watching),
+            // so consider it as a new file. If destination is already indexed, new local
+            // file will update its content.
+            Err(Error::UnIndexedRelativePath(_)) => {
+                log::info!(
+                    "Renamed file source {:?} is not indexed, consider {:?} as new file",
+                    before_relative_path,
+                    after_relative_path
+                );
+                return self.new_local_file(after_relative_path);
+            }
+            Err(error) => return Err(error),
+        };
+        let file_infos = util::FileInfos::from(
+            self.context.folder_path.clone(),
+            after_relative_path.clone(),
+        )?;
+
+        // Prepare to ignore remote trashed event
+        self.ignore_messages
+            .push(OperationalMessage::ModifiedRemoteFile(content_id));
+
+        // Revision after move and rename, when Tracim answers it
+        let mut revision_id = None;
+
+        // If path changes
+        if before_parent_relative_path != after_parent_relative_path {
+            log::debug!(
+                "Path of {:?} change for {:?}",
+                &before_parent_relative_path,
+                &after_parent_relative_path
+            );
+            // If path changes for a folder
+            revision_id = if let Some(after_parent_relative_path_) = after_parent_relative_path {
+                let after_parent_relative_path_str =
+                    util::path_to_string(after_parent_relative_path_)?;
+                match DatabaseOperation::new(&self.connection)
+                    .get_content_id_from_path(after_parent_relative_path_str.clone())
+                {
+                    // New parent folder is indexed, update remote with it
+                    Ok(after_parent_content_id) => self.client.move_content(
+                        content_id,
+                        ParentIdParameter::Some(after_parent_content_id),
+                    )?,
+                    // New parent folder is not indexed, create it on remote. Only the
+                    // folder is created : moved content is indexed and must not be
+                    // uploaded again as a child found in the new folder.
+                    Err(Error::UnIndexedRelativePath(_)) => {
+                        self.new_local_file(after_parent_relative_path_str.clone())?;
+                        self.created_ancestors
+                            .insert(OperationalMessage::NewLocalFile(
+                                after_parent_relative_path_str.clone(),
+                            ));
+                        self.queue.retain(|message| {
+                            *message
+                                != OperationalMessage::NewLocalFile(after_relative_path.clone())
+                        });
+                        let after_parent_content_id = DatabaseOperation::new(&self.connection)
+                            .get_content_id_from_path(after_parent_relative_path_str.clone())?;
+                        self.client.move_content(
+                            content_id,
+                            ParentIdParameter::Some(after_parent_content_id),
+                        )?
+                    }
+                    Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
+                }
+            // Or change for root
+            } else {
+                self.client
+                    .move_content(content_id, ParentIdParameter::Root)?
+            }
+        }
+
+        let before_file_name = util::string_path_file_name(&before_relative_path)?;
+        let after_file_name = util::string_path_file_name(&after_relative_path)?;
+
+        // Rename file name if changes
+        if before_file_name != after_file_name {
+            log::debug!(
+                "Rename remote {} from {:?} to {:?}",
+                content_id,
+                before_file_name,
+                after_file_name
+            );
+            revision_id = Some(self.client.update_content_file_name(
+                content_id,
+                after_file_name,
+                file_infos.content_type,
+            )?);
+        }
+
+        let revision_id = match revision_id {
+            Some(revision_id) => revision_id,
+            None => {
+                self.client
+                    .get_remote_content(content_id)?
+                    .current_revision_id
+            }
+        };
+        DatabaseOperation::new(&self.connection).move_entry(
+            content_id,
+            &after_relative_path,
+            revision_id,
+        )?;
+
+        Ok(())
+    }
+
+    fn new_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
+        // Grab file infos. Content (or parent, when called for a missing parent) can be
+        // deleted since event : its deletion event will clean up.
+        let remote_content = match self.client.get_remote_content(content_id) {
+            Ok(remote_content) if remote_content.is_deleted => {
+                return Err(Error::NotRelevant(format!(
+                    "Remote content {} is deleted, ignore it",
+                    content_id
+                )))
+            }
+            Ok(remote_content) => remote_content,
+            Err(ClientError::NotFoundResponse(_)) => {
+                return Err(Error::NotRelevant(format!(
+                    "Remote content {} not found, ignore it",
+                    content_id
+                )))
+            }
+            Err(error) => return Err(Error::from(error)),
+        };
+        if !remote_content.in_workspace(self.context.workspace_id) {
+            return Err(Error::NotRelevant(format!(
+                "Remote content {} is in workspace {:?}, ignore it",
+                content_id, remote_content.workspace_id
+            )));
+        }
+        // Filtered content is not indexed : it is taken once a revision of an accepted
+        // author is made
+        if remote_content.content_type != "folder"
+            && !self
+                .context
+                .author_filter
+                .accepts(remote_content.revision_author_id())
+        {
+            return Err(Error::NotRelevant(format!(
+                "Remote content {} revision is authored by user {:?}, filtered out",
+                content_id,
+                remote_content.revision_author_id()
+            )));
+        }
+        let relative_path = self.client.build_relative_path(&remote_content)?;
+        let relative_path = self.local_relative_path(&remote_content, relative_path)?;
+        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+        if self.context.is_owned_path(&absolute_path) {
+            return Err(Error::NotRelevant(format!(
+                "Remote content {} is a trsync file, ignore it",
+                content_id
+            )));
+        }
+        if DatabaseOperation::new(&self.connection).is_excluded(&relative_path)? {
+            return Err(Error::NotRelevant(format!(
+                "Remote content {} is excluded ({:?}), ignore it",
+                content_id, relative_path
+            )));
+        }
+
+        // Check tree before create new file
+        if let Some(parent_id) = remote_content.parent_id {
+            // If parent content id is unknown, folder is not on disk
+            if !DatabaseOperation::new(&self.connection).content_id_is_known(parent_id)? {
+                // Use recursive to create this parent and possible parents parent
+                log::debug!("Parent of {:?} is unknown, ensure it", &absolute_path);
+                self.new_remote_file(parent_id)?;
+                self.created_ancestors
+                    .insert(OperationalMessage::NewRemoteFile(parent_id));
+            }
+        }
+
+        // Not indexed disk file can be the same file obtained another way (copied from
+        // another machine, ...). It can also have been indexed meanwhile by its new local
+        // file message (existing remote content identical to disk file).
+        if remote_content.content_type != "folder" && absolute_path.is_file() {
+            let database_operation = DatabaseOperation::new(&self.connection);
+            if !database_operation.relative_path_is_known(&relative_path)? {
+                return self.adopt_disk_file(&remote_content, relative_path);
+            }
+            if database_operation.get_content_id_from_path(relative_path.clone())? == content_id
+                && database_operation.get_revision_id_from_content_id(content_id)?
+                    == remote_content.current_revision_id
+            {
+                return Err(Error::NotRelevant(format!(
+                    "Remote content {} is already on disk at {:?}",
+                    content_id, relative_path
+                )));
+            }
+        }
+
+        // Write file/folder on disk. Only a created folder produces a local change : a
+        // downloaded file is moved from metadata directory.
+        self.ensure_parent_folders(&relative_path)?;
+        let size = if remote_content.content_type == "folder" {
+            log::debug!("Create disk folder {:?}", &absolute_path);
+            let created = !absolute_path.is_dir();
+            match fs::create_dir_all(util::long_path(&absolute_path)) {
+                Ok(_) => {
+                    if created {
+                        self.ignore_messages
+                            .push(OperationalMessage::NewLocalFile(relative_path.clone()));
+                    }
+                    self.restore_xattrs(content_id, &absolute_path)?
+                }
+                Err(error) => match util::classify_io_error(&error) {
+                    util::IoErrorKind::NoSpace | util::IoErrorKind::PermissionDenied => {
+                        return Err(util::disk_write_error(
+                            error,
+                            format!("Unable to create folder {:?}", absolute_path),
+                        ))
+                    }
+                    _ => {
+                        let level = util::io_error_to_log_level(&error);
+                        log::log!(
+                            level,
+                            "Error during creation of {:?} : {}",
+                            absolute_path,
+                            error
+                        )
+                    }
+                },
+            }
+            None
+        } else {
+            log::debug!("Create disk file {:?}", &absolute_path);
+            Some(self.download_file(&remote_content, &absolute_path)?)
+        };
+
+        // Update database
+        // Downloading do not create revision, so fetched remote content is still up to date
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        let checksum = file_infos.checksum()?;
+        DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+            database_operation.insert_new_file(
+                file_infos.relative_path.clone(),
+                file_infos.last_modified_timestamp,
+                content_id,
+                remote_content.current_revision_id,
+                checksum,
+                file_infos.is_directory,
+            )?;
+            database_operation.update_size(file_infos.relative_path.clone(), size)?;
+            database_operation.update_content_type(content_id, &remote_content.content_type)
+        })?;
+
+        self.sync_label(&file_infos.relative_path, &remote_content, None)
+    }
+
+    // Index disk file found at remote content path instead of downloading it again. When
+    // it differs from remote content, it is resolved as a conflict (according to
+    // conflict strategy) instead of being overwritten : until then, it is indexed without
+    // disk state nor common revision, so that an interrupted resolution is resolved again.
+    fn adopt_disk_file(
+        &mut self,
+        remote_content: &RemoteContent,
+        relative_path: RelativeFilePath,
+    ) -> Result<(), Error> {
+        let file_infos =
+            util::FileInfos::from(self.context.folder_path.clone(), relative_path.clone())?;
+        let checksum = file_infos.checksum()?;
+        let identical = self.identical_to_remote(&file_infos, &checksum, remote_content)?;
+        let local_label = self.local_label(&relative_path)?;
+        let xattrs = self.xattrs_to_index(&file_infos)?;
+        DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+            if let Some(xattrs) = xattrs {
+                database_operation.set_xattrs(remote_content.content_id, &xattrs)?;
+            }
+            if identical {
+                database_operation.insert_new_file(
+                    relative_path.clone(),
+                    file_infos.last_modified_timestamp,
+                    remote_content.content_id,
+                    remote_content.current_revision_id,
+                    checksum,
+                    file_infos.is_directory,
+                )?;
+                database_operation.update_size(relative_path.clone(), file_infos.size)?;
+            } else {
+                database_operation.insert_new_file(
+                    relative_path.clone(),
+                    0,
+                    remote_content.content_id,
+                    UNRESOLVED_REVISION_ID,
+                    None,
+                    file_infos.is_directory,
+                )?;
+            }
+            database_operation
+                .update_content_type(remote_content.content_id, &remote_content.content_type)
+        })?;
+
+        if identical {
+            log::info!(
+                "Disk file {:?} is identical to remote content {}, adopt it",
+                relative_path,
+                remote_content.content_id
+            );
+            return self.sync_label(&relative_path, remote_content, local_label);
+        }
+
+        log::info!(
+            "Disk file {:?} differs from remote content {}",
+            relative_path,
+            remote_content.content_id
+        );
+        self.resolve_conflict(relative_path, remote_content)
+    }
+
+    // Label of disk file, when labels are synchronized
+    fn local_label(&self, relative_path: &str) -> Result<Option<String>, Error> {
+        if !self.context.labels_synchronized() {
+            return Ok(None);
+        }
+
+        util::read_label(&Path::new(&self.context.folder_path).join(relative_path))
+    }
+
+    // Reconcile remote label with disk file label (read before any disk write), indexed
+    // label being their common ancestor : changed side wins, and conflict strategy
+    // decide when both changed.
+    fn sync_label(
+        &mut self,
+        relative_path: &str,
+        remote_content: &RemoteContent,
+        local_label: Option<String>,
+    ) -> Result<(), Error> {
+        if !self.context.labels_synchronized() || remote_content.content_type == "folder" {
+            return Ok(());
+        }
+
+        let content_id = remote_content.content_id;
+        let indexed_label = DatabaseOperation::new(&self.connection).get_label(content_id)?;
+        let remote_label = &remote_content.label;
+        let disk_label_differs = local_label.as_ref() != Some(remote_label);
+        let pushed_label = match local_label {
+            Some(local_label) if local_label != *remote_label => {
+                if indexed_label.as_ref() == Some(&local_label) {
+                    None
+                } else if indexed_label.as_ref() == Some(remote_label) {
+                    Some(local_label)
+                } else {
+                    log::info!(
+                        "Label conflict on {:?} : disk label is {:?}, remote label is {:?}",
+                        relative_path,
+                        local_label,
+                        remote_label
+                    );
+                    match self.context.conflict_strategy {
+                        ConflictStrategy::LocalWins => Some(local_label),
+                        ConflictStrategy::RemoteWins | ConflictStrategy::KeepBoth => None,
+                        ConflictStrategy::Manual => {
+                            return self.record_conflict(relative_path.to_string(), remote_content)
+                        }
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let database_operation = DatabaseOperation::new(&self.connection);
+        match pushed_label {
+            Some(label) => {
+                let content_type =
+                    ContentType::from_str(&remote_content.content_type).map_err(|_| {
+                        Error::UnexpectedError(format!(
+                            "Unknown content type {} for content {}",
+                            remote_content.content_type, content_id
+                        ))
+                    })?;
+                log::info!("Update remote {} label to {:?}", content_id, label);
+                // Prepare to ignore remote modified event
+                self.ignore_messages
+                    .push(OperationalMessage::ModifiedRemoteFile(content_id));
+                let revision_id =
+                    self.client
+                        .update_content_label(content_id, label.clone(), content_type)?;
+                database_operation.with_transaction(|database_operation| {
+                    database_operation
+                        .update_revision_id(relative_path.to_string(), revision_id)?;
+                    database_operation.update_label(content_id, &label)
+                })?;
+            }
+            None => {
+                // Resulting local modified event find disk file synchronized
+                if disk_label_differs {
+                    util::write_label(
+                        &Path::new(&self.context.folder_path).join(relative_path),
+                        remote_label,
+                    );
+                }
+                database_operation.update_label(content_id, remote_label)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn modified_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
+        let database_operation = DatabaseOperation::new(&self.connection);
+
+        // If content is not indexed or its disk file disappeared, consider it as new
+        let known_relative_path = match database_operation.get_path_from_content_id(content_id) {
+            Ok(known_relative_path) => {
+                if !Path::new(&self.context.folder_path)
+                    .join(&known_relative_path)
+                    .exists()
+                {
+                    log::info!(
+                        "Disk file {:?} of content {} is missing, create it",
+                        known_relative_path,
+                        content_id
+                    );
+                    database_operation.delete_file(content_id)?;
+                    return self.new_remote_file(content_id);
+                }
+                known_relative_path
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                log::info!("Content {} is not indexed, create it", content_id);
+                return self.new_remote_file(content_id);
+            }
+            Err(error) => return Err(Error::from(error)),
+        };
+
+        // Grab file infos
+        let remote_content = self.client.get_remote_content(content_id)?;
+        if !remote_content.in_workspace(self.context.workspace_id) {
+            return self.left_workspace(&remote_content);
+        }
+        let relative_path = self.client.build_relative_path(&remote_content)?;
+        let relative_path = self.local_relative_path(&remote_content, relative_path)?;
+        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+
+        // Revision is authoritative : when it differs, remote content is taken whatever
+        // disk file timestamp is (reverting to a previous revision produce a new revision)
+        let known_revision_id = database_operation.get_revision_id_from_content_id(content_id)?;
+        if known_revision_id == remote_content.current_revision_id {
+            log::debug!(
+                "Content {} is already at revision {}",
+                content_id,
+                known_revision_id
+            );
+            return Ok(());
+        }
+        log::debug!(
+            "Take remote revision {} of content {} (known revision was {})",
+            remote_content.current_revision_id,
+            content_id,
+            known_revision_id
+        );
+
+        // A file which became a folder (or reverse) can't be updated in place
+        let known_content_type = database_operation.get_content_type(content_id)?;
+        let known_is_directory = match database_operation.get_is_directory(content_id)? {
+            Some(is_directory) => is_directory,
+            None => Path::new(&self.context.folder_path)
+                .join(&known_relative_path)
+                .is_dir(),
+        };
+        if known_is_directory != (remote_content.content_type == "folder") {
+            return self.change_content_type(&remote_content, known_relative_path);
+        }
+        if let Some(known_content_type) = known_content_type {
+            if known_content_type != remote_content.content_type {
+                log::info!(
+                    "Content {} changed of type from {} to {}",
+                    content_id,
+                    known_content_type,
+                    remote_content.content_type
+                );
+            }
+        }
+
+        // TODO : use enum for content_type
+        if remote_content.content_type == "folder" {
+            let old_relative_path =
+                DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
+            let old_absolute_path = Path::new(&self.context.folder_path).join(&old_relative_path);
+            let new_absolute_path = old_absolute_path
+                .parent()
+                .ok_or(Error::PathManipulationError(format!(
+                    "Unable to get parent folder of {:?}",
+                    old_absolute_path
+                )))?
+                .join(remote_content.local_name(&self.context));
+            let new_relative_path =
+                util::path_to_string(new_absolute_path.strip_prefix(&self.context.folder_path)?)?;
+
+            if old_relative_path != new_relative_path {
+                log::info!(
+                    "Rename disk folder {:?} into {:?}",
+                    &old_absolute_path,
+                    &new_absolute_path
+                );
+                self.rename_disk_file(&old_relative_path, &new_relative_path)?;
+            }
+
+            // Update database (with folder descendants)
+            DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+                database_operation.move_entry(
+                    content_id,
+                    &new_relative_path,
+                    remote_content.current_revision_id,
+                )?;
+                database_operation.update_content_type(content_id, &remote_content.content_type)
+            })?;
+            return Ok(());
+        }
+
+        // Manage renamed case
+        let current_relative_path =
+            DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
+        let file_infos =
+            util::FileInfos::from(self.context.folder_path.clone(), current_relative_path)?;
+        if util::string_path_file_name(&relative_path)? != file_infos.file_name {
+            log::debug!(
+                "Rename {} into {:?}",
+                file_infos.absolute_path,
+                &absolute_path
+            );
+            match self.rename_disk_file(&file_infos.relative_path, &relative_path) {
+                // Known revision is kept until remote revision is downloaded
+                Ok(_) => DatabaseOperation::new(&self.connection).move_entry(
+                    content_id,
+                    &relative_path,
+                    known_revision_id,
+                )?,
+                Err(error) => return Err(Error::UnexpectedError(format!("{:?}", error))),
+            }
+        }
+
+        // Disk file can have been modified since last known revision
+        let file_infos =
+            util::FileInfos::from(self.context.folder_path.clone(), relative_path.clone())?;
+        if self.disk_file_changed(&file_infos)? {
+            return self.resolve_conflict(relative_path, &remote_content);
+        }
+
+        // Downloaded file replace disk file, with its label
+        let local_label = self.local_label(&relative_path)?;
+        self.write_remote_file(&remote_content, relative_path.clone())?;
+        DatabaseOperation::new(&self.connection)
+            .update_content_type(content_id, &remote_content.content_type)?;
+        self.sync_label(&relative_path, &remote_content, local_label)
+    }
+
+    // Remove disk file (or folder) of a content which changed of type, then create it
+    // again as a new remote content. Disk changes of a file are kept as a conflicted
+    // copy. A folder containing files not synchronized is kept, according to leftover
+    // policy, and the content is then not synchronized.
+    fn change_content_type(
+        &mut self,
+        remote_content: &RemoteContent,
+        known_relative_path: RelativeFilePath,
+    ) -> Result<(), Error> {
+        let file_infos = util::FileInfos::from(
+            self.context.folder_path.clone(),
+            known_relative_path.clone(),
+        )?;
+        log::info!(
+            "Content {} is now a {}, replace disk {} {:?}",
+            remote_content.content_id,
+            remote_content.content_type,
+            if file_infos.is_directory {
+                "folder"
+            } else {
+                "file"
+            },
+            known_relative_path
+        );
+
+        let removed = if file_infos.is_directory {
+            if !self.remove_directory(&file_infos)? {
+                return Err(Error::UnexpectedError(format!(
+                    "Unable to replace folder {:?} by content {}, it contains files not synchronized",
+                    known_relative_path, remote_content.content_id
+                )));
+            }
+            true
+        } else if self.disk_file_changed(&file_infos)? {
+            let copy_relative_path = util::conflicted_copy_path(&known_relative_path)?;
+            log::info!(
+                "Disk file {:?} changed since synchronization, keep it as {:?}",
+                known_relative_path,
+                copy_relative_path
+            );
+            self.rename_disk_file(&known_relative_path, &copy_relative_path)?;
+            let message = OperationalMessage::NewLocalFile(copy_relative_path);
+            if !self.queue.contains(&message) {
+                self.queue.push_back(message);
+            }
+            false
+        } else {
+            fs::remove_file(util::long_path(Path::new(&file_infos.absolute_path)))?;
+            true
+        };
+        // Prepare to ignore deleted local file
+        if removed {
+            self.ignore_messages
+                .push(OperationalMessage::DeletedLocalFile(
+                    known_relative_path.clone(),
+                ));
+        }
+
+        // Descendants of a replaced folder are not on disk anymore
+        DatabaseOperation::new(&self.connection).delete_relative_path(&known_relative_path)?;
+        self.new_remote_file(remote_content.content_id)
+    }
+
+    // Disk path of remote content. A path differing only by case from the path of another
+    // indexed content is the same disk file on case insensitive file systems : content is
+    // then written with a disambiguated name, or not synchronized, depending on policy.
+    fn local_relative_path(
+        &self,
+        remote_content: &RemoteContent,
+        relative_path: RelativeFilePath,
+    ) -> Result<RelativeFilePath, Error> {
+        if self.case_collision == CaseCollision::Keep {
+            return Ok(relative_path);
+        }
+
+        let content_id = remote_content.content_id;
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let collision_path = util::case_collision_path(&relative_path, content_id)?;
+        if database_operation.get_path_from_content_id(content_id).ok()
+            == Some(collision_path.clone())
+        {
+            return Ok(collision_path);
+        }
+        let colliding_path =
+            match database_operation.get_relative_path_ignoring_case(&relative_path)? {
+                Some(colliding_path) => colliding_path,
+                None => return Ok(relative_path),
+            };
+        if database_operation.get_content_id_from_path(colliding_path.clone())? == content_id {
+            return Ok(relative_path);
+        }
+
+        // Descendants paths are built from remote names : folders can't be renamed
+        if self.case_collision == CaseCollision::Rename && remote_content.content_type != "folder" {
+            log::warn!(
+                "Remote content {} path {:?} collides by case with {:?}, write it at {:?}",
+                content_id,
+                relative_path,
+                colliding_path,
+                collision_path
+            );
+            return Ok(collision_path);
+        }
+        log::warn!(
+            "Remote content {} path {:?} collides by case with {:?}, do not synchronize it",
+            content_id,
+            relative_path,
+            colliding_path
+        );
+        Err(Error::NotRelevant(format!(
+            "Remote content {} collides by case with {:?}",
+            content_id, colliding_path
+        )))
+    }
+
+    // Indexed parent folders can be missing on disk (removed while trsync was stopped with
+    // deletions disabled, partial restore, ...) : create them again, and update their
+    // index entries to the recreated folders
+    fn ensure_parent_folders(&mut self, relative_path: &str) -> Result<(), Error> {
+        let mut missing_folders = vec![];
+        let mut parent = Path::new(relative_path).parent();
+        while let Some(folder) = parent {
+            if folder == Path::new("") || Path::new(&self.context.folder_path).join(folder).is_dir()
+            {
+                break;
+            }
+            missing_folders.push(util::path_to_string(folder)?);
+            parent = folder.parent();
+        }
+
+        let database_operation = DatabaseOperation::new(&self.connection);
+        for folder_relative_path in missing_folders.into_iter().rev() {
+            let absolute_path = Path::new(&self.context.folder_path).join(&folder_relative_path);
+            if let Err(error) = fs::create_dir(util::long_path(&absolute_path)) {
+                return Err(util::disk_write_error(
+                    error,
+                    format!("Unable to create missing folder {:?}", absolute_path),
+                ));
+            }
+            self.ignore_messages.push(OperationalMessage::NewLocalFile(
+                folder_relative_path.clone(),
+            ));
+
+            if database_operation.relative_path_is_known(&folder_relative_path)? {
+                log::warn!(
+                    "Indexed folder {:?} was missing on disk, it has been created again",
+                    folder_relative_path
+                );
+                let file_infos = util::FileInfos::from(
+                    self.context.folder_path.clone(),
+                    folder_relative_path.clone(),
+                )?;
+                database_operation.update_last_modified_timestamp(
+                    folder_relative_path,
+                    file_infos.last_modified_timestamp,
+                )?;
+            } else {
+                log::debug!("Create missing folder {:?}", absolute_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Write remote content revision on disk and index it
+    fn write_remote_file(
+        &mut self,
+        remote_content: &RemoteContent,
+        relative_path: RelativeFilePath,
+    ) -> Result<(), Error> {
+        let absolute_path = Path::new(&self.context.folder_path).join(&relative_path);
+        self.ensure_parent_folders(&relative_path)?;
+
+        // Write file on disk (downloaded file is moved from metadata directory, which
+        // produces no local change)
+        log::debug!(
+            "Update disk file {:?} with content {}",
+            &absolute_path,
+            remote_content.content_id,
+        );
+        let size = self.download_file(remote_content, &absolute_path)?;
+
+        // Update database
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        let checksum = file_infos.checksum()?;
+        database_operation.with_transaction(|database_operation| {
+            database_operation.update_last_modified_timestamp(
+                file_infos.relative_path.clone(),
+                file_infos.last_modified_timestamp,
+            )?;
+            database_operation.update_checksum(file_infos.relative_path.clone(), checksum)?;
+            database_operation.update_size(file_infos.relative_path.clone(), Some(size))?;
+            database_operation
+                .update_revision_id(file_infos.relative_path, remote_content.current_revision_id)
+        })?;
+
+        Ok(())
+    }
+
+    // Recovery of a corrupted disk file : remote content replace disk file (atomically,
+    // through a partial file) without comparing timestamps or revisions
+    fn force_download(&mut self, content_id: ContentId) -> Result<(), Error> {
+        let relative_path =
+            match DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id) {
+                Ok(relative_path) => relative_path,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    log::info!("Forced content {} is not indexed, create it", content_id);
+                    return self.new_remote_file(content_id);
+                }
+                Err(error) => return Err(Error::from(error)),
+            };
+        let remote_content = self.client.get_remote_content(content_id)?;
+        if remote_content.content_type == "folder" {
+            log::info!("Force download of {:?} content", relative_path);
+            return self.queue_forced_children(&relative_path, |child| {
+                OperationalMessage::ForceDownload(child.content_id)
+            });
+        }
+
+        log::info!(
+            "Force download of content {} into {:?}",
+            content_id,
+            relative_path
+        );
+        self.write_remote_file(&remote_content, relative_path.clone())?;
+        let database_operation = DatabaseOperation::new(&self.connection);
+        database_operation.delete_conflict(&relative_path)?;
+        database_operation.delete_failure(&relative_path)?;
+        Ok(())
+    }
+
+    // Recovery of a remote content in a bad state : disk file is uploaded as a new
+    // revision without comparing timestamps or revisions
+    fn force_push(&mut self, relative_path: RelativeFilePath) -> Result<(), Error> {
+        let content_id = match DatabaseOperation::new(&self.connection)
+            .get_content_id_from_path(relative_path.clone())
+        {
+            Ok(content_id) => content_id,
+            Err(Error::UnIndexedRelativePath(_)) => {
+                log::info!("Forced path {:?} is not indexed, create it", relative_path);
+                return self.new_local_file(relative_path);
+            }
+            Err(error) => return Err(error),
+        };
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+        if file_infos.is_directory {
+            log::info!("Force push of {:?} content", file_infos.relative_path);
+            return self.queue_forced_children(&file_infos.relative_path, |child| {
+                OperationalMessage::ForcePush(child.relative_path)
+            });
+        }
+
+        log::info!(
+            "Force push of {:?} into content {}",
+            file_infos.relative_path,
+            content_id
+        );
+        let relative_path = file_infos.relative_path.clone();
+        self.upload_file(content_id, file_infos)?;
+        let database_operation = DatabaseOperation::new(&self.connection);
+        database_operation.delete_conflict(&relative_path)?;
+        database_operation.delete_failure(&relative_path)?;
+        Ok(())
+    }
+
+    // Forced operation of a folder is forced on its indexed children (which recurse
+    // themselves), which are processed next
+    fn queue_forced_children(
+        &mut self,
+        relative_path: &str,
+        message: fn(IndexEntry) -> OperationalMessage,
+    ) -> Result<(), Error> {
+        let children = DatabaseOperation::new(&self.connection).get_children(relative_path)?;
+        for child in children.into_iter().rev() {
+            self.queue.push_front(message(child));
+        }
+        Ok(())
+    }
+
+    fn moved_remote_file(&mut self, content_id: ContentId) -> Result<(), Error> {
+        let old_relative_path =
+            match DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id) {
+                Ok(old_relative_path) => old_relative_path,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    log::info!("Moved content {} is not indexed, create it", content_id);
+                    return self.new_remote_file(content_id);
+                }
+                Err(error) => return Err(Error::from(error)),
+            };
+
+        // Grab file infos
+        let remote_content = self.client.get_remote_content(content_id)?;
+        if !remote_content.in_workspace(self.context.workspace_id) {
+            return self.left_workspace(&remote_content);
+        }
+        let new_relative_path = self.client.build_relative_path(&remote_content)?;
+        let new_relative_path = self.local_relative_path(&remote_content, new_relative_path)?;
+        if old_relative_path == new_relative_path {
+            return self.modified_remote_file(content_id);
+        }
+        let old_absolute_path = Path::new(&self.context.folder_path).join(&old_relative_path);
+        let new_absolute_path = Path::new(&self.context.folder_path).join(&new_relative_path);
+
+        // Destination parent can be not on disk yet
+        if let Some(parent_id) = remote_content.parent_id {
+            if !DatabaseOperation::new(&self.connection).content_id_is_known(parent_id)? {
+                log::debug!("Parent of {:?} is unknown, ensure it", &new_absolute_path);
+                self.new_remote_file(parent_id)?;
+                self.created_ancestors
+                    .insert(OperationalMessage::NewRemoteFile(parent_id));
+            }
+        }
+
+        self.ensure_parent_folders(&new_relative_path)?;
+
+        log::info!(
+            "Move disk file {:?} into {:?}",
+            &old_absolute_path,
+            &new_absolute_path
+        );
+        self.rename_disk_file(&old_relative_path, &new_relative_path)?;
+
+        // Update database (descendants included for folders). A file keeps its indexed
+        // revision until its content is taken : it can have been modified while moved.
+        let known_revision_id =
+            DatabaseOperation::new(&self.connection).get_revision_id_from_content_id(content_id)?;
+        let is_file = remote_content.content_type != "folder";
+        DatabaseOperation::new(&self.connection).move_entry(
+            content_id,
+            &new_relative_path,
+            if is_file {
+                known_revision_id
+            } else {
+                remote_content.current_revision_id
+            },
+        )?;
+
+        if is_file && known_revision_id != remote_content.current_revision_id {
+            log::debug!("Moved content {} changed too, take it", content_id);
+            return self.modified_remote_file(content_id);
+        }
+
+        Ok(())
+    }
+
+    // Content vanished from remote while an identical one appeared elsewhere : move
+    // disk file instead of deleting it and downloading the new content
+    fn relocated_remote_file(
+        &mut self,
+        old_content_id: ContentId,
+        new_content_id: ContentId,
+    ) -> Result<(), Error> {
+        let old_relative_path = match DatabaseOperation::new(&self.connection)
+            .get_path_from_content_id(old_content_id)
+        {
+            Ok(old_relative_path) => old_relative_path,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                log::info!(
+                    "Relocated content {} is not indexed, create content {}",
+                    old_content_id,
+                    new_content_id
+                );
+                return self.new_remote_file(new_content_id);
+            }
+            Err(error) => return Err(Error::from(error)),
+        };
+        if DatabaseOperation::new(&self.connection).content_id_is_known(new_content_id)? {
+            log::info!(
+                "Content {} is already indexed, only delete content {}",
+                new_content_id,
+                old_content_id
+            );
+            return self.deleted_remote_file(old_content_id);
+        }
+
+        let remote_content = self.client.get_remote_content(new_content_id)?;
+        if !remote_content.in_workspace(self.context.workspace_id) {
+            return self.deleted_remote_file(old_content_id);
+        }
+
+        // Contents were paired by name and size only : disk file is adopted only if its
+        // bytes are the new content ones
+        let disk_checksum =
+            util::FileInfos::from(self.context.folder_path.clone(), old_relative_path.clone())?
+                .checksum()?;
+        let remote_checksum = util::reader_checksum(&mut self.client.get_file_content_response(
+            remote_content.content_id,
+            remote_content.filename.clone(),
+        )?)?;
+        if Some(remote_checksum) != disk_checksum {
+            log::info!(
+                "Content {} differs from disk file {:?}, delete it and create content {}",
+                new_content_id,
+                old_relative_path,
+                new_content_id
+            );
+            self.deleted_remote_file(old_content_id)?;
+            return self.new_remote_file(new_content_id);
+        }
+
+        let new_relative_path = self.client.build_relative_path(&remote_content)?;
+        if old_relative_path != new_relative_path {
+            let old_absolute_path = Path::new(&self.context.folder_path).join(&old_relative_path);
+            let new_absolute_path = Path::new(&self.context.folder_path).join(&new_relative_path);
+
+            // Destination parent can be not on disk yet
+            if let Some(parent_id) = remote_content.parent_id {
+                if !DatabaseOperation::new(&self.connection).content_id_is_known(parent_id)? {
+                    log::debug!("Parent of {:?} is unknown, ensure it", &new_absolute_path);
+                    self.new_remote_file(parent_id)?;
+                    self.created_ancestors
+                        .insert(OperationalMessage::NewRemoteFile(parent_id));
+                }
+            }
+
+            log::info!(
+                "Move disk file {:?} into {:?} (content {} replaced by {})",
+                &old_absolute_path,
+                &new_absolute_path,
+                old_content_id,
+                new_content_id
+            );
+            self.rename_disk_file(&old_relative_path, &new_relative_path)?;
+        }
+
+        DatabaseOperation::new(&self.connection).with_transaction(|database_operation| {
+            database_operation.move_relative_path(&old_relative_path, &new_relative_path)?;
+            database_operation.replace_content_id(
+                old_content_id,
+                new_content_id,
+                remote_content.current_revision_id,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    // Content moved to another workspace is no longer in the synchronized tree, its
+    // disk file is deleted as if content was deleted
+    fn left_workspace(&mut self, remote_content: &RemoteContent) -> Result<(), Error> {
+        log::info!(
+            "Content {} moved to workspace {:?}, delete it locally",
+            remote_content.content_id,
+            remote_content.workspace_id
+        );
+        self.deleted_remote_file(remote_content.content_id)
+    }
+
+    fn deleted_remote_file(&mut self, content_id: i32) -> Result<(), Error> {
+        // Keep index entry to stay consistent with disk which still have this file
+        if self.context.disable_deletions {
+            log::info!(
+                "Deletions are disabled, do not delete disk file of content {}",
+                content_id
+            );
+            return Ok(());
+        }
+
+        let database_operation = DatabaseOperation::new(&self.connection);
+
+        // Grab file infos (from local index, remote content has name changes)
+
+        let relative_path =
+            DatabaseOperation::new(&self.connection).get_path_from_content_id(content_id)?;
+
+        // Disk file can already be deleted (crossed local and remote deletions)
+        if !Path::new(&self.context.folder_path)
+            .join(&relative_path)
+            .exists()
+        {
+            log::debug!("Disk file {:?} is already deleted", relative_path);
+            self.ignore_messages
+                .push(OperationalMessage::DeletedLocalFile(relative_path));
+            database_operation.delete_file(content_id)?;
+            return Ok(());
+        }
+
+        let file_infos = util::FileInfos::from(self.context.folder_path.clone(), relative_path)?;
+
+        // A file replaced by a folder (or reverse) since synchronization is not the
+        // deleted content : it is synchronized as a new one
+        if database_operation
+            .get_is_directory(content_id)?
+            .map(|is_directory| is_directory != file_infos.is_directory)
+            .unwrap_or(false)
+        {
+            log::warn!(
+                "Disk file {:?} changed of type since synchronization, do not delete it",
+                file_infos.relative_path
+            );
+            database_operation.delete_file(content_id)?;
+            let message = OperationalMessage::NewLocalFile(file_infos.relative_path);
+            if !self.queue.contains(&message) {
+                self.queue.push_back(message);
+            }
+            return Ok(());
+        }
+
+        // Delete disk file
+        log::debug!("Remove disk file {:?}", &file_infos.absolute_path);
+        let removed = if file_infos.is_directory {
+            self.remove_directory(&file_infos)?
+        } else {
+            fs::remove_file(util::long_path(Path::new(&file_infos.absolute_path)))?;
+            true
+        };
+
+        // Prepare to ignore deleted local file
+        if removed {
+            self.ignore_messages
+                .push(OperationalMessage::DeletedLocalFile(
+                    file_infos.relative_path,
+                ));
+        }
+
+        database_operation.delete_file(content_id)?;
+
+        Ok(())
+    }
+
+    // Remove folder deleted on remote. Files not synchronized inside it (ignored or not
+    // yet uploaded) are removed too, or kept with their folders according to leftover
+    // policy. Return false if folder is kept.
+    fn remove_directory(&self, file_infos: &util::FileInfos) -> Result<bool, Error> {
+        if self.context.leftover_policy == LeftoverPolicy::Remove {
+            fs::remove_dir_all(util::long_path(Path::new(&file_infos.absolute_path)))?;
+            return Ok(true);
+        }
+
+        let database_operation = DatabaseOperation::new(&self.connection);
+        let mut leftovers = vec![];
+        for entry in WalkDir::new(&file_infos.absolute_path).contents_first(true) {
+            let entry = entry.map_err(io::Error::from)?;
+            if entry.file_type().is_dir() {
+                if fs::read_dir(entry.path())?.next().is_none() {
+                    fs::remove_dir(util::long_path(entry.path()))?;
+                }
+                continue;
+            }
+            let relative_path =
+                util::path_to_string(entry.path().strip_prefix(&self.context.folder_path)?)?;
+            match database_operation.get_content_id_from_path(relative_path.clone()) {
+                Ok(_) => fs::remove_file(util::long_path(entry.path()))?,
+                Err(Error::UnIndexedRelativePath(_)) => leftovers.push(relative_path),
+                Err(error) => return Err(error),
+            }
+        }
+
+        if leftovers.is_empty() {
+            return Ok(true);
+        }
+        log::warn!(
+            "Folder {:?} deleted on remote is kept, it contains files not synchronized : {:?}",
+            file_infos.relative_path,
+            leftovers
+        );
+        Ok(false)
+    }
+
+    // Rename disk file (or folder), then ignore the local rename it produces. Nothing is
+    // ignored when rename fails : no local rename will come.
+    fn rename_disk_file(&mut self, relative_path: &str, new_relative_path: &str) -> io::Result<()> {
+        let folder_path = Path::new(&self.context.folder_path);
+        fs::rename(
+            util::long_path(&folder_path.join(relative_path)),
+            util::long_path(&folder_path.join(new_relative_path)),
+        )?;
+        self.ignore_messages
+            .push(OperationalMessage::RenamedLocalFile(
+                relative_path.to_string(),
+                new_relative_path.to_string(),
+            ));
+        Ok(())
+    }
+
+    // Download remote file content into a temporary file of metadata directory, then
+    // move it to the target path only if written content is complete. The target is
+    // never left with partial content. Verified size is returned, to be indexed.
+    fn download_file(
+        &self,
+        remote_content: &RemoteContent,
+        absolute_path: &Path,
+    ) -> Result<u64, Error> {
+        let mut response = self.client.get_file_content_response(
+            remote_content.content_id,
+            remote_content.filename.clone(),
+        )?;
+        // Prefer http announced size, fallback on content metadata size
+        let expected_size = response.content_length().or(remote_content.size);
+
+        let partial_absolute_path =
+            util::partial_file_path(&self.context.temp_dir(), absolute_path)?;
+        // Response body is read while it is written : reads are network time
+        let written = timing::measure(TimingPhase::Disk, || {
+            File::create(util::long_path(&partial_absolute_path))
+        })
+        .and_then(|out| {
+            let mut out = timing::Timed::new(out, TimingPhase::Disk);
+            let written_size = io::copy(
+                &mut timing::Timed::new(&mut response, TimingPhase::Remote),
+                &mut out,
+            )?;
+            let out = out.into_inner();
+            timing::measure(TimingPhase::Disk, || out.sync_all())?;
+            Ok((written_size, out.metadata()?.len()))
+        });
+        let (written_size, disk_size) = match written {
+            Ok(sizes) => sizes,
+            Err(error) => {
+                self.remove_partial_file(&partial_absolute_path);
+                let message = format!(
+                    "Download of content {} into {:?} failed",
+                    remote_content.content_id, partial_absolute_path
+                );
+                return Err(match util::classify_io_error(&error) {
+                    util::IoErrorKind::NoSpace | util::IoErrorKind::PermissionDenied => {
+                        util::disk_write_error(error, message)
+                    }
+                    _ => Error::IncompleteDownload(format!("{} : {:?}", message, error)),
+                });
+            }
+        };
+
+        // Tracim do not expose content checksum, so only size can be verified
+        let expected_size = expected_size.unwrap_or(written_size);
+        if written_size != expected_size || disk_size != expected_size {
+            self.remove_partial_file(&partial_absolute_path);
+            return Err(Error::IncompleteDownload(format!(
+                "Download of content {} into {:?} wrote {} bytes ({} on disk) but {} was expected",
+                remote_content.content_id,
+                partial_absolute_path,
+                written_size,
+                disk_size,
+                expected_size
+            )));
+        }
+
+        self.restore_xattrs(remote_content.content_id, &partial_absolute_path)?;
+        if let Err(error) =
+            self.apply_executable_policy(remote_content, &partial_absolute_path, absolute_path)
+        {
+            if partial_absolute_path.exists() {
+                self.remove_partial_file(&partial_absolute_path);
+            }
+            return Err(error);
+        }
+
+        // Local watcher ignore metadata directory events, so this rename is not seen as a change
+        if let Err(error) = fs::rename(
+            util::long_path(&partial_absolute_path),
+            util::long_path(absolute_path),
+        ) {
+            self.remove_partial_file(&partial_absolute_path);
+            return Err(util::disk_write_error(
+                error,
+                format!("Unable to move downloaded file to {:?}", absolute_path),
+            ));
+        }
+        self.context.metrics.downloaded(written_size);
+
+        Ok(expected_size)
+    }
+
+    // Set permissions of downloaded file before it takes place of disk file, so that no
+    // disk event is produced. With quarantine policy, executable content is moved into
+    // quarantine folder instead, disk file and index being left as they are.
+    fn apply_executable_policy(
+        &self,
+        remote_content: &RemoteContent,
+        partial_absolute_path: &Path,
+        absolute_path: &Path,
+    ) -> Result<(), Error> {
+        let executable = util::is_executable_content(partial_absolute_path)?;
+        match self.context.download_executable_policy {
+            DownloadExecutablePolicy::PreservePermissions => {
+                match fs::metadata(util::long_path(absolute_path)) {
+                    Ok(metadata) if metadata.is_file() => fs::set_permissions(
+                        util::long_path(partial_absolute_path),
+                        metadata.permissions(),
+                    )?,
+                    _ if executable => util::set_executable(partial_absolute_path, true)?,
+                    _ => {}
+                }
+            }
+            DownloadExecutablePolicy::StripExecutable => {
+                util::set_executable(partial_absolute_path, false)?
+            }
+            DownloadExecutablePolicy::Quarantine if executable => {
+                let relative_path = absolute_path.strip_prefix(&self.context.folder_path)?;
+                let quarantine_path = self.context.quarantine_dir().join(relative_path);
+                if let Some(parent) = quarantine_path.parent() {
+                    fs::create_dir_all(util::long_path(parent))?;
+                }
+                util::set_executable(partial_absolute_path, false)?;
+                fs::rename(
+                    util::long_path(partial_absolute_path),
+                    util::long_path(&quarantine_path),
+                )?;
+                log::warn!(
+                    "Content {} is executable, it is written into {:?} instead of {:?}",
+                    remote_content.content_id,
+                    quarantine_path,
+                    absolute_path
+                );
+                return Err(Error::NotRelevant(format!(
+                    "Content {} is quarantined",
+                    remote_content.content_id
+                )));
+            }
+            DownloadExecutablePolicy::Quarantine => {
+                util::set_executable(partial_absolute_path, false)?
+            }
+        }
+        Ok(())
+    }
+
+    fn restore_xattrs(&self, content_id: ContentId, absolute_path: &Path) -> Result<(), Error> {
+        if self.context.preserve_xattrs {
+            util::write_xattrs(
+                absolute_path,
+                &DatabaseOperation::new(&self.connection).get_xattrs(content_id)?,
+            );
+        }
+        Ok(())
+    }
+
+    fn remove_partial_file(&self, partial_absolute_path: &Path) {
+        if let Err(error) = fs::remove_file(util::long_path(partial_absolute_path)) {
+            log::error!(
+                "Unable to remove partial file {:?} : {:?}",
+                partial_absolute_path,
+                error
+            )
+        }
+    }
+}
+
+// Wake up messages are only a signal to read local watcher channel
+fn is_operation(message: &OperationalMessage) -> bool {
+    *message != OperationalMessage::LocalChanges
+}
+
+// Take messages waiting in main and local watcher channels. Signal is cleared before
+// reading local watcher channel : messages sent after that are signaled again.
+fn drain(
+    receiver: &Receiver<OperationalMessage>,
+    local_receiver: &Receiver<OperationalMessage>,
+    local_changes_signaled: &AtomicBool,
+    max: usize,
+) -> Vec<OperationalMessage> {
+    local_changes_signaled.store(false, Ordering::SeqCst);
+    receiver
+        .try_iter()
+        .filter(is_operation)
+        .chain(local_receiver.try_iter())
+        .take(max)
+        .collect()
+}
+
+// Wait a message from main or local watcher channel. Standard channels can't be waited
+// together : only main channel is waited, local watcher waking handler up through it
+// when it sends messages. Local watcher channel is disconnected when local watcher is not
+// running.
+fn receive(
+    receiver: &Receiver<OperationalMessage>,
+    local_receiver: &Receiver<OperationalMessage>,
+    local_changes_signaled: &AtomicBool,
+    timeout: Duration,
+) -> Result<OperationalMessage, RecvTimeoutError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        local_changes_signaled.store(false, Ordering::SeqCst);
+        if let Ok(message) = local_receiver.try_recv() {
+            return Ok(message);
+        }
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(OperationalMessage::LocalChanges) => continue,
+            received => return received,
+        }
+    }
+}
+
+// Clear a recorded conflict, considering its remote revision as known, and ask to
+// synchronize its disk file again
+pub fn clear_conflict(
+    connection: &Connection,
+    operational_sender: &Sender<OperationalMessage>,
+    relative_path: RelativeFilePath,
+) -> Result<(), Error> {
+    let database_operation = DatabaseOperation::new(connection);
+    let conflict = match database_operation.get_conflict(&relative_path)? {
+        Some(conflict) => conflict,
+        None => return Ok(()),
+    };
+
+    database_operation.with_transaction(|database_operation| {
+        if database_operation.content_id_is_known(conflict.content_id)? {
+            database_operation.update_revision_id(relative_path.clone(), conflict.revision_id)?;
+        }
+        database_operation.delete_conflict(&relative_path)
+    })?;
+
+    if let Err(error) =
+        operational_sender.send(OperationalMessage::ModifiedLocalFile(relative_path))
+    {
+        log::error!("Fail to send operational message : {:?}", error)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{mpsc::sync_channel, Arc},
+        thread,
+    };
+
+    use super::*;
+    use crate::testing::{self, StubResponse, TracimStub};
+
+    // Handler of a folder containing "file.txt" with given content, indexed as
+    // content 1 at given revision
+    fn handler_with_file(
+        stub: &TracimStub,
+        name: &str,
+        content: &[u8],
+        revision_id: RevisionId,
+    ) -> (OperationalHandler, PathBuf) {
+        let folder = testing::temp_folder(name);
+        let context = testing::context(stub, &folder);
+        (handler_with_context(context, content, revision_id), folder)
+    }
+
+    fn handler_with_context(
+        context: Context,
+        content: &[u8],
+        revision_id: RevisionId,
+    ) -> OperationalHandler {
+        let folder = PathBuf::from(&context.folder_path);
+        fs::write(folder.join("file.txt"), content).unwrap();
+        let connection = testing::connection();
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+        DatabaseOperation::new(&connection)
+            .insert_new_file(
+                "file.txt".to_string(),
+                file_infos.last_modified_timestamp,
+                1,
+                revision_id,
+                file_infos.checksum().unwrap(),
+                false,
+            )
+            .unwrap();
+        DatabaseOperation::new(&connection)
+            .update_size("file.txt".to_string(), file_infos.size)
+            .unwrap();
+        OperationalHandler::new(context, connection).unwrap()
+    }
+
+    fn remote_file(stub: &TracimStub, revision_id: RevisionId, content: &[u8]) {
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(
+                    1,
+                    revision_id,
+                    None,
+                    "file.txt",
+                    content.len() as u64,
+                ),
+            ),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/file.txt"),
+            StubResponse::bytes(content),
+        );
+    }
+
+    #[test]
+    fn reverted_remote_revision_is_downloaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "reverted", b"second", 6);
+        // Reverting to first revision content produce a new revision
+        remote_file(&stub, 7, b"first");
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"first");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn downloaded_file_leaves_no_planned_local_ignore() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "no-ignore", b"second", 6);
+        remote_file(&stub, 7, b"third");
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert!(handler
+            .ignore_messages
+            .iter()
+            .all(OperationalMessage::is_remote));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn truncated_download_keeps_disk_file_and_index() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "truncated", b"second", 6);
+        remote_file(&stub, 7, b"third revision");
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/file.txt"),
+            StubResponse {
+                content_length: Some(14),
+                ..StubResponse::bytes(b"third")
+            },
+        );
+
+        let result = handler.modified_remote_file(1);
+
+        assert!(matches!(result, Err(Error::IncompleteDownload(_))));
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"second");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            6
+        );
+        assert_eq!(fs::read_dir(handler.context.temp_dir()).unwrap().count(), 0);
+        assert!(handler
+            .ignore_messages
+            .iter()
+            .all(OperationalMessage::is_remote));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn remote_revision_lower_than_indexed_one_is_downloaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "lower", b"second", 6);
+        remote_file(&stub, 4, b"first");
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"first");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            4
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn remote_rename_produces_no_outgoing_rename() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "remote-rename", b"content", 6);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "renamed.txt", 7),
+            ),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/renamed.txt"),
+            StubResponse::bytes(b"content"),
+        );
+
+        handler.modified_remote_file(1).unwrap();
+        let local_rename =
+            OperationalMessage::RenamedLocalFile("file.txt".to_string(), "renamed.txt".to_string());
+
+        assert!(handler.ignore_message(&local_rename).unwrap());
+        assert!(!folder.join("file.txt").exists());
+        assert_eq!(fs::read(folder.join("renamed.txt")).unwrap(), b"content");
+        assert!(stub
+            .request_lines()
+            .iter()
+            .all(|request_line| request_line.starts_with("GET ")));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn failed_disk_rename_is_not_ignored() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "failed-rename", b"content", 6);
+
+        assert!(handler
+            .rename_disk_file("missing.txt", "renamed.txt")
+            .is_err());
+        assert!(handler.ignore_messages.is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn indexed_remote_revision_is_not_downloaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "indexed", b"second", 6);
+        remote_file(&stub, 6, b"first");
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"second");
+        assert!(!stub.request_lines().contains(&format!(
+            "GET {}",
+            stub.workspace_path("files/1/raw/file.txt")
+        )));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    fn remote_content(revision_id: RevisionId, size: u64) -> RemoteContent {
+        serde_json::from_str(&testing::remote_content_json(
+            1,
+            revision_id,
+            None,
+            "file.txt",
+            size,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn file_indexed_at_remote_revision_is_identical_without_download() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "identical-revision", b"same", 6);
+        remote_file(&stub, 6, b"same");
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+        let checksum = file_infos.checksum().unwrap();
+
+        let identical = handler
+            .identical_to_remote(&file_infos, &checksum, &remote_content(6, 4))
+            .unwrap();
+
+        assert!(identical);
+        assert!(!stub.request_lines().contains(&format!(
+            "GET {}",
+            stub.workspace_path("files/1/raw/file.txt")
+        )));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn file_of_other_size_differs_without_download() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "identical-size", b"same", 6);
+        remote_file(&stub, 7, b"longer");
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+        let checksum = file_infos.checksum().unwrap();
+
+        let identical = handler
+            .identical_to_remote(&file_infos, &checksum, &remote_content(7, 6))
+            .unwrap();
+
+        assert!(!identical);
+        assert!(stub.requests().is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn created_content_is_not_fetched_again() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("created");
+        fs::write(folder.join("new.txt"), b"new").unwrap();
+        let mut handler =
+            OperationalHandler::new(testing::context(&stub, &folder), testing::connection())
+                .unwrap();
+        stub.on(
+            "POST",
+            &stub.workspace_path("files"),
+            StubResponse::json(200, &testing::remote_content_json(2, 3, None, "new.txt", 3)),
+        );
+
+        handler.new_local_file("new.txt".to_string()).unwrap();
+
+        assert_eq!(
+            stub.request_lines(),
+            vec![format!("POST {}", stub.workspace_path("files"))]
+        );
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(2)
+                .unwrap(),
+            3
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn moved_and_modified_remote_file_is_downloaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "moved-modified", b"second", 6);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "moved.txt", 5),
+            ),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/moved.txt"),
+            StubResponse::bytes(b"third"),
+        );
+
+        handler.moved_remote_file(1).unwrap();
+
+        assert!(!folder.join("file.txt").exists());
+        assert_eq!(fs::read(folder.join("moved.txt")).unwrap(), b"third");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn kept_copy_leaves_no_new_local_file_ignore() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "keep-both", b"local", 6);
+        remote_file(&stub, 7, b"remote");
+        stub.on(
+            "POST",
+            &stub.workspace_path("files"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(2, 8, None, "file.txt", 5),
+            ),
+        );
+        // Copy name contains time of conflict, label is updated
+        stub.on(
+            "PUT",
+            &stub.workspace_path("files/2"),
+            StubResponse::json(200, r#"{"last_revision_id": 9}"#),
+        );
+        let remote_content = handler.client.get_remote_content(1).unwrap();
+
+        handler
+            .keep_both("file.txt".to_string(), &remote_content, 6)
+            .unwrap();
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"remote");
+        let copy_relative_path = DatabaseOperation::new(&handler.connection)
+            .get_path_from_content_id(2)
+            .unwrap();
+        assert_eq!(fs::read(folder.join(copy_relative_path)).unwrap(), b"local");
+        assert!(!handler
+            .ignore_messages
+            .iter()
+            .any(|message| matches!(message, OperationalMessage::NewLocalFile(_))));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_hard_link_to_indexed_file_is_not_uploaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "hard-link", b"content", 6);
+        handler.context.detect_hard_links = true;
+        fs::hard_link(folder.join("file.txt"), folder.join("link.txt")).unwrap();
+
+        let result = handler.new_local_file("link.txt".to_string());
+
+        assert!(matches!(result, Err(Error::NotRelevant(_))));
+        assert!(stub.requests().is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn disk_file_changed(handler: &OperationalHandler, folder: &Path) -> bool {
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+        handler.disk_file_changed(&file_infos).unwrap()
+    }
+
+    #[test]
+    fn disk_file_with_indexed_size_and_timestamp_is_not_hashed() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "fast-path", b"second", 6);
+        let path = folder.join("file.txt");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        // Content differs, but nothing tells it without hashing
+        fs::write(&path, b"SECOND").unwrap();
+        set_modified(&path, modified);
+
+        assert!(!disk_file_changed(&handler, &folder));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn touched_disk_file_with_indexed_content_is_not_changed() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "touched", b"second", 6);
+        let path = folder.join("file.txt");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        set_modified(&path, modified + Duration::from_secs(3600));
+
+        assert!(!disk_file_changed(&handler, &folder));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn disk_file_with_new_content_is_changed() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "new-content", b"second", 6);
+        let path = folder.join("file.txt");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, b"SECOND").unwrap();
+        set_modified(&path, modified + Duration::from_secs(3600));
+
+        assert!(disk_file_changed(&handler, &folder));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn new_folder_storm_creates_folder_once() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("storm");
+        fs::create_dir(folder.join("folder")).unwrap();
+        let mut folder_json: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(1000, 1, None, "folder", 0))
+                .unwrap();
+        folder_json["content_type"] = "folder".into();
+        stub.on(
+            "POST",
+            &stub.workspace_path("contents"),
+            StubResponse::json(200, &folder_json.to_string()),
+        );
+        let mut file_responses = vec![];
+        let (sender, receiver) = channel();
+        let (_local_sender, local_receiver) = channel();
+        for i in 0..100 {
+            let file_name = format!("file_{}.txt", i);
+            fs::write(folder.join("folder").join(&file_name), b"content").unwrap();
+            file_responses.push(StubResponse::json(
+                200,
+                &testing::remote_content_json(i + 1, i + 1, Some(1000), &file_name, 7),
+            ));
+            sender
+                .send(OperationalMessage::NewLocalFile(format!(
+                    "folder/{}",
+                    file_name
+                )))
+                .unwrap();
+        }
+        stub.on_each("POST", &stub.workspace_path("files"), file_responses);
+        // Folder own creation message arrives after its children ones
+        sender
+            .send(OperationalMessage::NewLocalFile("folder".to_string()))
+            .unwrap();
+        sender.send(OperationalMessage::Exit).unwrap();
+        let mut handler =
+            OperationalHandler::new(testing::context(&stub, &folder), testing::connection())
+                .unwrap();
+
+        handler.listen(receiver, local_receiver);
+
+        let request_lines = stub.request_lines();
+        let count = |request_line: String| {
+            request_lines
+                .iter()
+                .filter(|line| **line == request_line)
+                .count()
+        };
+        assert_eq!(
+            count(format!("POST {}", stub.workspace_path("contents"))),
+            1
+        );
+        assert_eq!(count(format!("POST {}", stub.workspace_path("files"))), 100);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn queue_is_compacted_again_once_doubled() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "compaction-growth", b"first", 6);
+        let modified = OperationalMessage::ModifiedLocalFile("file.txt".to_string());
+
+        handler
+            .queue
+            .extend(vec![modified.clone(); COMPACTION_MIN_QUEUE_LENGTH]);
+        handler.compact_grown_queue();
+        let compacted_length = handler.queue.len();
+        handler.queue.push_back(modified);
+        handler.compact_grown_queue();
+
+        assert_eq!(compacted_length, 1);
+        // Not compacted again before it doubled and reached minimum length
+        assert_eq!(handler.queue.len(), 2);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Handler proceeding a remote modification pulled at startup, with its cursor
+    fn remote_cursor_after_listen(stub: &TracimStub, name: &str) -> Option<String> {
+        let (mut handler, folder) = handler_with_file(stub, name, b"first", 6);
+        DatabaseOperation::new(&handler.connection)
+            .set_meta(remote::PENDING_REMOTE_CURSOR_KEY, "12")
+            .unwrap();
+        let (sender, receiver) = channel();
+        let (_local_sender, local_receiver) = channel();
+        sender
+            .send(OperationalMessage::ModifiedRemoteFile(1))
+            .unwrap();
+        sender.send(OperationalMessage::Exit).unwrap();
+
+        handler.listen(receiver, local_receiver);
+
+        let cursor = DatabaseOperation::new(&handler.connection)
+            .get_meta(remote::REMOTE_CURSOR_KEY)
+            .unwrap();
+        fs::remove_dir_all(folder).unwrap();
+        cursor
+    }
+
+    #[test]
+    fn pulled_remote_cursor_is_committed_once_changes_are_proceed() {
+        let stub = TracimStub::start();
+        remote_file(&stub, 7, b"second");
+
+        assert_eq!(
+            remote_cursor_after_listen(&stub, "cursor-commit"),
+            Some("12".to_string())
+        );
+    }
+
+    #[test]
+    fn pulled_remote_cursor_is_not_committed_when_a_change_failed() {
+        let stub = TracimStub::start();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(400, r#"{"code": 1000, "message": "Bad request"}"#),
+        );
+
+        assert_eq!(remote_cursor_after_listen(&stub, "cursor-failure"), None);
+    }
+
+    fn relocated_file(stub: &TracimStub, content: &[u8]) {
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/2"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(2, 9, None, "moved.txt", content.len() as u64),
+            ),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/2/raw/moved.txt"),
+            StubResponse::bytes(content),
+        );
+    }
+
+    #[test]
+    fn relocated_identical_file_is_moved() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "relocated", b"second", 6);
+        relocated_file(&stub, b"second");
+
+        handler.relocated_remote_file(1, 2).unwrap();
+
+        assert!(!folder.join("file.txt").exists());
+        assert_eq!(fs::read(folder.join("moved.txt")).unwrap(), b"second");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_path_from_content_id(2)
+                .unwrap(),
+            "moved.txt"
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn relocated_file_with_other_bytes_is_downloaded() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "relocated-other", b"second", 6);
+        relocated_file(&stub, b"SECOND");
+
+        handler.relocated_remote_file(1, 2).unwrap();
+
+        assert!(!folder.join("file.txt").exists());
+        assert_eq!(fs::read(folder.join("moved.txt")).unwrap(), b"SECOND");
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert!(!database_operation.content_id_is_known(1).unwrap());
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(2)
+                .unwrap(),
+            9
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn forced_download_is_journaled_while_offline() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "journal-force", b"content", 6);
+
+        handler.journal_message(OperationalMessage::ForceDownload(42));
+        handler.journal_message(OperationalMessage::ForcePush("file.txt".to_string()));
+
+        assert_eq!(
+            handler.take_journal().unwrap(),
+            vec![
+                OperationalMessage::ForceDownload(42),
+                OperationalMessage::ForcePush("file.txt".to_string()),
+            ]
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(unix)]
+    const EXECUTABLE_CONTENT: &[u8] = b"\x7fELF\x02\x01\x01";
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    // Handler of an executable disk file, whose remote content is a new executable
+    // revision
+    #[cfg(unix)]
+    fn handler_downloading_executable(
+        stub: &TracimStub,
+        name: &str,
+        policy: DownloadExecutablePolicy,
+    ) -> (OperationalHandler, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        let (mut handler, folder) = handler_with_file(stub, name, b"#!/bin/sh", 6);
+        handler.context.download_executable_policy = policy;
+        fs::set_permissions(folder.join("file.txt"), fs::Permissions::from_mode(0o755)).unwrap();
+        remote_file(stub, 7, EXECUTABLE_CONTENT);
+        (handler, folder)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_download_preserves_disk_file_permissions() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_downloading_executable(
+            &stub,
+            "preserve-permissions",
+            DownloadExecutablePolicy::PreservePermissions,
+        );
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(
+            fs::read(folder.join("file.txt")).unwrap(),
+            EXECUTABLE_CONTENT
+        );
+        assert_eq!(mode(&folder.join("file.txt")), 0o755);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_download_is_stripped_of_executable_bits() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_downloading_executable(
+            &stub,
+            "strip-executable",
+            DownloadExecutablePolicy::StripExecutable,
+        );
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(
+            fs::read(folder.join("file.txt")).unwrap(),
+            EXECUTABLE_CONTENT
+        );
+        assert_eq!(mode(&folder.join("file.txt")) & 0o111, 0);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_download_is_quarantined() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_downloading_executable(
+            &stub,
+            "quarantine",
+            DownloadExecutablePolicy::Quarantine,
+        );
+
+        let result = handler.modified_remote_file(1);
+
+        assert!(matches!(result, Err(Error::NotRelevant(_))));
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"#!/bin/sh");
+        let quarantine_path = handler.context.quarantine_dir().join("file.txt");
+        assert_eq!(fs::read(&quarantine_path).unwrap(), EXECUTABLE_CONTENT);
+        assert_eq!(mode(&quarantine_path) & 0o111, 0);
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            6
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Handler of a folder containing a new "bad:name.txt" file, which Tracim refuses
+    // to create once
+    fn handler_with_rejected_file(
+        stub: &TracimStub,
+        name: &str,
+        policy: RejectionPolicy,
+    ) -> (OperationalHandler, PathBuf) {
+        let folder = testing::temp_folder(name);
+        fs::write(folder.join("bad:name.txt"), b"content").unwrap();
+        let mut context = testing::context(stub, &folder);
+        context.rejection_policy = policy;
+        stub.on_each(
+            "POST",
+            &stub.workspace_path("files"),
+            vec![
+                StubResponse::json(400, r#"{"code": 2001, "message": "Invalid name"}"#),
+                StubResponse::json(
+                    200,
+                    &testing::remote_content_json(2, 3, None, "bad_name.txt", 7),
+                ),
+            ],
+        );
+        let handler = OperationalHandler::new(context, testing::connection()).unwrap();
+        (handler, folder)
+    }
+
+    #[test]
+    fn rejected_new_file_is_renamed_and_created() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_with_rejected_file(&stub, "rename-rejected", RejectionPolicy::RenameAndRetry);
+
+        handler.new_local_file("bad:name.txt".to_string()).unwrap();
+
+        assert!(!folder.join("bad:name.txt").exists());
+        assert_eq!(fs::read(folder.join("bad_name.txt")).unwrap(), b"content");
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(
+            database_operation.get_path_from_content_id(2).unwrap(),
+            "bad_name.txt"
+        );
+        assert!(database_operation
+            .get_rejection("bad:name.txt")
+            .unwrap()
+            .is_none());
+        assert_eq!(stub.requests().len(), 2);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn rejected_new_file_is_skipped_until_it_changes() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_with_rejected_file(&stub, "skip-rejected", RejectionPolicy::Skip);
+
+        let result = handler.new_local_file("bad:name.txt".to_string());
+
+        assert!(matches!(result, Err(Error::NotRelevant(_))));
+        assert!(folder.join("bad:name.txt").exists());
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert!(database_operation
+            .get_rejection("bad:name.txt")
+            .unwrap()
+            .is_some());
+        assert!(!database_operation.content_id_is_known(2).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn truncated_disk_name_is_uploaded_with_remote_file_name() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "truncated-name", b"second", 6);
+        // Disk name "file.txt" is the truncated remote name
+        handler.context.max_name_bytes = 8;
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "file_with_long_name.txt", 6),
+            ),
+        );
+        stub.on(
+            "PUT",
+            &stub.workspace_path("files/1/raw/file_with_long_name.txt"),
+            StubResponse::json(200, "{}"),
+        );
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+
+        handler.upload_file(1, file_infos).unwrap();
+
+        let put_lines: Vec<String> = stub
+            .request_lines()
+            .into_iter()
+            .filter(|line| line.starts_with("PUT "))
+            .collect();
+        assert_eq!(
+            put_lines,
+            vec![format!(
+                "PUT {}",
+                stub.workspace_path("files/1/raw/file_with_long_name.txt")
+            )]
+        );
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn force_pushed_collision_path_keeps_remote_file_name() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "collision-push", b"second", 6);
+        fs::rename(folder.join("file.txt"), folder.join("File (content 1).txt")).unwrap();
+        DatabaseOperation::new(&handler.connection)
+            .move_entry(1, "File (content 1).txt", 6)
+            .unwrap();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "File.txt", 6),
+            ),
+        );
+        stub.on(
+            "PUT",
+            &stub.workspace_path("files/1/raw/File.txt"),
+            StubResponse::json(200, "{}"),
+        );
+
+        handler
+            .force_push("File (content 1).txt".to_string())
+            .unwrap();
+
+        assert!(stub
+            .request_lines()
+            .iter()
+            .filter(|line| line.starts_with("PUT "))
+            .all(|line| *line == format!("PUT {}", stub.workspace_path("files/1/raw/File.txt"))));
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn local_relative_path_disambiguates_case_collisions() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "collision-path", b"second", 6);
+        let colliding: RemoteContent =
+            serde_json::from_str(&testing::remote_content_json(2, 3, None, "FILE.txt", 6)).unwrap();
+        let indexed = remote_content(7, 6);
+        let local_relative_path = |handler: &OperationalHandler, remote_content| {
+            handler.local_relative_path(remote_content, "FILE.txt".to_string())
+        };
+
+        handler.case_collision = CaseCollision::Keep;
+        assert_eq!(
+            local_relative_path(&handler, &colliding).unwrap(),
+            "FILE.txt"
+        );
+        handler.case_collision = CaseCollision::Skip;
+        assert!(matches!(
+            local_relative_path(&handler, &colliding),
+            Err(Error::NotRelevant(_))
+        ));
+        handler.case_collision = CaseCollision::Rename;
+        assert_eq!(
+            local_relative_path(&handler, &colliding).unwrap(),
+            "FILE (content 2).txt"
+        );
+        // Indexed content renamed on remote by case only keeps its path
+        assert_eq!(local_relative_path(&handler, &indexed).unwrap(), "FILE.txt");
+        // Content already written at its disambiguated path stays there
+        DatabaseOperation::new(&handler.connection)
+            .insert_new_file("FILE (content 2).txt".to_string(), 0, 2, 3, None, false)
+            .unwrap();
+        handler.case_collision = CaseCollision::Skip;
+        assert_eq!(
+            local_relative_path(&handler, &colliding).unwrap(),
+            "FILE (content 2).txt"
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn growing_file_is_stable_after_one_interval_without_change() {
+        use std::io::Write;
+
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "growing", b"first", 6);
+        handler.context.upload_stable_ms = Some(200);
+        let path = folder.join("file.txt");
+        let probe = |handler: &mut OperationalHandler| {
+            let file_infos = util::FileInfos::from(
+                util::path_to_string(&folder).unwrap(),
+                "file.txt".to_string(),
+            )
+            .unwrap();
+            handler.ensure_stable(&file_infos)
+        };
+
+        assert!(matches!(probe(&mut handler), Err(Error::FileUnstable(_))));
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(100));
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .write_all(b" growing")
+                .unwrap();
+            assert!(matches!(probe(&mut handler), Err(Error::FileUnstable(_))));
+        }
+        // Unchanged, but not for a whole interval yet
+        assert!(matches!(probe(&mut handler), Err(Error::FileUnstable(_))));
+        thread::sleep(Duration::from_millis(200));
+        assert!(probe(&mut handler).is_ok());
+        assert!(handler.stability_probes.is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn deleted_file_is_not_probed_anymore() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "probe-deleted", b"first", 6);
+        handler.context.upload_stable_ms = Some(60_000);
+        handler.context.disable_deletions = true;
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+        assert!(handler.ensure_stable(&file_infos).is_err());
+        fs::remove_file(folder.join("file.txt")).unwrap();
+        let (sender, receiver) = channel();
+        let (_local_sender, local_receiver) = channel();
+        sender
+            .send(OperationalMessage::DeletedLocalFile("file.txt".to_string()))
+            .unwrap();
+        sender.send(OperationalMessage::Exit).unwrap();
+
+        handler.listen(receiver, local_receiver);
+
+        assert!(handler.stability_probes.is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn only_alternating_modifications_pause_looping_content() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "loop", b"content", 6);
+        handler.context.loop_max_operations = 3;
+        let relative_path = Some("file.txt".to_string());
+        let local = OperationalMessage::ModifiedLocalFile("file.txt".to_string());
+        let remote = OperationalMessage::ModifiedRemoteFile(1);
+
+        // User saving the file again and again
+        for _ in 0..10 {
+            handler.track_loop(&local, &relative_path);
+        }
+        assert!(handler.looping_content_ids.is_empty());
+
+        for message in [&remote, &local, &remote] {
+            handler.track_loop(message, &relative_path);
+        }
+        assert!(handler.looping_content_ids.contains(&1));
+        assert!(stub.requests().is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn compressed_download_is_written_decompressed() {
+        use std::io::Write;
+
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("compressed");
+        let mut context = testing::context(&stub, &folder);
+        context.connection_pool.gzip = true;
+        let mut handler = handler_with_context(context, b"first", 6);
+        let content = "a line of text which compress well\n".repeat(10_000);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < content.len() / 10);
+        remote_file(&stub, 7, content.as_bytes());
+        let mut response = StubResponse::bytes(&compressed);
+        response
+            .headers
+            .push(("Content-Encoding".to_string(), "gzip".to_string()));
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/file.txt"),
+            response,
+        );
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(
+            fs::read(folder.join("file.txt")).unwrap(),
+            content.as_bytes()
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn upload_is_not_compressed_with_compressed_transfers() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("uncompressed-upload");
+        let mut context = testing::context(&stub, &folder);
+        context.connection_pool.gzip = true;
+        let content = "a line of text which compress well\n".repeat(1_000);
+        let mut handler = handler_with_context(context, content.as_bytes(), 6);
+        remote_file(&stub, 7, content.as_bytes());
+        stub.on(
+            "PUT",
+            &stub.workspace_path("files/1/raw/file.txt"),
+            StubResponse::json(200, "{}"),
+        );
+        let file_infos = util::FileInfos::from(
+            util::path_to_string(&folder).unwrap(),
+            "file.txt".to_string(),
+        )
+        .unwrap();
+
+        handler.upload_file(1, file_infos).unwrap();
+
+        let upload = stub
+            .requests()
+            .into_iter()
+            .find(|request| request.method == "PUT")
+            .unwrap();
+        assert!(!upload.headers.contains_key("content-encoding"));
+        assert!(upload
+            .body
+            .windows(content.len())
+            .any(|part| part == content.as_bytes()));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Remote content 2 "report.txt" whose current revision is made by given user
+    fn remote_content_by(stub: &TracimStub, user_id: i32) {
+        let mut remote_content: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(2, 3, None, "report.txt", 6))
+                .unwrap();
+        remote_content["last_modifier"] = serde_json::json!({ "user_id": user_id });
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/2"),
+            StubResponse::json(200, &remote_content.to_string()),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/2/raw/report.txt"),
+            StubResponse::bytes(b"report"),
+        );
+    }
+
+    #[test]
+    fn remote_file_of_filtered_author_is_skipped() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("filtered-author");
+        let mut context = testing::context(&stub, &folder);
+        context.author_filter.denied.insert(5);
+        let mut handler = OperationalHandler::new(context, testing::connection()).unwrap();
+        remote_content_by(&stub, 5);
+
+        let result = handler.new_remote_file(2);
+
+        assert!(matches!(result, Err(Error::NotRelevant(_))));
+        assert!(!folder.join("report.txt").exists());
+        assert!(!DatabaseOperation::new(&handler.connection)
+            .content_id_is_known(2)
+            .unwrap());
+        assert!(!stub.request_lines().contains(&format!(
+            "GET {}",
+            stub.workspace_path("files/2/raw/report.txt")
+        )));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn remote_file_of_accepted_author_is_downloaded() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("accepted-author");
+        let mut context = testing::context(&stub, &folder);
+        context.author_filter.denied.insert(5);
+        let mut handler = OperationalHandler::new(context, testing::connection()).unwrap();
+        remote_content_by(&stub, 6);
+
+        handler.new_remote_file(2).unwrap();
+
+        assert_eq!(fs::read(folder.join("report.txt")).unwrap(), b"report");
+        assert!(DatabaseOperation::new(&handler.connection)
+            .content_id_is_known(2)
+            .unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Remote content 1 at revision 7, as a content of given type
+    fn remote_content_of_type(stub: &TracimStub, file_name: &str, content_type: &str) {
+        let mut remote_content: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(1, 7, None, file_name, 6)).unwrap();
+        remote_content["content_type"] = serde_json::json!(content_type);
+        remote_content["label"] = serde_json::json!(file_name);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(200, &remote_content.to_string()),
+        );
+    }
+
+    #[test]
+    fn file_which_became_a_folder_is_replaced_by_a_folder() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "file-to-folder", b"content", 6);
+        remote_content_of_type(&stub, "file.txt", "folder");
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert!(folder.join("file.txt").is_dir());
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(database_operation.get_is_directory(1).unwrap(), Some(true));
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        assert!(handler
+            .ignore_messages
+            .contains(&OperationalMessage::DeletedLocalFile(
+                "file.txt".to_string()
+            )));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn folder_which_became_a_file_is_replaced_by_a_file() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("folder-to-file");
+        fs::create_dir(folder.join("docs")).unwrap();
+        fs::write(folder.join("docs").join("a.txt"), b"a").unwrap();
+        let connection = testing::connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("docs".to_string(), 0, 1, 6, None, true)
+            .unwrap();
+        database_operation
+            .insert_new_file("docs/a.txt".to_string(), 0, 2, 6, None, false)
+            .unwrap();
+        let mut handler =
+            OperationalHandler::new(testing::context(&stub, &folder), connection).unwrap();
+        remote_content_of_type(&stub, "docs", "file");
+        stub.on(
+            "GET",
+            &stub.workspace_path("files/1/raw/docs"),
+            StubResponse::bytes(b"report"),
+        );
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(fs::read(folder.join("docs")).unwrap(), b"report");
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(database_operation.get_is_directory(1).unwrap(), Some(false));
+        assert!(!database_operation.content_id_is_known(2).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn content_moved_to_another_workspace_is_removed_from_disk_and_index() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "left-workspace", b"content", 6);
+        let mut remote_content: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(1, 7, None, "file.txt", 7)).unwrap();
+        remote_content["workspace_id"] = serde_json::json!(testing::WORKSPACE_ID + 1);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(200, &remote_content.to_string()),
+        );
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert!(!folder.join("file.txt").exists());
+        assert!(!DatabaseOperation::new(&handler.connection)
+            .content_id_is_known(1)
+            .unwrap());
+        assert!(handler
+            .ignore_messages
+            .contains(&OperationalMessage::DeletedLocalFile(
+                "file.txt".to_string()
+            )));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn file_moved_into_new_folder_creates_folder_and_moves_content_only() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "move-new-folder", b"content", 6);
+        fs::create_dir(folder.join("new")).unwrap();
+        fs::rename(folder.join("file.txt"), folder.join("new").join("file.txt")).unwrap();
+        let mut new_folder: serde_json::Value =
+            serde_json::from_str(&testing::remote_content_json(5, 1, None, "new", 0)).unwrap();
+        new_folder["content_type"] = serde_json::json!("folder");
+        new_folder["label"] = serde_json::json!("new");
+        stub.on(
+            "POST",
+            &stub.workspace_path("contents"),
+            StubResponse::json(200, &new_folder.to_string()),
+        );
+        stub.on(
+            "PUT",
+            &stub.workspace_path("contents/1/move"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 8, Some(5), "file.txt", 7),
+            ),
+        );
+
+        handler
+            .renamed_local_file("file.txt".to_string(), "new/file.txt".to_string())
+            .unwrap();
+
+        assert_eq!(
+            stub.request_lines(),
+            vec![
+                format!("POST {}", stub.workspace_path("contents")),
+                format!("PUT {}", stub.workspace_path("contents/1/move")),
+            ]
+        );
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(
+            database_operation.get_path_from_content_id(1).unwrap(),
+            "new/file.txt"
+        );
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            8
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Handler of a folder containing "a/b/file.txt", nothing indexed
+    fn handler_with_deep_file(
+        stub: &TracimStub,
+        name: &str,
+        parent_creation: ParentCreation,
+    ) -> (OperationalHandler, PathBuf) {
+        let folder = testing::temp_folder(name);
+        fs::create_dir_all(folder.join("a").join("b")).unwrap();
+        fs::write(folder.join("a").join("b").join("file.txt"), b"content").unwrap();
+        let mut context = testing::context(stub, &folder);
+        context.parent_creation = parent_creation;
+        let handler = OperationalHandler::new(context, testing::connection()).unwrap();
+        (handler, folder)
+    }
+
+    fn remote_folder_json(content_id: ContentId, parent_id: Option<i32>, name: &str) -> String {
+        let mut remote_folder: serde_json::Value = serde_json::from_str(
+            &testing::remote_content_json(content_id, 1, parent_id, name, 0),
+        )
+        .unwrap();
+        remote_folder["content_type"] = serde_json::json!("folder");
+        remote_folder["label"] = serde_json::json!(name);
+        remote_folder.to_string()
+    }
+
+    #[test]
+    fn deep_new_file_creates_missing_parents() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_with_deep_file(&stub, "deep-parents", ParentCreation::Create);
+        stub.on_each(
+            "POST",
+            &stub.workspace_path("contents"),
+            vec![
+                StubResponse::json(200, &remote_folder_json(5, None, "a")),
+                StubResponse::json(200, &remote_folder_json(6, Some(5), "b")),
+            ],
+        );
+        stub.on(
+            "POST",
+            &stub.workspace_path("files"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(7, 1, Some(6), "file.txt", 7),
+            ),
+        );
+
+        handler.new_local_file("a/b/file.txt".to_string()).unwrap();
+
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        for (relative_path, content_id) in [("a", 5), ("a/b", 6), ("a/b/file.txt", 7)] {
+            assert_eq!(
+                database_operation
+                    .get_content_id_from_path(relative_path.to_string())
+                    .unwrap(),
+                content_id
+            );
+        }
+        assert_eq!(
+            stub.request_lines()
+                .iter()
+                .filter(|line| line.starts_with("POST"))
+                .count(),
+            3
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn missing_parent_is_not_created_when_rejected() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_with_deep_file(&stub, "reject-parents", ParentCreation::Reject);
+
+        let result = handler.new_local_file("a/b/file.txt".to_string());
+
+        assert!(matches!(result, Err(Error::MissingRemoteParent(_))));
+        assert!(stub.requests().is_empty());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn forbidden_parent_creation_is_reported() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_with_deep_file(&stub, "forbidden-parents", ParentCreation::Create);
+        stub.on(
+            "POST",
+            &stub.workspace_path("contents"),
+            StubResponse::json(403, r#"{"code": 2044, "message": "Insufficient rights"}"#),
+        );
+
+        let result = handler.new_local_file("a/b/file.txt".to_string());
+
+        assert!(matches!(
+            result,
+            Err(Error::RemoteFolderCreationForbidden(_))
+        ));
+        assert_eq!(
+            stub.request_lines(),
+            vec![format!("POST {}", stub.workspace_path("contents"))]
+        );
+        assert!(!DatabaseOperation::new(&handler.connection)
+            .content_id_is_known(5)
+            .unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    // Handler of "file.txt" indexed with given label, labels being synchronized
+    fn handler_syncing_labels(
+        stub: &TracimStub,
+        name: &str,
+        conflict_strategy: ConflictStrategy,
+        indexed_label: &str,
+    ) -> (OperationalHandler, PathBuf) {
+        let folder = testing::temp_folder(name);
+        let mut context = testing::context(stub, &folder);
+        context.sync_labels = true;
+        context.name_strategy = NameStrategy::Filename;
+        context.conflict_strategy = conflict_strategy;
+        let handler = handler_with_context(context, b"content", 6);
+        DatabaseOperation::new(&handler.connection)
+            .update_label(1, indexed_label)
+            .unwrap();
+        stub.on(
+            "PUT",
+            &stub.workspace_path("files/1"),
+            StubResponse::json(200, r#"{"last_revision_id": 8}"#),
+        );
+        (handler, folder)
+    }
+
+    fn labeled_remote_content(label: &str) -> RemoteContent {
+        let mut remote_content = remote_content(6, 7);
+        remote_content.label = label.to_string();
+        remote_content
+    }
+
+    // Label sent to remote, if any
+    fn pushed_label(stub: &TracimStub) -> Option<String> {
+        stub.requests()
+            .iter()
+            .find(|request| request.method == "PUT")
+            .map(|request| {
+                serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["label"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+    }
+
+    #[test]
+    fn changed_disk_label_is_pushed() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_syncing_labels(&stub, "disk-label", ConflictStrategy::Manual, "Draft");
+
+        handler
+            .sync_label(
+                "file.txt",
+                &labeled_remote_content("Draft"),
+                Some("Final".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(pushed_label(&stub), Some("Final".to_string()));
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(
+            database_operation.get_label(1).unwrap(),
+            Some("Final".to_string())
+        );
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            8
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn changed_remote_label_is_taken() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) =
+            handler_syncing_labels(&stub, "remote-label", ConflictStrategy::Manual, "Draft");
+
+        handler
+            .sync_label(
+                "file.txt",
+                &labeled_remote_content("Final"),
+                Some("Draft".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(pushed_label(&stub), None);
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_label(1)
+                .unwrap(),
+            Some("Final".to_string())
+        );
+        // File systems without extended attributes have no disk label
+        if let Some(disk_label) = util::read_label(&folder.join("file.txt")).unwrap() {
+            assert_eq!(disk_label, "Final");
+        }
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn label_changed_on_both_sides_follows_conflict_strategy() {
+        for (conflict_strategy, pushed, indexed) in [
+            (ConflictStrategy::LocalWins, Some("Local"), "Local"),
+            (ConflictStrategy::RemoteWins, None, "Remote"),
+            (ConflictStrategy::KeepBoth, None, "Remote"),
+        ] {
+            let stub = TracimStub::start();
+            let (mut handler, folder) =
+                handler_syncing_labels(&stub, "both-labels", conflict_strategy, "Draft");
+
+            handler
+                .sync_label(
+                    "file.txt",
+                    &labeled_remote_content("Remote"),
+                    Some("Local".to_string()),
+                )
+                .unwrap();
+
+            assert_eq!(pushed_label(&stub).as_deref(), pushed);
+            assert_eq!(
+                DatabaseOperation::new(&handler.connection)
+                    .get_label(1)
+                    .unwrap()
+                    .as_deref(),
+                Some(indexed)
+            );
+            fs::remove_dir_all(folder).unwrap();
+        }
+    }
+
+    #[test]
+    fn colleague_revision_after_own_upload_echo_is_not_ignored() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "colleague-revision", b"mine", 6);
+        let message = OperationalMessage::ModifiedRemoteFile(1);
+        stub.on_each(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            vec![
+                StubResponse::json(
+                    200,
+                    &testing::remote_content_json(1, 6, None, "file.txt", 4),
+                ),
+                StubResponse::json(
+                    200,
+                    &testing::remote_content_json(1, 7, None, "file.txt", 9),
+                ),
+            ],
+        );
+        // Upload planned to ignore its echo, remote is still at uploaded revision
+        handler.ignore_messages.push(message.clone());
+
+        let echo_ignored = handler.ignore_message(&message).unwrap();
+        let colleague_ignored = handler.ignore_message(&message).unwrap();
+
+        assert!(echo_ignored);
+        assert!(!colleague_ignored);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn colleague_revision_before_own_upload_echo_is_not_ignored() {
+        let stub = TracimStub::start();
+        let (mut handler, folder) = handler_with_file(&stub, "colleague-first", b"mine", 6);
+        let message = OperationalMessage::ModifiedRemoteFile(1);
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "file.txt", 9),
+            ),
+        );
+        handler.ignore_messages.push(message.clone());
+
+        assert!(!handler.ignore_message(&message).unwrap());
+        assert!(!handler.ignore_messages.contains(&message));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn new_remote_file_fetches_its_metadata_once() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("metadata-requests");
+        let mut handler =
+            OperationalHandler::new(testing::context(&stub, &folder), testing::connection())
+                .unwrap();
+        remote_content_by(&stub, 6);
+
+        handler.new_remote_file(2).unwrap();
+
+        let count = |path: &str| {
+            let request_line = format!("GET {}", stub.workspace_path(path));
+            stub.request_lines()
+                .iter()
+                .filter(|line| **line == request_line)
+                .count()
+        };
+        assert_eq!(count("contents/2"), 1);
+        assert_eq!(count("files/2/raw/report.txt"), 1);
+        assert_eq!(stub.requests().len(), 2);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn cleared_conflict_takes_conflicting_revision_and_syncs_disk_file() {
+        let stub = TracimStub::start();
+        let (handler, folder) = handler_with_file(&stub, "clear-conflict", b"mine", 6);
+        DatabaseOperation::new(&handler.connection)
+            .insert_conflict(&Conflict {
+                relative_path: "file.txt".to_string(),
+                last_modified_timestamp: None,
+                checksum: None,
+                content_id: 1,
+                revision_id: 7,
+                detected_timestamp: 0,
+            })
+            .unwrap();
+        let (sender, receiver) = channel();
+
+        clear_conflict(&handler.connection, &sender, "file.txt".to_string()).unwrap();
+        clear_conflict(&handler.connection, &sender, "other.txt".to_string()).unwrap();
+
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert!(database_operation
+            .get_conflict("file.txt")
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![OperationalMessage::ModifiedLocalFile(
+                "file.txt".to_string()
+            )]
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn interrupted_adoption_of_differing_disk_file_is_resolved_again() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("adopt-interrupted");
+        fs::write(folder.join("file.txt"), b"mine").unwrap();
+        let mut context = testing::context(&stub, &folder);
+        context.conflict_strategy = ConflictStrategy::RemoteWins;
+        let mut handler = OperationalHandler::new(context, testing::connection()).unwrap();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "file.txt", 7),
+            ),
+        );
+        stub.on_each(
+            "GET",
+            &stub.workspace_path("files/1/raw/file.txt"),
+            vec![
+                StubResponse::json(500, "{}"),
+                StubResponse::bytes(b"theirs!"),
+            ],
+        );
+
+        assert!(handler.new_remote_file(1).is_err());
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"mine");
+        let database_operation = DatabaseOperation::new(&handler.connection);
+        assert_eq!(
+            database_operation
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            UNRESOLVED_REVISION_ID
+        );
+        assert_eq!(database_operation.get_checksum("file.txt").unwrap(), None);
+
+        handler.modified_remote_file(1).unwrap();
+
+        assert_eq!(fs::read(folder.join("file.txt")).unwrap(), b"theirs!");
+        assert_eq!(
+            DatabaseOperation::new(&handler.connection)
+                .get_revision_id_from_content_id(1)
+                .unwrap(),
+            7
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn messages_are_received_once_under_load() {
+        const SENT_BY_EACH: usize = 20_000;
+        let (sender, receiver) = channel();
+        let (local_sender, local_receiver) = sync_channel(8);
+        let signaled = Arc::new(AtomicBool::new(false));
+        // Like local watcher : wake handler up only when it was not already
+        let wakeup_sender = sender.clone();
+        let local_signaled = signaled.clone();
+        let local_producer = thread::spawn(move || {
+            for i in 0..SENT_BY_EACH {
+                local_sender
+                    .send(OperationalMessage::NewLocalFile(i.to_string()))
+                    .unwrap();
+                if !local_signaled.swap(true, Ordering::SeqCst) {
+                    wakeup_sender
+                        .send(OperationalMessage::LocalChanges)
+                        .unwrap();
+                }
+            }
+        });
+        let remote_producer = thread::spawn(move || {
+            for i in 0..SENT_BY_EACH {
+                sender
+                    .send(OperationalMessage::NewRemoteFile(i as ContentId))
+                    .unwrap();
+            }
+        });
+
+        let mut received = vec![];
+        while received.len() < SENT_BY_EACH * 2 {
+            // A lost wake up would let handler wait while messages are waiting
+            match receive(
+                &receiver,
+                &local_receiver,
+                &signaled,
+                Duration::from_secs(5),
+            ) {
+                Ok(message) => received.push(message),
+                Err(error) => panic!("{:?} after {} messages", error, received.len()),
+            }
+            received.extend(drain(&receiver, &local_receiver, &signaled, 64));
+        }
+        local_producer.join().unwrap();
+        remote_producer.join().unwrap();
+        received.extend(drain(&receiver, &local_receiver, &signaled, usize::MAX));
+
+        let unique = received.iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(received.len(), SENT_BY_EACH * 2);
+        assert_eq!(unique.len(), SENT_BY_EACH * 2);
+        assert!(!unique.contains(&OperationalMessage::LocalChanges));
+    }
+
+    #[test]
+    fn waiting_handler_is_woken_up_by_local_watcher() {
+        let (sender, receiver) = channel();
+        let (local_sender, local_receiver) = sync_channel(1);
+        let signaled = Arc::new(AtomicBool::new(false));
+        let waiting = thread::spawn(move || {
+            let started = Instant::now();
+            let received = receive(
+                &receiver,
+                &local_receiver,
+                &signaled,
+                Duration::from_secs(5),
+            );
+            (received, started.elapsed())
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        local_sender
+            .send(OperationalMessage::NewLocalFile("file.txt".to_string()))
+            .unwrap();
+        sender.send(OperationalMessage::LocalChanges).unwrap();
+        let (received, waited) = waiting.join().unwrap();
+
+        assert_eq!(
+            received,
+            Ok(OperationalMessage::NewLocalFile("file.txt".to_string()))
+        );
+        assert!(waited < Duration::from_secs(1));
     }
 }
@@ -0,0 +1,122 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Token bucket limiting requests made to Tracim. It is shared by all clients (and
+// threads) built from the same context. Bucket hold at most one token : requests
+// are evenly spaced instead of being sent by bursts.
+#[derive(Debug, Clone, Default)]
+pub struct RequestPacer {
+    bucket: Option<Arc<Mutex<Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    max_requests_per_second: f64,
+    // Can be negative : tokens already reserved by throttled requests
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RequestPacer {
+    // No limit when max requests per second is None
+    pub fn new(max_requests_per_second: Option<f64>) -> Self {
+        Self {
+            bucket: max_requests_per_second
+                .filter(|max_requests_per_second| *max_requests_per_second > 0.0)
+                .map(|max_requests_per_second| {
+                    Arc::new(Mutex::new(Bucket {
+                        max_requests_per_second,
+                        tokens: 1.0,
+                        refilled: Instant::now(),
+                    }))
+                }),
+        }
+    }
+
+    // Wait until a request can be made. Token is reserved before sleeping, so each
+    // request sleep at most once, even when several threads are throttled.
+    pub fn wait(&self) {
+        if let Some(wait) = self.reserve() {
+            log::trace!("Throttle request for {:?}", wait);
+            thread::sleep(wait);
+        }
+    }
+
+    // Same as `wait`, for async requests
+    pub async fn wait_async(&self) {
+        if let Some(wait) = self.reserve() {
+            log::trace!("Throttle request for {:?}", wait);
+            async_std::task::sleep(wait).await;
+        }
+    }
+
+    fn reserve(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.as_ref()?.lock().ok()?;
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(bucket.refilled);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * bucket.max_requests_per_second).min(1.0);
+        bucket.refilled = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                -bucket.tokens / bucket.max_requests_per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        client::Client,
+        testing::{self, StubResponse, TracimStub},
+    };
+
+    #[test]
+    fn requests_are_paced_to_max_requests_per_second() {
+        let stub = TracimStub::start();
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 1, None, "file.txt", 0),
+            ),
+        );
+        let folder = testing::temp_folder("pacing");
+        let mut context = testing::context(&stub, &folder);
+        context.request_pacer = RequestPacer::new(Some(5.0));
+        let client = Client::new(context).unwrap();
+
+        let started = Instant::now();
+        for _ in 0..20 {
+            client.get_remote_content(1).unwrap();
+        }
+        let elapsed = started.elapsed();
+
+        // First request is immediate, the 19 others are spaced by 200ms
+        assert_eq!(stub.requests().len(), 20);
+        assert!(elapsed >= Duration::from_secs(3), "{:?}", elapsed);
+        assert!(elapsed <= Duration::from_secs(4), "{:?}", elapsed);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn no_limit_never_sleeps() {
+        let request_pacer = RequestPacer::new(None);
+
+        for _ in 0..100 {
+            assert_eq!(request_pacer.reserve(), None);
+        }
+    }
+}
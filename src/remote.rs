@@ -1,44 +1,62 @@
-use crate::Error;
-use async_std::task;
+use async_std::{future, task};
 use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use futures_util::StreamExt;
 use serde_derive::{Deserialize, Serialize};
-use serde_json::Value;
-use std::str;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::str::{self, FromStr};
 
 use rusqlite::Connection;
 
 use crate::{
     client::{self, Client},
     context::Context,
-    database::DatabaseOperation,
+    database::{Database, DatabaseOperation},
+    error::{ClientError, Error},
     operation::OperationalMessage,
-    types::{ContentId, RemoteEventType, RevisionId},
+    types::{ContentId, ContentType, NameStrategy, RelativeFilePath, RemoteEventType, RevisionId},
+    util,
 };
 
+// Delay between two pollings of remote contents : reset to minimum when changes are
+// found, doubled while nothing change
+const POLLING_MIN_INTERVAL_MS: u64 = 2_000;
+const POLLING_MAX_INTERVAL_MS: u64 = 60_000;
+// Sleeps are split to regularly check if trsync is stopping
+const STOPPING_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Meta key of last remote event taken into account (see `RemoteSync::pull`)
+pub const REMOTE_CURSOR_KEY: &str = "remote_event_cursor";
+// Meta key of last pulled remote event, until operational handler proceed pulled changes
+pub const PENDING_REMOTE_CURSOR_KEY: &str = "pending_remote_event_cursor";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RemoteEvent {
-    event_id: i32,
-    event_type: String,
-    fields: Value,
+    pub event_id: i32,
+    pub event_type: String,
+    pub fields: Value,
 }
 
-impl RemoteEvent {
-    pub fn from_str(json_as_str: &str) -> Result<Self, serde_json::Error> {
-        let event: Self = match serde_json::from_str(json_as_str) {
-            Ok(event) => event,
-            Err(error) => return Err(error),
-        };
+impl FromStr for RemoteEvent {
+    type Err = serde_json::Error;
 
-        Ok(event)
+    fn from_str(json_as_str: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(json_as_str)
     }
 }
 
 pub struct RemoteWatcher {
     context: Context,
     operational_sender: Sender<OperationalMessage>,
+    // Synchronizing user, known once connected
+    user_id: Option<i32>,
 }
 
 // TODO : Must have a local db with tuple (content_id,modified_timestamp)
@@ -50,42 +68,61 @@ impl RemoteWatcher {
         Self {
             context,
             operational_sender,
+            user_id: None,
         }
     }
 
     pub fn listen(&mut self) -> Result<(), Error> {
-        task::block_on::<_, Result<(), Error>>(async {
-            let client = client::Client::new(self.context.clone())?;
-            let user_id = client.get_user_id()?;
-            let response = client.get_user_live_messages_response(user_id).await?;
-            let mut stream = response.bytes_stream();
-            while let Some(thing) = stream.next().await {
-                match &thing {
-                    Ok(lines) => match self.proceed_event_lines(lines) {
-                        Err(error) => {
-                            log::error!("Error when proceed remote event lines: {:?}", error)
+        // Index is used to recognize moved contents
+        Database::new(
+            self.context.database_path.clone(),
+            self.context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            task::block_on::<_, Result<(), Error>>(async {
+                let client = client::Client::new(self.context.clone())?;
+                let user_id = client.get_user_id()?;
+                self.user_id = Some(user_id);
+                let response = client.get_user_live_messages_response(user_id).await?;
+                let mut stream = response.bytes_stream();
+                self.context
+                    .own_events_filtered
+                    .store(self.context.filter_own_events, Ordering::SeqCst);
+                // Stream is read with a timeout to regularly check if trsync is stopping
+                while !self.context.is_stopping() {
+                    let thing = match future::timeout(Duration::from_secs(1), stream.next()).await {
+                        Ok(Some(thing)) => thing,
+                        Ok(None) => break,
+                        Err(_) => continue,
+                    };
+                    match &thing {
+                        Ok(lines) => {
+                            if let Err(error) = self.proceed_event_lines(&connection, lines) {
+                                log::error!("Error when proceed remote event lines: {:?}", error)
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Error when reading remote TLM : {:?}", err)
                         }
-                        _ => {}
-                    },
-                    Err(err) => {
-                        log::error!("Error when reading remote TLM : {:?}", err)
                     }
                 }
-            }
+                self.context
+                    .own_events_filtered
+                    .store(false, Ordering::SeqCst);
 
-            Ok(())
+                Ok(())
+            })
         })?;
 
         Ok(())
     }
 
-    fn proceed_event_lines(&self, lines: &Bytes) -> Result<(), Error> {
+    fn proceed_event_lines(&self, connection: &Connection, lines: &Bytes) -> Result<(), Error> {
         if lines.starts_with(b"event: message") {
             for line in str::from_utf8(lines)?.lines() {
-                if line.starts_with("data: ") {
-                    let json_as_str = &line[6..];
+                if let Some(json_as_str) = line.strip_prefix("data: ") {
                     match RemoteEvent::from_str(json_as_str) {
-                        Ok(remote_event) => self.proceed_remote_event(remote_event)?,
+                        Ok(remote_event) => self.proceed_remote_event(connection, remote_event)?,
                         Err(error) => {
                             log::error!(
                                 "Error when decoding event : {}. Event as str was: {}",
@@ -101,20 +138,38 @@ impl RemoteWatcher {
         Ok(())
     }
 
-    fn proceed_remote_event(&self, remote_event: RemoteEvent) -> Result<(), Error> {
+    fn proceed_remote_event(
+        &self,
+        connection: &Connection,
+        remote_event: RemoteEvent,
+    ) -> Result<(), Error> {
         log::debug!("Proceed remote event {:?}", remote_event);
 
-        if RemoteEventType::from_str(&remote_event.event_type.as_str()).is_some() {
-            let content_id =
+        if let Some(reason) = self.own_event(&remote_event) {
+            log::debug!(
+                "Ignore remote event {} ({})",
+                &remote_event.event_type.as_str(),
+                reason
+            );
+            return Ok(());
+        }
+
+        if RemoteEventType::from_str(remote_event.event_type.as_str()).is_ok() {
+            let content =
                 remote_event.fields["content"]
                     .as_object()
-                    .ok_or(Error::UnexpectedError(format!(
-                        "Remote event content not appear to not be object"
-                    )))?["content_id"]
-                    .as_i64()
-                    .ok_or(Error::UnexpectedError(format!(
-                        "Remote event content content_id appear to not be integer"
-                    )))?;
+                    .ok_or(Error::UnexpectedError(
+                        "Remote event content not appear to not be object".to_string(),
+                    ))?;
+            let content_id = content["content_id"]
+                .as_i64()
+                .ok_or(Error::UnexpectedError(
+                    "Remote event content content_id appear to not be integer".to_string(),
+                ))?;
+            let parent_id = content
+                .get("parent_id")
+                .and_then(|parent_id| parent_id.as_i64())
+                .map(|parent_id| parent_id as ContentId);
             log::info!(
                 "remote event : {:} ({})",
                 &remote_event.event_type.as_str(),
@@ -125,7 +180,11 @@ impl RemoteWatcher {
                 "content.modified.html-document"
                 | "content.modified.file"
                 | "content.modified.folder" => {
-                    OperationalMessage::ModifiedRemoteFile(content_id as i32)
+                    if content_moved(connection, content_id as ContentId, parent_id)? {
+                        OperationalMessage::MovedRemoteFile(content_id as ContentId)
+                    } else {
+                        OperationalMessage::ModifiedRemoteFile(content_id as i32)
+                    }
                 }
                 "content.created.html-document"
                 | "content.created.file"
@@ -147,15 +206,13 @@ impl RemoteWatcher {
                     )))
                 }
             };
-            match self.operational_sender.send(message) {
-                Ok(_) => (),
-                // FIXME : stop trsync
-                Err(err) => {
-                    log::error!(
-                        "Error when send operational message from remote watcher : {}",
-                        err
-                    )
-                }
+            self.context.metrics.remote_event();
+            // FIXME : stop trsync
+            if let Err(err) = self.operational_sender.send(message) {
+                log::error!(
+                    "Error when send operational message from remote watcher : {}",
+                    err
+                )
             };
         } else {
             log::debug!(
@@ -166,6 +223,154 @@ impl RemoteWatcher {
 
         Ok(())
     }
+
+    // Event is an echo of a change made by this trsync : by its client token (exact) or
+    // by its author (when own events are filtered)
+    fn own_event(&self, remote_event: &RemoteEvent) -> Option<&'static str> {
+        if remote_event.fields["client_token"].as_str() == Some(&self.context.client_token) {
+            // Tracim echoes client token : all echoes of our changes will be dropped
+            self.context
+                .own_events_filtered
+                .store(true, Ordering::SeqCst);
+            return Some("own request");
+        }
+        let author_id = remote_event.fields["author"]["user_id"].as_i64();
+        if self.context.filter_own_events
+            && author_id.is_some()
+            && author_id == self.user_id.map(|user_id| user_id as i64)
+        {
+            return Some("own user");
+        }
+        None
+    }
+}
+
+// Watch remote changes by periodically listing remote contents, for Tracim instances
+// without live messages
+pub struct PollingRemoteWatcher {
+    context: Context,
+    operational_sender: Sender<OperationalMessage>,
+    // Revision of each remote content at previous polling
+    known_revisions: HashMap<ContentId, RevisionId>,
+    interval: Duration,
+}
+
+impl PollingRemoteWatcher {
+    pub fn new(context: Context, operational_sender: Sender<OperationalMessage>) -> Self {
+        Self {
+            context,
+            operational_sender,
+            known_revisions: HashMap::new(),
+            interval: Duration::from_millis(POLLING_MIN_INTERVAL_MS),
+        }
+    }
+
+    pub fn listen(&mut self) -> Result<(), Error> {
+        // Index is used to recognize moved contents
+        Database::new(
+            self.context.database_path.clone(),
+            self.context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let client = client::Client::new(self.context.clone())?;
+            // First polling is compared with index : changes made before it (as when
+            // polling replaces ended live messages) are not lost. Changes already found
+            // by remote sync can be reported again, they are then ignored.
+            self.known_revisions = DatabaseOperation::new(&connection).get_revisions()?;
+
+            loop {
+                let polling_at = Instant::now() + self.interval;
+                while Instant::now() < polling_at {
+                    if self.context.is_stopping() {
+                        return Ok(());
+                    }
+                    thread::sleep(
+                        STOPPING_CHECK_INTERVAL
+                            .min(polling_at.saturating_duration_since(Instant::now())),
+                    );
+                }
+                let changes_count = match client.get_remote_contents(None) {
+                    Ok(contents) => self.proceed_contents(&connection, &contents)?,
+                    Err(error) => {
+                        log::error!("Error when polling remote contents : {:?}", error);
+                        0
+                    }
+                };
+                self.interval = if changes_count > 0 {
+                    Duration::from_millis(POLLING_MIN_INTERVAL_MS)
+                } else {
+                    (self.interval * 2).min(Duration::from_millis(POLLING_MAX_INTERVAL_MS))
+                };
+            }
+        })
+    }
+
+    // Send operational messages for differences with previous polling, return the
+    // count of changes
+    fn proceed_contents(
+        &mut self,
+        connection: &Connection,
+        contents: &[RemoteContent],
+    ) -> Result<usize, Error> {
+        let mut messages = vec![];
+        let mut appeared_contents = vec![];
+
+        for content in contents {
+            match self.known_revisions.get(&content.content_id) {
+                None => appeared_contents.push(content),
+                Some(revision_id) if *revision_id != content.current_revision_id => {
+                    if content_moved(connection, content.content_id, content.parent_id)? {
+                        messages.push(OperationalMessage::MovedRemoteFile(content.content_id))
+                    } else {
+                        messages.push(OperationalMessage::ModifiedRemoteFile(content.content_id))
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let revisions = contents_revisions(contents);
+        let mut deleted_content_ids: Vec<ContentId> = self
+            .known_revisions
+            .keys()
+            .filter(|content_id| !revisions.contains_key(content_id))
+            .cloned()
+            .collect();
+        deleted_content_ids.sort_unstable();
+        let relocations = relocated_contents(
+            &self.context,
+            connection,
+            &deleted_content_ids,
+            &appeared_contents,
+        )?;
+        messages.extend(relocations_messages(
+            &relocations,
+            &deleted_content_ids,
+            &appeared_contents,
+        ));
+        self.known_revisions = revisions;
+
+        let changes_count = messages.len();
+        for message in messages {
+            log::info!("remote polling : {:?}", &message);
+            self.context.metrics.remote_event();
+            if let Err(error) = self.operational_sender.send(message) {
+                log::error!(
+                    "Error when send operational message from remote polling : {}",
+                    error
+                )
+            }
+        }
+
+        Ok(changes_count)
+    }
+}
+
+fn contents_revisions(contents: &[RemoteContent]) -> HashMap<ContentId, RevisionId> {
+    contents
+        .iter()
+        .map(|content| (content.content_id, content.current_revision_id))
+        .collect()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -176,11 +381,351 @@ pub struct RemoteContent {
     pub content_type: String,
     pub modified: String,
     pub filename: String,
+    pub label: String,
     pub is_deleted: bool,
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub workspace_id: Option<i32>,
+    #[serde(default)]
+    pub author: Option<RemoteUser>,
+    #[serde(default)]
+    pub last_modifier: Option<RemoteUser>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoteUser {
+    pub user_id: i32,
+}
+
+impl RemoteContent {
+    // User who made current revision : its last modifier, or its author when Tracim
+    // do not give it
+    pub fn revision_author_id(&self) -> Option<i32> {
+        self.last_modifier
+            .as_ref()
+            .or(self.author.as_ref())
+            .map(|user| user.user_id)
+    }
+
+    // Content moved to another workspace left the synchronized tree
+    pub fn in_workspace(&self, workspace_id: i32) -> bool {
+        self.workspace_id
+            .map(|workspace_id_| workspace_id_ == workspace_id)
+            .unwrap_or(true)
+    }
+
+    // Name of this content on disk, according to name strategy. Names too long for
+    // disk are truncated (see `util::limit_file_name`).
+    pub fn local_name(&self, context: &Context) -> String {
+        util::limit_file_name(&self.name(&context.name_strategy), context.max_name_bytes)
+    }
+
+    fn name(&self, name_strategy: &NameStrategy) -> String {
+        match name_strategy {
+            NameStrategy::Filename => self.filename.clone(),
+            NameStrategy::Label => self.label.clone(),
+            NameStrategy::LabelWithExtension => {
+                match Path::new(&self.filename)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                {
+                    Some(extension)
+                        if self.content_type != "folder"
+                            && !self.label.ends_with(&format!(".{}", extension)) =>
+                    {
+                        format!("{}.{}", self.label, extension)
+                    }
+                    _ => self.label.clone(),
+                }
+            }
+        }
+    }
+}
+
+// Remote contents organized by parent, to browse workspace without requesting each
+// content parent
+pub struct RemoteTree {
+    contents: HashMap<ContentId, RemoteContent>,
+    children: HashMap<Option<ContentId>, Vec<ContentId>>,
+}
+
+impl RemoteTree {
+    pub fn new(contents: Vec<RemoteContent>) -> Self {
+        let mut children: HashMap<Option<ContentId>, Vec<ContentId>> = HashMap::new();
+        for content in &contents {
+            children
+                .entry(content.parent_id)
+                .or_default()
+                .push(content.content_id);
+        }
+        let contents: HashMap<ContentId, RemoteContent> = contents
+            .into_iter()
+            .map(|content| (content.content_id, content))
+            .collect();
+        for content_ids in children.values_mut() {
+            content_ids.sort_by(|a, b| contents[a].filename.cmp(&contents[b].filename));
+        }
+
+        Self { contents, children }
+    }
+
+    // Children of given parent (workspace root for None), sorted by file name
+    pub fn children(&self, parent_id: Option<ContentId>) -> Vec<&RemoteContent> {
+        self.children
+            .get(&parent_id)
+            .map(|content_ids| {
+                content_ids
+                    .iter()
+                    .map(|content_id| &self.contents[content_id])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Path of content built from its parents, None if one of them is not in the tree
+    pub fn relative_path(
+        &self,
+        content_id: ContentId,
+        context: &Context,
+    ) -> Option<RelativeFilePath> {
+        let mut content = self.contents.get(&content_id)?;
+        let mut path_parts = vec![content.local_name(context)];
+        while let Some(parent_id) = content.parent_id {
+            content = self.contents.get(&parent_id)?;
+            path_parts.push(content.local_name(context));
+        }
+        path_parts.reverse();
+        util::path_to_string(&path_parts.iter().collect::<std::path::PathBuf>()).ok()
+    }
+
+    // Contents which parent is not in the tree (parent is not a folder for example)
+    pub fn orphans(&self) -> Vec<&RemoteContent> {
+        let mut orphans: Vec<&RemoteContent> = self
+            .contents
+            .values()
+            .filter(|content| match content.parent_id {
+                Some(parent_id) => !self.contents.contains_key(&parent_id),
+                None => false,
+            })
+            .collect();
+        orphans.sort_by_key(|content| content.content_id);
+        orphans
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "contents": self.children_json(None),
+            "orphans": self
+                .orphans()
+                .iter()
+                .map(|content| self.content_json(content))
+                .collect::<Vec<Value>>(),
+        })
+    }
+
+    fn children_json(&self, parent_id: Option<ContentId>) -> Vec<Value> {
+        self.children(parent_id)
+            .iter()
+            .map(|content| self.content_json(content))
+            .collect()
+    }
+
+    fn content_json(&self, content: &RemoteContent) -> Value {
+        json!({
+            "content_id": content.content_id,
+            "revision_id": content.current_revision_id,
+            "content_type": content.content_type,
+            "filename": content.filename,
+            "label": content.label,
+            "size": content.size,
+            "modified": content.modified,
+            "children": self.children_json(Some(content.content_id)),
+        })
+    }
+
+    fn fmt_children(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        parent_id: Option<ContentId>,
+        depth: usize,
+    ) -> fmt::Result {
+        for content in self.children(parent_id) {
+            fmt_content(f, content, depth)?;
+            self.fmt_children(f, Some(content.content_id), depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_content(f: &mut fmt::Formatter<'_>, content: &RemoteContent, depth: usize) -> fmt::Result {
+    writeln!(
+        f,
+        "{}{}{} (content_id={}, revision_id={}, {}, size={}, modified={})",
+        "    ".repeat(depth),
+        content.filename,
+        if content.content_type == "folder" {
+            "/"
+        } else {
+            ""
+        },
+        content.content_id,
+        content.current_revision_id,
+        content.content_type,
+        content
+            .size
+            .map(|size| size.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        content.modified
+    )
+}
+
+impl fmt::Display for RemoteTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_children(f, None, 0)?;
+
+        let orphans = self.orphans();
+        if !orphans.is_empty() {
+            writeln!(f, "Contents without listed parent :")?;
+            for content in orphans {
+                fmt_content(f, content, 1)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Content is moved when its remote parent is not the parent of its indexed path
+fn content_moved(
+    connection: &Connection,
+    content_id: ContentId,
+    parent_id: Option<ContentId>,
+) -> Result<bool, Error> {
+    let database_operation = DatabaseOperation::new(connection);
+    let relative_path = match database_operation.get_path_from_content_id(content_id) {
+        Ok(relative_path) => relative_path,
+        // Unknown content will be considered as new
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+        Err(error) => return Err(Error::from(error)),
+    };
+
+    let indexed_parent_id = match Path::new(&relative_path).parent() {
+        Some(parent_path) if parent_path != Path::new("") => {
+            match database_operation.get_content_id_from_path(util::path_to_string(parent_path)?) {
+                Ok(parent_content_id) => Some(parent_content_id),
+                Err(Error::UnIndexedRelativePath(_)) => return Ok(true),
+                Err(error) => return Err(error),
+            }
+        }
+        _ => None,
+    };
+
+    Ok(indexed_parent_id != parent_id)
+}
+
+// Contents which vanished from remote while a content with same name and size appeared
+// are considered as moved : this happen when a tool move a file by recreating it.
+// Tracim does not expose checksums, so size of indexed disk file is compared (handler
+// checks bytes before adopting disk file). Ambiguous matches are ignored, these
+// contents will be deleted and downloaded.
+fn relocated_contents(
+    context: &Context,
+    connection: &Connection,
+    vanished_content_ids: &[ContentId],
+    appeared_contents: &[&RemoteContent],
+) -> Result<HashMap<ContentId, ContentId>, Error> {
+    let database_operation = DatabaseOperation::new(connection);
+    let mut candidates: Vec<(ContentId, ContentId)> = vec![];
+
+    for vanished_content_id in vanished_content_ids {
+        let relative_path = match database_operation.get_path_from_content_id(*vanished_content_id)
+        {
+            Ok(relative_path) => relative_path,
+            Err(rusqlite::Error::QueryReturnedNoRows) => continue,
+            Err(error) => return Err(Error::from(error)),
+        };
+        let absolute_path = Path::new(&context.folder_path).join(&relative_path);
+        let size = match fs::metadata(util::long_path(&absolute_path)) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => continue,
+        };
+        let file_name = match absolute_path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+
+        let matches: Vec<ContentId> = appeared_contents
+            .iter()
+            .filter(|content| {
+                content.content_type != "folder"
+                    && content.size == Some(size)
+                    && content.local_name(context) == file_name
+            })
+            .map(|content| content.content_id)
+            .collect();
+        if let [appeared_content_id] = matches[..] {
+            candidates.push((*vanished_content_id, appeared_content_id));
+        }
+    }
+
+    // An appeared content can be the relocation of only one vanished content
+    let mut appeared_counts: HashMap<ContentId, usize> = HashMap::new();
+    for (_, appeared_content_id) in &candidates {
+        *appeared_counts.entry(*appeared_content_id).or_insert(0) += 1;
+    }
+    Ok(candidates
+        .into_iter()
+        .filter(|(_, appeared_content_id)| appeared_counts[appeared_content_id] == 1)
+        .collect())
+}
+
+// Messages for appeared and vanished contents : new contents, then relocations, then
+// deletions (so that a relocated file is moved before its old parent is deleted)
+fn relocations_messages(
+    relocations: &HashMap<ContentId, ContentId>,
+    vanished_content_ids: &[ContentId],
+    appeared_contents: &[&RemoteContent],
+) -> Vec<OperationalMessage> {
+    let relocated_content_ids: HashSet<ContentId> = relocations.values().cloned().collect();
+    let mut messages: Vec<OperationalMessage> = appeared_contents
+        .iter()
+        .filter(|content| !relocated_content_ids.contains(&content.content_id))
+        .map(|content| OperationalMessage::NewRemoteFile(content.content_id))
+        .collect();
+    for vanished_content_id in vanished_content_ids {
+        if let Some(appeared_content_id) = relocations.get(vanished_content_id) {
+            log::info!(
+                "Content {} vanished while content {} appeared, consider it as moved",
+                vanished_content_id,
+                appeared_content_id
+            );
+            messages.push(OperationalMessage::RelocatedRemoteFile(
+                *vanished_content_id,
+                *appeared_content_id,
+            ));
+        }
+    }
+    for vanished_content_id in vanished_content_ids {
+        if !relocations.contains_key(vanished_content_id) {
+            messages.push(OperationalMessage::DeletedRemoteFile(*vanished_content_id));
+        }
+    }
+    messages
+}
+
+// Changes pulled at startup have been proceed : next startup can pull changes after them.
+// Until then, a crash or a stop pull them again.
+pub fn commit_remote_cursor(connection: &Connection) -> Result<(), Error> {
+    let database_operation = DatabaseOperation::new(connection);
+    if let Some(cursor) = database_operation.get_meta(PENDING_REMOTE_CURSOR_KEY)? {
+        database_operation.set_meta(REMOTE_CURSOR_KEY, &cursor)?;
+        database_operation.delete_meta(PENDING_REMOTE_CURSOR_KEY)?;
+    }
+    Ok(())
 }
 
 pub struct RemoteSync {
-    _context: Context,
+    context: Context,
     connection: Connection,
     client: Client,
     operational_sender: Sender<OperationalMessage>,
@@ -193,7 +738,7 @@ impl RemoteSync {
         operational_sender: Sender<OperationalMessage>,
     ) -> Result<Self, Error> {
         Ok(Self {
-            _context: context.clone(),
+            context: context.clone(),
             connection,
             client: Client::new(context)?,
             operational_sender,
@@ -201,69 +746,346 @@ impl RemoteSync {
     }
 
     pub fn sync(&mut self) -> Result<(), Error> {
-        let contents = self.client.get_remote_contents(None)?;
+        // Trashed contents (if listed) are considered as deleted
+        let contents: Vec<RemoteContent> = self
+            .client
+            .get_remote_contents(None)?
+            .into_iter()
+            .filter(|content| !content.is_deleted)
+            .collect();
         let remote_content_ids: Vec<i32> = contents.iter().map(|c| c.content_id).collect();
+        let mut appeared_contents = vec![];
 
         for content in &contents {
-            match DatabaseOperation::new(&self.connection)
-                .get_revision_id_from_content_id(content.content_id)
+            if !self.compare_known_content(content)? {
+                appeared_contents.push(content)
+            }
+        }
+
+        // Search for remote deleted files
+        let vanished_content_ids: Vec<ContentId> = DatabaseOperation::new(&self.connection)
+            .get_content_ids()?
+            .into_iter()
+            .filter(|content_id| !remote_content_ids.contains(content_id))
+            .collect();
+        let relocations = relocated_contents(
+            &self.context,
+            &self.connection,
+            &vanished_content_ids,
+            &appeared_contents,
+        )?;
+        for message in relocations_messages(&relocations, &vanished_content_ids, &appeared_contents)
+        {
+            self.send(message)
+        }
+
+        Ok(())
+    }
+
+    // Search remote changes since last seen event when possible, by listing all remote
+    // contents otherwise. Last seen event is then remembered for next startup, once
+    // operational handler proceed changes (see `commit_remote_cursor`).
+    pub fn pull(&mut self) -> Result<(), Error> {
+        if !self.context.remote_since {
+            return self.sync();
+        }
+
+        let cursor = DatabaseOperation::new(&self.connection)
+            .get_meta(REMOTE_CURSOR_KEY)?
+            .and_then(|cursor| cursor.parse::<i32>().ok());
+        if let Some(cursor) = cursor {
+            match self.incremental(cursor) {
+                Ok(()) => return Ok(()),
+                Err(error) => log::warn!(
+                    "Unable to pull remote changes since event {} ({:?}), fallback on full synchronization",
+                    cursor,
+                    error
+                ),
+            }
+        }
+
+        // Events happening during listing will be pulled again next time, which is
+        // harmless as only revisions different from index produce messages
+        let last_event_id = match self
+            .client
+            .get_user_id()
+            .and_then(|user_id| self.client.get_last_event_id(user_id))
+        {
+            Ok(last_event_id) => last_event_id,
+            Err(error) => {
+                log::warn!("Unable to get last remote event : {:?}", error);
+                None
+            }
+        };
+        self.sync()?;
+        if let Some(last_event_id) = last_event_id {
+            DatabaseOperation::new(&self.connection)
+                .set_meta(PENDING_REMOTE_CURSOR_KEY, &last_event_id.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    // Compare with index only contents concerned by events after given one. Fail if
+    // cursor can't be used (remote reset, events not listed) so that caller fallback on
+    // a full synchronization.
+    pub fn incremental(&mut self, since_cursor: i32) -> Result<(), Error> {
+        let user_id = self.client.get_user_id()?;
+        let last_event_id = self.client.get_last_event_id(user_id)?;
+        if since_cursor > last_event_id.unwrap_or(0) {
+            return Err(Error::UnexpectedError(format!(
+                "Event cursor {} is ahead of last remote event {:?}",
+                since_cursor, last_event_id
+            )));
+        }
+
+        let events = self.client.get_events_since(user_id, since_cursor)?;
+        let mut content_ids = vec![];
+        for event in &events {
+            if RemoteEventType::from_str(&event.event_type).is_err() {
+                continue;
+            }
+            let content_id = match event.fields["content"]["content_id"].as_i64() {
+                Some(content_id) => content_id as ContentId,
+                None => continue,
+            };
+            // Contents moved out of workspace are known ones
+            let in_workspace = event.fields["content"]["workspace_id"].as_i64()
+                == Some(self.context.workspace_id as i64);
+            if !content_ids.contains(&content_id)
+                && (in_workspace
+                    || DatabaseOperation::new(&self.connection).content_id_is_known(content_id)?)
             {
-                Ok(known_revision_id) => {
-                    // File is known but have been modified ?
-                    if known_revision_id != content.current_revision_id {
-                        match self
-                            .operational_sender
-                            .send(OperationalMessage::ModifiedRemoteFile(content.content_id))
-                        {
-                            Err(error) => {
-                                log::error!(
-                                    "Error when send operational message from remote sync : {}",
-                                    error
-                                )
-                            }
-                            _ => {}
-                        }
+                content_ids.push(content_id)
+            }
+        }
+        log::info!(
+            "Pull {} remote changes since event {} ({} events)",
+            content_ids.len(),
+            since_cursor,
+            events.len()
+        );
+
+        for content_id in content_ids {
+            let content = match self.client.get_remote_content(content_id) {
+                Ok(content) => Some(content),
+                Err(ClientError::NotFoundResponse(_)) => None,
+                Err(error) => return Err(error.into()),
+            }
+            .filter(|content| {
+                !content.is_deleted
+                    && content.in_workspace(self.context.workspace_id)
+                    && ContentType::from_str(&content.content_type).is_ok()
+            });
+            match content {
+                Some(content) => {
+                    if !self.compare_known_content(&content)? {
+                        self.send(OperationalMessage::NewRemoteFile(content_id))
                     }
                 }
-                Err(rusqlite::Error::QueryReturnedNoRows) => {
-                    match self
-                        .operational_sender
-                        .send(OperationalMessage::NewRemoteFile(content.content_id))
-                    {
-                        Err(error) => {
-                            log::error!(
-                                "Error when send operational message from remote sync : {}",
-                                error
-                            )
-                        }
-                        _ => {}
+                None => {
+                    if DatabaseOperation::new(&self.connection).content_id_is_known(content_id)? {
+                        self.send(OperationalMessage::DeletedRemoteFile(content_id))
                     }
                 }
-                Err(error) => {
-                    log::error!("Error when comparing revision : {}", error)
-                }
             }
         }
 
-        // Search for remote deleted files
-        let content_ids = DatabaseOperation::new(&self.connection).get_content_ids()?;
-        for content_id in &content_ids {
-            if !remote_content_ids.contains(content_id) {
-                match self
-                    .operational_sender
-                    .send(OperationalMessage::DeletedRemoteFile(*content_id))
-                {
-                    Err(error) => {
-                        log::error!(
-                            "Error when send operational message from remote sync : {}",
-                            error
-                        )
-                    }
-                    _ => {}
+        let cursor = events
+            .iter()
+            .map(|event| event.event_id)
+            .chain(last_event_id)
+            .max()
+            .unwrap_or(since_cursor);
+        DatabaseOperation::new(&self.connection)
+            .set_meta(PENDING_REMOTE_CURSOR_KEY, &cursor.to_string())?;
+
+        Ok(())
+    }
+
+    // Send message if indexed content have another revision. Return false if content
+    // is not indexed.
+    fn compare_known_content(&self, content: &RemoteContent) -> Result<bool, Error> {
+        match DatabaseOperation::new(&self.connection)
+            .get_revision_id_from_content_id(content.content_id)
+        {
+            Ok(known_revision_id) => {
+                // File is known but have been modified ?
+                if known_revision_id != content.current_revision_id {
+                    let message = if content_moved(
+                        &self.connection,
+                        content.content_id,
+                        content.parent_id,
+                    )? {
+                        OperationalMessage::MovedRemoteFile(content.content_id)
+                    } else {
+                        OperationalMessage::ModifiedRemoteFile(content.content_id)
+                    };
+                    self.send(message)
                 }
             }
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(error) => {
+                log::error!("Error when comparing revision : {}", error)
+            }
         }
+        Ok(true)
+    }
 
-        Ok(())
+    fn send(&self, message: OperationalMessage) {
+        if let Err(error) = self.operational_sender.send(message) {
+            log::error!(
+                "Error when send operational message from remote sync : {}",
+                error
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Receiver};
+
+    use super::*;
+    use crate::testing::{self, StubResponse, TracimStub};
+
+    // Remote sync pulling changes after event 10 : Tracim knows event 11 (indexed content
+    // 1 modified) and event 12 (not about contents)
+    fn remote_sync_after_event_10(
+        stub: &TracimStub,
+        folder: &Path,
+    ) -> (RemoteSync, Receiver<OperationalMessage>) {
+        stub.on(
+            "GET",
+            "/api/auth/whoami",
+            StubResponse::json(200, r#"{"user_id": 1}"#),
+        );
+        let events = json!({
+            "has_next": false,
+            "has_previous": false,
+            "next_page_token": "",
+            "per_page": 2,
+            "previous_page_token": "",
+            "items": [
+                {
+                    "event_id": 11,
+                    "event_type": "content.modified.file",
+                    "fields": {"content": {"content_id": 1, "workspace_id": 1}},
+                },
+                {"event_id": 12, "event_type": "user.modified", "fields": {}},
+            ],
+        });
+        stub.on(
+            "GET",
+            "/api/users/1/messages",
+            StubResponse::json(200, &events.to_string()),
+        );
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents/1"),
+            StubResponse::json(
+                200,
+                &testing::remote_content_json(1, 7, None, "file.txt", 6),
+            ),
+        );
+        let mut context = testing::context(stub, folder);
+        context.remote_since = true;
+        let connection = testing::connection();
+        let database_operation = DatabaseOperation::new(&connection);
+        database_operation
+            .insert_new_file("file.txt".to_string(), 0, 1, 6, None, false)
+            .unwrap();
+        database_operation
+            .set_meta(REMOTE_CURSOR_KEY, "10")
+            .unwrap();
+        let (operational_sender, operational_receiver) = channel();
+        (
+            RemoteSync::new(context, connection, operational_sender).unwrap(),
+            operational_receiver,
+        )
+    }
+
+    fn meta(remote_sync: &RemoteSync, key: &str) -> Option<String> {
+        DatabaseOperation::new(&remote_sync.connection)
+            .get_meta(key)
+            .unwrap()
+    }
+
+    #[test]
+    fn pulled_changes_are_pulled_again_until_committed() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("remote-cursor");
+        let (mut remote_sync, operational_receiver) = remote_sync_after_event_10(&stub, &folder);
+
+        remote_sync.pull().unwrap();
+        // Stopped before operational handler proceed pulled changes
+        remote_sync.pull().unwrap();
+        let cursor_before_commit = meta(&remote_sync, REMOTE_CURSOR_KEY);
+        commit_remote_cursor(&remote_sync.connection).unwrap();
+
+        assert_eq!(
+            operational_receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                OperationalMessage::ModifiedRemoteFile(1),
+                OperationalMessage::ModifiedRemoteFile(1),
+            ]
+        );
+        assert_eq!(cursor_before_commit, Some("10".to_string()));
+        assert_eq!(
+            meta(&remote_sync, REMOTE_CURSOR_KEY),
+            Some("12".to_string())
+        );
+        assert_eq!(meta(&remote_sync, PENDING_REMOTE_CURSOR_KEY), None);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn polling_reports_changes_made_before_first_listing() {
+        let stub = TracimStub::start();
+        let folder = testing::temp_folder("polling-seed");
+        let mut context = testing::context(&stub, &folder);
+        context.database_path =
+            util::path_to_string(&context.metadata_dir().join("index.db")).unwrap();
+        Database::new(
+            context.database_path.clone(),
+            context.database_synchronous.clone(),
+        )
+        .with_new_connection(|connection| {
+            let database_operation = DatabaseOperation::new(&connection);
+            database_operation.create_tables()?;
+            database_operation.insert_new_file("file.txt".to_string(), 0, 1, 6, None, false)?;
+            Ok(())
+        })
+        .unwrap();
+        // Content was modified while nothing watched remote
+        let contents = json!({
+            "has_next": false,
+            "has_previous": false,
+            "items": [
+                serde_json::from_str::<Value>(&testing::remote_content_json(
+                    1, 7, None, "file.txt", 6
+                ))
+                .unwrap()
+            ],
+            "next_page_token": "",
+            "per_page": 1,
+            "previous_page_token": "",
+        });
+        stub.on(
+            "GET",
+            &stub.workspace_path("contents"),
+            StubResponse::json(200, &contents.to_string()),
+        );
+        let (operational_sender, operational_receiver) = channel();
+        let mut watcher = PollingRemoteWatcher::new(context.clone(), operational_sender);
+        let listening = thread::spawn(move || watcher.listen());
+
+        let message =
+            operational_receiver.recv_timeout(Duration::from_millis(POLLING_MIN_INTERVAL_MS * 3));
+        context.stopping.store(true, Ordering::SeqCst);
+        listening.join().unwrap().unwrap();
+
+        assert_eq!(message, Ok(OperationalMessage::ModifiedRemoteFile(1)));
+        fs::remove_dir_all(folder).unwrap();
     }
 }
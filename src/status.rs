@@ -0,0 +1,446 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    client::Client,
+    context::Context,
+    database::{DatabaseOperation, Failure, Rejection},
+    error::Error,
+    local::LocalSync,
+    operation::OperationalMessage,
+    remote::RemoteTree,
+    timing::OperationTimings,
+    types::{ContentId, LastModifiedTimestamp, RelativeFilePath},
+};
+
+// Synchronization state shared between threads, to report what trsync did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Status {
+    pub resolved_conflicts: u64,
+    pub phase: Phase,
+    // Last time an operation succeeded
+    pub last_success_timestamp: Option<LastModifiedTimestamp>,
+    // Messages waiting to be proceed by operational handler (including retries)
+    pub queue_depth: usize,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    pub paused: bool,
+    // Durations of proceed operations, by kind of operation
+    pub timings: BTreeMap<String, OperationTimings>,
+    // Local clock minus Tracim server clock, measured at startup
+    pub clock_skew_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    #[default]
+    Starting,
+    // Startup comparison of disk, index and remote
+    Synchronizing,
+    Watching,
+    Stopped,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Starting => write!(f, "starting"),
+            Phase::Synchronizing => write!(f, "synchronizing"),
+            Phase::Watching => write!(f, "watching"),
+            Phase::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+pub type SharedStatus = Arc<Mutex<Status>>;
+
+pub const EXIT_CODE_CLEAN: i32 = 0;
+pub const EXIT_CODE_WITH_ERRORS: i32 = 1;
+pub const EXIT_CODE_FATAL: i32 = 2;
+
+// Outcome of operations during this run, to summarize it at exit
+#[derive(Debug, Clone, Default)]
+pub struct RunReport {
+    // Errors of operations which finally failed, by path (or message when no path)
+    pub failures: BTreeMap<String, String>,
+    // Conflicts left to user, by path
+    pub conflicts: BTreeMap<RelativeFilePath, ContentId>,
+    // Operations still waiting (retry, remote quota) at exit
+    pub pending: usize,
+}
+
+impl RunReport {
+    pub fn failed(&mut self, key: String, error: String) {
+        self.failures.insert(key, error);
+    }
+
+    pub fn conflicted(&mut self, relative_path: RelativeFilePath, content_id: ContentId) {
+        self.conflicts.insert(relative_path, content_id);
+    }
+
+    // A later success of the same path replaces its previous failure or conflict
+    pub fn succeeded(&mut self, key: &str) {
+        self.failures.remove(key);
+        self.conflicts.remove(key);
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.failures.is_empty() && self.conflicts.is_empty() && self.pending == 0 {
+            EXIT_CODE_CLEAN
+        } else {
+            EXIT_CODE_WITH_ERRORS
+        }
+    }
+
+    pub fn to_json(&self, fatal_error: Option<&Error>) -> Value {
+        let exit_code = match fatal_error {
+            Some(_) => EXIT_CODE_FATAL,
+            None => self.exit_code(),
+        };
+        json!({
+            "exit_code": exit_code,
+            "fatal_error": fatal_error.map(|error| format!("{:?}", error)),
+            "failures": self
+                .failures
+                .iter()
+                .map(|(path, error)| json!({"path": path, "error": error}))
+                .collect::<Vec<_>>(),
+            "conflicts": self
+                .conflicts
+                .iter()
+                .map(|(path, content_id)| {
+                    json!({"path": path, "content_id": content_id})
+                })
+                .collect::<Vec<_>>(),
+            "pending": self.pending,
+        })
+    }
+
+    pub fn write(&self, file_path: &Path, fatal_error: Option<&Error>) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(&self.to_json(fatal_error))
+            .map_err(|error| Error::UnexpectedError(format!("{:?}", error)))?;
+        fs::write(file_path, content).map_err(|error| {
+            Error::UnexpectedError(format!(
+                "Unable to write report file {:?} : {:?}",
+                file_path, error
+            ))
+        })
+    }
+}
+
+pub type SharedReport = Arc<Mutex<RunReport>>;
+
+// Differences between index, disk and (optionally) remote, computed without
+// synchronizing anything
+#[derive(Debug, Default)]
+pub struct WorkspaceStatus {
+    pub locally_new: Vec<RelativeFilePath>,
+    pub locally_modified: Vec<RelativeFilePath>,
+    pub locally_deleted: Vec<RelativeFilePath>,
+    pub conflicts: Vec<RelativeFilePath>,
+    // Paths which operations keep failing, skipped once failures reach the limit
+    pub failing: Vec<Failure>,
+    // New local files refused by Tracim, skipped until they change
+    pub rejected: Vec<Rejection>,
+    // Paths excluded from synchronization, their changes are not listed
+    pub excluded: Vec<RelativeFilePath>,
+    // Remote parts are only filled when remote is compared
+    pub remotely_modified: Vec<RelativeFilePath>,
+    pub remotely_new: Vec<String>,
+    pub remotely_deleted: Vec<RelativeFilePath>,
+}
+
+impl WorkspaceStatus {
+    // Connection is expected to be read only : nothing is written on disk or in index
+    pub fn new(context: Context, connection: Connection, remote: bool) -> Result<Self, Error> {
+        let mut status = Self::default();
+
+        let database_operation = DatabaseOperation::new(&connection);
+        if database_operation.table_exists("conflict")? {
+            status.conflicts = database_operation
+                .get_conflicts()?
+                .into_iter()
+                .map(|conflict| conflict.relative_path)
+                .collect();
+        }
+        if database_operation.table_exists("failure")? {
+            status.failing = database_operation.get_failures()?;
+        }
+        if database_operation.table_exists("rejection")? {
+            status.rejected = database_operation.get_rejections()?;
+        }
+        if database_operation.table_exists("exclusion")? {
+            status.excluded = database_operation.get_exclusions()?;
+        }
+        if remote {
+            status.compare_remote(&context, &connection)?;
+        }
+
+        for change in LocalSync::new_read_only(context, connection)?.changes()? {
+            match change {
+                OperationalMessage::NewLocalFile(relative_path)
+                | OperationalMessage::ModifiedLocalFile(relative_path)
+                | OperationalMessage::DeletedLocalFile(relative_path)
+                    if status.is_excluded(&relative_path) => {}
+                OperationalMessage::NewLocalFile(relative_path)
+                    if !status
+                        .rejected
+                        .iter()
+                        .any(|rejection| rejection.relative_path == relative_path) =>
+                {
+                    status.locally_new.push(relative_path)
+                }
+                OperationalMessage::ModifiedLocalFile(relative_path) => {
+                    status.locally_modified.push(relative_path)
+                }
+                OperationalMessage::DeletedLocalFile(relative_path) => {
+                    status.locally_deleted.push(relative_path)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn compare_remote(&mut self, context: &Context, connection: &Connection) -> Result<(), Error> {
+        let database_operation = DatabaseOperation::new(connection);
+        let contents = Client::new(context.clone())?.get_remote_contents(None)?;
+        let remote_content_ids: Vec<ContentId> =
+            contents.iter().map(|content| content.content_id).collect();
+        let tree = RemoteTree::new(contents.clone());
+
+        for content in &contents {
+            match database_operation.get_revision_id_from_content_id(content.content_id) {
+                Ok(revision_id) if revision_id != content.current_revision_id => {
+                    let relative_path =
+                        database_operation.get_path_from_content_id(content.content_id)?;
+                    if !self.is_excluded(&relative_path) {
+                        self.remotely_modified.push(relative_path)
+                    }
+                }
+                Ok(_) => {}
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    match tree.relative_path(content.content_id, context) {
+                        Some(relative_path) if self.is_excluded(&relative_path) => {}
+                        Some(relative_path) => self.remotely_new.push(relative_path),
+                        None => self
+                            .remotely_new
+                            .push(format!("{} ({})", content.filename, content.content_id)),
+                    }
+                }
+                Err(error) => return Err(Error::from(error)),
+            }
+        }
+        for content_id in database_operation.get_content_ids()? {
+            if !remote_content_ids.contains(&content_id) {
+                let relative_path = database_operation.get_path_from_content_id(content_id)?;
+                if !self.is_excluded(&relative_path) {
+                    self.remotely_deleted.push(relative_path)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_excluded(&self, relative_path: &str) -> bool {
+        self.excluded.iter().any(|excluded| {
+            relative_path == excluded || relative_path.starts_with(&format!("{}/", excluded))
+        })
+    }
+
+    // Excluded paths are listed, but don't need to be synchronized
+    pub fn is_clean(&self) -> bool {
+        self.locally_new.is_empty()
+            && self.locally_modified.is_empty()
+            && self.locally_deleted.is_empty()
+            && self.conflicts.is_empty()
+            && self.failing.is_empty()
+            && self.rejected.is_empty()
+            && self.remotely_modified.is_empty()
+            && self.remotely_new.is_empty()
+            && self.remotely_deleted.is_empty()
+    }
+}
+
+impl fmt::Display for WorkspaceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            writeln!(f, "Nothing to synchronize")?;
+        }
+
+        let sections = [
+            ("Locally new", &self.locally_new),
+            ("Locally modified", &self.locally_modified),
+            ("Locally deleted", &self.locally_deleted),
+            ("Conflicts", &self.conflicts),
+            ("Remotely modified", &self.remotely_modified),
+            ("Remotely new", &self.remotely_new),
+            ("Remotely deleted", &self.remotely_deleted),
+        ];
+        for (title, paths) in sections.iter() {
+            if paths.is_empty() {
+                continue;
+            }
+            writeln!(f, "{} :", title)?;
+            for path in paths.iter() {
+                writeln!(f, "    {}", path)?;
+            }
+        }
+        if !self.failing.is_empty() {
+            writeln!(f, "Failing :")?;
+            for failure in self.failing.iter() {
+                writeln!(f, "    {}", failure.relative_path)?;
+                writeln!(
+                    f,
+                    "        {} consecutive failure(s), last one : {}",
+                    failure.count, failure.error
+                )?;
+            }
+        }
+        if !self.rejected.is_empty() {
+            writeln!(f, "Rejected :")?;
+            for rejection in self.rejected.iter() {
+                writeln!(f, "    {}", rejection.relative_path)?;
+                writeln!(f, "        {}", rejection.reason)?;
+            }
+        }
+        if !self.excluded.is_empty() {
+            writeln!(f, "Excluded :")?;
+            for relative_path in self.excluded.iter() {
+                writeln!(f, "    {}", relative_path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Status as written by a running trsync into its metadata directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LiveStatus {
+    #[serde(flatten)]
+    pub status: Status,
+    pub updated_timestamp: Option<LastModifiedTimestamp>,
+}
+
+// Index, disk (and optionally remote) differences and live state, as a single
+// serializable snapshot
+#[derive(Debug, Serialize)]
+pub struct StatusSnapshot {
+    pub folder_path: String,
+    pub workspace_id: Option<i32>,
+    pub indexed: u64,
+    pub conflicts: usize,
+    pub failing: usize,
+    pub rejected: usize,
+    pub excluded: usize,
+    pub locally_new: usize,
+    pub locally_modified: usize,
+    pub locally_deleted: usize,
+    // Only known when remote is compared
+    pub remotely_new: Option<usize>,
+    pub remotely_modified: Option<usize>,
+    pub remotely_deleted: Option<usize>,
+    // Last state written by trsync, none if it never ran with this version
+    pub live: Option<LiveStatus>,
+}
+
+impl StatusSnapshot {
+    pub fn new(
+        context: &Context,
+        workspace_id: Option<i32>,
+        indexed: u64,
+        workspace_status: &WorkspaceStatus,
+        remote: bool,
+    ) -> Result<Self, Error> {
+        let remote_count = |paths: usize| if remote { Some(paths) } else { None };
+        let live_status_path = context.live_status_path();
+        let live = if live_status_path.exists() {
+            Some(serde_json::from_str(&fs::read_to_string(
+                live_status_path,
+            )?)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            folder_path: context.folder_path.clone(),
+            workspace_id,
+            indexed,
+            conflicts: workspace_status.conflicts.len(),
+            failing: workspace_status.failing.len(),
+            rejected: workspace_status.rejected.len(),
+            excluded: workspace_status.excluded.len(),
+            locally_new: workspace_status.locally_new.len(),
+            locally_modified: workspace_status.locally_modified.len(),
+            locally_deleted: workspace_status.locally_deleted.len(),
+            remotely_new: remote_count(workspace_status.remotely_new.len()),
+            remotely_modified: remote_count(workspace_status.remotely_modified.len()),
+            remotely_deleted: remote_count(workspace_status.remotely_deleted.len()),
+            live,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn exit_code_follows_failures_conflicts_and_pending_operations() {
+        let mut failed = RunReport::default();
+        failed.failed("a.txt".to_string(), "Error".to_string());
+        let mut conflicted = RunReport::default();
+        conflicted.conflicted("b.txt".to_string(), 2);
+        let pending = RunReport {
+            pending: 1,
+            ..RunReport::default()
+        };
+        let mut succeeded_again = failed.clone();
+        succeeded_again.succeeded("a.txt");
+
+        assert_eq!(RunReport::default().exit_code(), EXIT_CODE_CLEAN);
+        assert_eq!(failed.exit_code(), EXIT_CODE_WITH_ERRORS);
+        assert_eq!(conflicted.exit_code(), EXIT_CODE_WITH_ERRORS);
+        assert_eq!(pending.exit_code(), EXIT_CODE_WITH_ERRORS);
+        assert_eq!(succeeded_again.exit_code(), EXIT_CODE_CLEAN);
+    }
+
+    #[test]
+    fn fatal_error_is_written_with_its_exit_code() {
+        let folder = testing::temp_folder("fatal-report");
+        let report_file = folder.join("report.json");
+        let mut report = RunReport::default();
+        report.failed("a.txt".to_string(), "Error".to_string());
+
+        report
+            .write(
+                &report_file,
+                Some(&Error::FolderMissing("Folder is missing".to_string())),
+            )
+            .unwrap();
+        let written: Value =
+            serde_json::from_str(&fs::read_to_string(&report_file).unwrap()).unwrap();
+
+        assert_eq!(written["exit_code"], EXIT_CODE_FATAL);
+        assert!(written["fatal_error"]
+            .as_str()
+            .unwrap()
+            .contains("Folder is missing"));
+        assert_eq!(written["failures"][0]["path"], "a.txt");
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+// Helpers of unit tests : a minimal HTTP server answering as Tracim would, and
+// contexts using it with a temporary synchronized folder
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use rusqlite::Connection;
+
+use crate::{context::Context, database::DatabaseOperation};
+
+pub const WORKSPACE_ID: i32 = 1;
+
+static NEXT_FOLDER_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // Announced body length when it differs from body one (connection is then closed
+    // after the body, like a server dying while answering)
+    pub content_length: Option<usize>,
+}
+
+impl StubResponse {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.as_bytes().to_vec(),
+            content_length: None,
+        }
+    }
+
+    pub fn bytes(body: &[u8]) -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            body: body.to_vec(),
+            content_length: None,
+        }
+    }
+}
+
+// Request received by stub server
+#[derive(Clone, Debug)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    // Header names are lower case
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+type StubResponses = Arc<Mutex<HashMap<(String, String), VecDeque<StubResponse>>>>;
+
+// Answer requests with responses registered by method and path (query string
+// excluded), 404 otherwise. Connections are kept alive.
+pub struct TracimStub {
+    pub address: String,
+    responses: StubResponses,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl TracimStub {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stub = Self {
+            address: listener.local_addr().unwrap().to_string(),
+            responses: Arc::new(Mutex::new(HashMap::new())),
+            requests: Arc::new(Mutex::new(vec![])),
+            connections: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let responses = stub.responses.clone();
+        let requests = stub.requests.clone();
+        let connections = stub.connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                connections.fetch_add(1, Ordering::SeqCst);
+                let responses = responses.clone();
+                let requests = requests.clone();
+                thread::spawn(move || serve(stream, responses, requests));
+            }
+        });
+
+        stub
+    }
+
+    pub fn on(&self, method: &str, path: &str, response: StubResponse) {
+        self.on_each(method, path, vec![response]);
+    }
+
+    // Answer each request with next response, last one being kept for all next requests
+    pub fn on_each(&self, method: &str, path: &str, responses: Vec<StubResponse>) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert((method.to_string(), path.to_string()), responses.into());
+    }
+
+    // Path of workspace api endpoint, as used by client
+    pub fn workspace_path(&self, path: &str) -> String {
+        format!("/api/workspaces/{}/{}", WORKSPACE_ID, path)
+    }
+
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // Accepted connections count
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    // Requests as "METHOD path" strings
+    pub fn request_lines(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect()
+    }
+}
+
+fn serve(stream: TcpStream, responses: StubResponses, requests: Arc<Mutex<Vec<StubRequest>>>) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts
+            .next()
+            .unwrap_or_default()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut header_line = String::new();
+            if reader.read_line(&mut header_line).unwrap_or(0) == 0 {
+                return;
+            }
+            let header_line = header_line.trim_end();
+            if header_line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header_line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+        let content_length = headers
+            .get("content-length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; content_length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        let response = responses
+            .lock()
+            .unwrap()
+            .get_mut(&(method.clone(), path.clone()))
+            .and_then(|responses| {
+                if responses.len() > 1 {
+                    responses.pop_front()
+                } else {
+                    responses.front().cloned()
+                }
+            })
+            .unwrap_or_else(|| StubResponse::json(404, "{}"));
+        requests.lock().unwrap().push(StubRequest {
+            method,
+            path,
+            headers,
+            body,
+        });
+
+        let mut head = format!(
+            "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\n",
+            response.status,
+            response.content_length.unwrap_or(response.body.len())
+        );
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        if writer.write_all(head.as_bytes()).is_err()
+            || writer.write_all(&response.body).is_err()
+            || response.content_length.is_some()
+        {
+            return;
+        }
+    }
+}
+
+// Empty temporary folder, unique for the test process
+pub fn temp_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!(
+        "trsync-test-{}-{}-{}",
+        std::process::id(),
+        NEXT_FOLDER_ID.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+// Context synchronizing given folder with stub server workspace
+pub fn context(stub: &TracimStub, folder: &Path) -> Context {
+    let context = Context::new(
+        false,
+        stub.address.clone(),
+        "user".to_string(),
+        "password".to_string(),
+        folder.to_str().unwrap().to_string(),
+        WORKSPACE_ID,
+        true,
+    )
+    .unwrap();
+    fs::create_dir_all(context.temp_dir()).unwrap();
+    context
+}
+
+// In memory index
+pub fn connection() -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    DatabaseOperation::new(&connection).create_tables().unwrap();
+    connection
+}
+
+// Remote content as Tracim describes it
+pub fn remote_content_json(
+    content_id: i32,
+    revision_id: i32,
+    parent_id: Option<i32>,
+    filename: &str,
+    size: u64,
+) -> String {
+    serde_json::json!({
+        "content_id": content_id,
+        "current_revision_id": revision_id,
+        "parent_id": parent_id,
+        "content_type": "file",
+        "modified": "2021-01-01T00:00:00Z",
+        "filename": filename,
+        "label": filename.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(filename),
+        "is_deleted": false,
+        "size": size,
+    })
+    .to_string()
+}
+
+// Answer whoami as a server whose clock is given seconds behind local clock
+pub fn server_clock_behind(stub: &TracimStub, seconds: i64) {
+    let date = (chrono::Utc::now() - chrono::Duration::seconds(seconds)).to_rfc2822();
+    let mut response = StubResponse::json(200, r#"{"user_id": 1}"#);
+    response.headers.push(("Date".to_string(), date));
+    stub.on("GET", "/api/auth/whoami", response);
+}
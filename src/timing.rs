@@ -0,0 +1,208 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io,
+    time::{Duration, Instant},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+// Last durations kept by kind of operation
+const TIMING_WINDOW: usize = 100;
+// Upper bounds (inclusive) of histogram buckets, in milliseconds. Last bucket hold
+// longer durations.
+pub const HISTOGRAM_BOUNDS_MS: [u64; 5] = [100, 1_000, 5_000, 30_000, 120_000];
+
+// Where an operation spent its time
+#[derive(Debug, Clone, Copy)]
+pub enum TimingPhase {
+    // Requests to Tracim, and transfer of downloaded content
+    Remote,
+    // Downloaded content written on disk
+    Disk,
+    // Index updates
+    Database,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseDurations {
+    pub remote_ms: u64,
+    pub disk_ms: u64,
+    pub database_ms: u64,
+}
+
+impl PhaseDurations {
+    fn add(&mut self, phase: TimingPhase, duration: Duration) {
+        let duration_ms = duration.as_millis() as u64;
+        match phase {
+            TimingPhase::Remote => self.remote_ms += duration_ms,
+            TimingPhase::Disk => self.disk_ms += duration_ms,
+            TimingPhase::Database => self.database_ms += duration_ms,
+        }
+    }
+}
+
+// Phases are accumulated by thread : an operation and its requests, disk writes and
+// index updates run in the same thread
+thread_local! {
+    static PHASES: RefCell<PhaseDurations> = RefCell::new(PhaseDurations::default());
+}
+
+// Run given function, counting its duration in given phase of current thread
+pub fn measure<T>(phase: TimingPhase, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    PHASES.with(|phases| phases.borrow_mut().add(phase, elapsed));
+    result
+}
+
+// Reader or writer whose calls are counted in given phase (as a response body read
+// while its content is written on disk)
+pub struct Timed<T> {
+    inner: T,
+    phase: TimingPhase,
+}
+
+impl<T> Timed<T> {
+    pub fn new(inner: T, phase: TimingPhase) -> Self {
+        Self { inner, phase }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: io::Read> io::Read for Timed<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        measure(self.phase, || self.inner.read(buf))
+    }
+}
+
+impl<T: io::Write> io::Write for Timed<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        measure(self.phase, || self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        measure(self.phase, || self.inner.flush())
+    }
+}
+
+// Phase durations measured in current thread since previous call
+pub fn take_phases() -> PhaseDurations {
+    PHASES.with(|phases| phases.replace(PhaseDurations::default()))
+}
+
+// Durations of one kind of operation, for status readers (as a GUI showing average
+// upload time)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationTimings {
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    // Durations of last operations, oldest first
+    pub recent_ms: VecDeque<u64>,
+}
+
+impl OperationTimings {
+    pub fn record(&mut self, duration: Duration) {
+        let duration_ms = duration.as_millis() as u64;
+        self.count += 1;
+        self.total_ms += duration_ms;
+        self.max_ms = self.max_ms.max(duration_ms);
+        if self.recent_ms.len() == TIMING_WINDOW {
+            self.recent_ms.pop_front();
+        }
+        self.recent_ms.push_back(duration_ms);
+    }
+
+    // Average of last operations
+    pub fn recent_average_ms(&self) -> Option<u64> {
+        if self.recent_ms.is_empty() {
+            return None;
+        }
+        Some(self.recent_ms.iter().sum::<u64>() / self.recent_ms.len() as u64)
+    }
+
+    // Count of last operations by bucket of HISTOGRAM_BOUNDS_MS (plus longer ones)
+    pub fn recent_histogram(&self) -> Vec<u64> {
+        let mut histogram = vec![0; HISTOGRAM_BOUNDS_MS.len() + 1];
+        for duration_ms in &self.recent_ms {
+            let bucket = HISTOGRAM_BOUNDS_MS
+                .iter()
+                .position(|bound| duration_ms <= bound)
+                .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+            histogram[bucket] += 1;
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, thread};
+
+    use super::*;
+
+    // Reader sending one byte per read, slowly as a distant server
+    struct SlowReader(usize);
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0 == 0 || buf.is_empty() {
+                return Ok(0);
+            }
+            thread::sleep(Duration::from_millis(20));
+            self.0 -= 1;
+            buf[0] = b'a';
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn transfer_is_counted_as_remote_and_write_as_disk() {
+        take_phases();
+        let mut reader = Timed::new(SlowReader(5), TimingPhase::Remote);
+        let mut writer = Timed::new(Vec::new(), TimingPhase::Disk);
+
+        io::copy(&mut reader, &mut writer).unwrap();
+
+        let phases = take_phases();
+        assert_eq!(writer.into_inner(), b"aaaaa");
+        assert!(phases.remote_ms >= 100, "{:?}", phases);
+        assert!(phases.disk_ms < phases.remote_ms, "{:?}", phases);
+        assert_eq!(take_phases(), PhaseDurations::default());
+    }
+
+    #[test]
+    fn operation_timings_keep_last_durations() {
+        let mut timings = OperationTimings::default();
+        assert_eq!(timings.recent_average_ms(), None);
+
+        timings.record(Duration::from_millis(200_000));
+        for _ in 0..TIMING_WINDOW {
+            timings.record(Duration::from_millis(50));
+        }
+        timings.record(Duration::from_millis(2_000));
+
+        assert_eq!(timings.count, TIMING_WINDOW as u64 + 2);
+        assert_eq!(timings.max_ms, 200_000);
+        assert_eq!(
+            timings.total_ms,
+            200_000 + 50 * TIMING_WINDOW as u64 + 2_000
+        );
+        assert_eq!(timings.recent_ms.len(), TIMING_WINDOW);
+        assert_eq!(timings.recent_ms.back(), Some(&2_000));
+        assert_eq!(
+            timings.recent_average_ms(),
+            Some((50 * (TIMING_WINDOW as u64 - 1) + 2_000) / TIMING_WINDOW as u64)
+        );
+        assert_eq!(
+            timings.recent_histogram(),
+            vec![TIMING_WINDOW as u64 - 1, 0, 1, 0, 0, 0]
+        );
+    }
+}
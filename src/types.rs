@@ -1,3 +1,5 @@
+use std::{collections::HashSet, fmt, path::Path, str::FromStr};
+
 pub type RelativeFilePath = String;
 pub type AbsoluteFilePath = String;
 pub type ContentId = i32;
@@ -11,19 +13,23 @@ pub enum ContentType {
     Folder,
 }
 
-impl ContentType {
-    pub fn from_str(str_: &str) -> Option<Self> {
+impl FromStr for ContentType {
+    type Err = ();
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
         match str_ {
-            "file" => Some(Self::File),
-            "folder" => Some(Self::Folder),
-            _ => None,
+            "file" => Ok(Self::File),
+            "folder" => Ok(Self::Folder),
+            _ => Err(()),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ContentType::File => "file".to_string(),
-            ContentType::Folder => "folder".to_string(),
+            ContentType::File => write!(f, "file"),
+            ContentType::Folder => write!(f, "folder"),
         }
     }
 }
@@ -35,18 +41,355 @@ pub enum RemoteEventType {
     Deleted,
 }
 
-impl RemoteEventType {
-    pub fn from_str(str_: &str) -> Option<Self> {
+impl FromStr for RemoteEventType {
+    type Err = ();
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "content.modified.file" => Ok(Self::Modified),
+            "content.modified.folder" => Ok(Self::Modified),
+            "content.created.file" => Ok(Self::Created),
+            "content.created.folder" => Ok(Self::Created),
+            "content.deleted.file" => Ok(Self::Deleted),
+            "content.deleted.folder" => Ok(Self::Deleted),
+            "content.undeleted.file" => Ok(Self::Created),
+            "content.undeleted.folder" => Ok(Self::Created),
+            _ => Err(()),
+        }
+    }
+}
+
+// How local file names are derived from Tracim contents "filename" and "label"
+#[derive(Debug, PartialEq, Clone)]
+pub enum NameStrategy {
+    Filename,
+    LabelWithExtension,
+    Label,
+}
+
+impl NameStrategy {
+    // Label to send to Tracim when local file name changes, so that building
+    // local name back from remote content give the same local file name
+    pub fn label_from_file_name(&self, file_name: &str, content_type: &ContentType) -> String {
+        match (self, content_type) {
+            (NameStrategy::LabelWithExtension, ContentType::File) => Path::new(file_name)
+                .file_stem()
+                .and_then(|file_stem| file_stem.to_str())
+                .unwrap_or(file_name)
+                .to_string(),
+            _ => file_name.to_string(),
+        }
+    }
+}
+
+impl FromStr for NameStrategy {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "filename" => Ok(Self::Filename),
+            "label-ext" => Ok(Self::LabelWithExtension),
+            "label" => Ok(Self::Label),
+            _ => Err(format!(
+                "Unknown name strategy '{}' (expected filename, label-ext or label)",
+                str_
+            )),
+        }
+    }
+}
+
+// How remote changes are watched : Tracim live messages, periodic polling of
+// contents, or live messages with polling fallback when they are not available
+#[derive(Debug, PartialEq, Clone)]
+pub enum RemoteMode {
+    Live,
+    Polling,
+    Auto,
+}
+
+impl FromStr for RemoteMode {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "live" => Ok(Self::Live),
+            "polling" => Ok(Self::Polling),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!(
+                "Unknown remote mode '{}' (expected live, polling or auto)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do when a file changed both on disk and on remote
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConflictStrategy {
+    LocalWins,
+    RemoteWins,
+    KeepBoth,
+    // Record conflict and stop synchronizing the file until user clear it
+    Manual,
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "local-wins" => Ok(Self::LocalWins),
+            "remote-wins" => Ok(Self::RemoteWins),
+            "keep-both" => Ok(Self::KeepBoth),
+            "manual" => Ok(Self::Manual),
+            _ => Err(format!(
+                "Unknown conflict strategy '{}' (expected local-wins, remote-wins, keep-both or manual)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do when a remote content path differs only by case from the path of another
+// indexed content : rename it on disk, do not synchronize it, or keep its path (the
+// right choice only for case sensitive file systems). Auto renames when synchronized
+// folder is on a case insensitive file system.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CaseCollision {
+    Auto,
+    Rename,
+    Skip,
+    Keep,
+}
+
+impl FromStr for CaseCollision {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
         match str_ {
-            "content.modified.file" => Some(Self::Modified),
-            "content.modified.folder" => Some(Self::Modified),
-            "content.created.file" => Some(Self::Created),
-            "content.created.folder" => Some(Self::Created),
-            "content.deleted.file" => Some(Self::Deleted),
-            "content.deleted.folder" => Some(Self::Deleted),
-            "content.undeleted.file" => Some(Self::Created),
-            "content.undeleted.folder" => Some(Self::Created),
-            _ => None,
+            "auto" => Ok(Self::Auto),
+            "rename" => Ok(Self::Rename),
+            "skip" => Ok(Self::Skip),
+            "keep" => Ok(Self::Keep),
+            _ => Err(format!(
+                "Unknown case collision '{}' (expected auto, rename, skip or keep)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do when a new local file is in a folder not yet created on remote : create
+// missing folders, or refuse to upload the file
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParentCreation {
+    Create,
+    Reject,
+}
+
+impl FromStr for ParentCreation {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "create" => Ok(Self::Create),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!(
+                "Unknown parent creation '{}' (expected create or reject)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do with files not synchronized (ignored or not yet uploaded) left in a
+// folder deleted on remote : remove them with the folder, or keep them (and the folder)
+#[derive(Debug, PartialEq, Clone)]
+pub enum LeftoverPolicy {
+    Remove,
+    Keep,
+}
+
+impl FromStr for LeftoverPolicy {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "remove" => Ok(Self::Remove),
+            "keep" => Ok(Self::Keep),
+            _ => Err(format!(
+                "Unknown leftover policy '{}' (expected remove or keep)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do with a new local file Tracim refuses to create (name or type not allowed) :
+// skip it until it changes, rename it with a sanitized name and retry once, or fail
+#[derive(Debug, PartialEq, Clone)]
+pub enum RejectionPolicy {
+    Skip,
+    RenameAndRetry,
+    Fail,
+}
+
+impl FromStr for RejectionPolicy {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "skip" => Ok(Self::Skip),
+            "rename-and-retry" => Ok(Self::RenameAndRetry),
+            "fail" => Ok(Self::Fail),
+            _ => Err(format!(
+                "Unknown rejection policy '{}' (expected skip, rename-and-retry or fail)",
+                str_
+            )),
+        }
+    }
+}
+
+// File system of synchronized folder : local one, or network one (NFS, SMB, ...) where
+// disk events are not reported and timestamps are stored by second. Auto detects network
+// file systems from mounts (Linux only).
+#[derive(Debug, PartialEq, Clone)]
+pub enum FilesystemProfile {
+    Auto,
+    Local,
+    Network,
+}
+
+impl FromStr for FilesystemProfile {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "auto" => Ok(Self::Auto),
+            "local" => Ok(Self::Local),
+            "network" => Ok(Self::Network),
+            _ => Err(format!(
+                "Unknown filesystem profile '{}' (expected auto, local or network)",
+                str_
+            )),
+        }
+    }
+}
+
+// What to do with downloaded files : keep disk file permissions (executable content
+// is made executable when new), remove their executable bit, or write executable
+// content into quarantine folder instead of synchronized folder
+#[derive(Debug, PartialEq, Clone)]
+pub enum DownloadExecutablePolicy {
+    PreservePermissions,
+    StripExecutable,
+    Quarantine,
+}
+
+impl FromStr for DownloadExecutablePolicy {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_ {
+            "preserve-permissions" => Ok(Self::PreservePermissions),
+            "strip-executable" => Ok(Self::StripExecutable),
+            "quarantine" => Ok(Self::Quarantine),
+            _ => Err(format!(
+                "Unknown download executable policy '{}' (expected preserve-permissions, \
+                strip-executable or quarantine)",
+                str_
+            )),
+        }
+    }
+}
+
+// Remote contents to take according to the Tracim user who made their current revision :
+// when allowed users are given, only theirs are taken. Denied users ones never are.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AuthorFilter {
+    pub allowed: HashSet<i32>,
+    pub denied: HashSet<i32>,
+}
+
+impl AuthorFilter {
+    // Unknown author is only accepted when no allowed users are given
+    pub fn accepts(&self, author_id: Option<i32>) -> bool {
+        match author_id {
+            Some(author_id) => {
+                !self.denied.contains(&author_id)
+                    && (self.allowed.is_empty() || self.allowed.contains(&author_id))
+            }
+            None => self.allowed.is_empty(),
+        }
+    }
+}
+
+// Local clock compared to Tracim server clock, at start and by doctor
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClockCheck {
+    // Skew (local clock minus server clock, ms) under warning threshold
+    InSync(i64),
+    // Skew over warning threshold, with the warning to give
+    Skewed(i64, String),
+    // Tracim server do not give its clock
+    Unknown,
+}
+
+// Additional request header, given as "Name: value"
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequestHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for RequestHeader {
+    type Err = String;
+
+    fn from_str(str_: &str) -> Result<Self, Self::Err> {
+        match str_.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() => Ok(Self {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(format!(
+                "Unknown request header '{}' (expected \"Name: value\")",
+                str_
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author_filter(allowed: &[i32], denied: &[i32]) -> AuthorFilter {
+        AuthorFilter {
+            allowed: allowed.iter().copied().collect(),
+            denied: denied.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn author_filter_accepts() {
+        for (filter, author_id, accepted) in [
+            (author_filter(&[], &[]), Some(1), true),
+            (author_filter(&[], &[]), None, true),
+            (author_filter(&[], &[1]), Some(1), false),
+            (author_filter(&[], &[1]), Some(2), true),
+            (author_filter(&[], &[1]), None, true),
+            (author_filter(&[1], &[]), Some(1), true),
+            (author_filter(&[1], &[]), Some(2), false),
+            (author_filter(&[1], &[]), None, false),
+            (author_filter(&[1], &[1]), Some(1), false),
+        ] {
+            assert_eq!(
+                filter.accepts(author_id),
+                accepted,
+                "{:?} {:?}",
+                filter,
+                author_id
+            );
         }
     }
 }
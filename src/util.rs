@@ -1,11 +1,14 @@
 use std::{
+    cell::OnceCell,
+    convert::TryFrom,
     io,
     path::{Component, Path, PathBuf},
-    time::UNIX_EPOCH,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use rusqlite::Connection;
-use std::fs;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
 
 use crate::{
     database::DatabaseOperation,
@@ -14,14 +17,49 @@ use crate::{
     util,
 };
 
+// Prefix of files used to write downloads before moving them to their final path
+pub const PARTIAL_FILE_PREFIX: &str = "part-";
+// ELF and Mach-O (32/64 bits, both endianness) magic numbers
+const EXECUTABLE_MAGIC_NUMBERS: [&[u8]; 5] = [
+    b"\x7fELF",
+    b"\xfe\xed\xfa\xce",
+    b"\xfe\xed\xfa\xcf",
+    b"\xce\xfa\xed\xfe",
+    b"\xcf\xfa\xed\xfe",
+];
+// Universal Mach-O magic number, shared with Java class files. It is followed by the
+// architectures count in universal binaries, by the class version (45 or more) in
+// Java class files.
+const UNIVERSAL_MACH_O_MAGIC_NUMBER: &[u8] = b"\xca\xfe\xba\xbe";
+const MAX_UNIVERSAL_MACH_O_ARCHITECTURES: u32 = 30;
+// DOS header (starting with "MZ") gives offset of PE header at this offset
+const PE_HEADER_OFFSET_POSITION: usize = 0x3c;
+const MAX_PE_HEADER_OFFSET: u32 = 0x10000;
+// Mount types of network file systems, besides "nfs*" ones
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEM_TYPES: [&str; 8] = [
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "fuse.sshfs",
+];
+
 pub struct FileInfos {
     pub file_name: String,
     pub is_directory: bool,
     pub last_modified_timestamp: LastModifiedTimestamp,
+    // Byte size of disk file (None for directories)
+    pub size: Option<u64>,
     pub relative_path: RelativeFilePath,
     pub absolute_path: AbsoluteFilePath,
     pub parent_relative_path: Option<RelativeFilePath>,
     pub content_type: ContentType,
+    // Computed at first need only, hashing a file is expensive
+    checksum: OnceCell<Option<String>>,
 }
 
 impl FileInfos {
@@ -72,22 +110,42 @@ impl FileInfos {
             ContentType::File
         };
         let metadata = absolute_path.metadata()?;
-        let modified = metadata.modified()?;
-        let since_epoch = modified.duration_since(UNIX_EPOCH)?;
-        let last_modified_timestamp = since_epoch.as_millis() as LastModifiedTimestamp;
+        let last_modified_timestamp = system_time_to_timestamp(metadata.modified()?);
         let is_directory = absolute_path.is_dir();
+        let size = if is_directory {
+            None
+        } else {
+            Some(metadata.len())
+        };
 
         Ok(Self {
             file_name,
             is_directory,
             last_modified_timestamp,
+            size,
             relative_path: relative_file_path,
             absolute_path: util::path_to_string(absolute_path)?,
             parent_relative_path,
             content_type,
+            checksum: OnceCell::new(),
         })
     }
 
+    pub fn checksum(&self) -> Result<Option<String>, Error> {
+        if let Some(checksum) = self.checksum.get() {
+            return Ok(checksum.clone());
+        }
+        let checksum = match self.is_directory {
+            true => None,
+            false => Some(file_checksum(Path::new(&self.absolute_path))?),
+        };
+        Ok(self.checksum.get_or_init(|| checksum).clone())
+    }
+
+    pub fn xattrs(&self) -> Result<Xattrs, Error> {
+        read_xattrs(Path::new(&self.absolute_path))
+    }
+
     pub fn parent_id(&self, connection: &Connection) -> Result<Option<ContentId>, Error> {
         if let Some(parent_relative_path) = &self.parent_relative_path {
             Ok(Some(
@@ -125,6 +183,118 @@ pub fn string_path_file_name(path: &str) -> Result<String, Error> {
         .to_string())
 }
 
+pub fn partial_file_path(temp_dir: &Path, absolute_path: &Path) -> Result<PathBuf, Error> {
+    let file_name = string_path_file_name(&path_to_string(absolute_path)?)?;
+    Ok(temp_dir.join(format!("{}{}", PARTIAL_FILE_PREFIX, file_name)))
+}
+
+// File name with only characters which Tracim accepts : others are replaced by "_",
+// leading and trailing spaces are removed
+pub fn sanitized_file_name(file_name: &str) -> String {
+    let sanitized: String = file_name
+        .chars()
+        .map(|char_| {
+            if char_.is_alphanumeric() || " ._-()".contains(char_) {
+                char_
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match sanitized.trim() {
+        "" => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+// Path of a copy of given file, used to keep local version of a conflicted file
+pub fn conflicted_copy_path(relative_path: &str) -> Result<RelativeFilePath, Error> {
+    path_with_suffix(
+        relative_path,
+        &format!(
+            "conflicted copy {}",
+            system_time_to_timestamp(SystemTime::now())
+        ),
+    )
+}
+
+// Path given to a remote content which path differs only by case from the path of
+// another content, which would be the same disk file on case insensitive file systems
+pub fn case_collision_path(
+    relative_path: &str,
+    content_id: ContentId,
+) -> Result<RelativeFilePath, Error> {
+    path_with_suffix(relative_path, &format!("content {}", content_id))
+}
+
+// File name was given by `case_collision_path` to this content
+pub fn has_case_collision_suffix(file_name: &str, content_id: ContentId) -> bool {
+    Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.ends_with(&format!(" (content {})", content_id)))
+}
+
+// Same file name than given path one, suffix between parentheses, before extension
+fn path_with_suffix(relative_path: &str, suffix: &str) -> Result<RelativeFilePath, Error> {
+    let path = Path::new(relative_path);
+    let stem =
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or(Error::PathManipulationError(format!(
+                "Unable to get file stem of {:?}",
+                relative_path
+            )))?;
+    let file_name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{} ({}).{}", stem, suffix, extension),
+        None => format!("{} ({})", stem, suffix),
+    };
+    path_to_string(&path.with_file_name(file_name))
+}
+
+// Only case folding rule used to compare names ignoring case (index included), so
+// that names equal for one comparison are equal for all of them
+pub fn fold_case(name: &str) -> String {
+    name.to_lowercase()
+}
+
+// Name on disk of given path. On case insensitive file systems, it can differ by case
+// from given path file name.
+pub fn disk_file_name(absolute_path: &Path) -> Result<Option<String>, Error> {
+    let file_name = string_path_file_name(&path_to_string(absolute_path)?)?;
+    let parent = match absolute_path.parent() {
+        Some(parent) => parent,
+        None => return Ok(None),
+    };
+    let disk_file_names: Vec<String> = match fs::read_dir(long_path(parent)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+            .collect(),
+        Err(_) => return Ok(None),
+    };
+
+    if disk_file_names.contains(&file_name) {
+        return Ok(Some(file_name));
+    }
+    Ok(disk_file_names
+        .into_iter()
+        .find(|disk_file_name| fold_case(disk_file_name) == fold_case(&file_name)))
+}
+
+// Create a probe file in given directory and look for it with another case
+pub fn is_case_insensitive_dir(dir: &Path) -> bool {
+    let probe_path = dir.join("case-probe");
+    if fs::write(&probe_path, b"").is_err() {
+        return false;
+    }
+    let case_insensitive = dir.join("CASE-PROBE").exists();
+    if let Err(error) = fs::remove_file(&probe_path) {
+        log::warn!("Unable to remove {:?} : {:?}", probe_path, error)
+    }
+    case_insensitive
+}
+
 pub fn path_to_string(path: &Path) -> Result<String, Error> {
     Ok(path
         .to_str()
@@ -135,9 +305,527 @@ pub fn path_to_string(path: &Path) -> Result<String, Error> {
         .to_string())
 }
 
-pub fn io_error_to_log_level(error: &io::Error) -> log::Level {
+// Timestamps are stored as signed milliseconds because sqlite integers are signed.
+// Modification dates before epoch (found in some bad archives) are considered as epoch.
+pub fn system_time_to_timestamp(time: SystemTime) -> LastModifiedTimestamp {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => LastModifiedTimestamp::try_from(since_epoch.as_millis())
+            .unwrap_or(LastModifiedTimestamp::MAX),
+        Err(_) => {
+            log::warn!("Modification date {:?} is before epoch, use epoch", time);
+            0
+        }
+    }
+}
+
+// Timestamps are truncated to given granularity (file systems storing them by second)
+// before being compared
+pub fn timestamps_match(
+    first: LastModifiedTimestamp,
+    second: LastModifiedTimestamp,
+    granularity_ms: u64,
+    tolerance_ms: u64,
+) -> bool {
+    let granularity_ms = granularity_ms.max(1) as LastModifiedTimestamp;
+    let truncate =
+        |timestamp: LastModifiedTimestamp| timestamp - timestamp.rem_euclid(granularity_ms);
+    truncate(first).abs_diff(truncate(second)) <= tolerance_ms
+}
+
+pub fn file_checksum(path: &Path) -> Result<String, Error> {
+    reader_checksum(&mut File::open(path)?)
+}
+
+// Extended attributes, as (name, value) pairs
+pub type Xattrs = Vec<(String, Vec<u8>)>;
+
+// Extended attribute holding remote content label, when labels are synchronized
+pub const LABEL_XATTR: &str = "user.tracim.label";
+
+// Extended attributes of file, empty where platform or file system do not support them
+pub fn read_xattrs(path: &Path) -> Result<Xattrs, Error> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(vec![]);
+    }
+
+    let names = match xattr::list(path) {
+        Ok(names) => names,
+        Err(error) if error.kind() == io::ErrorKind::Unsupported => return Ok(vec![]),
+        Err(error) => return Err(Error::from(error)),
+    };
+    let mut xattrs = vec![];
+    for name in names {
+        let name_ = match name.to_str() {
+            Some(name_) => name_.to_string(),
+            None => {
+                log::warn!(
+                    "Ignore not utf-8 extended attribute {:?} of {:?}",
+                    name,
+                    path
+                );
+                continue;
+            }
+        };
+        // Label is synchronized with remote content label, not preserved as is
+        if name_ == LABEL_XATTR {
+            continue;
+        }
+        if let Some(value) = xattr::get(path, &name)? {
+            xattrs.push((name_, value))
+        }
+    }
+
+    Ok(xattrs)
+}
+
+pub fn write_xattrs(path: &Path, xattrs: &[(String, Vec<u8>)]) {
+    if !xattr::SUPPORTED_PLATFORM {
+        return;
+    }
+
+    for (name, value) in xattrs {
+        // Some attributes (security ones, ...) can only be set by privileged users
+        if let Err(error) = xattr::set(path, name, value) {
+            log::warn!(
+                "Unable to set extended attribute {} on {:?} : {}",
+                name,
+                path,
+                error
+            )
+        }
+    }
+}
+
+// Label set on disk file, if any
+pub fn read_label(path: &Path) -> Result<Option<String>, Error> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(None);
+    }
+
+    match xattr::get(path, LABEL_XATTR) {
+        Ok(value) => Ok(value.map(|value| String::from_utf8_lossy(&value).to_string())),
+        Err(error) if error.kind() == io::ErrorKind::Unsupported => Ok(None),
+        Err(error) => Err(Error::from(error)),
+    }
+}
+
+pub fn write_label(path: &Path, label: &str) {
+    write_xattrs(
+        path,
+        &[(LABEL_XATTR.to_string(), label.as_bytes().to_vec())],
+    )
+}
+
+// File name fitting in given bytes count (file systems limit name components, often
+// to 255 bytes). Longer names are truncated, keeping their extension, and suffixed
+// with a short hash of the full name : truncated names stay unique and stable.
+pub fn limit_file_name(file_name: &str, max_bytes: usize) -> String {
+    if file_name.len() <= max_bytes {
+        return file_name.to_string();
+    }
+
+    let hash: String = Sha256::digest(file_name.as_bytes())
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| format!(".{}", extension))
+        .unwrap_or_default();
+    let (stem, suffix) = if extension.len() + hash.len() + 1 < max_bytes {
+        (
+            &file_name[..file_name.len() - extension.len()],
+            format!("-{}{}", hash, extension),
+        )
+    } else {
+        (file_name, format!("-{}", hash))
+    };
+
+    let mut stem_end = max_bytes.saturating_sub(suffix.len()).min(stem.len());
+    while !stem.is_char_boundary(stem_end) {
+        stem_end -= 1;
+    }
+    format!("{}{}", &stem[..stem_end], suffix)
+}
+
+// File name can be the result of `limit_file_name` for a longer name : truncated names
+// are at most 3 bytes shorter than the limit (cut on a char boundary)
+pub fn may_be_limited_file_name(file_name: &str, max_bytes: usize) -> bool {
+    file_name.len() + 3 >= max_bytes
+}
+
+// Rename file, or copy then remove it when it can't be renamed (other file system)
+pub fn move_file(from: &Path, to: &Path) -> Result<(), Error> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+pub fn reader_checksum<R: io::Read>(reader: &mut R) -> Result<String, Error> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+// Disk errors are classified to know if an operation must be retried later or not
+#[derive(Debug, PartialEq)]
+pub enum IoErrorKind {
+    AlreadyExists,
+    // Disk is full, operation can succeed once space is freed
+    NoSpace,
+    // Read only directory or file system, retrying is useless
+    PermissionDenied,
+    Other,
+}
+
+pub fn classify_io_error(error: &io::Error) -> IoErrorKind {
     match error.kind() {
-        io::ErrorKind::AlreadyExists => log::Level::Info,
-        _ => log::Level::Error,
+        io::ErrorKind::AlreadyExists => IoErrorKind::AlreadyExists,
+        io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => IoErrorKind::NoSpace,
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+            IoErrorKind::PermissionDenied
+        }
+        _ => IoErrorKind::Other,
+    }
+}
+
+pub fn io_error_to_log_level(error: &io::Error) -> log::Level {
+    match classify_io_error(error) {
+        IoErrorKind::AlreadyExists => log::Level::Info,
+        IoErrorKind::NoSpace => log::Level::Warn,
+        IoErrorKind::PermissionDenied | IoErrorKind::Other => log::Level::Error,
+    }
+}
+
+// Build error of a failed disk write, so that caller know if it can retry it
+pub fn disk_write_error(error: io::Error, message: String) -> Error {
+    match classify_io_error(&error) {
+        IoErrorKind::NoSpace => Error::NoSpaceLeft(format!("{} : {}", message, error)),
+        IoErrorKind::PermissionDenied => {
+            Error::PermissionDenied(format!("{} : {}", message, error))
+        }
+        IoErrorKind::AlreadyExists | IoErrorKind::Other => {
+            Error::UnexpectedError(format!("{} : {:?}", message, error))
+        }
+    }
+}
+
+// Windows refuse paths longer than 260 chars unless they are prefixed with the
+// extended-length prefix. This is a no-op on other platforms.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    let path_str = path.to_string_lossy();
+    if !path.is_absolute() || path_str.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+
+    let path_str = path_str.replace('/', r"\");
+    if let Some(unc_path) = path_str.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", unc_path))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", path_str))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+// File content is a script or a program, according to its first bytes (shebang,
+// ELF, PE or Mach-O headers)
+pub fn is_executable_content(path: &Path) -> io::Result<bool> {
+    use io::{Read, Seek, SeekFrom};
+    let mut file = File::open(long_path(path))?;
+    let mut header = Vec::with_capacity(PE_HEADER_OFFSET_POSITION + 4);
+    (&mut file)
+        .take(PE_HEADER_OFFSET_POSITION as u64 + 4)
+        .read_to_end(&mut header)?;
+
+    if header.starts_with(b"#!")
+        || EXECUTABLE_MAGIC_NUMBERS
+            .iter()
+            .any(|magic_number| header.starts_with(magic_number))
+    {
+        return Ok(true);
+    }
+    if header.starts_with(UNIVERSAL_MACH_O_MAGIC_NUMBER) && header.len() >= 8 {
+        let architectures = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        return Ok((1..=MAX_UNIVERSAL_MACH_O_ARCHITECTURES).contains(&architectures));
+    }
+    // Plain DOS programs and text starting with "MZ" have no PE header
+    if header.starts_with(b"MZ") && header.len() == PE_HEADER_OFFSET_POSITION + 4 {
+        let pe_header_offset = u32::from_le_bytes([
+            header[PE_HEADER_OFFSET_POSITION],
+            header[PE_HEADER_OFFSET_POSITION + 1],
+            header[PE_HEADER_OFFSET_POSITION + 2],
+            header[PE_HEADER_OFFSET_POSITION + 3],
+        ]);
+        if !(2..=MAX_PE_HEADER_OFFSET).contains(&pe_header_offset) {
+            return Ok(false);
+        }
+        let mut signature = Vec::with_capacity(4);
+        file.seek(SeekFrom::Start(pe_header_offset as u64))?;
+        file.take(4).read_to_end(&mut signature)?;
+        return Ok(signature == b"PE\0\0");
+    }
+    Ok(false)
+}
+
+// Give executable bit to those who can read the file, or remove it from everybody.
+// Platforms without permission modes have nothing to change.
+#[cfg(unix)]
+pub fn set_executable(path: &Path, executable: bool) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    permissions.set_mode(match executable {
+        true => mode | ((mode & 0o444) >> 2),
+        false => mode & !0o111,
+    });
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+pub fn set_executable(_path: &Path, _executable: bool) -> io::Result<()> {
+    Ok(())
+}
+
+// Identify a file having multiple hard links by its device and inode. Platforms
+// without inode semantic never identify hard links.
+#[cfg(unix)]
+pub fn hard_link_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.is_file() && metadata.nlink() > 1 {
+        return Some((metadata.dev(), metadata.ino()));
+    }
+    None
+}
+
+#[cfg(not(unix))]
+pub fn hard_link_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Network file system type of the mount holding given path, from mounts table
+#[cfg(target_os = "linux")]
+pub fn network_filesystem_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    let (_, filesystem_type) = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?.replace("\\040", " ");
+            let filesystem_type = fields.next()?;
+            Some((PathBuf::from(mount_point), filesystem_type.to_string()))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())?;
+    if filesystem_type.starts_with("nfs")
+        || NETWORK_FILESYSTEM_TYPES.contains(&filesystem_type.as_str())
+    {
+        return Some(filesystem_type);
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn network_filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+// Identify a folder, to know when it is deleted or replaced by another one at the
+// same path. Platforms without inode semantic only know if it exists.
+#[cfg(unix)]
+pub fn folder_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Some((metadata.dev(), metadata.ino())),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn folder_id(path: &Path) -> Option<(u64, u64)> {
+    if path.is_dir() {
+        return Some((0, 0));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(windows)]
+    #[test]
+    fn long_path_prefixes_absolute_paths() {
+        assert_eq!(
+            long_path(Path::new(r"C:\folder/file.txt")),
+            PathBuf::from(r"\\?\C:\folder\file.txt")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\file.txt")),
+            PathBuf::from(r"\\?\UNC\server\share\file.txt")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\?\C:\file.txt")),
+            PathBuf::from(r"\\?\C:\file.txt")
+        );
+        assert_eq!(
+            long_path(Path::new(r"folder\file.txt")),
+            PathBuf::from(r"folder\file.txt")
+        );
+    }
+
+    #[test]
+    fn long_path_allows_paths_over_260_chars() {
+        let folder = std::env::temp_dir().join(format!("trsync-long-path-{}", std::process::id()));
+        let mut path = folder.clone();
+        while path.to_string_lossy().len() <= 300 {
+            path.push("a".repeat(50));
+        }
+        fs::create_dir_all(long_path(&path)).unwrap();
+        let file_path = path.join("file.txt");
+
+        fs::write(long_path(&file_path), b"hello").unwrap();
+
+        assert!(file_path.to_string_lossy().len() > 260);
+        assert_eq!(fs::read(long_path(&file_path)).unwrap(), b"hello");
+        fs::remove_dir_all(long_path(&folder)).unwrap();
+    }
+
+    #[test]
+    fn file_infos_checksum_is_computed_once() {
+        let folder = std::env::temp_dir().join(format!("trsync-checksum-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("file.txt"), b"hello").unwrap();
+        let file_infos =
+            FileInfos::from(path_to_string(&folder).unwrap(), "file.txt".to_string()).unwrap();
+
+        let checksum = file_infos.checksum().unwrap();
+        fs::write(folder.join("file.txt"), b"hello world").unwrap();
+
+        assert!(checksum.is_some());
+        assert_eq!(file_infos.checksum().unwrap(), checksum);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn timestamps_match_by_granularity() {
+        // Same second, whatever tolerance
+        assert!(timestamps_match(1_000, 1_999, 1000, 0));
+        assert!(timestamps_match(5_123, 5_987, 1000, 0));
+        // Next second
+        assert!(!timestamps_match(1_999, 2_000, 1000, 0));
+        assert!(timestamps_match(1_999, 2_000, 1000, 1000));
+        // Millisecond granularity
+        assert!(!timestamps_match(1_000, 1_001, 1, 0));
+        assert!(timestamps_match(1_000, 1_000, 0, 0));
+    }
+
+    #[test]
+    fn negative_timestamps_are_truncated_toward_past() {
+        assert!(timestamps_match(-1, -999, 1000, 0));
+        assert!(timestamps_match(-1_000, -1, 1000, 0));
+        assert!(!timestamps_match(-1, 0, 1000, 0));
+        assert!(!timestamps_match(-1_001, -1_000, 1000, 0));
+        assert!(timestamps_match(-1, 0, 1000, 1000));
+    }
+
+    #[test]
+    fn executable_content_is_recognized_by_its_headers() {
+        let folder = std::env::temp_dir().join(format!("trsync-executable-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let mut pe = b"MZ".to_vec();
+        pe.resize(0x80, 0);
+        pe[PE_HEADER_OFFSET_POSITION..PE_HEADER_OFFSET_POSITION + 4]
+            .copy_from_slice(&0x40u32.to_le_bytes());
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let mut dos = pe.clone();
+        dos[0x40..0x44].copy_from_slice(b"\0\0\0\0");
+        let contents: [(&[u8], bool); 9] = [
+            (b"#!/bin/sh\necho", true),
+            (b"\x7fELF\x02\x01\x01", true),
+            (b"\xcf\xfa\xed\xfe\x07\x00\x00\x01", true),
+            (&pe, true),
+            (&dos, false),
+            (b"MZ is also a text", false),
+            (b"\xca\xfe\xba\xbe\x00\x00\x00\x02", true),
+            // Java class file, version 52
+            (b"\xca\xfe\xba\xbe\x00\x00\x00\x34", false),
+            (b"", false),
+        ];
+
+        for (index, (content, executable)) in contents.iter().enumerate() {
+            let path = folder.join(format!("file_{}", index));
+            fs::write(&path, content).unwrap();
+            assert_eq!(
+                is_executable_content(&path).unwrap(),
+                *executable,
+                "{:?}",
+                content
+            );
+        }
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn sanitized_file_name_keeps_only_accepted_characters() {
+        for (file_name, sanitized) in [
+            ("report.txt", "report.txt"),
+            ("bad:name?.txt", "bad_name_.txt"),
+            ("Été (2) - copie_1.odt", "Été (2) - copie_1.odt"),
+            ("  spaced  ", "spaced"),
+            ("a/b\\c", "a_b_c"),
+            ("  ", "_"),
+            ("", "_"),
+        ] {
+            assert_eq!(sanitized_file_name(file_name), sanitized);
+            // Sanitized name is sanitized again as itself
+            assert_eq!(sanitized_file_name(sanitized), sanitized);
+        }
+    }
+
+    #[test]
+    fn limited_file_name_fits_and_keeps_extension() {
+        let long_name = format!("{}.txt", "a".repeat(300));
+        let limited = limit_file_name(&long_name, 255);
+
+        assert_eq!(limited.len(), 255);
+        assert!(limited.ends_with(".txt"));
+        assert!(limited.starts_with("aaaa"));
+        // Stable, and distinct for distinct names
+        assert_eq!(limit_file_name(&long_name, 255), limited);
+        assert_ne!(
+            limit_file_name(&format!("{}.txt", "a".repeat(301)), 255),
+            limited
+        );
+        assert!(may_be_limited_file_name(&limited, 255));
+        assert_eq!(limit_file_name("short.txt", 255), "short.txt");
+        assert!(!may_be_limited_file_name("short.txt", 255));
+    }
+
+    #[test]
+    fn limited_file_name_is_cut_on_char_boundary() {
+        let long_name = format!("{}.odt", "é".repeat(200));
+        let limited = limit_file_name(&long_name, 255);
+
+        assert!(limited.len() <= 255);
+        assert!(may_be_limited_file_name(&limited, 255));
+        assert!(limited.ends_with(".odt"));
+        // Extension longer than the limit is cut with the name
+        let limited = limit_file_name(&format!("name.{}", "x".repeat(300)), 20);
+        assert!(limited.len() <= 20);
     }
 }
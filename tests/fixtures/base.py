@@ -1,8 +1,12 @@
+import contextlib
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
 import json
 import os
 from pickle import DEFAULT_PROTOCOL
 import signal
+import sqlite3
 import subprocess
+import threading
 import time
 from pathlib import Path
 import typing
@@ -107,28 +111,55 @@ def create_workspace(owner: User, name: str) -> Workspace:
     )
 
 
+def get_user_id(user: User) -> int:
+    response = requests.get(
+        f"http://{TRACIM_URL}/api/users/me",
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+    return json.loads(response.content)["user_id"]
+
+
+def add_workspace_member(owner: User, workspace: Workspace, user: User, role: str) -> None:
+    response = requests.post(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/members",
+        json={"user_id": get_user_id(user), "role": role},
+        auth=(owner.username, owner.password),
+    )
+    assert response.status_code == 200
+
+
 def execute_trsync_and_wait_finished(
-    folder: Path, workspace_id: int, user: User, stdout
-) -> None:
+    folder: Path,
+    workspace_id: int,
+    user: User,
+    stdout,
+    extra_args: typing.Optional[typing.List[str]] = None,
+    address: str = TRACIM_URL,
+    extra_env: typing.Optional[typing.Dict[str, str]] = None,
+    allowed_returncodes: typing.Tuple[int, ...] = (0, 1),
+) -> int:
     args = [
         f"{Path.home()}/.cargo/bin/cargo",
         "run",
         str(folder),
-        TRACIM_URL,
+        address,
         str(workspace_id),
         user.username,
         "--env-var-pass PASSWORD",
         "--exit-after-sync",
         "--no-ssl",
-    ]
-    subprocess.run(
+    ] + (extra_args or [])
+    completed = subprocess.run(
         " ".join(args),
         stdout=stdout,
         stderr=stdout,
-        env={"PASSWORD": user.password},
+        env={"PASSWORD": user.password, **(extra_env or {})},
         shell=True,
-        check=True,
     )
+    # 1 means completed with failures or conflicts, which scenarios check by themselves
+    assert completed.returncode in allowed_returncodes, completed.returncode
+    return completed.returncode
 
 
 @pytest.fixture(autouse=True, scope="module")
@@ -145,17 +176,24 @@ def setup(request):
     request.addfinalizer(end)
 
 
-def execute_trsync(folder: Path, workspace_id: int, user: User, stdout):
+def execute_trsync(
+    folder: Path,
+    workspace_id: int,
+    user: User,
+    stdout,
+    extra_args: typing.Optional[typing.List[str]] = None,
+    address: str = TRACIM_URL,
+):
     args = [
         f"{Path.home()}/.cargo/bin/cargo",
         "run",
         str(folder),
-        TRACIM_URL,
+        address,
         str(workspace_id),
         user.username,
         "--env-var-pass PASSWORD",
         "--no-ssl",
-    ]
+    ] + (extra_args or [])
     subprocess.Popen(
         " ".join(args),
         stdout=stdout,
@@ -168,7 +206,7 @@ def execute_trsync(folder: Path, workspace_id: int, user: User, stdout):
 def get_folder_listing(path: Path) -> typing.List[str]:
     paths = []
     for p in path.glob("**/*"):
-        if ".trsync.db" in p.name:
+        if ".trsync" in p.parts:
             continue
         paths.append(str(p).replace(str(path), ""))
     return list(sorted(paths))
@@ -225,6 +263,75 @@ def get_workspace_listing(user: User, workspace: Workspace) -> typing.List[str]:
     return list(sorted(paths))
 
 
+def get_remote_content_id(user: User, workspace: Workspace, path: str) -> int:
+    contents = {c["content_id"]: c for c in _get_workspace_contents(user, workspace)}
+
+    def content_path(content: dict) -> str:
+        names = [content["filename"]]
+        while content["parent_id"] in contents:
+            content = contents[content["parent_id"]]
+            names.append(content["filename"])
+        return "/" + "/".join(reversed(names))
+
+    content = next(c for c in contents.values() if content_path(c) == path)
+    return content["content_id"]
+
+
+def trash_remote_content(user: User, workspace: Workspace, path: str) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/contents/{content_id}/trashed",
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 204
+
+
+def move_remote_content_to_workspace(
+    user: User, workspace: Workspace, path: str, new_workspace: Workspace
+) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/contents/{content_id}/move",
+        json={"new_parent_id": None, "new_workspace_id": new_workspace.id},
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+
+
+def move_remote_content(user: User, workspace: Workspace, path: str, folder: str) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/contents/{content_id}/move",
+        json={
+            "new_parent_id": get_remote_content_id(user, workspace, folder),
+            "new_workspace_id": workspace.id,
+        },
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+
+
+def update_remote_label(user: User, workspace: Workspace, path: str, label: str) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/files/{content_id}",
+        json={"label": label},
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+
+
+def get_database_relative_paths(folder: Path) -> typing.List[str]:
+    connection = sqlite3.connect(folder / ".trsync" / "trsync.db")
+    try:
+        return [
+            f"/{row[0]}"
+            for row in connection.execute("SELECT relative_path FROM file").fetchall()
+        ]
+    finally:
+        connection.close()
+
+
 def check_until(callback, duration=10.0):
     start = time.time()
     while True:
@@ -235,3 +342,293 @@ def check_until(callback, duration=10.0):
             if time.time() - start > duration:
                 raise exc
             time.sleep(0.250)
+
+
+def get_remote_file_revisions(
+    user: User, workspace: Workspace, path: str
+) -> typing.List[dict]:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.get(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/files/{content_id}/revisions",
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+    return json.loads(response.content)["items"]
+
+
+def get_remote_file_content(user: User, workspace: Workspace, path: str) -> bytes:
+    content_id = get_remote_content_id(user, workspace, path)
+    response = requests.get(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/files/{content_id}/raw/{path[1:]}",
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+    return response.content
+
+
+def execute_trsync_exclusion(folder: Path, command: str, path: str) -> None:
+    """Exclude (command "exclude") or include again (command "include") given path"""
+    args = [
+        f"{Path.home()}/.cargo/bin/cargo",
+        "run",
+        "--",
+        command,
+        str(folder),
+        path,
+    ]
+    subprocess.run(" ".join(args), shell=True, check=True)
+
+
+def execute_trsync_status(
+    folder: Path,
+    workspace_id: int,
+    user: User,
+    extra_args: typing.Optional[typing.List[str]] = None,
+) -> str:
+    args = [
+        f"{Path.home()}/.cargo/bin/cargo",
+        "run",
+        "--",
+        "status",
+        str(folder),
+        TRACIM_URL,
+        str(workspace_id),
+        user.username,
+        "--env-var-pass PASSWORD",
+        "--no-ssl",
+    ] + (extra_args or [])
+    return subprocess.run(
+        " ".join(args),
+        stdout=subprocess.PIPE,
+        env={"PASSWORD": user.password},
+        shell=True,
+        check=True,
+    ).stdout.decode()
+
+
+def execute_trsync_doctor(
+    folder: Path,
+    workspace_id: int,
+    user: User,
+    password: str,
+    extra_args: typing.Optional[typing.List[str]] = None,
+) -> str:
+    args = [
+        f"{Path.home()}/.cargo/bin/cargo",
+        "run",
+        "--",
+        "doctor",
+        str(folder),
+        TRACIM_URL,
+        str(workspace_id),
+        user.username,
+        "--env-var-pass PASSWORD",
+        "--no-ssl",
+    ] + (extra_args or [])
+    # Doctor fails when it finds errors, findings are checked by scenarios
+    return subprocess.run(
+        " ".join(args),
+        stdout=subprocess.PIPE,
+        env={"PASSWORD": password},
+        shell=True,
+    ).stdout.decode()
+
+
+def execute_trsync_ls_remote(
+    workspace_id: int,
+    user: User,
+    extra_args: typing.Optional[typing.List[str]] = None,
+) -> str:
+    args = [
+        f"{Path.home()}/.cargo/bin/cargo",
+        "run",
+        "--",
+        "ls-remote",
+        TRACIM_URL,
+        str(workspace_id),
+        user.username,
+        "--env-var-pass PASSWORD",
+        "--no-ssl",
+    ] + (extra_args or [])
+    return subprocess.run(
+        " ".join(args),
+        stdout=subprocess.PIPE,
+        env={"PASSWORD": user.password},
+        shell=True,
+        check=True,
+    ).stdout.decode()
+
+
+class TracimProxy:
+    def __init__(self, address: str) -> None:
+        self.address = address
+        self.received_headers: typing.List[typing.Dict[str, str]] = []
+        # As "METHOD /path?query"
+        self.received_requests: typing.List[str] = []
+        # Refuse connections, as an unreachable Tracim would, until back online
+        self.go_offline: typing.Callable[[], None] = lambda: None
+        self.go_online: typing.Callable[[], None] = lambda: None
+
+
+@contextlib.contextmanager
+def tracim_proxy(
+    answer_creations_already_exist: bool = False,
+    reject_uploads_over_quota: bool = False,
+    server_clock_offset_s: int = 0,
+    rejected_creation_name: typing.Optional[str] = None,
+    api_key_user: typing.Optional[typing.Tuple[str, User]] = None,
+) -> typing.Iterator[TracimProxy]:
+    """Proxy to Tracim recording request headers and requests. It can answer file
+    creations with a "content already exist" error (content is created anyway), then
+    hide created content from the next listing. It can also refuse all uploads as
+    exceeding workspace quota, refuse creation of files with a given name as Tracim does
+    for unsupported names, or shift server clock given in "Date" header. Listed
+    messages are always the ones after the
+    "after_event_id" parameter, if any. With an API key and its user, it acts as a
+    Tracim configured with this API key : other requests are unauthorized. It can be
+    taken offline, connections are then refused until it is back online."""
+    hidden_content_ids: typing.Set[int] = set()
+    received_headers: typing.List[typing.Dict[str, str]] = []
+    received_requests: typing.List[str] = []
+
+    class Handler(BaseHTTPRequestHandler):
+        def forward(self):
+            received_headers.append(dict(self.headers.items()))
+            received_requests.append(f"{self.command} {self.path}")
+            length = int(self.headers.get("Content-Length", 0))
+            body = self.rfile.read(length) if length else None
+            headers = {k: v for k, v in self.headers.items() if k.lower() != "host"}
+            auth = None
+            if api_key_user:
+                api_key, api_user = api_key_user
+                if (
+                    self.headers.get("Tracim-Api-Key") != api_key
+                    or self.headers.get("Tracim-Api-Login") != api_user.username
+                ):
+                    content = json.dumps(
+                        {"code": 4001, "message": "Authentication failed", "details": {}}
+                    ).encode()
+                    self.send_response(401)
+                    self.send_header("Content-Type", "application/json")
+                    self.send_header("Content-Length", str(len(content)))
+                    self.end_headers()
+                    self.wfile.write(content)
+                    return
+                headers = {
+                    k: v for k, v in headers.items() if not k.lower().startswith("tracim-api-")
+                }
+                auth = (api_user.username, api_user.password)
+            rejected = None
+            if reject_uploads_over_quota and (
+                (self.command == "POST" and self.path.endswith("/files"))
+                or (self.command == "PUT" and "/raw/" in self.path)
+            ):
+                rejected = {"code": 3010, "message": "Workspace quota exceeded"}
+            elif (
+                rejected_creation_name
+                and self.command == "POST"
+                and self.path.endswith("/files")
+                and f'filename="{rejected_creation_name}"'.encode() in (body or b"")
+            ):
+                rejected = {"code": 2001, "message": "Validation error of input data"}
+            if rejected:
+                content = json.dumps({**rejected, "details": {}}).encode()
+                self.send_response(400)
+                self.send_header("Content-Type", "application/json")
+                self.send_header("Content-Length", str(len(content)))
+                self.end_headers()
+                self.wfile.write(content)
+                return
+            response = requests.request(
+                self.command,
+                f"http://{TRACIM_URL}{self.path}",
+                headers=headers,
+                auth=auth,
+                data=body,
+                allow_redirects=False,
+            )
+            status, content = response.status_code, response.content
+
+            if (
+                answer_creations_already_exist
+                and self.command == "POST"
+                and self.path.endswith("/files")
+                and status == 200
+            ):
+                hidden_content_ids.add(response.json()["content_id"])
+                status = 400
+                content = json.dumps(
+                    {"code": 3002, "message": "Content already exist", "details": {}}
+                ).encode()
+            elif self.command == "GET" and "/contents" in self.path and hidden_content_ids:
+                page = response.json()
+                page["items"] = [
+                    item
+                    for item in page["items"]
+                    if item["content_id"] not in hidden_content_ids
+                ]
+                hidden_content_ids.clear()
+                content = json.dumps(page).encode()
+            elif (
+                self.command == "GET"
+                and "/messages?" in self.path
+                and "after_event_id=" in self.path
+                and status == 200
+            ):
+                after_event_id = int(
+                    self.path.split("after_event_id=")[1].split("&")[0]
+                )
+                page = response.json()
+                page["items"] = [
+                    item for item in page["items"] if item["event_id"] > after_event_id
+                ]
+                content = json.dumps(page).encode()
+
+            self.send_response(status)
+            for key, value in response.headers.items():
+                if key.lower() not in (
+                    "connection",
+                    "content-encoding",
+                    "content-length",
+                    "transfer-encoding",
+                ):
+                    self.send_header(key, value)
+            self.send_header("Content-Length", str(len(content)))
+            self.end_headers()
+            self.wfile.write(content)
+
+        # First "Date" header of responses, sent by send_response
+        def date_time_string(self, timestamp=None):
+            return super().date_time_string(
+                (timestamp or time.time()) + server_clock_offset_s
+            )
+
+        do_GET = do_POST = do_PUT = do_DELETE = forward
+
+    servers: typing.List[ThreadingHTTPServer] = []
+
+    def start(port: int = 0) -> int:
+        server = ThreadingHTTPServer(("localhost", port), Handler)
+        threading.Thread(target=server.serve_forever, daemon=True).start()
+        servers.append(server)
+        return server.server_address[1]
+
+    def restart() -> None:
+        start(port)
+
+    def stop() -> None:
+        while servers:
+            server = servers.pop()
+            server.shutdown()
+            server.server_close()
+
+    port = start()
+    proxy = TracimProxy(f"localhost:{port}")
+    proxy.received_headers = received_headers
+    proxy.received_requests = received_requests
+    proxy.go_offline = stop
+    proxy.go_online = restart
+    try:
+        yield proxy
+    finally:
+        stop()
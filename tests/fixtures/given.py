@@ -1,8 +1,14 @@
+from pathlib import Path
+import pytest
 from pytest_bdd import parsers, given
 
 from tests.fixtures.model import User, Workspace
 import tests.fixtures.base as base
-from tests.fixtures.sets import create_set_on_remote
+from tests.fixtures.sets import (
+    create_file,
+    create_set_on_remote,
+    rename_content_label,
+)
 
 
 @given("I have a fresh Tracim instance")
@@ -28,6 +34,37 @@ def workspace(user: User, name: str) -> Workspace:
     return base.create_workspace(user, name)
 
 
+@given(parsers.cfparse('The user "{username}" is {role:w} of the workspace'))
+def workspace_member(user: User, workspace: Workspace, username: str, role: str) -> None:
+    base._ensure_user(base.USERS[username])
+    base.add_workspace_member(user, workspace, base.USERS[username], role)
+
+
 @given(parsers.cfparse('The workspace is filled with contents called "{set_name}"'))
 def workspace_filled_with_set(user: User, workspace: Workspace, set_name: str) -> None:
     create_set_on_remote(user, workspace, set_name)
+
+
+@given(
+    parsers.cfparse(
+        'The workspace contains file "{file_name}" labelled "{label}" with content "{content}"'
+    )
+)
+def workspace_contains_labelled_file(
+    user: User, workspace: Workspace, file_name: str, label: str, content: str
+) -> None:
+    content_id = create_file(user, workspace, file_name, content.encode())
+    rename_content_label(user, workspace, content_id, label)
+
+
+@given(parsers.cfparse("inotify watches are limited to {count:d}"))
+def inotify_watches_limited(count: int, request) -> None:
+    """Limit is system wide : it is restored after scenario. Scenario is skipped when
+    limit can't be changed (not root, or in a container)."""
+    setting = Path("/proc/sys/fs/inotify/max_user_watches")
+    previous = setting.read_text()
+    try:
+        setting.write_text(str(count))
+    except OSError as error:
+        pytest.skip(f"Unable to limit inotify watches : {error}")
+    request.addfinalizer(lambda: setting.write_text(previous))
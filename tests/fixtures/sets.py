@@ -81,3 +81,32 @@ def create_set_on_remote(user: User, workspace: Workspace, set_name: str) -> Non
             id = create_folder(user, workspace, concerned_part, parent_id=parent_id)
 
         content_ids[file_path] = id
+
+
+def rename_content_label(
+    user: User,
+    workspace: Workspace,
+    content_id: int,
+    label: str,
+) -> None:
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/files/{content_id}",
+        json={"label": label, "raw_content": ""},
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 200
+
+
+def update_file(
+    user: User,
+    workspace: Workspace,
+    content_id: int,
+    name: str,
+    content: bytes,
+) -> None:
+    response = requests.put(
+        f"http://{TRACIM_URL}/api/workspaces/{workspace.id}/files/{content_id}/raw/{name}",
+        files={"files": (name, content)},
+        auth=(user.username, user.password),
+    )
+    assert response.status_code == 204
@@ -1,7 +1,24 @@
+import hashlib
+import json
+import os
+import re
 from pathlib import Path
+import sqlite3
+import time
+import typing
 
 from pytest_bdd import parsers, then
-from tests.fixtures.base import check_until, get_folder_listing, get_workspace_listing
+from tests.fixtures.base import (
+    _get_content,
+    _get_workspace_contents,
+    check_until,
+    get_database_relative_paths,
+    get_folder_listing,
+    get_remote_content_id,
+    get_remote_file_content,
+    get_remote_file_revisions,
+    get_workspace_listing,
+)
 
 from tests.fixtures.model import User, Workspace
 from tests.fixtures.sets import SETS
@@ -9,7 +26,22 @@ from tests.fixtures.sets import SETS
 
 @then("I should see the trsync database file")
 def database_file_exist(user: User, workspace: Workspace, tmp_path: Path):
-    assert (tmp_path / workspace.folder(tmp_path) / ".trsync.db").exists()
+    assert (workspace.folder(tmp_path) / ".trsync" / "trsync.db").exists()
+
+
+@then(parsers.cfparse('I should see the trsync database file in user data directory "{name}"'))
+def database_file_in_data_dir(user: User, workspace: Workspace, name: str, tmp_path: Path):
+    folder_path = os.path.realpath(workspace.folder(tmp_path))
+    folder_hash = hashlib.sha256(folder_path.encode()).hexdigest()[:16]
+    databases_dir = tmp_path / name / "trsync" / "databases"
+    assert (databases_dir / f"{workspace.id}-{folder_hash}.db").exists()
+    assert not (workspace.folder(tmp_path) / ".trsync" / "trsync.db").exists()
+
+
+@then(parsers.cfparse('I should see the trsync database file at "{path}"'))
+def database_file_at(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert (tmp_path / path).exists()
+    assert not (workspace.folder(tmp_path) / ".trsync" / "trsync.db").exists()
 
 
 @then("Local folder is empty")
@@ -46,9 +78,401 @@ def workspace_contains_file(user: User, workspace: Workspace, path: str):
     check_until(check)
 
 
+@then(parsers.cfparse('I should see remote content at "{path}" {count:d} times'))
+def workspace_contains_content_times(
+    user: User, workspace: Workspace, path: str, count: int
+):
+    def check():
+        assert get_workspace_listing(user, workspace).count(path) == count
+
+    check_until(check)
+
+
+@then(parsers.cfparse('remote folder "{path}" should contain {count:d} distinct files'))
+def remote_folder_contains_distinct_files(
+    user: User, workspace: Workspace, path: str, count: int
+):
+    def check():
+        listing = [
+            content_path
+            for content_path in get_workspace_listing(user, workspace)
+            if content_path.startswith(f"{path}/")
+        ]
+        assert len(listing) == count, listing
+        assert len(set(listing)) == count, listing
+
+    check_until(check, duration=60.0)
+
+
 @then(parsers.cfparse('I should see remote folder at "{path}"'))
 def workspace_contains_folder(user: User, workspace: Workspace, path: str):
     def check():
         assert path in get_workspace_listing(user, workspace)
 
     check_until(check)
+
+
+@then(parsers.cfparse('I should see local file at "{path}"'))
+def folder_contains_file(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    def check():
+        assert path in get_folder_listing(workspace.folder(tmp_path))
+
+    check_until(check)
+
+
+@then(parsers.cfparse('I should not see local file at "{path}"'))
+def folder_not_contains_file(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert path not in get_folder_listing(workspace.folder(tmp_path))
+
+
+@then(parsers.cfparse('I should see remote content labelled "{label}"'))
+def workspace_contains_label(user: User, workspace: Workspace, label: str):
+    def check():
+        labels = [c["label"] for c in _get_workspace_contents(user, workspace)]
+        assert label in labels
+
+    check_until(check)
+
+
+@then(parsers.cfparse('trsync database should not contain "{path}"'))
+def database_not_contains(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    def check():
+        assert path not in get_database_relative_paths(workspace.folder(tmp_path))
+
+    check_until(check)
+
+
+@then(parsers.cfparse('trsync database should index "{path}" at its remote revision'))
+def database_indexes_remote_revision(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+):
+    content_id = get_remote_content_id(user, workspace, path)
+    revision_id = _get_content(user, content_id)["current_revision_id"]
+
+    def check():
+        connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+        try:
+            rows = connection.execute(
+                "SELECT relative_path, revision_id FROM file WHERE content_id = ?",
+                (content_id,),
+            ).fetchall()
+        finally:
+            connection.close()
+        assert rows == [(path[1:], revision_id)]
+
+    check_until(check)
+
+
+@then(parsers.cfparse('trsync database should contain "{path}" {count:d} times'))
+def database_contains_times(
+    user: User, workspace: Workspace, path: str, count: int, tmp_path: Path
+):
+    def check():
+        assert get_database_relative_paths(workspace.folder(tmp_path)).count(path) == count
+
+    check_until(check)
+
+
+@then("trsync logs should not contain errors")
+def logs_without_errors(tmp_path: Path):
+    assert "ERROR" not in (tmp_path / "trsync.log").read_text()
+
+
+@then("trsync database should be in WAL mode")
+def database_in_wal_mode(user: User, workspace: Workspace, tmp_path: Path):
+    database_path = workspace.folder(tmp_path) / ".trsync" / "trsync.db"
+    connection = sqlite3.connect(database_path)
+    try:
+        journal_mode = connection.execute("PRAGMA journal_mode").fetchone()[0]
+    finally:
+        connection.close()
+    assert journal_mode == "wal"
+
+
+@then(parsers.cfparse('I should not see remote file at "{path}"'))
+def workspace_not_contains_file(user: User, workspace: Workspace, path: str):
+    assert path not in get_workspace_listing(user, workspace)
+
+
+@then(parsers.cfparse('trsync logs should not contain "{text}"'))
+def logs_without_text(tmp_path: Path, text: str):
+    assert text not in (tmp_path / "trsync.log").read_text()
+
+
+@then(parsers.cfparse('I should not see remote folder at "{path}"'))
+def workspace_not_contains_folder(user: User, workspace: Workspace, path: str):
+    assert path not in get_workspace_listing(user, workspace)
+
+
+@then(parsers.cfparse('trsync logs should contain "{text}"'))
+def logs_with_text(tmp_path: Path, text: str):
+    assert text in (tmp_path / "trsync.log").read_text()
+
+
+@then(parsers.cfparse('trsync logs should show "{first}" before "{second}"'))
+def logs_with_text_order(tmp_path: Path, first: str, second: str):
+    logs = (tmp_path / "trsync.log").read_text()
+    assert first in logs
+    assert second in logs
+    assert logs.index(first) < logs.index(second)
+
+
+@then(parsers.cfparse('trsync logs should contain "{text}" {count:d} times'))
+def logs_with_text_count(tmp_path: Path, text: str, count: int):
+    assert (tmp_path / "trsync.log").read_text().count(text) == count
+
+
+@then("trsync temporary directory should be empty")
+def temporary_directory_empty(user: User, workspace: Workspace, tmp_path: Path):
+    assert list((workspace.folder(tmp_path) / ".trsync" / "tmp").iterdir()) == []
+
+
+@then(parsers.cfparse('remote file at "{path}" should have {count:d} revisions'))
+def remote_file_revisions_count(user: User, workspace: Workspace, path: str, count: int):
+    def check():
+        assert len(get_remote_file_revisions(user, workspace, path)) == count
+
+    check_until(check)
+
+
+# Count is reached then kept : a duplicated upload would add a revision later
+@then(parsers.cfparse('remote file at "{path}" should keep {count:d} revisions'))
+def remote_file_revisions_count_kept(
+    user: User, workspace: Workspace, path: str, count: int
+):
+    remote_file_revisions_count(user, workspace, path, count)
+    time.sleep(3.0)
+    assert len(get_remote_file_revisions(user, workspace, path)) == count
+
+
+@then(parsers.cfparse('remote file at "{path}" should contain "{content}"'))
+def remote_file_contains(user: User, workspace: Workspace, path: str, content: str):
+    def check():
+        assert get_remote_file_content(user, workspace, path) == content.encode()
+
+    check_until(check)
+
+
+@then(parsers.cfparse('local file at "{path}" should not exist'))
+def local_file_not_exist(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert not (workspace.folder(tmp_path) / path[1:]).exists()
+
+
+@then(parsers.cfparse('local file at "{path}" should be executable'))
+def local_file_executable(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert os.access(workspace.folder(tmp_path) / path[1:], os.X_OK)
+
+
+@then(parsers.cfparse('local file at "{path}" should not be executable'))
+def local_file_not_executable(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert not os.access(workspace.folder(tmp_path) / path[1:], os.X_OK)
+
+
+@then(parsers.cfparse('quarantined file at "{path}" should contain "{content}"'))
+def quarantined_file_content(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+):
+    quarantined_path = workspace.folder(tmp_path) / ".trsync" / "quarantine" / path[1:]
+    assert quarantined_path.read_text() == content
+    assert not os.access(quarantined_path, os.X_OK)
+
+
+@then(parsers.cfparse('local file at "{path}" should contain "{content}"'))
+def local_file_content(user: User, workspace: Workspace, path: str, content: str, tmp_path: Path):
+    assert (workspace.folder(tmp_path) / path[1:]).read_text() == content
+
+
+@then(parsers.cfparse('local file at "{path}" should not contain "{content}"'))
+def local_file_other_content(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+):
+    assert (workspace.folder(tmp_path) / path[1:]).read_bytes() != content.encode()
+
+
+@then(parsers.cfparse('I should see a conflicted copy of local file "{path}"'))
+def conflicted_copy_exists(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert _conflicted_copies(workspace.folder(tmp_path), path)
+
+
+@then(parsers.cfparse('I should not see a conflicted copy of local file "{path}"'))
+def conflicted_copy_not_exists(user: User, workspace: Workspace, path: str, tmp_path: Path):
+    assert not _conflicted_copies(workspace.folder(tmp_path), path)
+
+
+def _conflicted_copies(folder: Path, path: str) -> typing.List[str]:
+    stem = Path(path).stem
+    return [
+        listed_path
+        for listed_path in get_folder_listing(folder)
+        if Path(listed_path).name.startswith(f"{stem} (conflicted copy ")
+    ]
+
+
+@then(parsers.cfparse('trsync status should list "{path}" as "{state}"'))
+def status_lists(status_output: str, path: str, state: str):
+    section = status_output.split(f"{state} :\n", 1)[1]
+    listed = []
+    for line in section.splitlines():
+        if not line.startswith("    "):
+            break
+        listed.append(line.strip())
+    assert path in listed
+
+
+@then(parsers.cfparse('trsync json status should report {value} as "{key}"'))
+def json_status_reports(status_output: str, value: str, key: str):
+    status = json.loads(status_output)
+    for key_part in key.split("."):
+        status = status[key_part]
+    assert json.dumps(status) == value
+
+
+@then(parsers.cfparse('trsync ls-remote should list "{path}"'))
+def ls_remote_lists(ls_remote_output: str, path: str):
+    def paths(contents, parent=""):
+        for content in contents:
+            content_path = f"{parent}/{content['filename']}"
+            yield content_path
+            yield from paths(content["children"], content_path)
+
+    assert path in list(paths(json.loads(ls_remote_output)["contents"]))
+
+
+@then(parsers.cfparse('health file should report phase "{phase}"'))
+def health_file_phase(tmp_path: Path, phase: str):
+    def check():
+        assert json.loads((tmp_path / "health.json").read_text())["phase"] == phase
+
+    check_until(check)
+
+
+@then(parsers.cfparse("health file should report {count:d} errors"))
+def health_file_errors(tmp_path: Path, count: int):
+    assert json.loads((tmp_path / "health.json").read_text())["errors"] == count
+
+
+@then(
+    parsers.cfparse(
+        'local file at "{path}" should have extended attribute "{name}" with value "{value}"'
+    )
+)
+def local_file_xattr(
+    user: User, workspace: Workspace, path: str, name: str, value: str, tmp_path: Path
+):
+    assert os.getxattr(workspace.folder(tmp_path) / path[1:], name) == value.encode()
+
+
+@then(parsers.cfparse('hooks log should contain "{text}"'))
+def hooks_log_with_text(tmp_path: Path, text: str):
+    def check():
+        hooks_log_path = tmp_path / "hooks.log"
+        assert hooks_log_path.exists()
+        assert text in hooks_log_path.read_text()
+
+    check_until(check)
+
+
+@then(parsers.cfparse("local file names should fit in {max_bytes:d} bytes"))
+def local_file_names_fit(
+    user: User, workspace: Workspace, max_bytes: int, tmp_path: Path
+):
+    def check():
+        names = [
+            name
+            for name in os.listdir(workspace.folder(tmp_path))
+            if not name.startswith(".")
+        ]
+        assert names
+        assert all(len(name.encode()) <= max_bytes for name in names)
+        assert all(name.endswith(".txt") for name in names)
+
+    check_until(check)
+
+
+@then(parsers.cfparse("local folder should contain {count:d} files"))
+def local_folder_files_count(
+    user: User, workspace: Workspace, count: int, tmp_path: Path
+):
+    names = [
+        name
+        for name in os.listdir(workspace.folder(tmp_path))
+        if not name.startswith(".")
+    ]
+    assert len(names) == count
+
+
+@then(parsers.cfparse('every proxied request should have header "{name}" with value "{value}"'))
+def proxied_requests_header(
+    proxy_received_headers: typing.List[typing.Dict[str, str]], name: str, value: str
+):
+    assert proxy_received_headers
+    for headers in proxy_received_headers:
+        assert {k.lower(): v for k, v in headers.items()}[name.lower()] == value
+
+
+@then(parsers.cfparse('proxied requests should not have header "{name}"'))
+def proxied_requests_without_header(
+    proxy_received_headers: typing.List[typing.Dict[str, str]], name: str
+):
+    assert proxy_received_headers
+    for headers in proxy_received_headers:
+        assert name.lower() not in {k.lower() for k in headers}
+
+
+@then("proxied requests should not list workspace contents")
+def proxied_requests_not_listing_contents(proxy_received_requests: typing.List[str]):
+    assert proxy_received_requests
+    for received_request in proxy_received_requests:
+        method, path = received_request.split(" ", 1)
+        # Listing children of a folder is not a full listing
+        assert (
+            method != "GET"
+            or not path.split("?")[0].endswith("/contents")
+            or "parent_ids=" in path
+        ), received_request
+
+
+@then(
+    parsers.cfparse(
+        'proxied requests should contain {count:d} "{method}" requests to "{path_suffix}"'
+    )
+)
+def proxied_requests_count(
+    proxy_received_requests: typing.List[str], count: int, method: str, path_suffix: str
+):
+    matching = [
+        received_request
+        for received_request in proxy_received_requests
+        if re.fullmatch(
+            f"{method} .*{path_suffix}", received_request.split("?")[0]
+        )
+    ]
+    assert len(matching) == count, proxy_received_requests
+
+
+@then(parsers.cfparse("trsync should exit with code {code:d}"))
+def trsync_exit_code_is(trsync_exit_code: int, code: int):
+    assert trsync_exit_code == code
+
+
+@then(parsers.cfparse('report file should report {value} as "{key}"'))
+def report_file_reports(tmp_path: Path, value: str, key: str):
+    report = json.loads((tmp_path / "report.json").read_text())
+    assert json.dumps(report[key]) == value
+
+
+@then(parsers.cfparse('report file should list "{path}" in "{section}"'))
+def report_file_lists(tmp_path: Path, path: str, section: str):
+    report = json.loads((tmp_path / "report.json").read_text())
+    assert path in [entry["path"] for entry in report[section]], report
+
+
+@then(parsers.cfparse('trsync doctor should report "{severity}" for "{check}"'))
+def doctor_reports(doctor_output: str, severity: str, check: str):
+    assert f"[{severity}] {check} : " in doctor_output, doctor_output
+
+
+@then(parsers.cfparse('local path "{path}" should be a {kind}'))
+def local_path_kind(user: User, workspace: Workspace, path: str, kind: str, tmp_path: Path):
+    disk_path = workspace.folder(tmp_path) / path[1:]
+    assert disk_path.is_dir() if kind == "folder" else disk_path.is_file()
@@ -1,13 +1,32 @@
 import os
 from pathlib import Path
+import shutil
 import signal
+import sqlite3
+import threading
+import time
 from pytest_bdd import when, parsers
 
 from tests.fixtures.base import (
+    tracim_proxy,
+    TracimProxy,
     execute_trsync,
     execute_trsync_and_wait_finished,
+    execute_trsync_doctor,
+    execute_trsync_exclusion,
+    execute_trsync_ls_remote,
+    create_workspace,
+    execute_trsync_status,
+    get_remote_content_id,
+    get_user_id,
+    USERS,
+    move_remote_content,
+    move_remote_content_to_workspace,
+    trash_remote_content,
+    update_remote_label,
 )
 from tests.fixtures.model import User, Workspace
+from tests.fixtures.sets import FILE_CONTENTS, create_file, create_folder, update_file
 
 
 @when("I start and wait the end of synchronization")
@@ -21,6 +40,412 @@ def sync_and_wait(user: User, workspace: Workspace, tmp_path: Path):
         )
 
 
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization with report file and option "{option}"'
+    ),
+    target_fixture="trsync_exit_code",
+)
+def sync_and_wait_with_report_file(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        return execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[f"--report-file {tmp_path / 'report.json'}", option],
+            allowed_returncodes=(0, 1, 2),
+        )
+
+
+@when(
+    "I start and wait the end of synchronization with health and report files inside "
+    "folder"
+)
+def sync_and_wait_with_files_inside_folder(
+    user: User, workspace: Workspace, tmp_path: Path
+):
+    folder = workspace.folder(tmp_path)
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=folder,
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[
+                f"--health-file={folder / 'health.json'}",
+                f"--report-file={folder / 'report.json'}",
+            ],
+        )
+
+
+# Without HOME nor XDG_DATA_HOME, index is kept in metadata directory
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization with user data directory "{name}"'
+    )
+)
+def sync_and_wait_with_data_dir(
+    user: User, workspace: Workspace, name: str, tmp_path: Path
+):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_env={"XDG_DATA_HOME": str(tmp_path / name)},
+        )
+
+
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization with database file "{name}"'
+    )
+)
+def sync_and_wait_with_database_file(
+    user: User, workspace: Workspace, name: str, tmp_path: Path
+):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=["--database-path", str(tmp_path / name)],
+        )
+
+
+@when(parsers.cfparse('I start and wait the end of synchronization {filter_:w} author "{username}"'))
+def sync_and_wait_with_author_filter(
+    user: User, workspace: Workspace, filter_: str, username: str, tmp_path: Path
+):
+    option = {"allowing": "--allow-author-id", "denying": "--deny-author-id"}[filter_]
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[f"{option}={get_user_id(USERS[username])}"],
+        )
+
+
+@when(parsers.cfparse('I start and wait the end of synchronization with option "{option}"'))
+def sync_and_wait_with_option(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[option],
+        )
+
+
+@when(
+    "I start and wait the end of synchronization through a proxy answering creations "
+    'with "already exist"'
+)
+def sync_and_wait_through_already_exist_proxy(
+    user: User, workspace: Workspace, tmp_path: Path
+):
+    with tracim_proxy(answer_creations_already_exist=True) as proxy, open(
+        tmp_path / "trsync.log", "w+"
+    ) as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            address=proxy.address,
+        )
+
+
+@when(
+    "I start and wait the end of synchronization through a proxy refusing uploads "
+    "over quota"
+)
+def sync_and_wait_through_over_quota_proxy(
+    user: User, workspace: Workspace, tmp_path: Path
+):
+    with tracim_proxy(reject_uploads_over_quota=True) as proxy, open(
+        tmp_path / "trsync.log", "w+"
+    ) as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            address=proxy.address,
+        )
+
+
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization through a proxy rejecting creation '
+        'of "{name}" with option "{option}"'
+    )
+)
+def sync_and_wait_through_rejecting_proxy(
+    user: User, workspace: Workspace, name: str, option: str, tmp_path: Path
+):
+    with tracim_proxy(rejected_creation_name=name) as proxy, open(
+        tmp_path / "trsync.log", "w+"
+    ) as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[option],
+            address=proxy.address,
+        )
+
+
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization through a proxy with option "{option}"'
+    ),
+    target_fixture="proxy_received_headers",
+)
+def sync_and_wait_through_proxy_with_option(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+):
+    with tracim_proxy() as proxy, open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[option],
+            address=proxy.address,
+        )
+    return proxy.received_headers
+
+
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization through a proxy expecting API key '
+        '"{api_key}" given "{given_api_key}"'
+    ),
+    target_fixture="proxy_received_headers",
+)
+def sync_and_wait_through_api_key_proxy(
+    user: User, workspace: Workspace, api_key: str, given_api_key: str, tmp_path: Path
+):
+    with tracim_proxy(api_key_user=(api_key, user)) as proxy, open(
+        tmp_path / "trsync.log", "w+"
+    ) as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=["--env-var-api-key TRSYNC_API_KEY"],
+            address=proxy.address,
+            extra_env={"TRSYNC_API_KEY": given_api_key},
+            # Refused credentials stop synchronization
+            allowed_returncodes=(0, 1, 2),
+        )
+    return proxy.received_headers
+
+
+@when(
+    parsers.cfparse(
+        'I start and wait the end of synchronization through a proxy recording requests '
+        'with option "{option}"'
+    ),
+    target_fixture="proxy_received_requests",
+)
+def sync_and_wait_through_proxy_recording_requests(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+):
+    with tracim_proxy() as proxy, open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[option],
+            address=proxy.address,
+        )
+    return proxy.received_requests
+
+
+@when(
+    parsers.cfparse(
+        "I start and wait the end of synchronization through a proxy with server clock "
+        "{seconds:d} seconds ahead"
+    )
+)
+def sync_and_wait_through_proxy_with_clock_ahead(
+    user: User, workspace: Workspace, seconds: int, tmp_path: Path
+):
+    with tracim_proxy(server_clock_offset_s=seconds) as proxy, open(
+        tmp_path / "trsync.log", "w+"
+    ) as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            address=proxy.address,
+        )
+
+
+@when(
+    "I start synchronization through a proxy recording requests",
+    target_fixture="proxy_received_requests",
+)
+def sync_through_proxy_recording_requests(
+    user: User, workspace: Workspace, tmp_path: Path, request
+):
+    proxy_context = tracim_proxy()
+    proxy = proxy_context.__enter__()
+    request.addfinalizer(lambda: proxy_context.__exit__(None, None, None))
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            address=proxy.address,
+        )
+    return proxy.received_requests
+
+
+@when(
+    "I start synchronization with health file through a proxy",
+    target_fixture="proxy",
+)
+def sync_with_health_file_through_proxy(
+    user: User, workspace: Workspace, tmp_path: Path, request
+):
+    proxy_context = tracim_proxy()
+    proxy = proxy_context.__enter__()
+    request.addfinalizer(lambda: proxy_context.__exit__(None, None, None))
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[f"--health-file={tmp_path / 'health.json'}"],
+            address=proxy.address,
+        )
+    return proxy
+
+
+@when("the proxy becomes unreachable")
+def proxy_unreachable(proxy: TracimProxy):
+    proxy.go_offline()
+
+
+@when("the proxy becomes reachable")
+def proxy_reachable(proxy: TracimProxy):
+    proxy.go_online()
+
+
+@when(parsers.cfparse("set remote event cursor to {event_id:d}"))
+def set_remote_event_cursor(workspace: Workspace, event_id: int, tmp_path: Path) -> None:
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('remote_event_cursor', ?)",
+        (str(event_id),),
+    )
+    connection.commit()
+    connection.close()
+
+
+# As a network file system storing timestamps by second : disk timestamp is truncated
+# to its second, indexed one keeps given milliseconds
+@when(
+    parsers.cfparse(
+        'truncate local file timestamp at "{path}" to its second, index keeping {ms:d} ms'
+    )
+)
+def truncate_local_file_timestamp(workspace: Workspace, path: str, ms: int, tmp_path: Path):
+    disk_path = workspace.folder(tmp_path) / path[1:]
+    seconds = int(disk_path.stat().st_mtime)
+    os.utime(disk_path, ns=(seconds * 10**9, seconds * 10**9))
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "UPDATE file SET last_modified_timestamp = ? WHERE relative_path = ?",
+        (seconds * 1000 + ms, path[1:]),
+    )
+    connection.commit()
+    connection.close()
+
+
+# As if indexed by a trsync version not knowing sizes
+@when("forget indexed sizes")
+def forget_indexed_sizes(workspace: Workspace, tmp_path: Path) -> None:
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute("UPDATE file SET size = NULL")
+    connection.commit()
+    connection.close()
+
+
+@when(parsers.cfparse('replace indexed "{path}" by a local {kind} of previous revision'))
+def replace_indexed_by_previous_revision(
+    workspace: Workspace, path: str, kind: str, tmp_path: Path
+) -> None:
+    # As if remote content was of this kind at its previous revision
+    disk_path = workspace.folder(tmp_path) / path[1:]
+    if disk_path.is_dir():
+        shutil.rmtree(disk_path)
+    else:
+        disk_path.unlink()
+    if kind == "folder":
+        disk_path.mkdir()
+    else:
+        disk_path.write_text("previous")
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "UPDATE file SET is_directory = ?, content_type = ?, revision_id = revision_id - 1, "
+        "last_modified_timestamp = ? WHERE relative_path = ?",
+        (kind == "folder", kind, int(disk_path.stat().st_mtime * 1000), path[1:]),
+    )
+    connection.commit()
+    connection.close()
+
+
+@when("make trsync database fail on revision updates")
+def make_database_fail_on_revision_updates(workspace: Workspace, tmp_path: Path) -> None:
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "CREATE TRIGGER fail_revision_update BEFORE UPDATE OF revision_id ON file "
+        "BEGIN SELECT RAISE(ABORT, 'simulated index failure'); END"
+    )
+    connection.commit()
+    connection.close()
+
+
+@when(
+    parsers.cfparse(
+        "I start and wait the end of synchronization forcing download of remote {kind:w} "
+        'at "{path}"'
+    )
+)
+def sync_and_wait_forcing_download(
+    user: User, workspace: Workspace, kind: str, path: str, tmp_path: Path
+):
+    content_id = get_remote_content_id(user, workspace, path)
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync_and_wait_finished(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=["--force-download", str(content_id)],
+        )
+
+
 @when("I start synchronization")
 def start_sync(user: User, workspace: Workspace, tmp_path: Path):
     with open(tmp_path / "trsync.log", "w+") as trsync_logs:
@@ -32,6 +457,20 @@ def start_sync(user: User, workspace: Workspace, tmp_path: Path):
         )
 
 
+@when(parsers.cfparse('I start synchronization with option "{option}"'))
+def start_sync_with_option(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[option],
+        )
+
+
 @when(
     parsers.cfparse('create local file at "{path}" with content "{content}"'),
 )
@@ -48,3 +487,460 @@ def create_local_folder(
     user: User, workspace: Workspace, path: str, tmp_path: Path
 ) -> Workspace:
     (workspace.folder(tmp_path) / str(path)[1:]).mkdir(parents=True)
+
+
+@when(
+    parsers.cfparse('delete local file at "{path}"'),
+)
+def delete_local_file(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> Workspace:
+    (workspace.folder(tmp_path) / str(path)[1:]).unlink()
+
+
+@when(parsers.cfparse('delete local folder at "{path}"'))
+def delete_local_folder(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    shutil.rmtree(workspace.folder(tmp_path) / path[1:])
+
+
+@when(parsers.cfparse("lock the trsync database during {seconds:d} seconds"))
+def lock_database(user: User, workspace: Workspace, seconds: int, tmp_path: Path):
+    connection = sqlite3.connect(
+        workspace.folder(tmp_path) / ".trsync" / "trsync.db",
+        isolation_level=None,
+        check_same_thread=False,
+    )
+    connection.execute("BEGIN EXCLUSIVE")
+
+    def release():
+        time.sleep(seconds)
+        connection.execute("COMMIT")
+        connection.close()
+
+    threading.Thread(target=release).start()
+
+
+# Like a slow copy to a network mount : file content keeps growing in background
+@when(parsers.cfparse('grow local file at "{path}" during {seconds:d} seconds'))
+def grow_local_file(user: User, workspace: Workspace, path: str, seconds: int, tmp_path: Path):
+    file_path = workspace.folder(tmp_path) / path[1:]
+    file_path.write_bytes(b"")
+
+    def grow():
+        end = time.time() + seconds
+        while time.time() < end:
+            with open(file_path, "ab") as file_:
+                file_.write(b"x" * 1024)
+            time.sleep(0.25)
+
+    threading.Thread(target=grow).start()
+
+
+# Like a synchronization loop : file content keeps changing in background
+@when(parsers.cfparse('keep modifying local file at "{path}" during {seconds:d} seconds'))
+def keep_modifying_local_file(
+    user: User, workspace: Workspace, path: str, seconds: int, tmp_path: Path
+):
+    file_path = workspace.folder(tmp_path) / path[1:]
+
+    def modify():
+        end = time.time() + seconds
+        modification = 0
+        while time.time() < end:
+            modification += 1
+            file_path.write_text(f"modification {modification}")
+            time.sleep(0.5)
+
+    threading.Thread(target=modify).start()
+
+
+@when(
+    parsers.cfparse(
+        'keep modifying local and remote file at "{path}" alternately during {seconds:d} seconds'
+    )
+)
+def keep_modifying_local_and_remote_file(
+    user: User, workspace: Workspace, path: str, seconds: int, tmp_path: Path
+):
+    file_path = workspace.folder(tmp_path) / path[1:]
+    content_id = get_remote_content_id(user, workspace, path)
+
+    def modify():
+        end = time.time() + seconds
+        modification = 0
+        while time.time() < end:
+            modification += 1
+            if modification % 2:
+                file_path.write_text(f"modification {modification}")
+            else:
+                update_file(
+                    user,
+                    workspace,
+                    content_id,
+                    path[1:],
+                    f"modification {modification}".encode(),
+                )
+            time.sleep(0.5)
+
+    threading.Thread(target=modify).start()
+
+
+@when(parsers.cfparse('delete remote file at "{path}"'))
+def delete_remote_file(user: User, workspace: Workspace, path: str) -> None:
+    trash_remote_content(user, workspace, path)
+
+
+@when(parsers.cfparse('create local hard link at "{path}" to "{target}"'))
+def create_local_hard_link(
+    user: User, workspace: Workspace, path: str, target: str, tmp_path: Path
+) -> None:
+    folder = workspace.folder(tmp_path)
+    os.link(folder / target[1:], folder / path[1:])
+
+
+@when(parsers.cfparse('make local folder "{path}" read only'))
+def make_local_folder_read_only(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    (workspace.folder(tmp_path) / path[1:]).chmod(0o555)
+
+
+@when(parsers.cfparse('make local folder "{path}" unreadable'))
+def make_local_folder_unreadable(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    (workspace.folder(tmp_path) / path[1:]).chmod(0o000)
+
+
+@when(parsers.cfparse('make local file "{path}" executable'))
+def make_local_file_executable(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    (workspace.folder(tmp_path) / path[1:]).chmod(0o755)
+
+
+# Disk events are only reported for paths inside watched folder : content written
+# through an outside hard link is not seen until an attribute of the file changes
+@when(
+    parsers.cfparse(
+        'write local file at "{path}" with content "{content}" through an outside hard link'
+    )
+)
+def write_local_file_through_outside_link(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+) -> None:
+    outside = tmp_path / "outside_link"
+    os.link(workspace.folder(tmp_path) / path[1:], outside)
+    outside.write_text(content)
+    outside.unlink()
+
+
+@when(parsers.cfparse('make local file "{path}" unreadable'))
+def make_local_file_unreadable(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    (workspace.folder(tmp_path) / path[1:]).chmod(0o000)
+
+
+@when(parsers.cfparse('make local file "{path}" readable'))
+def make_local_file_readable(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    (workspace.folder(tmp_path) / path[1:]).chmod(0o644)
+
+
+@when(parsers.cfparse('create remote file at "{path}" with content "{content}"'))
+def create_remote_file(user: User, workspace: Workspace, path: str, content: str) -> None:
+    create_file(user, workspace, path[1:], content.encode())
+
+
+@when(
+    parsers.cfparse('user "{username}" creates remote file at "{path}" with content "{content}"')
+)
+def create_remote_file_as(
+    workspace: Workspace, username: str, path: str, content: str
+) -> None:
+    create_file(USERS[username], workspace, path[1:], content.encode())
+
+
+@when(
+    parsers.cfparse('user "{username}" updates remote file at "{path}" with content "{content}"')
+)
+def update_remote_file_as(
+    user: User, workspace: Workspace, username: str, path: str, content: str
+) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    update_file(USERS[username], workspace, content_id, path[1:], content.encode())
+
+
+@when(parsers.cfparse('create remote folder at "{path}"'))
+def create_remote_folder(user: User, workspace: Workspace, path: str) -> None:
+    create_folder(user, workspace, path[1:])
+
+
+@when(
+    parsers.cfparse(
+        'create remote file "{name}" in remote folder "{folder}" with content "{content}"'
+    )
+)
+def create_remote_file_in_folder(
+    user: User, workspace: Workspace, name: str, folder: str, content: str
+) -> None:
+    create_file(
+        user,
+        workspace,
+        name,
+        content.encode(),
+        parent_id=get_remote_content_id(user, workspace, folder),
+    )
+
+
+@when(parsers.cfparse('create remote file with a {length:d} characters name'))
+def create_remote_file_with_long_name(user: User, workspace: Workspace, length: int) -> None:
+    create_file(user, workspace, "a" * (length - 4) + ".txt", b"long name")
+
+
+@when(parsers.cfparse('update remote file at "{path}" with content "{content}"'))
+def update_remote_file(user: User, workspace: Workspace, path: str, content: str) -> None:
+    content_id = get_remote_content_id(user, workspace, path)
+    update_file(user, workspace, content_id, path[1:], content.encode())
+
+
+@when(parsers.cfparse('touch local file at "{path}"'))
+def touch_local_file(user: User, workspace: Workspace, path: str, tmp_path: Path) -> None:
+    (workspace.folder(tmp_path) / path[1:]).touch()
+
+
+# Corruption keeps modification time, so that it is not seen as a local change
+@when(parsers.cfparse('corrupt local file at "{path}"'))
+def corrupt_local_file(user: User, workspace: Workspace, path: str, tmp_path: Path) -> None:
+    file_path = workspace.folder(tmp_path) / path[1:]
+    stat = file_path.stat()
+    file_path.write_bytes(b"\x00" * len(file_path.read_bytes()))
+    os.utime(file_path, ns=(stat.st_atime_ns, stat.st_mtime_ns))
+
+
+# Like an unmount : synchronized folder disappear with its content and index
+@when("move synchronized folder away")
+def move_synchronized_folder_away(workspace: Workspace, tmp_path: Path) -> None:
+    os.rename(workspace.folder(tmp_path), tmp_path / "away")
+
+
+@when("move synchronized folder back")
+def move_synchronized_folder_back(workspace: Workspace, tmp_path: Path) -> None:
+    os.rename(tmp_path / "away", tmp_path / workspace.name)
+
+
+# LibreOffice save : new content written in a temporary file, original deleted, then
+# temporary file renamed to original name
+@when(parsers.cfparse('save local file at "{path}" with content "{content}" like LibreOffice'))
+def save_local_file_like_libreoffice(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+) -> None:
+    file_path = workspace.folder(tmp_path) / path[1:]
+    temp_path = file_path.with_name("lu1234abcd.tmp")
+    temp_path.write_text(content)
+    file_path.unlink()
+    os.rename(temp_path, file_path)
+
+
+# Vim save with "backupcopy=no" : original moved aside as backup, new content written
+# at original name, then backup deleted
+@when(parsers.cfparse('save local file at "{path}" with content "{content}" like vim'))
+def save_local_file_like_vim(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+) -> None:
+    file_path = workspace.folder(tmp_path) / path[1:]
+    backup_path = file_path.with_name(f"{file_path.name}~")
+    os.rename(file_path, backup_path)
+    file_path.write_text(content)
+    backup_path.unlink()
+
+
+# Plain safe save : file deleted and created again
+@when(parsers.cfparse('replace local file at "{path}" with content "{content}"'))
+def replace_local_file(
+    user: User, workspace: Workspace, path: str, content: str, tmp_path: Path
+) -> None:
+    file_path = workspace.folder(tmp_path) / path[1:]
+    file_path.unlink()
+    file_path.write_text(content)
+
+
+@when(parsers.cfparse("wait {seconds:d} seconds"))
+def wait_seconds(seconds: int) -> None:
+    time.sleep(seconds)
+
+
+@when(parsers.cfparse('I {command:w} local path "{path}"'))
+def exclusion(workspace: Workspace, command: str, path: str, tmp_path: Path) -> None:
+    execute_trsync_exclusion(workspace.folder(tmp_path), command, path)
+
+
+@when("I run trsync status", target_fixture="status_output")
+def run_status(user: User, workspace: Workspace, tmp_path: Path) -> str:
+    return execute_trsync_status(workspace.folder(tmp_path), workspace.id, user)
+
+
+@when("I run trsync status with remote", target_fixture="status_output")
+def run_status_with_remote(user: User, workspace: Workspace, tmp_path: Path) -> str:
+    return execute_trsync_status(
+        workspace.folder(tmp_path), workspace.id, user, extra_args=["--remote"]
+    )
+
+
+@when("I run trsync status as json", target_fixture="status_output")
+def run_status_as_json(user: User, workspace: Workspace, tmp_path: Path) -> str:
+    return execute_trsync_status(
+        workspace.folder(tmp_path), workspace.id, user, extra_args=["--json"]
+    )
+
+
+@when(parsers.cfparse('rename local file "{path}" to "{new_path}"'))
+def rename_local_file(
+    user: User, workspace: Workspace, path: str, new_path: str, tmp_path: Path
+) -> None:
+    folder = workspace.folder(tmp_path)
+    (folder / path[1:]).rename(folder / new_path[1:])
+
+
+@when("I run trsync doctor", target_fixture="doctor_output")
+def run_doctor(user: User, workspace: Workspace, tmp_path: Path) -> str:
+    return execute_trsync_doctor(
+        workspace.folder(tmp_path), workspace.id, user, user.password
+    )
+
+
+@when(
+    parsers.cfparse('I run trsync doctor with option "{option}"'),
+    target_fixture="doctor_output",
+)
+def run_doctor_with_option(
+    user: User, workspace: Workspace, option: str, tmp_path: Path
+) -> str:
+    return execute_trsync_doctor(
+        workspace.folder(tmp_path), workspace.id, user, user.password, extra_args=[option]
+    )
+
+
+@when("I run trsync doctor with a wrong password", target_fixture="doctor_output")
+def run_doctor_with_wrong_password(
+    user: User, workspace: Workspace, tmp_path: Path
+) -> str:
+    return execute_trsync_doctor(
+        workspace.folder(tmp_path), workspace.id, user, f"{user.password}-wrong"
+    )
+
+
+@when("I run trsync ls-remote as json", target_fixture="ls_remote_output")
+def run_ls_remote_as_json(user: User, workspace: Workspace) -> str:
+    return execute_trsync_ls_remote(workspace.id, user, extra_args=["--json"])
+
+
+@when("I start synchronization with health file")
+def start_sync_with_health_file(user: User, workspace: Workspace, tmp_path: Path):
+    with open(tmp_path / "trsync.log", "w+") as trsync_logs:
+        execute_trsync(
+            folder=workspace.folder(tmp_path),
+            workspace_id=workspace.id,
+            user=user,
+            stdout=trsync_logs,
+            extra_args=[f"--health-file={tmp_path / 'health.json'}"],
+        )
+
+
+@when(
+    parsers.cfparse(
+        'set extended attribute "{name}" to "{value}" on local file "{path}"'
+    )
+)
+def set_local_file_xattr(
+    user: User, workspace: Workspace, name: str, value: str, path: str, tmp_path: Path
+) -> None:
+    os.setxattr(workspace.folder(tmp_path) / path[1:], name, value.encode())
+
+
+@when(parsers.cfparse('move remote file at "{path}" to new workspace "{name}"'))
+def move_remote_file_to_new_workspace(
+    user: User, workspace: Workspace, path: str, name: str
+) -> None:
+    move_remote_content_to_workspace(
+        user, workspace, path, create_workspace(user, name)
+    )
+
+
+@when(parsers.cfparse('move remote file at "{path}" into remote folder "{folder}"'))
+def move_remote_file_into_folder(
+    user: User, workspace: Workspace, path: str, folder: str
+) -> None:
+    move_remote_content(user, workspace, path, folder)
+
+
+@when(parsers.cfparse('create {count:d} local files in new folder "{path}"'))
+def create_local_files_in_new_folder(
+    user: User, workspace: Workspace, count: int, path: str, tmp_path: Path
+) -> None:
+    folder = workspace.folder(tmp_path) / path[1:]
+    folder.mkdir(parents=True)
+    for i in range(count):
+        (folder / f"file_{i}.txt").write_text(f"content {i}")
+
+
+# Tree is built outside synchronized folder then moved in at once : watcher only see
+# the folder creation
+@when(parsers.cfparse('drop local folder tree at "{path}"'))
+def drop_local_folder_tree(user: User, workspace: Workspace, path: str, tmp_path: Path) -> None:
+    outside = tmp_path / "outside" / path[1:]
+    (outside / "sub" / "deep").mkdir(parents=True)
+    (outside / "file_1.txt").write_text("content 1")
+    (outside / "sub" / "file_2.txt").write_text("content 2")
+    (outside / "sub" / "deep" / "file_3.txt").write_text("content 3")
+    os.rename(outside, workspace.folder(tmp_path) / path[1:])
+
+
+@when(parsers.cfparse('recreate remote file at "{path}" into remote folder "{folder}"'))
+def recreate_remote_file_into_folder(
+    user: User, workspace: Workspace, path: str, folder: str
+) -> None:
+    create_file(
+        user,
+        workspace,
+        path.split("/")[-1],
+        FILE_CONTENTS[path],
+        parent_id=get_remote_content_id(user, workspace, folder),
+    )
+    trash_remote_content(user, workspace, path)
+
+
+@when(parsers.cfparse('journal local change "{kind}" of "{path}"'))
+def journal_local_change(
+    user: User, workspace: Workspace, kind: str, path: str, tmp_path: Path
+) -> None:
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "INSERT INTO journal (kind, relative_path) VALUES (?, ?)", (kind, path[1:])
+    )
+    connection.commit()
+    connection.close()
+
+
+@when(parsers.cfparse('save local scan checkpoint at "{path}"'))
+def save_local_scan_checkpoint(
+    user: User, workspace: Workspace, path: str, tmp_path: Path
+) -> None:
+    connection = sqlite3.connect(workspace.folder(tmp_path) / ".trsync" / "trsync.db")
+    connection.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('local_scan_checkpoint', ?)",
+        (path[1:],),
+    )
+    connection.commit()
+    connection.close()
+
+
+@when(parsers.cfparse('update remote label of "{path}" to "{label}"'))
+def update_remote_content_label(
+    user: User, workspace: Workspace, path: str, label: str
+) -> None:
+    update_remote_label(user, workspace, path, label)
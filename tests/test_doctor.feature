@@ -0,0 +1,36 @@
+Feature: Doctor
+    Diagnose common misconfigurations without synchronizing
+
+    Scenario: Valid configuration has no error
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I run trsync doctor
+
+        Then trsync doctor should report "ok" for "folder"
+        And trsync doctor should report "ok" for "credentials"
+        And trsync doctor should report "ok" for "workspace"
+        And trsync doctor should report "ok" for "ignored files"
+        And I should not see remote file at "/file_1.txt"
+
+    Scenario: Wrong password is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I run trsync doctor with a wrong password
+
+        Then trsync doctor should report "error" for "credentials"
+
+    Scenario: Ignore pattern matching every file is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And create local file at "/file_2.txt" with content "hello"
+        And I run trsync doctor with option "--ignore-pattern=*.txt"
+
+        Then trsync doctor should report "error" for "ignored files"
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_doctor.feature",
+    "Valid configuration has no error",
+)
+def test_valid_configuration():
+    pass
+
+
+@scenario(
+    "test_doctor.feature",
+    "Wrong password is reported",
+)
+def test_wrong_password():
+    pass
+
+
+@scenario(
+    "test_doctor.feature",
+    "Ignore pattern matching every file is reported",
+)
+def test_ignore_pattern_matching_everything():
+    pass
@@ -0,0 +1,14 @@
+Feature: Health
+    Report synchronization health in a status file
+
+    Scenario: Health file reports watching phase after startup
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start synchronization with health file
+
+        Then I should see local file at "/folder_1/file_1.txt"
+        And health file should report phase "watching"
+        And health file should report 0 errors
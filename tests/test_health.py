@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_health.feature",
+    "Health file reports watching phase after startup",
+)
+def test_health():
+    pass
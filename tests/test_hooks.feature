@@ -0,0 +1,14 @@
+Feature: Hooks
+    Run user commands on synchronization events
+
+    Scenario: After download hook receives downloaded file path
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start and wait the end of synchronization with option "--hook-after-download 'echo $TRSYNC_EVENT $TRSYNC_RELATIVE_PATH >> ../hooks.log'"
+
+        Then I should see local file at "/folder_1/file_1.txt"
+        And hooks log should contain "after-download folder_1/file_1.txt"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_hooks.feature",
+    "After download hook receives downloaded file path",
+)
+def test_after_download_hook():
+    pass
@@ -0,0 +1,14 @@
+Feature: LsRemote
+    List remote workspace tree without local folder
+
+    Scenario: Remote tree is listed as json
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I run trsync ls-remote as json
+
+        Then trsync ls-remote should list "/file_2.txt"
+        And trsync ls-remote should list "/folder_1"
+        And trsync ls-remote should list "/folder_1/file_1.txt"
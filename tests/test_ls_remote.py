@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_ls_remote.feature",
+    "Remote tree is listed as json",
+)
+def test_ls_remote():
+    pass
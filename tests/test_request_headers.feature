@@ -0,0 +1,15 @@
+Feature: RequestHeaders
+    Send custom user agent and headers with every request
+
+    Scenario: User agent and extra headers are sent with every request
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy with option "--user-agent 'Acme sync' --header 'X-Trace-Id: 42'"
+
+        Then I should see local file at "/file_1.txt"
+        And every proxied request should have header "User-Agent" with value "Acme sync"
+        And every proxied request should have header "X-Trace-Id" with value "42"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_request_headers.feature",
+    "User agent and extra headers are sent with every request",
+)
+def test_request_headers_are_sent():
+    pass
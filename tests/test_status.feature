@@ -0,0 +1,38 @@
+Feature: Status
+    Show differences between index, disk and remote without synchronizing
+
+    Scenario: Status lists local and remote changes
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And create local file at "/titi.txt" with content "titi"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+        And create local file at "/toto.txt" with content "toto2"
+        And delete local file at "/titi.txt"
+        And create local file at "/tata.txt" with content "tata"
+        And update remote file at "/titi.txt" with content "titi2"
+        And I run trsync status with remote
+
+        Then trsync status should list "toto.txt" as "Locally modified"
+        And trsync status should list "titi.txt" as "Locally deleted"
+        And trsync status should list "tata.txt" as "Locally new"
+        And trsync status should list "titi.txt" as "Remotely modified"
+        And I should not see remote file at "/tata.txt"
+
+    Scenario: Status as json combines index, disk and last run state
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And create local file at "/tata.txt" with content "tata"
+        And I run trsync status as json
+
+        Then trsync json status should report 1 as "indexed"
+        And trsync json status should report 1 as "locally_new"
+        And trsync json status should report "stopped" as "live.phase"
+        And trsync json status should report 0 as "live.errors"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_status.feature",
+    "Status lists local and remote changes",
+)
+def test_status():
+    pass
+
+
+@scenario(
+    "test_status.feature",
+    "Status as json combines index, disk and last run state",
+)
+def test_status_as_json():
+    pass
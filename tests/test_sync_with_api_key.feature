@@ -0,0 +1,27 @@
+Feature: SynchronizeWithApiKey
+    Authenticate with a Tracim API key instead of password
+
+    Scenario: Requests are authenticated by API key
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy expecting API key "secret" given "secret"
+
+        Then local file at "/file_1.txt" should contain "hello"
+        And every proxied request should have header "Tracim-Api-Key" with value "secret"
+        And every proxied request should have header "Tracim-Api-Login" with value "user1"
+        And proxied requests should not have header "Authorization"
+        And trsync logs should not contain errors
+
+    Scenario: Refused API key is reported as unauthorized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy expecting API key "secret" given "wrong"
+
+        Then I should not see local file at "/file_1.txt"
+        And trsync logs should contain "Unauthorized"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_api_key.feature",
+    "Requests are authenticated by API key",
+)
+def test_requests_authenticated_by_api_key():
+    pass
+
+
+@scenario(
+    "test_sync_with_api_key.feature",
+    "Refused API key is reported as unauthorized",
+)
+def test_refused_api_key_unauthorized():
+    pass
@@ -0,0 +1,49 @@
+Feature: SynchronizeWithAuthorFilter
+    Take remote contents according to the user who made their current revision
+
+    Scenario: Contents of a denied author are not synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The user "user2" is contributor of the workspace
+
+        When create remote file at "/mine.txt" with content "mine"
+        And user "user2" creates remote file at "/theirs.txt" with content "theirs"
+        And I start and wait the end of synchronization denying author "user2"
+
+        Then local file at "/mine.txt" should contain "mine"
+        And I should not see local file at "/theirs.txt"
+        And trsync database should not contain "/theirs.txt"
+        And I should see remote file at "/theirs.txt"
+        And trsync logs should not contain errors
+
+    Scenario: Only contents of an allowed author are synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The user "user2" is contributor of the workspace
+
+        When create remote folder at "/shared"
+        And user "user2" creates remote file at "/theirs.txt" with content "theirs"
+        And create remote file at "/mine.txt" with content "mine"
+        And I start and wait the end of synchronization allowing author "user1"
+
+        Then local file at "/mine.txt" should contain "mine"
+        And I should see local file at "/shared"
+        And I should not see local file at "/theirs.txt"
+        And trsync database should not contain "/theirs.txt"
+
+    Scenario: Filtered content edited by an allowed author is synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The user "user2" is contributor of the workspace
+
+        When user "user2" creates remote file at "/theirs.txt" with content "theirs"
+        And I start and wait the end of synchronization allowing author "user1"
+        And user "user1" updates remote file at "/theirs.txt" with content "edited"
+        And I start and wait the end of synchronization allowing author "user1"
+
+        Then local file at "/theirs.txt" should contain "edited"
+        And trsync database should contain "/theirs.txt" 1 times
+        And trsync logs should not contain errors
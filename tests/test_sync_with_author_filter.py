@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_author_filter.feature",
+    "Contents of a denied author are not synchronized",
+)
+def test_denied_author_contents_not_synchronized():
+    pass
+
+
+@scenario(
+    "test_sync_with_author_filter.feature",
+    "Only contents of an allowed author are synchronized",
+)
+def test_only_allowed_author_contents_synchronized():
+    pass
+
+
+@scenario(
+    "test_sync_with_author_filter.feature",
+    "Filtered content edited by an allowed author is synchronized",
+)
+def test_filtered_content_edited_by_allowed_author():
+    pass
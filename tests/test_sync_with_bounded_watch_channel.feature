@@ -0,0 +1,31 @@
+Feature: SynchronizeWithBoundedWatchChannel
+    Local watcher messages go through a bounded channel without being lost or duplicated
+
+    Scenario: Burst of new local files through a tiny watch channel
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--watch-channel-capacity=1 --watch-debounce-ms=100"
+        And wait 3 seconds
+        And create 300 local files in new folder "/folder"
+
+        Then remote folder "/folder" should contain 300 distinct files
+        And trsync database should contain "/folder/file_299.txt" 1 times
+        And trsync logs should not contain errors
+
+    Scenario: Short debounce window still correlates editor safe save
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--watch-debounce-ms=200"
+        And wait 3 seconds
+        And save local file at "/file_1.txt" with content "world" like LibreOffice
+        And wait 3 seconds
+
+        Then remote file at "/file_1.txt" should contain "world"
+        And remote file at "/file_1.txt" should have 2 revisions
+        And trsync logs should not contain errors
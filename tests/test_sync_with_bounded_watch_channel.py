@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_bounded_watch_channel.feature",
+    "Burst of new local files through a tiny watch channel",
+)
+def test_burst_through_tiny_watch_channel():
+    pass
+
+
+@scenario(
+    "test_sync_with_bounded_watch_channel.feature",
+    "Short debounce window still correlates editor safe save",
+)
+def test_short_debounce_correlates_safe_save():
+    pass
@@ -0,0 +1,33 @@
+Feature: SynchronizeWithCaseCollisions
+    Synchronize one workspace containing names differing only by case
+
+    Scenario: Remote files colliding by case are both kept on disk
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/Readme.md" with content "first"
+        And create remote file at "/README.md" with content "second"
+        And I start and wait the end of synchronization with option "--case-collision=rename"
+
+        Then local folder should contain 2 files
+        And trsync logs should contain "collides by case"
+        And trsync logs should not contain errors
+
+        When I start and wait the end of synchronization with option "--case-collision=rename"
+
+        Then local folder should contain 2 files
+        And trsync logs should not contain errors
+
+    Scenario: Remote files colliding by case are skipped
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/Readme.md" with content "first"
+        And create remote file at "/README.md" with content "second"
+        And I start and wait the end of synchronization with option "--case-collision=skip"
+
+        Then local folder should contain 1 files
+        And trsync logs should contain "do not synchronize it"
+        And trsync logs should not contain errors
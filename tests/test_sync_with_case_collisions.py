@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_case_collisions.feature",
+    "Remote files colliding by case are both kept on disk",
+)
+def test_case_collision_renamed():
+    pass
+
+
+@scenario(
+    "test_sync_with_case_collisions.feature",
+    "Remote files colliding by case are skipped",
+)
+def test_case_collision_skipped():
+    pass
@@ -0,0 +1,31 @@
+Feature: SynchronizeWithCaseOnlyRename
+    Synchronize one workspace when a file is renamed by only changing its case
+
+    Scenario: Case only rename while synchronizing is propagated
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/file.txt" with content "toto"
+
+        Then I should see remote file at "/file.txt"
+
+        When rename local file "/file.txt" to "/File.txt"
+
+        Then I should see remote file at "/File.txt"
+        And I should not see remote file at "/file.txt"
+
+    Scenario: Case only rename while stopped is propagated
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And rename local file "/file.txt" to "/File.txt"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/File.txt"
+        And I should not see remote file at "/file.txt"
+        And trsync logs should not contain errors
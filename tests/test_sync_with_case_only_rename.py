@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_case_only_rename.feature",
+    "Case only rename while synchronizing is propagated",
+)
+def test_sync_with_case_only_rename_while_synchronizing():
+    pass
+
+
+@scenario(
+    "test_sync_with_case_only_rename.feature",
+    "Case only rename while stopped is propagated",
+)
+def test_sync_with_case_only_rename_while_stopped():
+    pass
@@ -0,0 +1,49 @@
+Feature: SynchronizeWithChmodEvents
+    Attributes changes are modifications when disk file differs from index
+
+    Scenario: Content change reported as attributes change is uploaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization
+        And wait 3 seconds
+        And write local file at "/file_1.txt" with content "hello world" through an outside hard link
+        And make local file "/file_1.txt" executable
+        And wait 5 seconds
+
+        Then remote file at "/file_1.txt" should contain "hello world"
+        And trsync logs should not contain errors
+
+    Scenario: Attributes change alone is ignored
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--log-level=debug"
+        And wait 3 seconds
+        And make local file "/file_1.txt" executable
+        And wait 5 seconds
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should not contain "Operation : ModifiedLocalFile"
+
+    Scenario: Content change reported as attributes change is ignored when check is disabled
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--no-chmod-check"
+        And wait 3 seconds
+        And write local file at "/file_1.txt" with content "hello world" through an outside hard link
+        And make local file "/file_1.txt" executable
+        And wait 5 seconds
+
+        Then remote file at "/file_1.txt" should contain "hello"
+        And remote file at "/file_1.txt" should have 1 revisions
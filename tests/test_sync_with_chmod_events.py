@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_chmod_events.feature",
+    "Content change reported as attributes change is uploaded",
+)
+def test_content_change_reported_as_chmod_uploaded():
+    pass
+
+
+@scenario(
+    "test_sync_with_chmod_events.feature",
+    "Attributes change alone is ignored",
+)
+def test_attributes_change_alone_ignored():
+    pass
+
+
+@scenario(
+    "test_sync_with_chmod_events.feature",
+    "Content change reported as attributes change is ignored when check is disabled",
+)
+def test_content_change_reported_as_chmod_ignored_when_disabled():
+    pass
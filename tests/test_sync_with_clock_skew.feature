@@ -0,0 +1,24 @@
+Feature: SynchronizeWithClockSkew
+    Report a local clock differing from Tracim server clock
+
+    Scenario: Local clock behind server clock is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy with server clock 600 seconds ahead
+
+        Then I should see remote file at "/file_1.txt"
+        And trsync logs should contain "behind Tracim server clock"
+
+    Scenario: Synchronized clocks are not reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy with server clock 0 seconds ahead
+
+        Then I should see remote file at "/file_1.txt"
+        And trsync logs should not contain "Tracim server clock, check local time settings"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_clock_skew.feature",
+    "Local clock behind server clock is reported",
+)
+def test_clock_behind_reported():
+    pass
+
+
+@scenario(
+    "test_sync_with_clock_skew.feature",
+    "Synchronized clocks are not reported",
+)
+def test_synchronized_clocks_not_reported():
+    pass
@@ -0,0 +1,16 @@
+Feature: SynchronizeWithCompressedTransfers
+    Synchronize one workspace with gzip compressed transfers
+
+    Scenario: Files round trip with compressed transfers
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--compress-transfers"
+        And create remote file at "/remote.txt" with content "remote content"
+        And create local file at "/local.txt" with content "local content"
+
+        Then I should see local file at "/remote.txt"
+        And local file at "/remote.txt" should contain "remote content"
+        And I should see remote file at "/local.txt"
+        And trsync logs should not contain errors
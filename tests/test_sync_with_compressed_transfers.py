@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_compressed_transfers.feature",
+    "Files round trip with compressed transfers",
+)
+def test_files_round_trip_with_compressed_transfers():
+    pass
@@ -0,0 +1,62 @@
+Feature: SynchronizeWithConflictDetection
+    Synchronize one workspace where files changed while trsync was stopped
+
+    Scenario: File changed on neither side is not synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+
+        When touch local file at "/toto.txt"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/toto.txt" should have 1 revisions
+        And I should not see a conflicted copy of local file "/toto.txt"
+
+    Scenario: File changed only on disk is uploaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+
+        When create local file at "/toto.txt" with content "toto2"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/toto.txt" should have 2 revisions
+        And I should not see a conflicted copy of local file "/toto.txt"
+
+    Scenario: File changed only on remote is downloaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+
+        When update remote file at "/toto.txt" with content "toto2"
+        And I start and wait the end of synchronization
+
+        Then local file at "/toto.txt" should contain "toto2"
+        And I should not see a conflicted copy of local file "/toto.txt"
+
+    Scenario: File changed on both sides is a conflict
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+
+        When update remote file at "/toto.txt" with content "toto2"
+        And create local file at "/toto.txt" with content "toto3"
+        And I start and wait the end of synchronization
+
+        Then I should see a conflicted copy of local file "/toto.txt"
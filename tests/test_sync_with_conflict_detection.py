@@ -0,0 +1,33 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_conflict_detection.feature",
+    "File changed on neither side is not synchronized",
+)
+def test_sync_with_conflict_detection_neither_changed():
+    pass
+
+
+@scenario(
+    "test_sync_with_conflict_detection.feature",
+    "File changed only on disk is uploaded",
+)
+def test_sync_with_conflict_detection_local_changed():
+    pass
+
+
+@scenario(
+    "test_sync_with_conflict_detection.feature",
+    "File changed only on remote is downloaded",
+)
+def test_sync_with_conflict_detection_remote_changed():
+    pass
+
+
+@scenario(
+    "test_sync_with_conflict_detection.feature",
+    "File changed on both sides is a conflict",
+)
+def test_sync_with_conflict_detection_both_changed():
+    pass
@@ -0,0 +1,30 @@
+Feature: SynchronizeWithContentTypeChange
+    Replace disk file by a folder (or reverse) when remote content changed of type
+
+    Scenario: Local file of a content which became a folder is replaced by a folder
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/Folder"
+        And I start and wait the end of synchronization
+        And replace indexed "/Folder" by a local file of previous revision
+        And I start and wait the end of synchronization
+
+        Then local path "/Folder" should be a folder
+        And trsync logs should contain "is now a folder, replace disk file"
+        And I should see remote folder at "/Folder"
+
+    Scenario: Local folder of a content which became a file is replaced by a file
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And replace indexed "/file_1.txt" by a local folder of previous revision
+        And I start and wait the end of synchronization
+
+        Then local path "/file_1.txt" should be a file
+        And local file at "/file_1.txt" should contain "hello"
+        And trsync logs should contain "is now a file, replace disk folder"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_content_type_change.feature",
+    "Local file of a content which became a folder is replaced by a folder",
+)
+def test_file_became_folder():
+    pass
+
+
+@scenario(
+    "test_sync_with_content_type_change.feature",
+    "Local folder of a content which became a file is replaced by a file",
+)
+def test_folder_became_file():
+    pass
@@ -0,0 +1,20 @@
+Feature: SynchronizeWithCrossedDeletions
+    Synchronize one workspace when a file is deleted both locally and remotely
+
+    Scenario: File deleted locally and remotely is removed from index
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/toto.txt"
+
+        When delete remote file at "/toto.txt"
+        And delete local file at "/toto.txt"
+        And create local file at "/toto2.txt" with content "toto2"
+
+        Then I should see remote file at "/toto2.txt"
+        And trsync database should not contain "/toto.txt"
+        And trsync logs should not contain errors
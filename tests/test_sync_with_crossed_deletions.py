@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_crossed_deletions.feature",
+    "File deleted locally and remotely is removed from index",
+)
+def test_sync_with_crossed_deletions():
+    pass
@@ -0,0 +1,42 @@
+Feature: SynchronizeWithDatabaseLocation
+    Keep the index outside of synchronized folder
+
+    Scenario: Index is kept in user data directory
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with user data directory "data"
+
+        Then local file at "/file_1.txt" should contain "hello"
+        And I should see the trsync database file in user data directory "data"
+        And trsync logs should not contain errors
+
+    Scenario: Index of metadata directory is moved to user data directory
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then I should see the trsync database file
+
+        When I start and wait the end of synchronization with user data directory "data"
+
+        Then I should see the trsync database file in user data directory "data"
+        And trsync logs should contain "Move database file"
+        And trsync logs should not contain errors
+
+    Scenario: Index is kept at given database path
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with database file "index.db"
+
+        Then local file at "/file_1.txt" should contain "hello"
+        And I should see the trsync database file at "index.db"
+        And trsync logs should not contain errors
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_database_location.feature",
+    "Index is kept in user data directory",
+)
+def test_index_in_user_data_directory():
+    pass
+
+
+@scenario(
+    "test_sync_with_database_location.feature",
+    "Index of metadata directory is moved to user data directory",
+)
+def test_index_moved_to_user_data_directory():
+    pass
+
+
+@scenario(
+    "test_sync_with_database_location.feature",
+    "Index is kept at given database path",
+)
+def test_index_at_given_database_path():
+    pass
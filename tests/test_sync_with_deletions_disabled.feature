@@ -0,0 +1,18 @@
+Feature: SynchronizeWithDeletionsDisabled
+    Synchronize one workspace without propagating deletions
+
+    Scenario: Local deletion is not propagated when deletions are disabled
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--no-delete"
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/toto.txt"
+
+        When delete local file at "/toto.txt"
+        And create local file at "/toto2.txt" with content "toto2"
+
+        Then I should see remote file at "/toto2.txt"
+        And I should see remote file at "/toto.txt"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_deletions_disabled.feature",
+    "Local deletion is not propagated when deletions are disabled",
+)
+def test_sync_with_deletions_disabled():
+    pass
@@ -0,0 +1,32 @@
+Feature: SynchronizeWithDiskErrors
+    Synchronize one workspace when disk refuse to write files
+
+    Scenario: File downloaded into read only folder is not indexed
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When create local folder at "/folder_1"
+        And make local folder "/folder_1" read only
+        And I start and wait the end of synchronization
+
+        Then trsync database should not contain "/folder_1/file_1.txt"
+        And trsync logs should contain "PermissionDenied"
+        And trsync temporary directory should be empty
+
+    Scenario: Files of unreadable folder are not deleted on remote
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start and wait the end of synchronization
+        Then I should see local file at "/folder_1/file_1.txt"
+
+        When make local folder "/folder_1" unreadable
+        And I start and wait the end of synchronization
+
+        Then trsync logs should contain "Disk scan skipped 1 path(s)"
+        And I should see remote file at "/folder_1/file_1.txt"
+        And I should see remote file at "/file_2.txt"
@@ -0,0 +1,24 @@
+import os
+
+import pytest
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_disk_errors.feature",
+    "File downloaded into read only folder is not indexed",
+)
+def test_sync_with_disk_errors():
+    pass
+
+
+@pytest.mark.skipif(
+    hasattr(os, "geteuid") and os.geteuid() == 0,
+    reason="root can read unreadable folders",
+)
+@scenario(
+    "test_sync_with_disk_errors.feature",
+    "Files of unreadable folder are not deleted on remote",
+)
+def test_sync_with_unreadable_folder():
+    pass
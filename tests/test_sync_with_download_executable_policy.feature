@@ -0,0 +1,58 @@
+Feature: SynchronizeWithDownloadExecutablePolicy
+    Control permissions of downloaded files and executable contents
+
+    Scenario: Executable content is made executable with preserve permissions policy
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/script.sh" with content "#!/bin/sh echo hello"
+        And create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with option "--download-executable-policy=preserve-permissions"
+
+        Then local file at "/script.sh" should contain "#!/bin/sh echo hello"
+        And local file at "/script.sh" should be executable
+        And local file at "/file_1.txt" should not be executable
+
+    Scenario: Updated disk file keeps its permissions with preserve permissions policy
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/tool.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And make local file "/tool.txt" executable
+        And update remote file at "/tool.txt" with content "world"
+        And I start and wait the end of synchronization with option "--download-executable-policy=preserve-permissions"
+
+        Then local file at "/tool.txt" should contain "world"
+        And local file at "/tool.txt" should be executable
+
+    Scenario: Executable bit is removed with strip executable policy
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/script.sh" with content "#!/bin/sh echo hello"
+        And I start and wait the end of synchronization
+        And make local file "/script.sh" executable
+        And update remote file at "/script.sh" with content "#!/bin/sh echo world"
+        And I start and wait the end of synchronization with option "--download-executable-policy=strip-executable"
+
+        Then local file at "/script.sh" should contain "#!/bin/sh echo world"
+        And local file at "/script.sh" should not be executable
+
+    Scenario: Executable content is written into quarantine folder with quarantine policy
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/script.sh" with content "#!/bin/sh echo hello"
+        And create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with option "--download-executable-policy=quarantine"
+
+        Then I should not see local file at "/script.sh"
+        And quarantined file at "/script.sh" should contain "#!/bin/sh echo hello"
+        And local file at "/file_1.txt" should contain "hello"
+        And I should see remote file at "/script.sh"
+        And trsync logs should not contain errors
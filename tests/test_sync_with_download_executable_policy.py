@@ -0,0 +1,33 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_download_executable_policy.feature",
+    "Executable content is made executable with preserve permissions policy",
+)
+def test_executable_content_with_preserve_permissions():
+    pass
+
+
+@scenario(
+    "test_sync_with_download_executable_policy.feature",
+    "Updated disk file keeps its permissions with preserve permissions policy",
+)
+def test_updated_file_keeps_permissions():
+    pass
+
+
+@scenario(
+    "test_sync_with_download_executable_policy.feature",
+    "Executable bit is removed with strip executable policy",
+)
+def test_executable_bit_stripped():
+    pass
+
+
+@scenario(
+    "test_sync_with_download_executable_policy.feature",
+    "Executable content is written into quarantine folder with quarantine policy",
+)
+def test_executable_content_quarantined():
+    pass
@@ -0,0 +1,75 @@
+Feature: SynchronizeWithEmptyFolders
+    Synchronize local folders as remote folders, whether they contain files or not
+
+    Scenario: Empty folder created while synchronizing is created on remote
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local folder at "/empty"
+        And create local folder at "/parent/nested_empty"
+
+        Then I should see remote folder at "/empty"
+        And I should see remote folder at "/parent/nested_empty"
+        And trsync logs should not contain errors
+
+    Scenario: Empty folder found at startup is created on remote
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/empty"
+        And I start and wait the end of synchronization
+
+        Then I should see remote folder at "/empty"
+        And trsync logs should not contain errors
+
+    Scenario: Folder emptied of its last file is kept on remote
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder"
+        And create local file at "/folder/file.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And delete local file at "/folder/file.txt"
+        And I start and wait the end of synchronization
+
+        Then I should see remote folder at "/folder"
+        And I should not see remote file at "/folder/file.txt"
+        And trsync logs should not contain "Operation : ModifiedLocalFile"
+        And trsync logs should not contain errors
+
+    Scenario: Folder deleted on remote is removed with its ignored files
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder"
+        And create local file at "/folder/file.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And create local file at "/folder/~draft.txt" with content "draft"
+        And delete remote file at "/folder"
+        And I start and wait the end of synchronization
+
+        Then local file at "/folder/~draft.txt" should not exist
+        And local file at "/folder" should not exist
+        And trsync logs should not contain errors
+
+    Scenario: Folder deleted on remote is kept when it contains ignored files
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder"
+        And create local file at "/folder/file.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And create local file at "/folder/~draft.txt" with content "draft"
+        And delete remote file at "/folder"
+        And I start and wait the end of synchronization with option "--leftover-files=keep"
+
+        Then local file at "/folder/file.txt" should not exist
+        And I should see local file at "/folder/~draft.txt"
+        And trsync logs should contain "deleted on remote is kept"
+        And trsync logs should not contain errors
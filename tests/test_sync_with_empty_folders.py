@@ -0,0 +1,41 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_empty_folders.feature",
+    "Empty folder created while synchronizing is created on remote",
+)
+def test_empty_folder_created_while_synchronizing():
+    pass
+
+
+@scenario(
+    "test_sync_with_empty_folders.feature",
+    "Empty folder found at startup is created on remote",
+)
+def test_empty_folder_found_at_startup():
+    pass
+
+
+@scenario(
+    "test_sync_with_empty_folders.feature",
+    "Folder emptied of its last file is kept on remote",
+)
+def test_folder_emptied_kept_on_remote():
+    pass
+
+
+@scenario(
+    "test_sync_with_empty_folders.feature",
+    "Folder deleted on remote is removed with its ignored files",
+)
+def test_remote_deleted_folder_removed_with_ignored_files():
+    pass
+
+
+@scenario(
+    "test_sync_with_empty_folders.feature",
+    "Folder deleted on remote is kept when it contains ignored files",
+)
+def test_remote_deleted_folder_kept_with_ignored_files():
+    pass
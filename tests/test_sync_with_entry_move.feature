@@ -0,0 +1,54 @@
+Feature: SynchronizeWithEntryMove
+    A moved content is indexed once, at its new path and its remote revision
+
+    Scenario: Local file rename is indexed with its revision
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization
+        And wait 3 seconds
+        And rename local file "/file_1.txt" to "/file_2.txt"
+        And wait 5 seconds
+
+        Then I should see remote file at "/file_2.txt"
+        And trsync database should not contain "/file_1.txt"
+        And trsync database should index "/file_2.txt" at its remote revision
+        And trsync logs should not contain errors
+
+    Scenario: Local folder rename moves indexed descendants
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/Folder_1"
+        And create local file at "/Folder_1/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization
+        And wait 3 seconds
+        And rename local file "/Folder_1" to "/Folder_2"
+        And wait 5 seconds
+
+        Then I should see remote file at "/Folder_2/file_1.txt"
+        And trsync database should not contain "/Folder_1/file_1.txt"
+        And trsync database should index "/Folder_2" at its remote revision
+        And trsync database should index "/Folder_2/file_1.txt" at its remote revision
+        And trsync logs should not contain errors
+
+    Scenario: Remote move is indexed with its revision
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/Folder_1"
+        And create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And move remote file at "/file_1.txt" into remote folder "/Folder_1"
+        And I start and wait the end of synchronization
+
+        Then I should see local file at "/Folder_1/file_1.txt"
+        And trsync database should not contain "/file_1.txt"
+        And trsync database should index "/Folder_1/file_1.txt" at its remote revision
+        And trsync logs should not contain errors
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_entry_move.feature",
+    "Local file rename is indexed with its revision",
+)
+def test_local_file_rename_indexed_with_revision():
+    pass
+
+
+@scenario(
+    "test_sync_with_entry_move.feature",
+    "Local folder rename moves indexed descendants",
+)
+def test_local_folder_rename_moves_descendants():
+    pass
+
+
+@scenario(
+    "test_sync_with_entry_move.feature",
+    "Remote move is indexed with its revision",
+)
+def test_remote_move_indexed_with_revision():
+    pass
@@ -0,0 +1,61 @@
+Feature: SynchronizeWithExclusions
+    Never synchronize excluded paths, in any direction, until they are included again
+
+    Scenario: Local changes of excluded paths are synchronized once included again
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/file_1.txt"
+
+        When I exclude local path "/file_1.txt"
+        And I exclude local path "folder_1"
+        And create local folder at "/folder_1"
+        And create local file at "/folder_1/file_2.txt" with content "hello"
+        And replace local file at "/file_1.txt" with content "hello2"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And I should not see remote folder at "/folder_1"
+        And I should not see remote file at "/folder_1/file_2.txt"
+        And trsync logs should not contain errors
+
+        When I run trsync status
+
+        Then trsync status should list "file_1.txt" as "Excluded"
+        And trsync status should list "folder_1" as "Excluded"
+
+        When I include local path "/file_1.txt"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/file_1.txt" should have 2 revisions
+        And I should not see remote file at "/folder_1/file_2.txt"
+
+    Scenario: Remote changes of excluded paths keep local copy untouched
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then I should see local file at "/file_1.txt"
+
+        When I exclude local path "/file_1.txt"
+        And update remote file at "/file_1.txt" with content "hello2"
+        And I start and wait the end of synchronization
+
+        Then local file at "/file_1.txt" should not contain "hello2"
+
+        When delete remote file at "/file_1.txt"
+        And I start and wait the end of synchronization
+
+        Then I should see local file at "/file_1.txt"
+
+        When I include local path "/file_1.txt"
+        And I start and wait the end of synchronization
+
+        Then local file at "/file_1.txt" should not exist
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_exclusions.feature",
+    "Local changes of excluded paths are synchronized once included again",
+)
+def test_excluded_local_changes():
+    pass
+
+
+@scenario(
+    "test_sync_with_exclusions.feature",
+    "Remote changes of excluded paths keep local copy untouched",
+)
+def test_excluded_remote_changes():
+    pass
@@ -0,0 +1,29 @@
+Feature: SynchronizeWithExistingLocalFiles
+    Synchronize one workspace into a folder already containing its files
+
+    Scenario: Local file identical to remote file is adopted
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When create local file at "/file_2.txt" with content "Hello world !"
+        And I start and wait the end of synchronization
+
+        Then Local folder contains "Set1"
+        And trsync logs should contain "is identical to remote content"
+        And I should not see a conflicted copy of local file "/file_2.txt"
+        And remote file at "/file_2.txt" should have 1 revisions
+        And trsync logs should not contain errors
+
+    Scenario: Local file different from remote file is a conflict
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When create local file at "/file_2.txt" with content "Hello other world !"
+        And I start and wait the end of synchronization
+
+        Then I should see a conflicted copy of local file "/file_2.txt"
+        And local file at "/file_2.txt" should contain "Hello world !"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_existing_local_files.feature",
+    "Local file identical to remote file is adopted",
+)
+def test_identical_local_file_is_adopted():
+    pass
+
+
+@scenario(
+    "test_sync_with_existing_local_files.feature",
+    "Local file different from remote file is a conflict",
+)
+def test_different_local_file_is_a_conflict():
+    pass
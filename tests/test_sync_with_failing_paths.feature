@@ -0,0 +1,30 @@
+Feature: SynchronizeWithFailingPaths
+    Skip paths which keep failing instead of retrying them forever
+
+    Scenario: Path failing consecutively is skipped until it changes
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And make local file "/file_1.txt" unreadable
+        And I start and wait the end of synchronization with option "--max-consecutive-failures=2"
+        And I start and wait the end of synchronization with option "--max-consecutive-failures=2"
+
+        Then trsync logs should contain "failed 2 consecutive times"
+
+        When I start and wait the end of synchronization with option "--max-consecutive-failures=2"
+
+        Then trsync logs should not contain "Error when"
+        And I should not see remote file at "/file_1.txt"
+
+        When I run trsync status
+
+        Then trsync status should list "file_1.txt" as "Failing"
+
+        When make local file "/file_1.txt" readable
+        And create local file at "/file_1.txt" with content "hello again"
+        And I start and wait the end of synchronization with option "--max-consecutive-failures=2"
+
+        Then I should see remote file at "/file_1.txt"
+        And trsync logs should contain "changed on disk"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_failing_paths.feature",
+    "Path failing consecutively is skipped until it changes",
+)
+def test_failing_path_is_skipped():
+    pass
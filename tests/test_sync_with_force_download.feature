@@ -0,0 +1,23 @@
+Feature: SynchronizeWithForceDownload
+    Download again a single remote content on demand, whatever its disk state
+
+    Scenario: Corrupted local file is restored from remote by forcing its download
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then local file at "/file_1.txt" should contain "hello"
+
+        When corrupt local file at "/file_1.txt"
+        And I start and wait the end of synchronization
+
+        Then local file at "/file_1.txt" should not contain "hello"
+
+        When I start and wait the end of synchronization forcing download of remote file at "/file_1.txt"
+
+        Then local file at "/file_1.txt" should contain "hello"
+        And remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_force_download.feature",
+    "Corrupted local file is restored from remote by forcing its download",
+)
+def test_corrupted_file_restored_by_force_download():
+    pass
@@ -0,0 +1,23 @@
+Feature: SynchronizeWithForcePush
+    Upload a disk file again, replacing remote content whatever its state
+
+    Scenario: Forced push replaces a newer remote revision
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "v1"
+        And I start and wait the end of synchronization
+
+        Then I should see local file at "/file_1.txt"
+
+        When update remote file at "/file_1.txt" with content "remote"
+        And replace local file at "/file_1.txt" with content "local"
+        And I start and wait the end of synchronization with option "--force-push=/file_1.txt"
+
+        Then remote file at "/file_1.txt" should contain "local"
+        And remote file at "/file_1.txt" should have 3 revisions
+        And local file at "/file_1.txt" should contain "local"
+        And I should not see a conflicted copy of local file "/file_1.txt"
+        And trsync logs should contain "Force push of"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_force_push.feature",
+    "Forced push replaces a newer remote revision",
+)
+def test_force_push_replaces_newer_remote_revision():
+    pass
@@ -0,0 +1,42 @@
+Feature: SynchronizeWithForcedFolders
+    Force download or upload of a folder applies to all its indexed contents
+
+    Scenario: Corrupted local file is restored by forcing download of its folder
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote folder at "/folder_1"
+        And create remote file "file_1.txt" in remote folder "/folder_1" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then local file at "/folder_1/file_1.txt" should contain "hello"
+
+        When corrupt local file at "/folder_1/file_1.txt"
+        And I start and wait the end of synchronization
+
+        Then local file at "/folder_1/file_1.txt" should not contain "hello"
+
+        When I start and wait the end of synchronization forcing download of remote folder at "/folder_1"
+
+        Then local file at "/folder_1/file_1.txt" should contain "hello"
+        And trsync logs should contain "Force download of"
+        And trsync logs should not contain errors
+
+    Scenario: Unchanged local file is uploaded again by forcing push of its folder
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote folder at "/folder_1"
+        And create remote file "file_1.txt" in remote folder "/folder_1" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then local file at "/folder_1/file_1.txt" should contain "hello"
+
+        When I start and wait the end of synchronization with option "--force-push=/folder_1"
+
+        Then remote file at "/folder_1/file_1.txt" should have 2 revisions
+        And remote file at "/folder_1/file_1.txt" should contain "hello"
+        And trsync logs should contain "Force push of"
+        And trsync logs should not contain errors
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_forced_folders.feature",
+    "Corrupted local file is restored by forcing download of its folder",
+)
+def test_corrupted_file_restored_by_folder_force_download():
+    pass
+
+
+@scenario(
+    "test_sync_with_forced_folders.feature",
+    "Unchanged local file is uploaded again by forcing push of its folder",
+)
+def test_unchanged_file_uploaded_by_folder_force_push():
+    pass
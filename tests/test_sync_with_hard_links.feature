@@ -0,0 +1,14 @@
+Feature: SynchronizeWithHardLinks
+    Synchronize one workspace containing hard linked files
+
+    Scenario: Only one path of hard linked files is synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And create local hard link at "/toto_link.txt" to "/toto.txt"
+        And I start and wait the end of synchronization with option "--detect-hardlinks"
+
+        Then I should see remote file at "/toto.txt"
+        And I should not see remote file at "/toto_link.txt"
@@ -0,0 +1,13 @@
+import sys
+
+import pytest
+from pytest_bdd import scenario, given, when, then
+
+
+@pytest.mark.skipif(sys.platform == "win32", reason="hard links need unix inodes")
+@scenario(
+    "test_sync_with_hard_links.feature",
+    "Only one path of hard linked files is synchronized",
+)
+def test_sync_with_hard_links():
+    pass
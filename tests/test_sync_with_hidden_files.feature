@@ -0,0 +1,16 @@
+Feature: SynchronizeWithHiddenFiles
+    Synchronize one workspace with hidden files and custom ignore patterns
+
+    Scenario: Hidden files are synchronized and custom patterns are ignored
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--sync-hidden-files --ignore-pattern '*.swp'"
+        And create local file at "/.env.example" with content "KEY=value"
+        And create local file at "/notes.txt.swp" with content "swap"
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/.env.example"
+        And I should see remote file at "/toto.txt"
+        And I should not see remote file at "/notes.txt.swp"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_hidden_files.feature",
+    "Hidden files are synchronized and custom patterns are ignored",
+)
+def test_sync_with_hidden_files():
+    pass
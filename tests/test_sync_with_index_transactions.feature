@@ -0,0 +1,19 @@
+Feature: SynchronizeWithIndexTransactions
+    Index changes of an operation are all committed, or none of them
+
+    Scenario: Failed rename leaves index entry untouched
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And make trsync database fail on revision updates
+        And I start synchronization
+        And wait 3 seconds
+        And rename local file "/file_1.txt" to "/file_2.txt"
+        And wait 5 seconds
+
+        Then trsync logs should contain "simulated index failure"
+        And trsync database should contain "/file_1.txt" 1 times
+        And trsync database should not contain "/file_2.txt"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_index_transactions.feature",
+    "Failed rename leaves index entry untouched",
+)
+def test_failed_rename_leaves_index_untouched():
+    pass
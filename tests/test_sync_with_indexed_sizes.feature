@@ -0,0 +1,49 @@
+Feature: SynchronizeWithIndexedSizes
+    Indexed sizes tell if a disk file modified while stopped really changed
+
+    Scenario: Touched file of indexed size is not uploaded again
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+        And touch local file at "/file_1.txt"
+        And I start and wait the end of synchronization with option "--log-level=debug"
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should contain "changed but not its content"
+        And trsync logs should not contain errors
+
+    Scenario: Modified file of indexed size is uploaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+        And replace local file at "/file_1.txt" with content "world"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/file_1.txt" should contain "world"
+        And remote file at "/file_1.txt" should have 2 revisions
+        And trsync logs should not contain errors
+
+    Scenario: Touched file of unknown indexed size is not compared at startup
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And forget indexed sizes
+        And wait 3 seconds
+        And touch local file at "/file_1.txt"
+        And I start and wait the end of synchronization with option "--log-level=debug"
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should not contain "changed but not its content"
+        And trsync logs should contain "is already synchronized, nothing to upload"
+        And trsync logs should not contain errors
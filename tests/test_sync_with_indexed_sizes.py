@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_indexed_sizes.feature",
+    "Touched file of indexed size is not uploaded again",
+)
+def test_touched_file_of_indexed_size_not_uploaded():
+    pass
+
+
+@scenario(
+    "test_sync_with_indexed_sizes.feature",
+    "Modified file of indexed size is uploaded",
+)
+def test_modified_file_of_indexed_size_uploaded():
+    pass
+
+
+@scenario(
+    "test_sync_with_indexed_sizes.feature",
+    "Touched file of unknown indexed size is not compared at startup",
+)
+def test_touched_file_of_unknown_size_not_compared():
+    pass
@@ -0,0 +1,20 @@
+Feature: SynchronizeWithLabels
+    Synchronize one workspace with remote labels as extended attributes
+
+    Scenario: Labels round trip between remote and disk
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When update remote label of "/file_2.txt" to "Remote label"
+        And I start and wait the end of synchronization with option "--sync-labels"
+
+        Then local file at "/file_2.txt" should have extended attribute "user.tracim.label" with value "Remote label"
+
+        When I start synchronization with option "--sync-labels"
+        And set extended attribute "user.tracim.label" to "Disk label" on local file "/file_2.txt"
+
+        Then I should see remote content labelled "Disk label"
+        And I should see local file at "/file_2.txt"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_labels.feature",
+    "Labels round trip between remote and disk",
+)
+def test_labels_round_trip():
+    pass
@@ -0,0 +1,22 @@
+Feature: SynchronizeWithLocalMove
+    Move remote content when its local file is moved, without uploading it again
+
+    Scenario: File moved into a new local folder is moved on remote
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization through a proxy recording requests
+        And wait 3 seconds
+        And create local folder at "/new_folder"
+        And rename local file "/file_1.txt" to "/new_folder/file_1.txt"
+
+        Then I should see remote file at "/new_folder/file_1.txt"
+        And I should not see remote file at "/file_1.txt"
+        And proxied requests should contain 1 "POST" requests to "/contents"
+        And proxied requests should contain 1 "PUT" requests to "/move"
+        And proxied requests should contain 0 "POST" requests to "/files"
+        And proxied requests should contain 0 "PUT" requests to "/raw/.*"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_local_move.feature",
+    "File moved into a new local folder is moved on remote",
+)
+def test_file_moved_into_new_folder():
+    pass
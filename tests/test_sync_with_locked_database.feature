@@ -0,0 +1,17 @@
+Feature: SynchronizeWithLockedDatabase
+    Synchronize one workspace while database is briefly locked by an other process
+
+    Scenario: Local file is synchronized after database lock is released
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/toto.txt"
+
+        When lock the trsync database during 3 seconds
+        And create local file at "/toto2.txt" with content "toto2"
+
+        Then I should see remote file at "/toto2.txt"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_locked_database.feature",
+    "Local file is synchronized after database lock is released",
+)
+def test_sync_with_locked_database():
+    pass
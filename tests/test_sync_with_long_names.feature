@@ -0,0 +1,18 @@
+Feature: SynchronizeWithLongNames
+    Synchronize one workspace containing names too long for disk
+
+    Scenario: Remote file with a too long name is truncated on disk
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file with a 300 characters name
+        And I start and wait the end of synchronization
+
+        Then local file names should fit in 255 bytes
+        And trsync logs should not contain errors
+
+        When I start and wait the end of synchronization
+
+        Then local folder should contain 1 files
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_long_names.feature",
+    "Remote file with a too long name is truncated on disk",
+)
+def test_too_long_remote_name_is_truncated():
+    pass
@@ -0,0 +1,47 @@
+Feature: SynchronizeWithLoopDetection
+    Pause synchronization of a content modified endlessly
+
+    Scenario: Content modified too many times is paused
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--log-level=debug --loop-max-operations=3"
+        And wait 3 seconds
+        And keep modifying local and remote file at "/file_1.txt" alternately during 6 seconds
+        And wait 9 seconds
+
+        Then trsync logs should contain "looks like a synchronization loop"
+        And trsync logs should contain "is looping"
+
+    Scenario: Content modified many times is not paused when detection is disabled
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--log-level=debug --loop-max-operations=0"
+        And wait 3 seconds
+        And keep modifying local and remote file at "/file_1.txt" alternately during 6 seconds
+        And wait 9 seconds
+
+        Then trsync logs should not contain "looks like a synchronization loop"
+        And trsync logs should not contain "is looping"
+
+    Scenario: Content saved many times on disk is not paused
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And I start synchronization with option "--log-level=debug --loop-max-operations=3"
+        And wait 3 seconds
+        And keep modifying local file at "/file_1.txt" during 6 seconds
+        And wait 9 seconds
+
+        Then trsync logs should not contain "looks like a synchronization loop"
+        And trsync logs should not contain "is looping"
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_loop_detection.feature",
+    "Content modified too many times is paused",
+)
+def test_content_modified_too_many_times_is_paused():
+    pass
+
+
+@scenario(
+    "test_sync_with_loop_detection.feature",
+    "Content modified many times is not paused when detection is disabled",
+)
+def test_content_not_paused_when_detection_disabled():
+    pass
+
+
+@scenario(
+    "test_sync_with_loop_detection.feature",
+    "Content saved many times on disk is not paused",
+)
+def test_content_saved_many_times_is_not_paused():
+    pass
@@ -0,0 +1,21 @@
+Feature: SynchronizeWithMetadataDirectory
+    Synchronize one workspace without synchronizing trsync metadata directory
+
+    Scenario: Files of metadata directory are not synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/toto.txt"
+        And I should see the trsync database file
+
+        When create local file at "/.trsync/notes.txt" with content "notes"
+        And create local file at "/toto2.txt" with content "toto2"
+
+        Then I should see remote file at "/toto2.txt"
+        And I should not see remote file at "/.trsync/notes.txt"
+        And I should not see remote folder at "/.trsync"
+        And trsync logs should not contain ".trsync/notes.txt"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_metadata_directory.feature",
+    "Files of metadata directory are not synchronized",
+)
+def test_sync_with_metadata_directory():
+    pass
@@ -0,0 +1,42 @@
+Feature: SynchronizeWithMissingFolder
+    Pause synchronization while synchronized folder is missing, instead of deleting its content
+
+    Scenario: Synchronization resume when synchronized folder is back
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/file_1.txt" with content "hello"
+
+        Then I should see remote file at "/file_1.txt"
+
+        When move synchronized folder away
+        And wait 3 seconds
+
+        Then I should see remote file at "/file_1.txt"
+        And trsync logs should contain "disappeared, pause synchronization until it is back"
+
+        When move synchronized folder back
+        And create local file at "/file_2.txt" with content "hello"
+
+        Then I should see remote file at "/file_2.txt"
+        And I should see remote file at "/file_1.txt"
+        And trsync logs should contain "is back, watch it again"
+        And trsync logs should not contain errors
+
+    Scenario: Synchronization fail when synchronized folder stays missing
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--missing-folder-timeout-s=2"
+        And create local file at "/file_1.txt" with content "hello"
+
+        Then I should see remote file at "/file_1.txt"
+
+        When move synchronized folder away
+        And wait 5 seconds
+
+        Then trsync logs should contain "is missing since"
+        And I should see remote file at "/file_1.txt"
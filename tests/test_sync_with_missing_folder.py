@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_missing_folder.feature",
+    "Synchronization resume when synchronized folder is back",
+)
+def test_resume_when_folder_back():
+    pass
+
+
+@scenario(
+    "test_sync_with_missing_folder.feature",
+    "Synchronization fail when synchronized folder stays missing",
+)
+def test_fail_when_folder_stays_missing():
+    pass
@@ -0,0 +1,21 @@
+Feature: SynchronizeWithMissingLocalFolders
+    Download into indexed folders which are missing on disk
+
+    Scenario: Indexed folder removed from disk is created again for a download
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder_1"
+        And create local file at "/folder_1/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/folder_1/file_1.txt"
+
+        When delete local folder at "/folder_1"
+        And create remote file "file_2.txt" in remote folder "/folder_1" with content "hello"
+        And I start and wait the end of synchronization with option "--no-delete"
+
+        Then I should see local file at "/folder_1/file_2.txt"
+        And trsync logs should contain "was missing on disk, it has been created again"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_missing_local_folders.feature",
+    "Indexed folder removed from disk is created again for a download",
+)
+def test_missing_indexed_folder_is_created_again():
+    pass
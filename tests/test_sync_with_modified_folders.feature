@@ -0,0 +1,21 @@
+Feature: SynchronizeWithModifiedFolders
+    Folder modification time changes with its children, without anything to upload
+
+    Scenario: Children changes of a watched folder do not update the remote folder
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder_1"
+        And create local file at "/folder_1/file_1.txt" with content "hello"
+        And I start synchronization with option "--log-level=debug"
+
+        Then I should see remote file at "/folder_1/file_1.txt"
+
+        When create local file at "/folder_1/file_2.txt" with content "world"
+        And delete local file at "/folder_1/file_1.txt"
+
+        Then I should see remote file at "/folder_1/file_2.txt"
+        And I should not see remote file at "/folder_1/file_1.txt"
+        And trsync logs should not contain "Update remote remote"
+        And trsync logs should not contain errors
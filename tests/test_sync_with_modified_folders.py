@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_modified_folders.feature",
+    "Children changes of a watched folder do not update the remote folder",
+)
+def test_children_changes_do_not_update_remote_folder():
+    pass
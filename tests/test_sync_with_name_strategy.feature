@@ -0,0 +1,56 @@
+Feature: SynchronizeWithNameStrategy
+    Synchronize one workspace with local file names built from label or filename
+
+    Scenario: Local file names are remote filenames
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace contains file "report.txt" labelled "Quarterly report" with content "toto"
+
+        When I start synchronization with option "--name-strategy filename"
+        And create local file at "/notes.txt" with content "notes"
+
+        Then I should see local file at "/Quarterly report.txt"
+        And I should see remote file at "/notes.txt"
+
+    Scenario: Local file names are remote labels with extension
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace contains file "report.txt" labelled "Quarterly report" with content "toto"
+
+        When I start synchronization with option "--name-strategy label-ext"
+        And create local file at "/notes.txt" with content "notes"
+
+        Then I should see local file at "/Quarterly report.txt"
+        And I should see remote file at "/notes.txt"
+        And I should see remote content labelled "notes"
+
+    Scenario: Local file names are remote labels
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace contains file "report.txt" labelled "Quarterly report" with content "toto"
+
+        When I start synchronization with option "--name-strategy label"
+        And create local file at "/notes.txt" with content "notes"
+
+        Then I should see local file at "/Quarterly report"
+        And I should see remote content labelled "notes.txt"
+
+    Scenario: Local rename of a file whose label differs from its filename
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace contains file "report.txt" labelled "Quarterly report" with content "toto"
+
+        When I start synchronization with option "--name-strategy filename"
+        Then I should see local file at "/Quarterly report.txt"
+
+        When rename local file "/Quarterly report.txt" to "/Annual report.txt"
+
+        Then I should see remote file at "/Annual report.txt"
+        And I should not see remote file at "/Quarterly report.txt"
+        And I should see local file at "/Annual report.txt"
+        And local file at "/Quarterly report.txt" should not exist
+        And trsync logs should not contain errors
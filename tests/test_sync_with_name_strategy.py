@@ -0,0 +1,33 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_name_strategy.feature",
+    "Local file names are remote filenames",
+)
+def test_sync_with_filename_strategy():
+    pass
+
+
+@scenario(
+    "test_sync_with_name_strategy.feature",
+    "Local file names are remote labels with extension",
+)
+def test_sync_with_label_with_extension_strategy():
+    pass
+
+
+@scenario(
+    "test_sync_with_name_strategy.feature",
+    "Local file names are remote labels",
+)
+def test_sync_with_label_strategy():
+    pass
+
+
+@scenario(
+    "test_sync_with_name_strategy.feature",
+    "Local rename of a file whose label differs from its filename",
+)
+def test_rename_with_label_different_from_filename():
+    pass
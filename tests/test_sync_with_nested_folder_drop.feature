@@ -0,0 +1,36 @@
+Feature: SynchronizeWithNestedFolderDrop
+    Synchronize folder trees appearing at once, whatever the order of their events
+
+    Scenario: Folder tree moved in at once is created once with its content
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And drop local folder tree at "/dropped"
+
+        Then I should see remote file at "/dropped/sub/deep/file_3.txt"
+        And I should see remote content at "/dropped" 1 times
+        And I should see remote content at "/dropped/sub" 1 times
+        And I should see remote content at "/dropped/sub/deep" 1 times
+        And I should see remote content at "/dropped/file_1.txt" 1 times
+        And I should see remote content at "/dropped/sub/file_2.txt" 1 times
+        And I should see remote content at "/dropped/sub/deep/file_3.txt" 1 times
+        And trsync database should contain "/dropped/sub" 1 times
+        And trsync database should contain "/dropped/sub/deep/file_3.txt" 1 times
+        And trsync logs should not contain errors
+
+    Scenario: Folder tree written while synchronizing is created once with its content
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create 20 local files in new folder "/folder/sub"
+
+        Then I should see remote file at "/folder/sub/file_19.txt"
+        And I should see remote content at "/folder" 1 times
+        And I should see remote content at "/folder/sub" 1 times
+        And I should see remote content at "/folder/sub/file_0.txt" 1 times
+        And trsync database should contain "/folder/sub/file_0.txt" 1 times
+        And trsync logs should not contain errors
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_nested_folder_drop.feature",
+    "Folder tree moved in at once is created once with its content",
+)
+def test_folder_tree_moved_in():
+    pass
+
+
+@scenario(
+    "test_sync_with_nested_folder_drop.feature",
+    "Folder tree written while synchronizing is created once with its content",
+)
+def test_folder_tree_written():
+    pass
@@ -0,0 +1,32 @@
+Feature: SynchronizeWithNetworkFilesystem
+    Network file systems store timestamps by second and don't report disk events
+
+    Scenario: Timestamps truncated to the second are unchanged with network profile
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And truncate local file timestamp at "/file_1.txt" to its second, index keeping 999 ms
+        And I start and wait the end of synchronization with option "--log-level=debug --timestamp-tolerance-ms=0 --filesystem-profile=network"
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should contain "Network file system profile"
+        And trsync logs should not contain "changed but not its content"
+        And trsync logs should not contain errors
+
+    Scenario: Timestamps truncated to the second are changed with local profile
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+        And truncate local file timestamp at "/file_1.txt" to its second, index keeping 999 ms
+        And I start and wait the end of synchronization with option "--log-level=debug --timestamp-tolerance-ms=0 --filesystem-profile=local"
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should not contain "Network file system profile"
+        And trsync logs should contain "changed but not its content"
+        And trsync logs should not contain errors
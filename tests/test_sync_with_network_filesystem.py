@@ -0,0 +1,18 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_network_filesystem.feature",
+    "Timestamps truncated to the second are unchanged with network profile",
+)
+def test_truncated_timestamps_unchanged_with_network_profile():
+    pass
+
+
+@scenario(
+    "test_sync_with_network_filesystem.feature",
+    "Timestamps truncated to the second are changed with local profile",
+)
+def test_truncated_timestamps_changed_with_local_profile():
+    pass
+
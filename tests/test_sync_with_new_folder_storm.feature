@@ -0,0 +1,16 @@
+Feature: SynchronizeWithNewFolderStorm
+    Synchronize many new files under a new folder
+
+    Scenario: Many new files under a new folder create the folder once
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "-v"
+        And wait 2 seconds
+        And create 100 local files in new folder "/folder"
+
+        Then I should see remote folder at "/folder"
+        And I should see remote file at "/folder/file_99.txt"
+        And trsync logs should contain "Create folder" 1 times
+        And trsync logs should not contain errors
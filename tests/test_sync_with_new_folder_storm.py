@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_new_folder_storm.feature",
+    "Many new files under a new folder create the folder once",
+)
+def test_new_folder_created_once():
+    pass
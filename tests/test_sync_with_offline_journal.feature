@@ -0,0 +1,47 @@
+Feature: SynchronizeWithOfflineJournal
+    Synchronize one workspace with local changes journaled while remote was unreachable
+
+    Scenario: Local change made while remote is unreachable is replayed
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with health file through a proxy
+        Then health file should report phase "watching"
+
+        When the proxy becomes unreachable
+        And create local file at "/offline.txt" with content "offline"
+        And wait 2 seconds
+
+        Then I should not see remote file at "/offline.txt"
+        And trsync logs should contain "Remote unreachable, journal local changes"
+
+        When the proxy becomes reachable
+        And wait 10 seconds
+
+        Then I should see remote file at "/offline.txt"
+        And trsync logs should contain "Replay 1 journaled local change(s)"
+
+    Scenario: Local change made while remote is unreachable of a file modified on remote is a conflict
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/both.txt" with content "v1"
+        And I start synchronization with health file through a proxy
+
+        Then health file should report phase "watching"
+        And I should see local file at "/both.txt"
+
+        When the proxy becomes unreachable
+        And update remote file at "/both.txt" with content "remote"
+        And create local file at "/both.txt" with content "local"
+        And wait 2 seconds
+
+        Then trsync logs should contain "Remote unreachable, journal local changes"
+
+        When the proxy becomes reachable
+        And wait 10 seconds
+
+        Then I should see a conflicted copy of local file "/both.txt"
+        And trsync logs should contain "Replay 1 journaled local change(s)"
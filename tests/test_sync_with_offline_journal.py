@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_offline_journal.feature",
+    "Local change made while remote is unreachable is replayed",
+)
+def test_offline_local_change_is_replayed():
+    pass
+
+
+@scenario(
+    "test_sync_with_offline_journal.feature",
+    "Local change made while remote is unreachable of a file modified on remote is a conflict",
+)
+def test_offline_local_change_conflict():
+    pass
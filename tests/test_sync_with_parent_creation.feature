@@ -0,0 +1,17 @@
+Feature: SynchronizeWithParentCreation
+    Synchronize one workspace when new local files are in folders not yet on remote
+
+    Scenario: File in several levels deep new folders is uploaded with its folders
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local folder at "/folder_a/folder_b/folder_c"
+        And create local file at "/folder_a/folder_b/folder_c/file.txt" with content "deep"
+
+        Then I should see remote folder at "/folder_a"
+        And I should see remote folder at "/folder_a/folder_b"
+        And I should see remote folder at "/folder_a/folder_b/folder_c"
+        And I should see remote file at "/folder_a/folder_b/folder_c/file.txt"
+        And trsync logs should not contain errors
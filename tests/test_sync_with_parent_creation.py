@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_parent_creation.feature",
+    "File in several levels deep new folders is uploaded with its folders",
+)
+def test_deep_new_path_is_uploaded():
+    pass
@@ -0,0 +1,16 @@
+Feature: SynchronizeWithPartialFiles
+    Synchronize one workspace containing partial files left by a crashed trsync
+
+    Scenario: Partial files are removed and not uploaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/.trsync/tmp"
+        And create local file at "/.trsync/tmp/part-toto.txt" with content "toto"
+        And create local file at "/file.txt" with content "file"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/file.txt"
+        And I should not see remote file at "/part-toto.txt"
+        And local file at "/.trsync/tmp/part-toto.txt" should not exist
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_partial_files.feature",
+    "Partial files are removed and not uploaded",
+)
+def test_sync_with_partial_files():
+    pass
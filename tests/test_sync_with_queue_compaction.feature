@@ -0,0 +1,25 @@
+Feature: SynchronizeWithQueueCompaction
+    Compact redundant queued local changes before executing them
+
+    Scenario: Redundant journaled local changes are compacted
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/file_1.txt"
+
+        When replace local file at "/file_1.txt" with content "hello2"
+        And journal local change "ModifiedLocalFile" of "/file_1.txt"
+        And journal local change "ModifiedLocalFile" of "/file_1.txt"
+        And journal local change "ModifiedLocalFile" of "/file_1.txt"
+        And journal local change "NewLocalFile" of "/vanished.txt"
+        And journal local change "DeletedLocalFile" of "/vanished.txt"
+        And I start and wait the end of synchronization with option "-v"
+
+        Then remote file at "/file_1.txt" should have 2 revisions
+        And trsync logs should contain "Queue compacted from"
+        And trsync logs should contain "Compact NewLocalFile"
+        And trsync logs should not contain errors
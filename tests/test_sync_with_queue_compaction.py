@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_queue_compaction.feature",
+    "Redundant journaled local changes are compacted",
+)
+def test_redundant_local_changes_compacted():
+    pass
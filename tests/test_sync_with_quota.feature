@@ -0,0 +1,26 @@
+Feature: SynchronizeWithQuota
+    Block uploads refused for lack of remote space instead of retrying them endlessly
+
+    Scenario: Uploads refused over quota are blocked then synchronized once space is available
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And create local file at "/file_2.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy refusing uploads over quota
+
+        Then trsync logs should contain "Remote quota exceeded" 1 times
+        And trsync logs should contain "Exit with 2 upload(s) blocked by remote quota"
+        And I should not see remote file at "/file_1.txt"
+
+        When I run trsync status
+
+        Then trsync status should list "file_1.txt" as "Failing"
+        And trsync status should list "file_2.txt" as "Failing"
+
+        When I start and wait the end of synchronization
+
+        Then I should see remote file at "/file_1.txt"
+        And I should see remote file at "/file_2.txt"
+        And trsync logs should not contain errors
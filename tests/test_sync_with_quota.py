@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_quota.feature",
+    "Uploads refused over quota are blocked then synchronized once space is available",
+)
+def test_uploads_blocked_by_quota():
+    pass
@@ -0,0 +1,47 @@
+Feature: SynchronizeWithRejectedFiles
+    Handle files Tracim refuses to create according to the rejection policy
+
+    Scenario: Rejected files are skipped until they change
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/bad:name.txt" with content "hello"
+        And create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy rejecting creation of "bad:name.txt" with option "--rejection-policy=skip"
+
+        Then trsync logs should contain "skip it until it changes" 1 times
+        And I should see remote file at "/file_1.txt"
+        And I should not see remote file at "/bad:name.txt"
+
+        When I start and wait the end of synchronization through a proxy rejecting creation of "bad:name.txt" with option "--rejection-policy=skip"
+
+        Then trsync logs should contain "skip it until it changes" 1 times
+
+        When I run trsync status
+
+        Then trsync status should list "bad:name.txt" as "Rejected"
+
+    Scenario: Rejected files are renamed then uploaded again
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/bad:name.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy rejecting creation of "bad:name.txt" with option "--rejection-policy=rename-and-retry"
+
+        Then I should see remote file at "/bad_name.txt"
+        And I should see local file at "/bad_name.txt"
+        And local file at "/bad:name.txt" should not exist
+        And trsync logs should not contain errors
+
+    Scenario: Rejected files fail by default
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/bad:name.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy rejecting creation of "bad:name.txt" with option "-v"
+
+        Then trsync logs should contain "bad:name.txt refused"
+        And I should not see remote file at "/bad:name.txt"
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_rejected_files.feature",
+    "Rejected files are skipped until they change",
+)
+def test_rejected_files_skipped():
+    pass
+
+
+@scenario(
+    "test_sync_with_rejected_files.feature",
+    "Rejected files are renamed then uploaded again",
+)
+def test_rejected_files_renamed():
+    pass
+
+
+@scenario(
+    "test_sync_with_rejected_files.feature",
+    "Rejected files fail by default",
+)
+def test_rejected_files_fail():
+    pass
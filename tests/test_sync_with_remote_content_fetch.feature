@@ -0,0 +1,17 @@
+Feature: SynchronizeWithRemoteContentFetch
+    Fetch remote content metadata once for each downloaded file
+
+    Scenario: Cloned files fetch their metadata once
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And create remote file at "/file_2.txt" with content "world"
+        And I start and wait the end of synchronization through a proxy recording requests with option "--log-level=debug"
+
+        Then local file at "/file_1.txt" should contain "hello"
+        And local file at "/file_2.txt" should contain "world"
+        And proxied requests should contain 2 "GET" requests to "/contents/[0-9]+"
+        And proxied requests should contain 2 "GET" requests to "/files/[0-9]+/raw/.*"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_remote_content_fetch.feature",
+    "Cloned files fetch their metadata once",
+)
+def test_cloned_files_fetch_metadata_once():
+    pass
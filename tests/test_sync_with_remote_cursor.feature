@@ -0,0 +1,36 @@
+Feature: SynchronizeWithRemoteCursor
+    Pull at startup only remote changes since last seen event
+
+    Scenario: Only remote changes since last startup are pulled
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with option "--since"
+
+        Then I should see local file at "/file_1.txt"
+
+        When create remote file at "/file_2.txt" with content "world"
+        And update remote file at "/file_1.txt" with content "hello again"
+        And I start and wait the end of synchronization through a proxy recording requests with option "--since"
+
+        Then local file at "/file_1.txt" should contain "hello again"
+        And local file at "/file_2.txt" should contain "world"
+        And proxied requests should not list workspace contents
+        And trsync logs should contain "Pull 2 remote changes since event"
+        And trsync logs should not contain errors
+
+    Scenario: Stale remote cursor fallback on full synchronization
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create remote file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with option "--since"
+        And create remote file at "/file_2.txt" with content "world"
+        And set remote event cursor to 999999
+        And I start and wait the end of synchronization with option "--since"
+
+        Then local file at "/file_2.txt" should contain "world"
+        And trsync logs should contain "fallback on full synchronization"
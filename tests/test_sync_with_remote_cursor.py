@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_remote_cursor.feature",
+    "Only remote changes since last startup are pulled",
+)
+def test_only_changes_since_cursor_pulled():
+    pass
+
+
+@scenario(
+    "test_sync_with_remote_cursor.feature",
+    "Stale remote cursor fallback on full synchronization",
+)
+def test_stale_cursor_fallback_on_full_sync():
+    pass
@@ -0,0 +1,16 @@
+Feature: SynchronizeWithRemotePolling
+    Synchronize one workspace by polling remote contents instead of using live messages
+
+    Scenario: Remote file created while polling is downloaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--remote-mode=polling"
+        And create remote file at "/toto.txt" with content "toto"
+
+        Then I should see local file at "/toto.txt"
+
+        When delete remote file at "/toto.txt"
+
+        Then trsync database should not contain "/toto.txt"
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_remote_polling.feature",
+    "Remote file created while polling is downloaded",
+)
+def test_sync_with_remote_polling():
+    pass
@@ -0,0 +1,20 @@
+Feature: SynchronizeWithRemoteRelocation
+    Synchronize one workspace when a remote file is recreated in another folder
+
+    Scenario: File recreated in another remote folder is moved locally
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start and wait the end of synchronization
+        Then I should see local file at "/file_2.txt"
+
+        When recreate remote file at "/file_2.txt" into remote folder "/folder_1"
+        And I start and wait the end of synchronization
+
+        Then I should see local file at "/folder_1/file_2.txt"
+        And local file at "/file_2.txt" should not exist
+        And trsync logs should contain "consider it as moved"
+        And trsync logs should contain "Move disk file"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_remote_relocation.feature",
+    "File recreated in another remote folder is moved locally",
+)
+def test_file_recreated_in_another_folder():
+    pass
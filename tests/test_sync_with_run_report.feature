@@ -0,0 +1,56 @@
+Feature: SynchronizeWithRunReport
+    Summarize failures and conflicts of a run with exit code and report file
+
+    Scenario: Clean run exits with success
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with report file and option "-v"
+
+        Then trsync should exit with code 0
+        And report file should report 0 as "exit_code"
+        And report file should report [] as "failures"
+        And report file should report [] as "conflicts"
+
+    Scenario: Failing path is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And make local file "/file_1.txt" unreadable
+        And I start and wait the end of synchronization with report file and option "-v"
+
+        Then trsync should exit with code 1
+        And report file should report 1 as "exit_code"
+        And report file should list "file_1.txt" in "failures"
+
+    Scenario: Conflict left to user is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+        And wait 3 seconds
+
+        When update remote file at "/toto.txt" with content "toto2"
+        And create local file at "/toto.txt" with content "toto3"
+        And I start and wait the end of synchronization with report file and option "--conflict-strategy=manual"
+
+        Then trsync should exit with code 1
+        And report file should list "toto.txt" in "conflicts"
+
+    Scenario: Fatal error is reported
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When move synchronized folder away
+        And I start and wait the end of synchronization with report file and option "-v"
+
+        Then trsync should exit with code 2
+        And report file should report 2 as "exit_code"
+        And report file should report [] as "failures"
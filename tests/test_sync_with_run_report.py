@@ -0,0 +1,33 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_run_report.feature",
+    "Clean run exits with success",
+)
+def test_clean_run_exits_with_success():
+    pass
+
+
+@scenario(
+    "test_sync_with_run_report.feature",
+    "Failing path is reported",
+)
+def test_failing_path_reported():
+    pass
+
+
+@scenario(
+    "test_sync_with_run_report.feature",
+    "Conflict left to user is reported",
+)
+def test_conflict_reported():
+    pass
+
+
+@scenario(
+    "test_sync_with_run_report.feature",
+    "Fatal error is reported",
+)
+def test_fatal_error_reported():
+    pass
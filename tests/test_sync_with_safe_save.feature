@@ -0,0 +1,56 @@
+Feature: SynchronizeWithSafeSave
+    Editors saving by replacing files update the remote content instead of recreating it
+
+    Scenario: LibreOffice save updates remote content
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/file.txt" with content "hello"
+
+        Then I should see remote file at "/file.txt"
+
+        When save local file at "/file.txt" with content "hello world" like LibreOffice
+        And wait 5 seconds
+
+        Then I should see remote file at "/file.txt"
+        And remote file at "/file.txt" should have 2 revisions
+        And trsync logs should not contain "Operation : DeletedLocalFile"
+        And trsync logs should not contain errors
+
+    Scenario: Vim save with backup moved aside updates remote content
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/file.txt" with content "hello"
+
+        Then I should see remote file at "/file.txt"
+
+        When save local file at "/file.txt" with content "hello world" like vim
+        And wait 5 seconds
+
+        Then I should see remote file at "/file.txt"
+        And remote file at "/file.txt" should have 2 revisions
+        And trsync logs should not contain "Operation : DeletedLocalFile"
+        And trsync logs should not contain errors
+
+    Scenario: File deleted and created again updates remote content
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/file.txt" with content "hello"
+
+        Then I should see remote file at "/file.txt"
+
+        When replace local file at "/file.txt" with content "hello world"
+        And wait 5 seconds
+
+        Then I should see remote file at "/file.txt"
+        And remote file at "/file.txt" should have 2 revisions
+        And trsync logs should not contain "Operation : DeletedLocalFile"
+        And trsync logs should not contain errors
@@ -0,0 +1,25 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_safe_save.feature",
+    "LibreOffice save updates remote content",
+)
+def test_libreoffice_save():
+    pass
+
+
+@scenario(
+    "test_sync_with_safe_save.feature",
+    "Vim save with backup moved aside updates remote content",
+)
+def test_vim_save():
+    pass
+
+
+@scenario(
+    "test_sync_with_safe_save.feature",
+    "File deleted and created again updates remote content",
+)
+def test_replaced_file():
+    pass
@@ -0,0 +1,26 @@
+Feature: SynchronizeWithScanCheckpoint
+    Synchronize one workspace after an interrupted disk scan
+
+    Scenario: Interrupted disk scan resumes after its checkpoint
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start and wait the end of synchronization
+        And create local file at "/a.txt" with content "a"
+        And create local file at "/b.txt" with content "b"
+        And create local file at "/c.txt" with content "c"
+        And save local scan checkpoint at "/b.txt"
+        And I start and wait the end of synchronization
+
+        Then I should see remote file at "/c.txt"
+        And I should not see remote file at "/a.txt"
+        And I should not see remote file at "/b.txt"
+        And trsync logs should contain "Resume interrupted disk scan after"
+
+        When I start and wait the end of synchronization
+
+        Then I should see remote file at "/a.txt"
+        And I should see remote file at "/b.txt"
+        And trsync logs should not contain "Resume interrupted disk scan after"
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_scan_checkpoint.feature",
+    "Interrupted disk scan resumes after its checkpoint",
+)
+def test_interrupted_scan_resumes_after_checkpoint():
+    pass
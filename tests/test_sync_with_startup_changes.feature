@@ -0,0 +1,35 @@
+Feature: SynchronizeWithStartupChanges
+    Synchronize one workspace when a file change during startup
+
+    Scenario: File modified during startup is uploaded once
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And I start and wait the end of synchronization
+
+        Then remote file at "/toto.txt" should have 1 revisions
+
+        When I start synchronization
+        And create local file at "/toto.txt" with content "toto2"
+
+        Then remote file at "/toto.txt" should keep 2 revisions
+        And trsync logs should not contain errors
+
+    Scenario: File deleted on remote while stopped is deleted locally
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start and wait the end of synchronization
+        Then I should see local file at "/file_2.txt"
+
+        When delete remote file at "/file_2.txt"
+        And I start and wait the end of synchronization
+
+        Then local file at "/file_2.txt" should not exist
+        And trsync database should not contain "file_2.txt"
+        And I should see local file at "/folder_1/file_1.txt"
+        And trsync logs should not contain errors
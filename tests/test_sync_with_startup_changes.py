@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_startup_changes.feature",
+    "File modified during startup is uploaded once",
+)
+def test_sync_with_startup_changes():
+    pass
+
+
+@scenario(
+    "test_sync_with_startup_changes.feature",
+    "File deleted on remote while stopped is deleted locally",
+)
+def test_remote_deletion_while_stopped():
+    pass
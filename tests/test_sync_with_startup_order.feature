@@ -0,0 +1,19 @@
+Feature: SynchronizeWithStartupOrder
+    Startup messages are emitted in a reproducible order, parents first
+
+    Scenario: Parent folder is uploaded before its children
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local folder at "/folder_1"
+        And create local folder at "/folder_1/folder_2"
+        And create local file at "/folder_1/folder_2/file_1.txt" with content "hello"
+        And create local file at "/folder_1/file_2.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        Then trsync logs should show "Operation : NewLocalFile("folder_1")" before "Operation : NewLocalFile("folder_1/file_2.txt")"
+        And trsync logs should show "Operation : NewLocalFile("folder_1")" before "Operation : NewLocalFile("folder_1/folder_2")"
+        And trsync logs should show "Operation : NewLocalFile("folder_1/folder_2")" before "Operation : NewLocalFile("folder_1/folder_2/file_1.txt")"
+        And I should see remote file at "/folder_1/folder_2/file_1.txt"
+        And trsync logs should not contain errors
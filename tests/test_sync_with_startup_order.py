@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_startup_order.feature",
+    "Parent folder is uploaded before its children",
+)
+def test_parent_folder_uploaded_first():
+    pass
@@ -0,0 +1,18 @@
+Feature: SynchronizeWithTimings
+    Measure operations duration and report slow ones with their time spent by phase
+
+    Scenario: Operations durations are reported in status and slow operations are logged
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And create local file at "/file_2.txt" with content "hello"
+        And I start and wait the end of synchronization with option "--slow-operation-s=0"
+
+        Then trsync logs should contain "Slow operation NewLocalFile"
+        And trsync logs should contain "ms (remote "
+
+        When I run trsync status as json
+
+        Then trsync json status should report 2 as "live.timings.NewLocalFile.count"
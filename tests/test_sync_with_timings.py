@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_timings.feature",
+    "Operations durations are reported in status and slow operations are logged",
+)
+def test_operations_timings():
+    pass
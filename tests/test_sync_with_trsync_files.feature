@@ -0,0 +1,36 @@
+Feature: SynchronizeWithTrsyncFiles
+    Files written by trsync are never synchronized, even inside workspace folder
+
+    Scenario: Health and report files inside folder are not synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization with health and report files inside folder
+
+        Then I should see remote file at "/file_1.txt"
+        And I should not see remote file at "/health.json"
+        And trsync logs should contain "is inside synchronized folder"
+
+        When I start and wait the end of synchronization with health and report files inside folder
+
+        Then I should not see remote file at "/health.json"
+        And I should not see remote file at "/report.json"
+
+    Scenario: Legacy database files are not synchronized
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization
+
+        When create local file at "/.trsync.db" with content "legacy"
+        And create local file at "/.trsync.db-wal" with content "legacy"
+        And I start and wait the end of synchronization with option "--sync-hidden-files"
+
+        Then I should see remote file at "/file_1.txt"
+        And I should not see remote file at "/.trsync.db"
+        And I should not see remote file at "/.trsync.db-wal"
+        And trsync logs should contain "is inside synchronized folder"
@@ -0,0 +1,17 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_trsync_files.feature",
+    "Health and report files inside folder are not synchronized",
+)
+def test_health_and_report_files_not_synchronized():
+    pass
+
+
+@scenario(
+    "test_sync_with_trsync_files.feature",
+    "Legacy database files are not synchronized",
+)
+def test_legacy_database_files_not_synchronized():
+    pass
@@ -0,0 +1,20 @@
+Feature: SynchronizeWithUnfoundExistingContent
+    Synchronize new local files Tracim refuses to create without finding the existing one
+
+    Scenario: Existing remote content is found again and indexed
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/file_1.txt" with content "hello"
+        And I start and wait the end of synchronization through a proxy answering creations with "already exist"
+
+        Then I should see remote file at "/file_1.txt"
+        And remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should contain "after creation refused"
+        And trsync logs should not contain errors
+
+        When I start and wait the end of synchronization
+
+        Then remote file at "/file_1.txt" should have 1 revisions
+        And trsync logs should not contain errors
@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_unfound_existing_content.feature",
+    "Existing remote content is found again and indexed",
+)
+def test_unfound_existing_content_is_indexed():
+    pass
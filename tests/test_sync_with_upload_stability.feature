@@ -0,0 +1,20 @@
+Feature: SynchronizeWithUploadStability
+    Upload disk files only once they stopped changing, for network and cloud mounts
+
+    Scenario: Growing file is uploaded once it is stable
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization with option "--upload-stable-ms=2000"
+        And grow local file at "/growing.txt" during 6 seconds
+        And wait 4 seconds
+
+        Then I should not see remote file at "/growing.txt"
+
+        When wait 6 seconds
+
+        Then I should see remote file at "/growing.txt"
+        And remote file at "/growing.txt" should have 1 revisions
+        And trsync logs should contain "may still be written"
+        And trsync logs should not contain errors
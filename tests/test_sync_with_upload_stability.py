@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_upload_stability.feature",
+    "Growing file is uploaded once it is stable",
+)
+def test_growing_file_uploaded_once_stable():
+    pass
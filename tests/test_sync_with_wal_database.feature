@@ -0,0 +1,20 @@
+Feature: SynchronizeWithWalDatabase
+    Synchronize one workspace with database readable during synchronization
+
+    Scenario: Database is readable while synchronization writes into it
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When I start synchronization
+        And create local file at "/toto.txt" with content "toto"
+
+        Then I should see remote file at "/toto.txt"
+        And trsync database should be in WAL mode
+
+        When create local file at "/toto2.txt" with content "toto2"
+        And delete local file at "/toto.txt"
+
+        Then trsync database should not contain "/toto.txt"
+        And I should see remote file at "/toto2.txt"
+        And I should see local file at "/toto2.txt"
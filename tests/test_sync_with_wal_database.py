@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_wal_database.feature",
+    "Database is readable while synchronization writes into it",
+)
+def test_sync_with_wal_database():
+    pass
@@ -0,0 +1,17 @@
+Feature: SynchronizeWithWatchLimit
+    Search local changes periodically when inotify watches limit prevents watching the folder
+
+    Scenario: Local changes are synchronized when folder can't be watched
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And inotify watches are limited to 1
+
+        When create local folder at "/folder_1"
+        And I start synchronization with option "--local-poll-interval-s=2"
+        And create local file at "/folder_1/file_1.txt" with content "hello"
+
+        Then I should see remote file at "/folder_1/file_1.txt"
+        And trsync logs should contain "Inotify max_user_watches limit reached"
+        And trsync logs should contain "Search changes every 2 seconds instead"
+        And trsync logs should not contain errors
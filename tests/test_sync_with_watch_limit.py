@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_watch_limit.feature",
+    "Local changes are synchronized when folder can't be watched",
+)
+def test_sync_with_watch_limit():
+    pass
@@ -0,0 +1,17 @@
+Feature: SynchronizeWithWorkspaceMove
+    Synchronize one workspace when contents are moved to another workspace
+
+    Scenario: Content moved to another workspace is deleted locally
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start synchronization
+        Then I should see local file at "/file_2.txt"
+
+        When move remote file at "/file_2.txt" to new workspace "MyWorskpace2"
+
+        Then trsync database should not contain "file_2.txt"
+        And local file at "/file_2.txt" should not exist
+        And I should see local file at "/folder_1/file_1.txt"
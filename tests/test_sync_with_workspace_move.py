@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_sync_with_workspace_move.feature",
+    "Content moved to another workspace is deleted locally",
+)
+def test_content_moved_to_another_workspace():
+    pass
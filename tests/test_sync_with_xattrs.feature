@@ -0,0 +1,16 @@
+Feature: SynchronizeWithXattrs
+    Synchronize one workspace preserving extended attributes of files
+
+    Scenario: Extended attributes are restored when file is downloaded
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+
+        When create local file at "/toto.txt" with content "toto"
+        And set extended attribute "user.trsync_test" to "tag" on local file "/toto.txt"
+        And I start and wait the end of synchronization with option "--preserve-xattrs"
+        And update remote file at "/toto.txt" with content "toto2"
+        And I start and wait the end of synchronization with option "--preserve-xattrs"
+
+        Then local file at "/toto.txt" should contain "toto2"
+        And local file at "/toto.txt" should have extended attribute "user.trsync_test" with value "tag"
@@ -0,0 +1,15 @@
+import os
+
+import pytest
+from pytest_bdd import scenario, given, when, then
+
+
+@pytest.mark.skipif(
+    not hasattr(os, "setxattr"), reason="Extended attributes are not supported"
+)
+@scenario(
+    "test_sync_with_xattrs.feature",
+    "Extended attributes are restored when file is downloaded",
+)
+def test_xattrs_restored_on_download():
+    pass
@@ -0,0 +1,13 @@
+Feature: Verbosity
+    Configure trsync logs verbosity from command line
+
+    Scenario: Quiet synchronization only logs warnings
+        Given I have a fresh Tracim instance
+        And I'm the user "user1"
+        And I own the workspace "MyWorskpace1"
+        And The workspace is filled with contents called "Set1"
+
+        When I start and wait the end of synchronization with option "-q"
+
+        Then I should see local file at "/folder_1/file_1.txt"
+        And trsync logs should not contain "INFO"
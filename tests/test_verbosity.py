@@ -0,0 +1,9 @@
+from pytest_bdd import scenario, given, when, then
+
+
+@scenario(
+    "test_verbosity.feature",
+    "Quiet synchronization only logs warnings",
+)
+def test_verbosity():
+    pass